@@ -66,6 +66,26 @@ fn benchmarks(c: &mut Criterion) {
             },
         );
 
+        group.bench_with_input(
+            BenchmarkId::new("v2::Header::try_from source", id),
+            input.as_slice(),
+            |b, i| {
+                b.iter(|| match v2::Header::try_from(i).unwrap().addresses {
+                    v2::Addresses::IPv4(addresses) => addresses.source_port,
+                    v2::Addresses::IPv6(addresses) => addresses.source_port,
+                    _ => 0,
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("v2::HeaderRef::try_from source", id),
+            input.as_slice(),
+            |b, i| {
+                b.iter(|| v2::HeaderRef::try_from(i).unwrap().source());
+            },
+        );
+
         group.bench_with_input(
             BenchmarkId::new("v2::Header::as_bytes", id),
             &v2::Header::try_from(input.as_slice()).unwrap(),
@@ -0,0 +1,30 @@
+//! Re-encodes headers parsed from arbitrary input and checks they parse back to the same header,
+//! and that re-encoding an unchanged header reproduces its bytes.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ppp::{v1, v2};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(header) = v1::Header::try_from(data) {
+        let encoded = header.addresses.to_string();
+        let decoded = v1::Header::try_from(encoded.as_str()).unwrap();
+
+        assert_eq!(decoded.addresses, header.addresses);
+        assert_eq!(header.to_string().as_bytes(), &data[..header.header.len()]);
+    }
+
+    if let Ok(header) = v2::Header::try_from(data) {
+        let encoded = v2::Builder::from_header(&header)
+            .and_then(v2::Builder::build)
+            .unwrap();
+        let decoded = v2::Header::try_from(encoded.as_slice()).unwrap();
+
+        assert_eq!(encoded, header.as_bytes());
+
+        assert_eq!(decoded.command, header.command);
+        assert_eq!(decoded.protocol, header.protocol);
+        assert_eq!(decoded.addresses, header.addresses);
+        assert_eq!(decoded.tlv_bytes(), header.tlv_bytes());
+    }
+});
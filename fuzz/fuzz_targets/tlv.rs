@@ -0,0 +1,33 @@
+//! Decodes arbitrary input as the TLVs of a binary header.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ppp::v2::{Builder, Command, Header, Protocol, TypeLengthValues};
+
+fuzz_target!(|data: &[u8]| {
+    for tlv in TypeLengthValues::from(data) {
+        if tlv.is_err() {
+            break;
+        }
+    }
+
+    if data.len() > u16::MAX as usize {
+        return;
+    }
+
+    let header = Builder::unspecified(Command::Proxy, Protocol::Stream)
+        .write_payload(data)
+        .and_then(Builder::build)
+        .unwrap();
+    let header = Header::try_from(header.as_slice()).unwrap();
+
+    if let Ok(tlvs) = header.tlv_list() {
+        let _ = (tlvs.iter().count(), tlvs.get(tlvs.len()));
+    }
+
+    for tlv in header.tlvs() {
+        if tlv.is_err() {
+            break;
+        }
+    }
+});
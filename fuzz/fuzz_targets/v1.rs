@@ -0,0 +1,20 @@
+//! Parses arbitrary input as a text header, through every entry point that accepts one.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ppp::v1;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = v1::Header::parse_strict(data);
+
+    if let Ok(header) = v1::Header::try_from(data) {
+        let _ = (header.addresses_str(), header.to_string(), header.to_owned());
+    }
+
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = v1::Header::try_from(input).map(|header| header.addresses_str().len());
+    }
+
+    let _ = ppp::HeaderResult::parse(data);
+    let _ = ppp::sniff(data);
+});
@@ -0,0 +1,23 @@
+//! Parses arbitrary input as a binary header, through every entry point that accepts one.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ppp::v2;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(header) = v2::Header::try_from(data) {
+        let _ = (header.length(), header.address_bytes(), header.tlv_bytes());
+        let _ = (header.proxied_addresses(), header.to_string(), header.to_owned());
+
+        if let v2::Addresses::Unix(unix) = header.addresses {
+            let _ = (unix.source_address(), unix.destination_address());
+        }
+    }
+
+    if let Ok(header) = v2::HeaderRef::try_from(data) {
+        let _ = (header.source(), header.destination(), header.proxied_addresses());
+    }
+
+    let _ = v2::Header::parse_lenient(data).map(|header| header.len());
+    let _ = ppp::HeaderResult::parse(data);
+});
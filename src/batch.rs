@@ -0,0 +1,223 @@
+//! Parsing of many independent headers at once, e.g. extracted from packet captures or replayed from logs.
+//!
+//! Each input is expected to start with a header, as with `HeaderResult::parse`.
+//! The results are the same as parsing each input on its own, but the batch helpers skip the work that only
+//! matters for a single input: inputs are sorted by version from their first byte, in fixed-size groups,
+//! before any is parsed, and the results are written into a vector the caller reuses across batches.
+//!
+//! ## Examples
+//! ```rust
+//! use ppp::batch::{parse_stream, Summary};
+//!
+//! let captured: [&[u8]; 3] = [
+//!     b"PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n",
+//!     b"PROXY UNKNOWN\r\n",
+//!     b"PROXY TCP4 127.0.0.1 127.0.0.2 80 65536\r\n",
+//! ];
+//! let summary: Summary = parse_stream(captured).collect();
+//!
+//! assert_eq!(summary.headers, 2);
+//! assert_eq!(summary.errors.get("v1.invalid_destination_port"), Some(&1));
+//! ```
+use crate::prelude::*;
+
+use crate::{v1, v2, HeaderResult, PartialResult};
+use alloc::collections::BTreeMap;
+
+/// The number of inputs classified at a time by `parse_batch`.
+const GROUP: usize = 64;
+
+/// Tests whether the input should be parsed as a binary header first, as `HeaderResult::parse` would.
+/// A text header and other inputs are never valid binary headers, and only need the text parser.
+fn is_binary(input: &[u8]) -> bool {
+    input
+        .first()
+        .map_or(true, |&first| first == v2::PROTOCOL_PREFIX[0])
+}
+
+/// Parses the input as a header of either version, skipping the binary parser for inputs that cannot be one.
+fn parse(input: &[u8], binary: bool) -> HeaderResult<'_> {
+    if binary {
+        HeaderResult::parse(input)
+    } else {
+        v1::Header::try_from(input).into()
+    }
+}
+
+/// An `Iterator` of the results of parsing each input as a header.
+#[derive(Clone, Debug)]
+pub struct ParseStream<I> {
+    inputs: I,
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]>> Iterator for ParseStream<I> {
+    type Item = HeaderResult<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inputs
+            .next()
+            .map(|input| parse(input, is_binary(input)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inputs.size_hint()
+    }
+}
+
+/// Lazily parses each of the inputs as a header of either version.
+pub fn parse_stream<'a, I: IntoIterator<Item = &'a [u8]>>(inputs: I) -> ParseStream<I::IntoIter> {
+    ParseStream {
+        inputs: inputs.into_iter(),
+    }
+}
+
+/// Parses each of the inputs as a header of either version, replacing the contents of `results`.
+/// Reusing the same `results` for each batch only allocates when a batch is larger than any before it.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::batch::parse_batch;
+///
+/// let mut results = Vec::new();
+///
+/// for batch in [[&b"PROXY UNKNOWN\r\n"[..]; 100], [&b"GET / HTTP/1.1\r\n"[..]; 100]] {
+///     parse_batch(&batch, &mut results);
+///
+///     assert_eq!(results.len(), 100);
+/// }
+///
+/// assert!(results.iter().all(|result| matches!(result, ppp::HeaderResult::V1(Err(..)))));
+/// ```
+pub fn parse_batch<'a>(inputs: &[&'a [u8]], results: &mut Vec<HeaderResult<'a>>) {
+    results.clear();
+    results.reserve(inputs.len());
+
+    for group in inputs.chunks(GROUP) {
+        let mut binary = [false; GROUP];
+
+        // Classifying a whole group first keeps this loop free of the parsers' branches.
+        for (binary, input) in binary.iter_mut().zip(group) {
+            *binary = is_binary(input);
+        }
+
+        for (input, &binary) in group.iter().zip(&binary[..]) {
+            results.push(parse(input, binary));
+        }
+    }
+}
+
+/// Counts of the results of parsing many headers, by version and by error code.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    /// The number of valid headers of either version.
+    pub headers: usize,
+    /// The number of valid text headers.
+    pub v1: usize,
+    /// The number of valid binary headers.
+    pub v2: usize,
+    /// The number of inputs that end before the header is complete.
+    pub incomplete: usize,
+    /// The number of invalid inputs, incomplete ones included, by the name of their `ErrorCode`.
+    pub errors: BTreeMap<&'static str, usize>,
+}
+
+impl Summary {
+    /// Counts the result of parsing a single input.
+    pub fn record(&mut self, result: &HeaderResult<'_>) {
+        if result.is_incomplete() {
+            self.incomplete += 1;
+        }
+
+        let code = match result {
+            HeaderResult::V1(Ok(_)) => {
+                self.v1 += 1;
+                None
+            }
+            HeaderResult::V2(Ok(_)) => {
+                self.v2 += 1;
+                None
+            }
+            HeaderResult::V1(Err(error)) => Some(error.code()),
+            HeaderResult::V2(Err(error)) => Some(error.code()),
+        };
+
+        match code {
+            Some(code) => *self.errors.entry(code.name).or_default() += 1,
+            None => self.headers += 1,
+        }
+    }
+
+    /// The total number of inputs counted.
+    pub fn total(&self) -> usize {
+        self.headers + self.errors.values().sum::<usize>()
+    }
+}
+
+impl<'a> Extend<HeaderResult<'a>> for Summary {
+    fn extend<T: IntoIterator<Item = HeaderResult<'a>>>(&mut self, results: T) {
+        for result in results {
+            self.record(&result);
+        }
+    }
+}
+
+impl<'a> FromIterator<HeaderResult<'a>> for Summary {
+    fn from_iter<T: IntoIterator<Item = HeaderResult<'a>>>(results: T) -> Self {
+        let mut summary = Summary::default();
+
+        summary.extend(results);
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::VECTORS;
+
+    #[test]
+    fn same_as_parsing_alone() {
+        let inputs: Vec<&[u8]> = VECTORS
+            .iter()
+            .cycle()
+            .take(VECTORS.len() * 3 + 5)
+            .map(|vector| vector.bytes)
+            .collect();
+        let mut results = Vec::new();
+
+        parse_batch(&inputs, &mut results);
+
+        assert_eq!(results.len(), inputs.len());
+
+        for ((input, batched), streamed) in inputs
+            .iter()
+            .zip(&results)
+            .zip(parse_stream(inputs.iter().copied()))
+        {
+            assert_eq!(batched, &HeaderResult::parse(input));
+            assert_eq!(streamed, HeaderResult::parse(input));
+        }
+
+        let summary: Summary = parse_stream(inputs.iter().copied()).collect();
+
+        assert_eq!(summary.total(), inputs.len());
+        assert_eq!(summary.headers, summary.v1 + summary.v2);
+    }
+
+    #[test]
+    fn summary() {
+        let inputs: [&[u8]; 4] = [
+            b"PROXY UNKNOWN\r\n",
+            b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00",
+            b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x04",
+            b"",
+        ];
+        let summary: Summary = parse_stream(inputs).collect();
+
+        assert_eq!(summary.v1, 1);
+        assert_eq!(summary.v2, 1);
+        assert_eq!(summary.incomplete, 2);
+        assert_eq!(summary.errors.len(), 2);
+        assert_eq!(summary.total(), 4);
+    }
+}
@@ -0,0 +1,361 @@
+//! Command-line tool for decoding and encoding PROXY protocol headers.
+use ppp::encoding::{from_base64, from_hex, to_base64, to_hex};
+use ppp::v1;
+use ppp::v2::{self, Builder, Command, Protocol};
+use ppp::{explain, HeaderResult};
+use std::env::args;
+use std::fs::File;
+use std::io::{self, prelude::*};
+use std::net::SocketAddr;
+use std::process::exit;
+
+const USAGE: &str = "Usage:
+    ppp decode [--format hex|base64|raw] [FILE]
+        Explains the fields of a header read from FILE, or standard input when FILE is absent or '-'.
+        Whitespace is ignored in hex and base64 input.
+
+    ppp encode [--version 1|2] [--local] [--datagram] [--tlv TYPE=HEX]... [--format hex|base64|raw] [SOURCE DESTINATION]
+        Encodes a header for the given socket addresses, or an UNKNOWN (v1) or unspecified (v2) header without them.
+        TYPE is a TLV type in decimal or hexadecimal (e.g. 0x02).";
+
+/// The encoding of header bytes on the command line.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    Hex,
+    Base64,
+    Raw,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Format::Hex),
+            "base64" => Ok(Format::Base64),
+            "raw" => Ok(Format::Raw),
+            _ => Err(format!("unknown format '{}'", s)),
+        }
+    }
+}
+
+impl Format {
+    fn decode(self, input: &[u8]) -> Result<Vec<u8>, String> {
+        let text = || String::from_utf8_lossy(input);
+
+        match self {
+            Format::Hex => from_hex(&text()).map_err(|error| error.to_string()),
+            Format::Base64 => from_base64(&text()).map_err(|error| error.to_string()),
+            Format::Raw => Ok(input.to_vec()),
+        }
+    }
+
+    fn encode(self, input: &[u8]) -> Vec<u8> {
+        match self {
+            Format::Hex => format!("{}\n", to_hex(input)).into_bytes(),
+            Format::Base64 => format!("{}\n", to_base64(input)).into_bytes(),
+            Format::Raw => input.to_vec(),
+        }
+    }
+}
+
+/// Explains the header at the start of the input, one field per line, followed by the length of any payload.
+fn decode(input: &[u8]) -> Result<Vec<String>, String> {
+    let length = match HeaderResult::parse(input) {
+        HeaderResult::V1(Ok(header)) => header.header.len(),
+        HeaderResult::V2(Ok(header)) => header.len(),
+        HeaderResult::V1(Err(error)) => return Err(format!("invalid v1 header: {}", error)),
+        HeaderResult::V2(Err(error)) => return Err(format!("invalid v2 header: {}", error)),
+    };
+    let mut lines: Vec<String> = explain(&input[..length])
+        .lines()
+        .map(String::from)
+        .collect();
+
+    if input.len() > length {
+        lines.push(format!(
+            "Payload: {} bytes after the header",
+            input.len() - length
+        ));
+    }
+
+    Ok(lines)
+}
+
+/// The arguments of the `encode` command.
+#[derive(Debug, Default, PartialEq)]
+struct Encode {
+    version: u8,
+    local: bool,
+    datagram: bool,
+    tlvs: Vec<(u8, Vec<u8>)>,
+    addresses: Option<(SocketAddr, SocketAddr)>,
+}
+
+fn parse_tlv(argument: &str) -> Result<(u8, Vec<u8>), String> {
+    let (kind, value) = argument
+        .split_once('=')
+        .ok_or_else(|| format!("TLV '{}' must be of the form TYPE=HEX", argument))?;
+    let kind = match kind.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => kind.parse(),
+    }
+    .map_err(|_| format!("invalid TLV type '{}'", kind))?;
+
+    Ok((kind, from_hex(value).map_err(|error| error.to_string())?))
+}
+
+fn encode(arguments: &Encode) -> Result<Vec<u8>, String> {
+    if let Some((source, destination)) = arguments.addresses {
+        if source.is_ipv4() != destination.is_ipv4() {
+            return Err("source and destination must be the same address family".to_string());
+        }
+    }
+
+    match arguments.version {
+        1 => {
+            if arguments.local || arguments.datagram || !arguments.tlvs.is_empty() {
+                return Err(
+                    "--local, --datagram and --tlv are only supported by version 2".to_string(),
+                );
+            }
+
+            let addresses = match arguments.addresses {
+                Some(addresses) => v1::Addresses::from(addresses),
+                None => v1::Addresses::Unknown,
+            };
+
+            Ok(addresses.to_string().into_bytes())
+        }
+        2 => {
+            let command = if arguments.local {
+                Command::Local
+            } else {
+                Command::Proxy
+            };
+            let protocol = match (arguments.addresses, arguments.datagram) {
+                (None, _) => Protocol::Unspecified,
+                (Some(..), false) => Protocol::Stream,
+                (Some(..), true) => Protocol::Datagram,
+            };
+            let builder = match arguments.addresses {
+                Some(addresses) => {
+                    Builder::with_addresses(v2::Version::Two | command, protocol, addresses)
+                }
+                None => Builder::unspecified(command, protocol).into(),
+            };
+
+            arguments
+                .tlvs
+                .iter()
+                .try_fold(builder, |builder, (kind, value)| {
+                    builder.write_tlv(*kind, value.as_slice())
+                })
+                .and_then(Builder::build)
+                .map_err(|error| error.to_string())
+        }
+        version => Err(format!("unsupported version {}", version)),
+    }
+}
+
+fn read_input(path: Option<&str>) -> io::Result<Vec<u8>> {
+    let mut input = Vec::new();
+
+    match path {
+        None | Some("-") => io::stdin().read_to_end(&mut input)?,
+        Some(path) => File::open(path)?.read_to_end(&mut input)?,
+    };
+
+    Ok(input)
+}
+
+fn run(arguments: Vec<String>) -> Result<(), String> {
+    let mut arguments = arguments.into_iter();
+    let command = arguments.next();
+    let mut format = Format::Hex;
+    let mut positional = Vec::new();
+    let mut encoding = Encode {
+        version: 2,
+        ..Default::default()
+    };
+
+    while let Some(argument) = arguments.next() {
+        let mut value = |name: &str| {
+            arguments
+                .next()
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+
+        match argument.as_str() {
+            "--format" => format = value("--format")?.parse()?,
+            "--version" => {
+                encoding.version = value("--version")?
+                    .parse()
+                    .map_err(|_| "--version must be 1 or 2".to_string())?
+            }
+            "--local" => encoding.local = true,
+            "--datagram" => encoding.datagram = true,
+            "--tlv" => encoding.tlvs.push(parse_tlv(&value("--tlv")?)?),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => positional.push(argument),
+        }
+    }
+
+    match command.as_deref() {
+        Some("decode") if positional.len() <= 1 => {
+            let input = read_input(positional.first().map(String::as_str))
+                .map_err(|error| error.to_string())?;
+
+            for line in decode(format.decode(&input)?.as_slice())? {
+                println!("{}", line);
+            }
+
+            Ok(())
+        }
+        Some("encode") if positional.is_empty() || positional.len() == 2 => {
+            if let [source, destination] = positional.as_slice() {
+                let source = source
+                    .parse()
+                    .map_err(|_| format!("invalid source address '{}'", source))?;
+                let destination = destination
+                    .parse()
+                    .map_err(|_| format!("invalid destination address '{}'", destination))?;
+
+                encoding.addresses = Some((source, destination));
+            }
+
+            io::stdout()
+                .write_all(format.encode(encode(&encoding)?.as_slice()).as_slice())
+                .map_err(|error| error.to_string())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    if let Err(message) = run(args().skip(1).collect()) {
+        eprintln!("{}", message);
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trip() {
+        for input in [
+            &b""[..],
+            b"f",
+            b"fo",
+            b"foo",
+            b"foob",
+            b"\r\n\r\n\0\r\nQUIT\n",
+        ] {
+            let encoded = Format::Base64.encode(input);
+
+            assert_eq!(Format::Base64.decode(&encoded).unwrap(), input);
+        }
+
+        assert_eq!(Format::Base64.encode(b"fo"), b"Zm8=\n");
+        assert!(Format::Base64.decode(b"Zm=8").is_err());
+        assert!(Format::Base64.decode(b"Zm8*").is_err());
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(
+            Format::Hex.decode(b"0d0A 20\n00").unwrap(),
+            vec![0x0D, 0x0A, 0x20, 0x00]
+        );
+        assert_eq!(Format::Hex.encode(&[0x0D, 0xFF]), b"0dff\n");
+        assert!(Format::Hex.decode(b"0d0").is_err());
+        assert!(Format::Hex.decode(b"zz").is_err());
+    }
+
+    #[test]
+    fn decode_v2() {
+        let header = Builder::unspecified(Command::Local, Protocol::Unspecified)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            decode(&header).unwrap(),
+            vec![
+                "0000  0d 0a 0d 0a 00 0d 0a 51 55 49 54 0a              prefix",
+                "000c  20                                               version 2, command LOCAL",
+                "000d  00                                               address family UNSPEC, protocol UNSPEC",
+                "000e  00 00                                            length 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let arguments = Encode {
+            version: 2,
+            tlvs: vec![(0x02, b"example.com".to_vec())],
+            addresses: Some((
+                "127.0.0.1:80".parse().unwrap(),
+                "192.168.1.1:443".parse().unwrap(),
+            )),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            decode(&encode(&arguments).unwrap()).unwrap(),
+            vec![
+                "0000  0d 0a 0d 0a 00 0d 0a 51 55 49 54 0a              prefix",
+                "000c  21                                               version 2, command PROXY",
+                "000d  11                                               address family INET, protocol STREAM",
+                "000e  00 1a                                            length 26",
+                "0010  7f 00 00 01                                      source address 127.0.0.1",
+                "0014  c0 a8 01 01                                      destination address 192.168.1.1",
+                "0018  00 50                                            source port 80",
+                "001a  01 bb                                            destination port 443",
+                "001c  02 00 0b                                         TLV AUTHORITY (0x02), length 11",
+                "001f  65 78 61 6d 70 6c 65 2e 63 6f 6d                 value \"example.com\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_empty_tlv() {
+        let arguments = Encode {
+            version: 2,
+            tlvs: vec![parse_tlv("0x04=").unwrap()],
+            addresses: Some((
+                "127.0.0.1:80".parse().unwrap(),
+                "192.168.1.1:443".parse().unwrap(),
+            )),
+            ..Default::default()
+        };
+
+        assert_eq!(arguments.tlvs, vec![(0x04, Vec::new())]);
+        assert_eq!(
+            decode(&encode(&arguments).unwrap()).unwrap(),
+            vec![
+                "0000  0d 0a 0d 0a 00 0d 0a 51 55 49 54 0a              prefix",
+                "000c  21                                               version 2, command PROXY",
+                "000d  11                                               address family INET, protocol STREAM",
+                "000e  00 0f                                            length 15",
+                "0010  7f 00 00 01                                      source address 127.0.0.1",
+                "0014  c0 a8 01 01                                      destination address 192.168.1.1",
+                "0018  00 50                                            source port 80",
+                "001a  01 bb                                            destination port 443",
+                "001c  04 00 00                                         TLV NOOP (0x04), length 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_v1() {
+        let arguments = Encode {
+            version: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(encode(&arguments).unwrap(), b"PROXY UNKNOWN\r\n");
+    }
+}
@@ -0,0 +1,156 @@
+//! Parsing and encoding shared by the language bindings, which exchange addresses as strings in `ip:port` form
+//! and report errors as their stable code followed by their message.
+use std::prelude::v1::*;
+
+use crate::v2::{Builder, Command, Protocol};
+use crate::{v1, v2, HeaderResult, PartialResult, ProxyInfo};
+use std::net::SocketAddr;
+
+/// Parses a header of either version, along with its length in bytes.
+/// Returns `None` if more bytes are needed.
+pub(crate) fn parse(input: &[u8]) -> Result<Option<(ProxyInfo, usize)>, String> {
+    let result = HeaderResult::parse(input);
+
+    if result.is_incomplete() {
+        return Ok(None);
+    }
+
+    match result {
+        HeaderResult::V1(Ok(header)) => Ok(Some((ProxyInfo::from(&header), header.header.len()))),
+        HeaderResult::V2(Ok(header)) => Ok(Some((ProxyInfo::from(&header), header.len()))),
+        HeaderResult::V1(Err(error)) => Err(format!("{}: {}", error.code(), error)),
+        HeaderResult::V2(Err(error)) => Err(format!("{}: {}", error.code(), error)),
+    }
+}
+
+/// Parses the pair of addresses, if both are given, failing if only one of them is.
+fn addresses(
+    source: Option<&str>,
+    destination: Option<&str>,
+) -> Result<Option<(SocketAddr, SocketAddr)>, String> {
+    match (source, destination) {
+        (Some(source), Some(destination)) => {
+            let source = source
+                .parse()
+                .map_err(|_| format!("Invalid address {}.", source))?;
+            let destination = destination
+                .parse()
+                .map_err(|_| format!("Invalid address {}.", destination))?;
+
+            Ok(Some((source, destination)))
+        }
+        (None, None) => Ok(None),
+        _ => Err("Addresses must be given in pairs.".to_string()),
+    }
+}
+
+/// Encodes a text header, which requires both addresses to be of the same family.
+/// Encodes an `UNKNOWN` header when both addresses are missing.
+pub(crate) fn encode_v1(
+    source: Option<&str>,
+    destination: Option<&str>,
+) -> Result<Vec<u8>, String> {
+    let addresses = match addresses(source, destination)? {
+        None => v1::Addresses::Unknown,
+        Some((SocketAddr::V4(source), SocketAddr::V4(destination))) => v1::Addresses::new_tcp4(
+            *source.ip(),
+            *destination.ip(),
+            source.port(),
+            destination.port(),
+        ),
+        Some((SocketAddr::V6(source), SocketAddr::V6(destination))) => v1::Addresses::new_tcp6(
+            *source.ip(),
+            *destination.ip(),
+            source.port(),
+            destination.port(),
+        ),
+        Some(..) => return Err("Addresses must be of the same family.".to_string()),
+    };
+
+    Ok(addresses.to_string().into_bytes())
+}
+
+/// Encodes a binary header, appending the TLVs as they are, which requires both addresses to be of the same family.
+/// Encodes a `LOCAL` header without addresses when both addresses are missing.
+pub(crate) fn encode_v2(
+    source: Option<&str>,
+    destination: Option<&str>,
+    datagram: bool,
+    tlvs: &[u8],
+) -> Result<Vec<u8>, String> {
+    let builder = match addresses(source, destination)? {
+        None => Builder::unspecified(Command::Local, Protocol::Unspecified).into(),
+        Some((source, destination)) if source.is_ipv4() != destination.is_ipv4() => {
+            return Err("Addresses must be of the same family.".to_string())
+        }
+        Some(addresses) => {
+            let protocol = if datagram {
+                Protocol::Datagram
+            } else {
+                Protocol::Stream
+            };
+
+            Builder::with_addresses(v2::Version::Two | Command::Proxy, protocol, addresses)
+        }
+    };
+
+    builder
+        .write_payload(tlvs)
+        .and_then(Builder::build)
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let v1 = encode_v1(Some("127.0.0.1:80"), Some("192.168.1.1:443")).unwrap();
+        let (info, length) = parse(&v1).unwrap().unwrap();
+
+        assert_eq!(info.version, crate::Version::One);
+        assert_eq!(info.source, Some("127.0.0.1:80".parse().unwrap()));
+        assert_eq!(length, v1.len());
+
+        let v2 = encode_v2(
+            Some("[2001:db8::1]:53"),
+            Some("[2001:db8::2]:53"),
+            true,
+            b"\x02\x00\x03abc",
+        )
+        .unwrap();
+        let (info, length) = parse(&v2).unwrap().unwrap();
+
+        assert_eq!(info.version, crate::Version::Two);
+        assert_eq!(info.command, Command::Proxy);
+        assert_eq!(info.protocol, Protocol::Datagram);
+        assert_eq!(info.destination, Some("[2001:db8::2]:53".parse().unwrap()));
+        assert_eq!(info.authority.as_deref(), Some("abc"));
+        assert_eq!(length, v2.len());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse(b"PROXY TCP4 127.0.0.1").unwrap(), None);
+        assert!(parse(b"PROXY TCP5 127.0.0.1 127.0.0.1 80 443\r\n")
+            .unwrap_err()
+            .starts_with("v1.invalid_protocol"));
+        assert!(encode_v1(Some("127.0.0.1:80"), Some("[2001:db8::1]:443")).is_err());
+        assert!(encode_v2(Some("localhost:80"), Some("127.0.0.1:443"), false, &[]).is_err());
+        assert_eq!(encode_v1(None, None).unwrap(), b"PROXY UNKNOWN\r\n");
+    }
+
+    #[test]
+    fn mismatched_addresses() {
+        let family = Err("Addresses must be of the same family.".to_string());
+        let pairs = Err("Addresses must be given in pairs.".to_string());
+
+        assert_eq!(
+            encode_v2(Some("127.0.0.1:80"), Some("[2001:db8::1]:443"), false, &[]),
+            family
+        );
+        assert_eq!(encode_v1(Some("127.0.0.1:80"), None), pairs);
+        assert_eq!(encode_v2(None, Some("[2001:db8::1]:443"), true, &[]), pairs);
+    }
+}
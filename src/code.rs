@@ -0,0 +1,217 @@
+//! Stable codes for parse errors, for aggregating errors across services and versions of this crate.
+#[cfg(all(any(feature = "std", feature = "tstd"), feature = "v1", feature = "v2"))]
+use crate::io;
+#[cfg(feature = "v1")]
+use crate::v1;
+#[cfg(feature = "v2")]
+use crate::v2;
+use core::fmt;
+
+/// A stable identifier of an error variant, with both a numeric and a string form.
+/// Codes are never reused or reassigned, even if the variant they identify is removed.
+/// Text header errors are numbered from 100, binary header errors from 200 and stream errors from 300.
+///
+/// ## Examples
+#[cfg_attr(feature = "v2", doc = "```rust")]
+#[cfg_attr(not(feature = "v2"), doc = "```ignore")]
+/// use ppp::v2::ParseError;
+///
+/// let code = ParseError::Prefix.code();
+///
+/// assert_eq!(code.number, 202);
+/// assert_eq!(code.name, "v2.prefix");
+/// assert_eq!(code.to_string(), "v2.prefix");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ErrorCode {
+    /// The numeric form of the code.
+    pub number: u16,
+    /// The string form of the code, namespaced by the protocol version or `io`.
+    pub name: &'static str,
+}
+
+impl ErrorCode {
+    const fn new(number: u16, name: &'static str) -> Self {
+        ErrorCode { number, name }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+#[cfg(feature = "v1")]
+impl v1::ParseError {
+    /// The stable code of this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::InvalidPrefix => ErrorCode::new(101, "v1.invalid_prefix"),
+            Self::Partial => ErrorCode::new(102, "v1.partial"),
+            Self::MissingPrefix => ErrorCode::new(103, "v1.missing_prefix"),
+            Self::MissingNewLine => ErrorCode::new(104, "v1.missing_new_line"),
+            Self::MissingProtocol => ErrorCode::new(105, "v1.missing_protocol"),
+            Self::MissingSourceAddress => ErrorCode::new(106, "v1.missing_source_address"),
+            Self::MissingDestinationAddress => {
+                ErrorCode::new(107, "v1.missing_destination_address")
+            }
+            Self::MissingSourcePort => ErrorCode::new(108, "v1.missing_source_port"),
+            Self::MissingDestinationPort => ErrorCode::new(109, "v1.missing_destination_port"),
+            Self::HeaderTooLong => ErrorCode::new(110, "v1.header_too_long"),
+            Self::InvalidProtocol => ErrorCode::new(111, "v1.invalid_protocol"),
+            Self::InvalidSuffix => ErrorCode::new(112, "v1.invalid_suffix"),
+            Self::InvalidSourceAddress(..) => ErrorCode::new(113, "v1.invalid_source_address"),
+            Self::InvalidDestinationAddress(..) => {
+                ErrorCode::new(114, "v1.invalid_destination_address")
+            }
+            Self::InvalidSourcePort(..) => ErrorCode::new(115, "v1.invalid_source_port"),
+            Self::InvalidDestinationPort(..) => ErrorCode::new(116, "v1.invalid_destination_port"),
+            Self::ExtraFields => ErrorCode::new(118, "v1.extra_fields"),
+            Self::EmptyField => ErrorCode::new(119, "v1.empty_field"),
+            Self::ControlCharacter => ErrorCode::new(120, "v1.control_character"),
+        }
+    }
+}
+
+#[cfg(feature = "v1")]
+impl v1::BinaryParseError {
+    /// The stable code of this error, which is the code of the underlying `ParseError` if there is one.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Parse(error) => error.code(),
+            Self::InvalidUtf8(..) => ErrorCode::new(117, "v1.invalid_utf8"),
+        }
+    }
+}
+
+#[cfg(feature = "v2")]
+impl v2::ParseError {
+    /// The stable code of this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Incomplete(..) => ErrorCode::new(201, "v2.incomplete"),
+            Self::Prefix => ErrorCode::new(202, "v2.prefix"),
+            Self::Version(..) => ErrorCode::new(203, "v2.version"),
+            Self::Command(..) => ErrorCode::new(204, "v2.command"),
+            Self::AddressFamily(..) => ErrorCode::new(205, "v2.address_family"),
+            Self::Protocol(..) => ErrorCode::new(206, "v2.protocol"),
+            Self::Partial(..) => ErrorCode::new(207, "v2.partial"),
+            Self::InvalidAddresses(..) => ErrorCode::new(208, "v2.invalid_addresses"),
+            Self::InvalidTLV(..) => ErrorCode::new(209, "v2.invalid_tlv"),
+            Self::Leftovers(..) => ErrorCode::new(210, "v2.leftovers"),
+        }
+    }
+}
+
+#[cfg(all(any(feature = "std", feature = "tstd"), feature = "v1", feature = "v2"))]
+impl io::Error {
+    /// The stable code of this error, which is the code of the underlying parse (or captured) error if there is one.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Io(..) => ErrorCode::new(301, "io.io"),
+            Self::V1(error) => error.code(),
+            Self::V2(error) => error.code(),
+            Self::MissingHeader => ErrorCode::new(302, "io.missing_header"),
+            Self::Untrusted => ErrorCode::new(303, "io.untrusted"),
+            Self::Rejected(..) => ErrorCode::new(304, "io.rejected"),
+            Self::Overloaded => ErrorCode::new(305, "io.overloaded"),
+            Self::Captured { error, .. } => error.code(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "v1", feature = "v2"))]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn codes() -> Vec<ErrorCode> {
+        let address = "".parse::<Ipv4Addr>().unwrap_err();
+        let port = "".parse::<u16>().unwrap_err();
+        let utf8 = String::from_utf8(vec![0xFF]).unwrap_err().utf8_error();
+
+        let v1 = vec![
+            v1::ParseError::InvalidPrefix,
+            v1::ParseError::Partial,
+            v1::ParseError::MissingPrefix,
+            v1::ParseError::MissingNewLine,
+            v1::ParseError::MissingProtocol,
+            v1::ParseError::MissingSourceAddress,
+            v1::ParseError::MissingDestinationAddress,
+            v1::ParseError::MissingSourcePort,
+            v1::ParseError::MissingDestinationPort,
+            v1::ParseError::HeaderTooLong,
+            v1::ParseError::InvalidProtocol,
+            v1::ParseError::InvalidSuffix,
+            v1::ParseError::InvalidSourceAddress(address.clone()),
+            v1::ParseError::InvalidDestinationAddress(address),
+            v1::ParseError::InvalidSourcePort(Some(port.clone())),
+            v1::ParseError::InvalidDestinationPort(Some(port)),
+            v1::ParseError::ExtraFields,
+            v1::ParseError::EmptyField,
+            v1::ParseError::ControlCharacter,
+        ];
+        let v2 = vec![
+            v2::ParseError::Incomplete(0),
+            v2::ParseError::Prefix,
+            v2::ParseError::Version(0),
+            v2::ParseError::Command(0),
+            v2::ParseError::AddressFamily(0),
+            v2::ParseError::Protocol(0),
+            v2::ParseError::Partial(0, 0),
+            v2::ParseError::InvalidAddresses(0, 0),
+            v2::ParseError::InvalidTLV(0, 0),
+            v2::ParseError::Leftovers(0),
+        ];
+        let io = vec![
+            io::Error::Io(std::io::ErrorKind::Other.into()),
+            io::Error::MissingHeader,
+            io::Error::Untrusted,
+            io::Error::Rejected(crate::policy::Violation::ZeroPort),
+            io::Error::Overloaded,
+        ];
+
+        v1.iter()
+            .map(v1::ParseError::code)
+            .chain(Some(v1::BinaryParseError::InvalidUtf8(utf8).code()))
+            .chain(v2.iter().map(v2::ParseError::code))
+            .chain(io.iter().map(io::Error::code))
+            .collect()
+    }
+
+    #[test]
+    fn unique_codes() {
+        let codes = codes();
+
+        for (index, code) in codes.iter().enumerate() {
+            for other in &codes[index + 1..] {
+                assert_ne!(code.number, other.number, "{}", code);
+                assert_ne!(code.name, other.name, "{}", code);
+            }
+        }
+    }
+
+    #[test]
+    fn namespaced_codes() {
+        for code in codes() {
+            let namespace = match code.number / 100 {
+                1 => "v1.",
+                2 => "v2.",
+                3 => "io.",
+                _ => panic!("{}", code),
+            };
+
+            assert!(code.name.starts_with(namespace), "{}", code);
+        }
+    }
+
+    #[test]
+    fn wrapped_codes() {
+        let error = v1::BinaryParseError::from(v1::ParseError::InvalidSuffix);
+
+        assert_eq!(error.code(), v1::ParseError::InvalidSuffix.code());
+        assert_eq!(io::Error::from(error).code().number, 112);
+        assert_eq!(io::Error::from(v2::ParseError::Prefix).code().number, 202);
+    }
+}
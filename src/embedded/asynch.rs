@@ -0,0 +1,82 @@
+//! Asynchronous helpers for reading and writing PROXY protocol headers over `embedded-io-async` streams,
+//! such as the sockets of `embassy-net`.
+use crate::prelude::*;
+
+use super::{Error, ProxiedStream};
+use crate::policy::Policy;
+use crate::transport::{advance, READ_SIZE};
+use crate::Header;
+use embedded_io_async::{Read, Write};
+
+impl<S: Read> Read for ProxiedStream<S> {
+    async fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.buffered().is_empty() {
+            self.inner.read(buffer).await
+        } else {
+            Ok(self.read_buffered(buffer))
+        }
+    }
+}
+
+impl<S: Write> Write for ProxiedStream<S> {
+    async fn write(&mut self, buffer: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buffer).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await
+    }
+}
+
+/// Reads a PROXY protocol header of either version from the start of the stream according to the given `Policy`.
+/// Returns the stream, positioned after the header, along with the header if one was present.
+///
+/// Bytes are read from the stream until a header is complete, so some of the payload may be buffered by the returned stream.
+///
+/// The returned future is not cancel-safe: the bytes it has read are buffered inside it,
+/// so dropping it (e.g. on a timeout) loses them and the rest of the stream no longer starts at a header.
+#[allow(clippy::type_complexity)]
+pub async fn read_header<S: Read>(
+    mut stream: S,
+    policy: Policy,
+) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error<S::Error>> {
+    let mut buffer = Vec::new();
+    let header = read_buffered(&mut stream, policy, &mut buffer).await?;
+
+    if let Some(header) = header.as_ref() {
+        buffer.drain(..header.len());
+    }
+
+    Ok((ProxiedStream::new(stream, buffer), header))
+}
+
+/// Reads from the stream into the buffer until a header is complete, or the bytes cannot be a header.
+async fn read_buffered<S: Read>(
+    stream: &mut S,
+    policy: Policy,
+    buffer: &mut Vec<u8>,
+) -> Result<Option<Header<'static>>, Error<S::Error>> {
+    if policy == Policy::Ignore {
+        return Ok(None);
+    }
+
+    let mut chunk = [0; READ_SIZE];
+
+    // Reads into a separate chunk so the buffer only ever holds bytes read from the stream.
+    loop {
+        let read = stream.read(&mut chunk).await.map_err(Error::Io)?;
+
+        buffer.extend_from_slice(&chunk[..read]);
+
+        if let Some(result) = advance(buffer.as_slice(), read, policy) {
+            return result.map(|header| header.map(|header| header.to_owned()));
+        }
+    }
+}
+
+/// Writes an encoded header (e.g. from `v2::FixedBuilder` or `Header::as_bytes`) to the start of the stream,
+/// flushing it so the header is sent before any of the payload.
+pub async fn write_header<S: Write>(stream: &mut S, header: &[u8]) -> Result<(), S::Error> {
+    stream.write_all(header).await?;
+    stream.flush().await
+}
@@ -0,0 +1,229 @@
+//! Helpers for reading and writing PROXY protocol headers over `embedded-io` streams,
+//! for bare-metal and RTOS network stacks without `std::io`.
+//!
+//! Mirrors the `read_header` and `ProxiedStream` of `io`, and only needs `alloc`.
+//! Asynchronous variants over `embedded-io-async` streams are in `asynch`.
+//!
+//! ## Examples
+//! ```rust
+//! use embedded_io::Read;
+//! use ppp::embedded::read_header;
+//! use ppp::policy::Policy;
+//!
+//! let input: &[u8] = b"PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\nHello";
+//! let (mut stream, header) = read_header(input, Policy::Require).unwrap();
+//!
+//! let mut payload = [0; 5];
+//! stream.read_exact(&mut payload).unwrap();
+//!
+//! assert!(header.is_some());
+//! assert_eq!(&payload, b"Hello");
+//! ```
+use crate::prelude::*;
+
+#[cfg(feature = "embedded-io-async")]
+pub mod asynch;
+
+use crate::policy::Policy;
+use crate::transport;
+use crate::Header;
+use embedded_io::{ErrorKind, ErrorType, Read, Write};
+
+pub use crate::transport::Error;
+
+impl<E: embedded_io::Error> embedded_io::Error for Error<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io(error) => error.kind(),
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// A stream positioned after the PROXY protocol header (if any).
+/// Any bytes that were read from the inner stream but are not part of the header are returned first by `read`.
+/// Writes are passed through to the inner stream.
+#[derive(Debug)]
+pub struct ProxiedStream<S> {
+    inner: S,
+    buffer: Vec<u8>,
+    offset: usize,
+}
+
+impl<S> ProxiedStream<S> {
+    /// Creates a new `ProxiedStream` that returns the given buffered bytes before reading from the inner stream.
+    pub fn new(inner: S, buffer: Vec<u8>) -> Self {
+        ProxiedStream {
+            inner,
+            buffer,
+            offset: 0,
+        }
+    }
+
+    /// The bytes read from the inner stream that have not yet been consumed.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer[self.offset..]
+    }
+
+    /// Gets a reference to the inner stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the inner stream.
+    /// Reading directly from the inner stream skips any buffered bytes.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consumes this `ProxiedStream`, returning the inner stream and the bytes that have not yet been consumed.
+    pub fn into_parts(mut self) -> (S, Vec<u8>) {
+        self.buffer.drain(..self.offset);
+
+        (self.inner, self.buffer)
+    }
+
+    /// Copies as many of the buffered bytes as fit into the given buffer, returning how many were copied.
+    fn read_buffered(&mut self, buffer: &mut [u8]) -> usize {
+        let buffered = self.buffered();
+        let length = core::cmp::min(buffered.len(), buffer.len());
+
+        buffer[..length].copy_from_slice(&buffered[..length]);
+        self.offset += length;
+
+        length
+    }
+}
+
+impl<S: ErrorType> ErrorType for ProxiedStream<S> {
+    type Error = S::Error;
+}
+
+impl<S: Read> Read for ProxiedStream<S> {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        if self.buffered().is_empty() {
+            self.inner.read(buffer)
+        } else {
+            Ok(self.read_buffered(buffer))
+        }
+    }
+}
+
+impl<S: Write> Write for ProxiedStream<S> {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.write(buffer)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+}
+
+/// Reads a PROXY protocol header of either version from the start of the stream according to the given `Policy`.
+/// Returns the stream, positioned after the header, along with the header if one was present.
+///
+/// Bytes are read from the stream until a header is complete, so some of the payload may be buffered by the returned stream.
+#[allow(clippy::type_complexity)]
+pub fn read_header<S: Read>(
+    mut stream: S,
+    policy: Policy,
+) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error<S::Error>> {
+    let (header, buffer) = transport::read_with(|buffer| stream.read(buffer), policy)?;
+
+    Ok((ProxiedStream::new(stream, buffer), header))
+}
+
+/// Writes an encoded header (e.g. from `v2::FixedBuilder` or `Header::as_bytes`) to the start of the stream,
+/// flushing it so the header is sent before any of the payload.
+pub fn write_header<S: Write>(stream: &mut S, header: &[u8]) -> Result<(), S::Error> {
+    stream.write_all(header)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    /// A reader that returns a single byte per call to `read`.
+    struct OneByte<'a>(&'a [u8]);
+
+    impl ErrorType for OneByte<'_> {
+        type Error = Infallible;
+    }
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Infallible> {
+            match (self.0.split_first(), buffer.first_mut()) {
+                (Some((byte, rest)), Some(first)) => {
+                    *first = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    const V2: &[u8] =
+        b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB";
+
+    #[test]
+    fn one_byte_at_a_time() {
+        let input = [V2, b"Hello"].concat();
+        let (mut stream, header) = read_header(OneByte(&input), Policy::Require).unwrap();
+        let mut payload = [0; 5];
+
+        assert_eq!(header.unwrap().len(), V2.len());
+        assert!(stream.buffered().is_empty());
+
+        stream.read_exact(&mut payload).unwrap();
+        assert_eq!(&payload, b"Hello");
+    }
+
+    #[test]
+    fn coalesced() {
+        let input: &[u8] = b"PROXY UNKNOWN\r\nHello";
+        let (stream, header) = read_header(input, Policy::Require).unwrap();
+
+        assert_eq!(header.unwrap().as_bytes(), b"PROXY UNKNOWN\r\n");
+        assert_eq!(stream.into_parts().1, b"Hello");
+    }
+
+    #[test]
+    fn errors() {
+        let truncated = &V2[..16];
+
+        assert!(matches!(
+            read_header(truncated, Policy::Require),
+            Err(Error::UnexpectedEof)
+        ));
+        assert!(matches!(
+            read_header(&b"GET / HTTP/1.1\r\n"[..], Policy::Require),
+            Err(Error::MissingHeader)
+        ));
+        assert!(matches!(
+            read_header(
+                &b"PROXY TCP4 127.0.0.1 127.0.0.1 80 65536\r\n"[..],
+                Policy::Require
+            ),
+            Err(Error::V1(..))
+        ));
+
+        let (stream, header) = read_header(&b"GET / HTTP/1.1\r\n"[..], Policy::Optional).unwrap();
+        assert!(header.is_none());
+        assert_eq!(stream.buffered(), b"GET / HTTP/1.1\r\n");
+    }
+
+    #[test]
+    fn write() {
+        let mut buffer = [0; 64];
+        let mut stream = ProxiedStream::new(&mut buffer[..], Vec::new());
+
+        write_header(&mut stream, V2).unwrap();
+        stream.write_all(b"Hello").unwrap();
+
+        assert_eq!(&buffer[..V2.len()], V2);
+        assert_eq!(&buffer[V2.len()..V2.len() + 5], b"Hello");
+    }
+}
@@ -0,0 +1,285 @@
+//! Hexadecimal and base64 forms of headers, for embedding them in log lines, bug reports and test fixtures.
+//!
+//! The methods of `Header` encode and decode whole headers; the functions of this module do the same for any bytes,
+//! e.g. a header followed by a payload, or a TLV value.
+use crate::prelude::*;
+
+use crate::{v1, v2, Header, HeaderResult};
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// An error in decoding a header from its hexadecimal or base64 form.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum DecodeError {
+    #[error("Hexadecimal input has an odd number of digits.")]
+    OddLength,
+    #[error("Invalid hexadecimal digit '{0}'.")]
+    InvalidHex(char),
+    #[error("Invalid base64 character '{0}'.")]
+    InvalidBase64(char),
+    #[error("Base64 input has invalid padding.")]
+    InvalidPadding,
+    #[error("Decoded input contains {0} bytes after the header.")]
+    Leftovers(usize),
+    #[error(transparent)]
+    V1(#[from] v1::BinaryParseError),
+    #[error(transparent)]
+    V2(#[from] v2::ParseError),
+}
+
+/// The given bytes as lowercase hexadecimal.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The given bytes in padded, standard base64.
+pub fn to_base64(bytes: &[u8]) -> String {
+    let mut output = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64[(bits >> (18 - 6 * i)) as usize & 0x3F] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+/// Decodes bytes from hexadecimal in either case. Whitespace is ignored.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::encoding::{from_hex, DecodeError};
+///
+/// assert_eq!(from_hex("0d0A 20\n00"), Ok(vec![0x0D, 0x0A, 0x20, 0x00]));
+/// assert_eq!(from_hex("0d0"), Err(DecodeError::OddLength));
+/// ```
+pub fn from_hex(input: &str) -> Result<Vec<u8>, DecodeError> {
+    let digits = input
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| {
+            c.to_digit(16)
+                .map(|digit| digit as u8)
+                .ok_or(DecodeError::InvalidHex(c))
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+
+    if digits.len() % 2 != 0 {
+        return Err(DecodeError::OddLength);
+    }
+
+    Ok(digits
+        .chunks(2)
+        .map(|pair| pair[0] << 4 | pair[1])
+        .collect())
+}
+
+/// Decodes bytes from standard base64, with or without padding. Whitespace is ignored.
+/// Padding must only appear at the end and complete the last group of 4 characters,
+/// and the unused bits of the last character must be zero.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::encoding::{from_base64, DecodeError};
+///
+/// assert_eq!(from_base64("Zm8="), Ok(b"fo".to_vec()));
+/// assert_eq!(from_base64("Zm8"), Ok(b"fo".to_vec()));
+/// assert_eq!(from_base64("Zm=8"), Err(DecodeError::InvalidPadding));
+/// ```
+pub fn from_base64(input: &str) -> Result<Vec<u8>, DecodeError> {
+    let symbols: Vec<char> = input.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    let data = symbols
+        .iter()
+        .position(|&c| c == '=')
+        .unwrap_or(symbols.len());
+    let padding = symbols.len() - data;
+
+    if data % 4 == 1
+        || padding > 2
+        || (padding > 0 && symbols.len() % 4 != 0)
+        || symbols[data..].iter().any(|&c| c != '=')
+    {
+        return Err(DecodeError::InvalidPadding);
+    }
+
+    let mut bytes = Vec::with_capacity(data * 3 / 4);
+    let mut bits = 0u32;
+    let mut length = 0;
+
+    for &c in &symbols[..data] {
+        let value = BASE64
+            .iter()
+            .position(|&b| b as char == c)
+            .ok_or(DecodeError::InvalidBase64(c))?;
+
+        bits = (bits << 6) | value as u32;
+        length += 6;
+
+        if length >= 8 {
+            length -= 8;
+            bytes.push((bits >> length) as u8);
+        }
+    }
+
+    if bits & ((1 << length) - 1) != 0 {
+        return Err(DecodeError::InvalidPadding);
+    }
+
+    Ok(bytes)
+}
+
+impl<'a> Header<'a> {
+    /// The bytes of this `Header` as lowercase hexadecimal.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::{Header, v1};
+    ///
+    /// let header: Header = v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap().into();
+    ///
+    /// assert_eq!(header.to_hex(), "50524f585920554e4b4e4f574e0d0a");
+    /// assert_eq!(Header::from_hex(&header.to_hex()), Ok(header.to_owned()));
+    /// ```
+    pub fn to_hex(&self) -> String {
+        to_hex(self.as_bytes())
+    }
+
+    /// The bytes of this `Header` in padded, standard base64.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::{Header, v1};
+    ///
+    /// let header: Header = v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap().into();
+    ///
+    /// assert_eq!(header.to_base64(), "UFJPWFkgVU5LTk9XTg0K");
+    /// assert_eq!(Header::from_base64(&header.to_base64()), Ok(header.to_owned()));
+    /// ```
+    pub fn to_base64(&self) -> String {
+        to_base64(self.as_bytes())
+    }
+}
+
+impl Header<'static> {
+    /// Decodes a header of either version from hexadecimal in either case.
+    /// Whitespace is ignored, and the decoded bytes must hold exactly one header.
+    pub fn from_hex(input: &str) -> Result<Self, DecodeError> {
+        decode(&from_hex(input)?)
+    }
+
+    /// Decodes a header of either version from standard base64, with or without padding.
+    /// Whitespace is ignored, and the decoded bytes must hold exactly one header.
+    pub fn from_base64(input: &str) -> Result<Self, DecodeError> {
+        decode(&from_base64(input)?)
+    }
+}
+
+/// Parses decoded bytes that must hold exactly one header.
+fn decode(bytes: &[u8]) -> Result<Header<'static>, DecodeError> {
+    let header: Header = match HeaderResult::parse(bytes) {
+        HeaderResult::V1(header) => header?.into(),
+        HeaderResult::V2(header) => header?.into(),
+    };
+
+    match bytes.len() - header.len() {
+        0 => Ok(header.to_owned()),
+        leftovers => Err(DecodeError::Leftovers(leftovers)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::VECTORS;
+
+    #[test]
+    fn bytes_round_trip() {
+        for input in [
+            &b""[..],
+            b"f",
+            b"fo",
+            b"foo",
+            b"foob",
+            b"\r\n\r\n\0\r\nQUIT\n",
+        ] {
+            assert_eq!(from_hex(&to_hex(input)).unwrap(), input);
+            assert_eq!(from_base64(&to_base64(input)).unwrap(), input);
+        }
+
+        assert_eq!(to_base64(b"fo"), "Zm8=");
+        assert_eq!(from_base64("Zg"), Ok(b"f".to_vec()));
+        assert_eq!(from_base64("Zm9v\nYg=="), Ok(b"foob".to_vec()));
+    }
+
+    #[test]
+    fn malformed_base64() {
+        for input in [
+            "Z", "Zm9vY", "Zg=", "Zg===", "=Zg=", "Zm=8", "Zg==Zg==", "Zh==",
+        ] {
+            assert_eq!(
+                from_base64(input),
+                Err(DecodeError::InvalidPadding),
+                "{}",
+                input
+            );
+        }
+
+        assert_eq!(from_base64("Zm-v"), Err(DecodeError::InvalidBase64('-')));
+    }
+
+    #[test]
+    fn round_trip() {
+        for vector in VECTORS.iter().filter(|vector| vector.valid) {
+            let header = match HeaderResult::parse(vector.bytes) {
+                HeaderResult::V1(header) => Header::from(header.unwrap()),
+                HeaderResult::V2(header) => Header::from(header.unwrap()),
+            };
+
+            assert_eq!(Header::from_hex(&header.to_hex()), Ok(header.to_owned()));
+            assert_eq!(
+                Header::from_base64(&header.to_base64()),
+                Ok(header.to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(
+            Header::from_hex("50 52 4F 58 59 20 55 4E 4B 4E 4F 57 4E 0D 0A").map(|h| h.len()),
+            Ok(15)
+        );
+        assert_eq!(
+            Header::from_base64("UFJPWFkgVU5LTk9XTg0K\n").map(|h| h.len()),
+            Ok(15)
+        );
+        assert_eq!(Header::from_hex("505"), Err(DecodeError::OddLength));
+        assert_eq!(Header::from_hex("5g"), Err(DecodeError::InvalidHex('g')));
+        assert_eq!(
+            Header::from_base64("UF*P"),
+            Err(DecodeError::InvalidBase64('*'))
+        );
+        assert_eq!(
+            Header::from_base64("UFJPWFkgVU5LTk9XTg0K="),
+            Err(DecodeError::InvalidPadding)
+        );
+        assert_eq!(
+            Header::from_hex("50524f585920554e4b4e4f574e0d0a00"),
+            Err(DecodeError::Leftovers(1))
+        );
+        assert_eq!(
+            Header::from_hex("0d0a0d0a000d0a515549540a"),
+            Err(DecodeError::V2(v2::ParseError::Incomplete(12)))
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! Annotated breakdowns of PROXY protocol headers for logs and support tooling.
+use crate::prelude::*;
+
+use crate::net::{Ipv4Addr, Ipv6Addr};
+use crate::v2::field;
+use crate::{sniff, v1, v2, Sniff};
+use core::fmt::Write;
+
+/// The number of bytes shown on each line of an explanation.
+const BYTES_PER_LINE: usize = 16;
+
+/// The labels of the fields of a text header, in order.
+const V1_FIELDS: [&str; 6] = [
+    "prefix",
+    "protocol",
+    "source address",
+    "destination address",
+    "source port",
+    "destination port",
+];
+
+/// Explains the bytes of a PROXY protocol header, one field per line, labelled with its offset.
+/// Malformed and truncated headers are explained up to the first problem, which is described on the last line.
+///
+/// ## Examples
+/// ```rust
+/// let explanation = ppp::explain(b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00");
+///
+/// assert_eq!(
+///     explanation,
+///     "0000  0d 0a 0d 0a 00 0d 0a 51 55 49 54 0a              prefix\n\
+///      000c  20                                               version 2, command LOCAL\n\
+///      000d  00                                               address family UNSPEC, protocol UNSPEC\n\
+///      000e  00 00                                            length 0\n"
+/// );
+/// ```
+pub fn explain(input: &[u8]) -> String {
+    let mut explanation = Explanation::default();
+
+    match sniff(input) {
+        Sniff::ProxyV1 => explanation.v1(input),
+        Sniff::ProxyV2 => explanation.v2(input),
+        Sniff::Incomplete => explanation.problem(format!(
+            "{} bytes are too few to identify a header",
+            input.len()
+        )),
+        sniff => {
+            let label = match sniff {
+                Sniff::TlsClientHello => "TLS ClientHello",
+                Sniff::Http => "HTTP request",
+                _ => "unknown",
+            };
+
+            explanation.field(0, &input[..input.len().min(BYTES_PER_LINE)], label);
+            explanation.problem("not a PROXY protocol header".to_string());
+        }
+    }
+
+    explanation.output
+}
+
+/// An explanation under construction.
+#[derive(Debug, Default)]
+struct Explanation {
+    output: String,
+}
+
+impl Explanation {
+    /// Writes a field, wrapping long fields over multiple lines.
+    fn field(&mut self, offset: usize, bytes: &[u8], label: &str) {
+        let mut label = label;
+        let chunks: Vec<&[u8]> = if bytes.is_empty() {
+            vec![bytes]
+        } else {
+            bytes.chunks(BYTES_PER_LINE).collect()
+        };
+
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let line = format!(
+                "{:04x}  {:<width$}  {}",
+                offset + index * BYTES_PER_LINE,
+                hex.join(" "),
+                label,
+                width = BYTES_PER_LINE * 3 - 1
+            );
+
+            writeln!(self.output, "{}", line.trim_end()).ok();
+            label = "";
+        }
+    }
+
+    /// Writes a description of a problem with the header.
+    fn problem(&mut self, description: String) {
+        writeln!(self.output, "error: {}", description).ok();
+    }
+
+    fn v1(&mut self, input: &[u8]) {
+        let end = input
+            .windows(2)
+            .take(v1::MAX_LENGTH - 1)
+            .position(|window| window == b"\r\n");
+        let line = &input[..end.unwrap_or_else(|| input.len().min(v1::MAX_LENGTH))];
+        let mut offset = 0;
+        let mut unknown = false;
+
+        for (index, token) in line.split(|&b| b == b' ').enumerate() {
+            let text = String::from_utf8_lossy(token);
+            let label = match V1_FIELDS.get(index) {
+                _ if unknown => format!("ignored {:?}", text),
+                Some(field) => format!("{} {:?}", field, text),
+                None => format!("unexpected {:?}", text),
+            };
+
+            unknown |= index == 1 && token == v1::UNKNOWN.as_bytes();
+            self.field(offset, token, label.as_str());
+            offset += token.len() + 1;
+        }
+
+        match end {
+            Some(end) => self.field(end, b"\r\n", "terminator"),
+            None if input.len() >= v1::MAX_LENGTH => {
+                self.problem(format!("no terminator within {} bytes", v1::MAX_LENGTH))
+            }
+            None => self.problem("truncated before the terminator".to_string()),
+        }
+    }
+
+    fn v2(&mut self, input: &[u8]) {
+        let prefix = v2::PROTOCOL_PREFIX.len();
+
+        self.field(0, &input[..prefix], "prefix");
+
+        let header = match input.get(prefix..prefix + 4) {
+            Some(header) => header,
+            None => {
+                return self.problem(format!(
+                    "truncated after {} bytes, before the length",
+                    input.len()
+                ))
+            }
+        };
+
+        let version = header[0] >> 4;
+        let command = match header[0] & 0x0F {
+            0 => "LOCAL",
+            1 => "PROXY",
+            _ => "unknown",
+        };
+        let family = header[1] >> 4;
+        let protocol = match header[1] & 0x0F {
+            0 => "UNSPEC",
+            1 => "STREAM",
+            2 => "DGRAM",
+            _ => "unknown",
+        };
+        let (family_name, address_length) = match family {
+            0 => ("UNSPEC", 0),
+            1 => ("INET", 12),
+            2 => ("INET6", 36),
+            3 => ("UNIX", 216),
+            _ => ("unknown", 0),
+        };
+        let length = field::read_u16(header, 2) as usize;
+
+        self.field(
+            prefix,
+            &header[..1],
+            &format!("version {}, command {}", version, command),
+        );
+        self.field(
+            prefix + 1,
+            &header[1..2],
+            &format!("address family {}, protocol {}", family_name, protocol),
+        );
+        self.field(prefix + 2, &header[2..], &format!("length {}", length));
+
+        let start = prefix + 4;
+        let end = start + length;
+        let payload = &input[start..input.len().min(end)];
+
+        if length < address_length {
+            return self.problem(format!(
+                "length {} is too short for the {} bytes of {} addresses",
+                length, address_length, family_name
+            ));
+        }
+
+        if payload.len() >= address_length {
+            self.addresses(start, &payload[..address_length], family);
+            self.tlvs(start + address_length, &payload[address_length..]);
+        }
+
+        if input.len() < end {
+            self.problem(format!(
+                "truncated: length {} requires {} bytes but only {} are present",
+                length,
+                end,
+                input.len()
+            ));
+        }
+    }
+
+    fn addresses(&mut self, offset: usize, addresses: &[u8], family: u8) {
+        let port = |bytes: &[u8]| field::read_u16(bytes, 0);
+
+        match family {
+            1 => {
+                let address = |bytes: &[u8]| Ipv4Addr::from(field::array::<4>(bytes, 0));
+
+                self.field(
+                    offset,
+                    &addresses[..4],
+                    &format!("source address {}", address(&addresses[..4])),
+                );
+                self.field(
+                    offset + 4,
+                    &addresses[4..8],
+                    &format!("destination address {}", address(&addresses[4..8])),
+                );
+                self.field(
+                    offset + 8,
+                    &addresses[8..10],
+                    &format!("source port {}", port(&addresses[8..10])),
+                );
+                self.field(
+                    offset + 10,
+                    &addresses[10..],
+                    &format!("destination port {}", port(&addresses[10..])),
+                );
+            }
+            2 => {
+                let address = |bytes: &[u8]| Ipv6Addr::from(field::array::<16>(bytes, 0));
+
+                self.field(
+                    offset,
+                    &addresses[..16],
+                    &format!("source address {}", address(&addresses[..16])),
+                );
+                self.field(
+                    offset + 16,
+                    &addresses[16..32],
+                    &format!("destination address {}", address(&addresses[16..32])),
+                );
+                self.field(
+                    offset + 32,
+                    &addresses[32..34],
+                    &format!("source port {}", port(&addresses[32..34])),
+                );
+                self.field(
+                    offset + 34,
+                    &addresses[34..],
+                    &format!("destination port {}", port(&addresses[34..])),
+                );
+            }
+            3 => {
+                let unix = v2::Unix::new(
+                    field::array::<108>(addresses, 0),
+                    field::array::<108>(addresses, 108),
+                );
+                let path = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+
+                self.field(
+                    offset,
+                    &addresses[..108],
+                    &format!("source address {:?}", path(unix.source_path())),
+                );
+                self.field(
+                    offset + 108,
+                    &addresses[108..],
+                    &format!("destination address {:?}", path(unix.destination_path())),
+                );
+            }
+            _ => (),
+        }
+    }
+
+    fn tlvs(&mut self, offset: usize, tlvs: &[u8]) {
+        let mut position = 0;
+
+        while position < tlvs.len() {
+            let remaining = &tlvs[position..];
+
+            if remaining.len() < 3 {
+                self.field(offset + position, remaining, "leftover bytes");
+                return self.problem(format!("{} bytes are too few for a TLV", remaining.len()));
+            }
+
+            let kind = remaining[0];
+            let length = field::read_u16(remaining, 1) as usize;
+
+            self.field(
+                offset + position,
+                &remaining[..3],
+                &format!("TLV {} ({:#04x}), length {}", tlv_name(kind), kind, length),
+            );
+
+            let value = match remaining.get(3..3 + length) {
+                Some(value) => value,
+                None => {
+                    return self.problem(format!(
+                        "TLV length {} exceeds the remaining {} bytes",
+                        length,
+                        remaining.len() - 3
+                    ))
+                }
+            };
+            let value_offset = offset + position + 3;
+
+            if kind == u8::from(v2::Type::SSL) && value.len() >= 5 {
+                let verify = field::read_u32(value, 1);
+
+                self.field(
+                    value_offset,
+                    &value[..1],
+                    &format!("client flags {:#04x}", value[0]),
+                );
+                self.field(
+                    value_offset + 1,
+                    &value[1..5],
+                    &format!("verify {}", verify),
+                );
+                self.tlvs(value_offset + 5, &value[5..]);
+            } else if !value.is_empty() {
+                self.field(value_offset, value, &describe_value(value));
+            }
+
+            position += 3 + length;
+        }
+    }
+}
+
+/// Describes a TLV value as text when it is printable.
+fn describe_value(value: &[u8]) -> String {
+    match core::str::from_utf8(value) {
+        Ok(text) if !text.is_empty() && !text.chars().any(char::is_control) => {
+            format!("value {:?}", text)
+        }
+        _ => "value".to_string(),
+    }
+}
+
+/// The name of a TLV type defined by the specification.
+fn tlv_name(kind: u8) -> &'static str {
+    match kind {
+        0x01 => "ALPN",
+        0x02 => "AUTHORITY",
+        0x03 => "CRC32C",
+        0x04 => "NOOP",
+        0x05 => "UNIQUE_ID",
+        0x20 => "SSL",
+        0x21 => "SSL_VERSION",
+        0x22 => "SSL_CN",
+        0x23 => "SSL_CIPHER",
+        0x24 => "SSL_SIG_ALG",
+        0x25 => "SSL_KEY_ALG",
+        0x30 => "NETNS",
+        0xE0..=0xEF => "custom",
+        0xF0..=0xF7 => "experimental",
+        _ => "unassigned",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Command, Protocol, Type};
+
+    #[test]
+    fn explain_v1() {
+        assert_eq!(
+            explain(b"PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\nGET"),
+            "0000  50 52 4f 58 59                                   prefix \"PROXY\"\n\
+             0006  54 43 50 34                                      protocol \"TCP4\"\n\
+             000b  31 32 37 2e 30 2e 30 2e 31                       source address \"127.0.0.1\"\n\
+             0015  31 39 32 2e 31 36 38 2e 31 2e 31                 destination address \"192.168.1.1\"\n\
+             0021  38 30                                            source port \"80\"\n\
+             0024  34 34 33                                         destination port \"443\"\n\
+             0027  0d 0a                                            terminator\n"
+        );
+    }
+
+    #[test]
+    fn explain_v1_unknown() {
+        let explanation = explain(b"PROXY UNKNOWN ::1 ::1 80 443\r\n");
+
+        assert!(explanation.contains("protocol \"UNKNOWN\""));
+        assert!(explanation.contains("ignored \"::1\""));
+    }
+
+    #[test]
+    fn explain_v1_unterminated() {
+        let explanation = explain(b"PROXY TCP4 127.0.0.1");
+
+        assert!(explanation.ends_with("error: truncated before the terminator\n"));
+    }
+
+    #[test]
+    fn explain_v2() {
+        let header = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            v2::IPv4::new([127, 0, 0, 1], [192, 168, 1, 1], 80, 443),
+        )
+        .write_tlv(Type::Authority, b"example.com")
+        .unwrap()
+        .build()
+        .unwrap();
+
+        assert_eq!(
+            explain(&header),
+            "0000  0d 0a 0d 0a 00 0d 0a 51 55 49 54 0a              prefix\n\
+             000c  21                                               version 2, command PROXY\n\
+             000d  11                                               address family INET, protocol STREAM\n\
+             000e  00 1a                                            length 26\n\
+             0010  7f 00 00 01                                      source address 127.0.0.1\n\
+             0014  c0 a8 01 01                                      destination address 192.168.1.1\n\
+             0018  00 50                                            source port 80\n\
+             001a  01 bb                                            destination port 443\n\
+             001c  02 00 0b                                         TLV AUTHORITY (0x02), length 11\n\
+             001f  65 78 61 6d 70 6c 65 2e 63 6f 6d                 value \"example.com\"\n"
+        );
+    }
+
+    #[test]
+    fn explain_v2_empty_tlv() {
+        let header = Builder::unspecified(Command::Local, Protocol::Unspecified)
+            .write_tlv(Type::NoOp, &[])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert!(explain(&header).ends_with(
+            "0010  04 00 00                                         TLV NOOP (0x04), length 0\n"
+        ));
+    }
+
+    #[test]
+    fn explain_v2_ssl() {
+        let explanation = explain(
+            crate::vectors::VECTORS
+                .iter()
+                .find(|v| v.name == "haproxy-v2-tcp4-ssl")
+                .unwrap()
+                .bytes,
+        );
+
+        assert!(explanation.contains("client flags 0x07"));
+        assert!(explanation.contains("TLV SSL_CN (0x22), length 18"));
+        assert!(explanation.contains("value \"client.example.com\""));
+    }
+
+    #[test]
+    fn explain_v2_truncated() {
+        let explanation = explain(b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00");
+
+        assert!(explanation
+            .ends_with("error: truncated: length 12 requires 28 bytes but only 18 are present\n"));
+    }
+
+    #[test]
+    fn explain_unknown() {
+        assert_eq!(
+            explain(b"GET / HTTP/1.1\r\n"),
+            "0000  47 45 54 20 2f 20 48 54 54 50 2f 31 2e 31 0d 0a  HTTP request\n\
+             error: not a PROXY protocol header\n"
+        );
+        assert_eq!(
+            explain(b"PRO"),
+            "error: 3 bytes are too few to identify a header\n"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! A typed map of per-connection values, for handing the parsed header to the middleware of HTTP frameworks.
+//!
+//! Frameworks conventionally look up connection information by type in a map of extensions (e.g. `http::Extensions`).
+//! `Header::insert_proxy_info` stores a `ProxyInfo` in any `ExtensionMap`, so the same code serves `Extensions`
+//! and, with the `http` feature, `http::Extensions`.
+use crate::prelude::*;
+
+use crate::{Header, ProxyInfo};
+use core::any::{Any, TypeId};
+use core::fmt;
+
+/// A map that holds at most one value of each type, like `http::Extensions`, which can store values of type `T`.
+/// `Extensions` stores values of any type, while `http::Extensions` requires them to be `Clone`.
+pub trait ExtensionMap<T: Send + Sync + 'static> {
+    /// Inserts a value, returning the previous value of the same type if there was one.
+    fn insert(&mut self, value: T) -> Option<T>;
+
+    /// Gets a reference to the value of this type, if there is one.
+    fn get(&self) -> Option<&T>;
+}
+
+/// A minimal typed map for servers that do not use the `http` crate.
+/// Holds few values, so lookups scan a `Vec` rather than hashing.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::extensions::Extensions;
+///
+/// let mut extensions = Extensions::new();
+///
+/// assert_eq!(extensions.insert(5u16), None);
+/// assert_eq!(extensions.insert(8u16), Some(5));
+/// assert_eq!(extensions.get::<u16>(), Some(&8));
+/// assert_eq!(extensions.get::<u32>(), None);
+/// assert_eq!(extensions.remove::<u16>(), Some(8));
+/// assert!(extensions.is_empty());
+/// ```
+#[derive(Default)]
+pub struct Extensions {
+    values: Vec<(TypeId, Box<dyn Any + Send + Sync>)>,
+}
+
+impl Extensions {
+    /// Creates an empty `Extensions`.
+    pub fn new() -> Self {
+        Extensions::default()
+    }
+
+    /// Inserts a value, returning the previous value of the same type if there was one.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        let previous = self.remove::<T>();
+
+        self.values.push((TypeId::of::<T>(), Box::new(value)));
+
+        previous
+    }
+
+    /// Gets a reference to the value of the given type, if there is one.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.values
+            .iter()
+            .find(|(id, _)| *id == TypeId::of::<T>())
+            .and_then(|(_, value)| value.downcast_ref())
+    }
+
+    /// Gets a mutable reference to the value of the given type, if there is one.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.values
+            .iter_mut()
+            .find(|(id, _)| *id == TypeId::of::<T>())
+            .and_then(|(_, value)| value.downcast_mut())
+    }
+
+    /// Removes and returns the value of the given type, if there is one.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        let index = self
+            .values
+            .iter()
+            .position(|(id, _)| *id == TypeId::of::<T>())?;
+
+        self.values
+            .swap_remove(index)
+            .1
+            .downcast()
+            .ok()
+            .map(|value| *value)
+    }
+
+    /// The number of values in the map.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Tests whether the map has no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Removes all values from the map.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+impl<T: Send + Sync + 'static> ExtensionMap<T> for Extensions {
+    fn insert(&mut self, value: T) -> Option<T> {
+        Extensions::insert(self, value)
+    }
+
+    fn get(&self) -> Option<&T> {
+        Extensions::get(self)
+    }
+}
+
+#[cfg(feature = "http")]
+impl<T: Clone + Send + Sync + 'static> ExtensionMap<T> for http::Extensions {
+    fn insert(&mut self, value: T) -> Option<T> {
+        http::Extensions::insert(self, value)
+    }
+
+    fn get(&self) -> Option<&T> {
+        http::Extensions::get(self)
+    }
+}
+
+impl<'a> Header<'a> {
+    /// Stores the `ProxyInfo` of this header in the given map of extensions, where middleware can look it up by type.
+    /// Returns the `ProxyInfo` that was already stored, if any.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::extensions::{ExtensionMap, Extensions};
+    /// use ppp::{v1, Header, ProxyInfo};
+    /// use std::net::SocketAddr;
+    ///
+    /// fn client<E: ExtensionMap<ProxyInfo>>(extensions: &E) -> Option<SocketAddr> {
+    ///     extensions.get()?.source
+    /// }
+    ///
+    /// let header = Header::V1(v1::Header::try_from("PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\n").unwrap());
+    /// let mut extensions = Extensions::new();
+    ///
+    /// header.insert_proxy_info(&mut extensions);
+    ///
+    /// assert_eq!(client(&extensions), Some("127.0.0.1:80".parse().unwrap()));
+    /// ```
+    pub fn insert_proxy_info<E: ExtensionMap<ProxyInfo> + ?Sized>(
+        &self,
+        extensions: &mut E,
+    ) -> Option<ProxyInfo> {
+        extensions.insert(ProxyInfo::from(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{v1, v2};
+
+    #[test]
+    fn typed_values() {
+        let mut extensions = Extensions::new();
+
+        extensions.insert("text");
+        extensions.insert(1u8);
+
+        *extensions.get_mut::<u8>().unwrap() += 1;
+
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions.get::<&str>(), Some(&"text"));
+        assert_eq!(extensions.remove::<u8>(), Some(2));
+        assert_eq!(extensions.remove::<u8>(), None);
+
+        extensions.clear();
+
+        assert!(extensions.is_empty());
+    }
+
+    #[test]
+    fn values_without_clone() {
+        #[derive(Debug, PartialEq)]
+        struct Connection(u8);
+
+        fn store<E: ExtensionMap<Connection>>(extensions: &mut E, id: u8) -> Option<Connection> {
+            extensions.insert(Connection(id))
+        }
+
+        let mut extensions = Extensions::new();
+
+        assert_eq!(store(&mut extensions, 1), None);
+        assert_eq!(store(&mut extensions, 2), Some(Connection(1)));
+        assert_eq!(
+            ExtensionMap::<Connection>::get(&extensions),
+            Some(&Connection(2))
+        );
+    }
+
+    #[test]
+    fn replace_proxy_info() {
+        let text = Header::V1(v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap());
+        let input =
+            b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\xC0\xA8\x01\x01\x00\x50\x01\xBB";
+        let binary = Header::V2(v2::Header::try_from(&input[..]).unwrap());
+        let mut extensions = Extensions::new();
+
+        assert_eq!(text.insert_proxy_info(&mut extensions), None);
+        assert_eq!(
+            binary.insert_proxy_info(&mut extensions),
+            Some(ProxyInfo::from(&text))
+        );
+        assert_eq!(
+            extensions.get::<ProxyInfo>(),
+            Some(&ProxyInfo::from(&binary))
+        );
+        assert_eq!(extensions.len(), 1);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn http_extensions() {
+        let header = Header::V1(v1::Header::try_from("PROXY TCP6 ::1 ::2 80 443\r\n").unwrap());
+        let mut extensions = http::Extensions::new();
+
+        assert_eq!(header.insert_proxy_info(&mut extensions), None);
+        assert_eq!(
+            extensions
+                .get::<ProxyInfo>()
+                .and_then(|info| info.destination),
+            "[::2]:443".parse().ok()
+        );
+    }
+}
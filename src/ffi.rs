@@ -0,0 +1,547 @@
+//! A C interface for parsing and encoding headers of both versions, so proxies written in C or C++ can reuse this parser.
+//!
+//! Build a shared library with `cargo rustc --release --features ffi --crate-type cdylib`,
+//! then generate its C header with `cbindgen --config cbindgen.toml --output ppp.h`.
+//!
+//! Headers are exchanged as a flat `PppHeader`, whose fields use the numeric values of the binary protocol.
+//! Parsing never copies the TLVs; they are borrowed from the input, and iterated with `ppp_tlvs` and `ppp_tlv_next`.
+use std::prelude::v1::*;
+
+use crate::ip::{IPv4, IPv6};
+use crate::v2::{Builder, Command, Protocol, TypeLengthValues, Unix, Version, MINIMUM_TLV_LENGTH};
+use crate::{v1, v2, HeaderResult, PartialResult};
+use std::slice;
+
+/// The operation succeeded.
+pub const PPP_OK: i32 = 0;
+/// The input is a valid prefix of a header, but more bytes are needed to parse it.
+pub const PPP_INCOMPLETE: i32 = -1;
+/// A required pointer was null.
+pub const PPP_NULL: i32 = -2;
+/// The buffer is too small to hold the encoded header.
+pub const PPP_CAPACITY: i32 = -3;
+/// The header has a field value that cannot be encoded in its version, or more TLVs than a binary header can hold.
+pub const PPP_INVALID: i32 = -4;
+
+/// The length of the address fields, which can hold the addresses of any address family.
+pub const PPP_ADDRESS_LENGTH: usize = 108;
+
+/// The version 1 value of `PppHeader::version`.
+pub const PPP_VERSION_1: u8 = 1;
+/// The version 2 value of `PppHeader::version`.
+pub const PPP_VERSION_2: u8 = 2;
+
+/// The `LOCAL` value of `PppHeader::command`.
+pub const PPP_COMMAND_LOCAL: u8 = 0;
+/// The `PROXY` value of `PppHeader::command`.
+pub const PPP_COMMAND_PROXY: u8 = 1;
+
+/// The unspecified value of `PppHeader::address_family`, as in `UNKNOWN` text headers.
+pub const PPP_FAMILY_UNSPECIFIED: u8 = 0;
+/// The IPv4 value of `PppHeader::address_family`, whose addresses are the first 4 bytes of each address field.
+pub const PPP_FAMILY_IPV4: u8 = 1;
+/// The IPv6 value of `PppHeader::address_family`, whose addresses are the first 16 bytes of each address field.
+pub const PPP_FAMILY_IPV6: u8 = 2;
+/// The unix socket value of `PppHeader::address_family`, whose addresses are the whole address fields.
+pub const PPP_FAMILY_UNIX: u8 = 3;
+
+/// The unspecified value of `PppHeader::protocol`.
+pub const PPP_PROTOCOL_UNSPECIFIED: u8 = 0;
+/// The stream (i.e. TCP) value of `PppHeader::protocol`.
+pub const PPP_PROTOCOL_STREAM: u8 = 1;
+/// The datagram (i.e. UDP) value of `PppHeader::protocol`.
+pub const PPP_PROTOCOL_DATAGRAM: u8 = 2;
+
+/// A header of either version as a flat C struct.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PppHeader {
+    /// One of the `PPP_VERSION_*` values.
+    pub version: u8,
+    /// One of the `PPP_COMMAND_*` values. Text headers always use `PPP_COMMAND_PROXY`.
+    pub command: u8,
+    /// One of the `PPP_FAMILY_*` values.
+    pub address_family: u8,
+    /// One of the `PPP_PROTOCOL_*` values. Text headers use `PPP_PROTOCOL_STREAM` unless they are `UNKNOWN`.
+    pub protocol: u8,
+    /// The source address, in network byte order.
+    pub source_address: [u8; PPP_ADDRESS_LENGTH],
+    /// The destination address, in network byte order.
+    pub destination_address: [u8; PPP_ADDRESS_LENGTH],
+    /// The source port.
+    pub source_port: u16,
+    /// The destination port.
+    pub destination_port: u16,
+    /// The TLVs of a binary header, borrowed from the parsed input (or the caller's buffer when encoding).
+    /// Null when there are none.
+    pub tlvs: *const u8,
+    /// The length in bytes of the TLVs.
+    pub tlvs_length: usize,
+    /// The length in bytes of the whole header.
+    pub length: usize,
+}
+
+/// A Type-Length-Value of a binary header, borrowed from the parsed input.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PppTlv {
+    /// The type of the TLV.
+    pub kind: u8,
+    /// The value of the TLV.
+    pub value: *const u8,
+    /// The length in bytes of the value.
+    pub length: usize,
+}
+
+/// The TLVs of a header that have not been iterated yet.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PppTlvIterator {
+    /// The first byte of the next TLV.
+    pub next: *const u8,
+    /// The number of bytes left to iterate.
+    pub remaining: usize,
+}
+
+impl Default for PppHeader {
+    fn default() -> Self {
+        PppHeader {
+            version: 0,
+            command: PPP_COMMAND_PROXY,
+            address_family: PPP_FAMILY_UNSPECIFIED,
+            protocol: PPP_PROTOCOL_UNSPECIFIED,
+            source_address: [0; PPP_ADDRESS_LENGTH],
+            destination_address: [0; PPP_ADDRESS_LENGTH],
+            source_port: 0,
+            destination_port: 0,
+            tlvs: std::ptr::null(),
+            tlvs_length: 0,
+            length: 0,
+        }
+    }
+}
+
+impl PppHeader {
+    /// Copies the IP addresses and ports into the address fields.
+    fn set_ip(&mut self, source: &[u8], destination: &[u8], ports: (u16, u16)) {
+        self.source_address[..source.len()].copy_from_slice(source);
+        self.destination_address[..destination.len()].copy_from_slice(destination);
+        self.source_port = ports.0;
+        self.destination_port = ports.1;
+    }
+
+    /// The first `N` bytes of the source and destination address fields.
+    fn addresses<const N: usize>(&self) -> ([u8; N], [u8; N]) {
+        let mut source = [0; N];
+        let mut destination = [0; N];
+
+        source.copy_from_slice(&self.source_address[..N]);
+        destination.copy_from_slice(&self.destination_address[..N]);
+
+        (source, destination)
+    }
+}
+
+impl<'a> From<&v1::Header<'a>> for PppHeader {
+    fn from(header: &v1::Header<'a>) -> Self {
+        let mut flat = PppHeader {
+            version: PPP_VERSION_1,
+            length: header.header.len(),
+            ..Default::default()
+        };
+
+        match header.addresses {
+            v1::Addresses::Unknown => (),
+            v1::Addresses::Tcp4(a) => {
+                flat.address_family = PPP_FAMILY_IPV4;
+                flat.protocol = PPP_PROTOCOL_STREAM;
+                flat.set_ip(
+                    &a.source_address.octets(),
+                    &a.destination_address.octets(),
+                    (a.source_port, a.destination_port),
+                );
+            }
+            v1::Addresses::Tcp6(a) => {
+                flat.address_family = PPP_FAMILY_IPV6;
+                flat.protocol = PPP_PROTOCOL_STREAM;
+                flat.set_ip(
+                    &a.source_address.octets(),
+                    &a.destination_address.octets(),
+                    (a.source_port, a.destination_port),
+                );
+            }
+        }
+
+        flat
+    }
+}
+
+impl<'a> From<&v2::Header<'a>> for PppHeader {
+    fn from(header: &v2::Header<'a>) -> Self {
+        let tlvs = header.tlv_bytes();
+        let mut flat = PppHeader {
+            version: PPP_VERSION_2,
+            command: header.command as u8,
+            protocol: header.protocol as u8,
+            tlvs: if tlvs.is_empty() {
+                std::ptr::null()
+            } else {
+                tlvs.as_ptr()
+            },
+            tlvs_length: tlvs.len(),
+            length: header.len(),
+            ..Default::default()
+        };
+
+        match header.addresses {
+            v2::Addresses::Unspecified => (),
+            v2::Addresses::IPv4(a) => {
+                flat.address_family = PPP_FAMILY_IPV4;
+                flat.set_ip(
+                    &a.source_address.octets(),
+                    &a.destination_address.octets(),
+                    (a.source_port, a.destination_port),
+                );
+            }
+            v2::Addresses::IPv6(a) => {
+                flat.address_family = PPP_FAMILY_IPV6;
+                flat.set_ip(
+                    &a.source_address.octets(),
+                    &a.destination_address.octets(),
+                    (a.source_port, a.destination_port),
+                );
+            }
+            v2::Addresses::Unix(a) => {
+                flat.address_family = PPP_FAMILY_UNIX;
+                flat.source_address = a.source;
+                flat.destination_address = a.destination;
+            }
+        }
+
+        flat
+    }
+}
+
+/// Parses a header of either version from the start of the input into `header`.
+///
+/// Returns `PPP_OK` when a header was parsed, `PPP_INCOMPLETE` when more bytes are needed,
+/// `PPP_NULL` when a pointer is null, or the (positive) stable error code of the parse error otherwise
+/// (see `ppp::ErrorCode`). `header` is only written to on success.
+///
+/// # Safety
+/// `input` must point to `length` readable bytes, and `header` to a writable `PppHeader`.
+/// The TLVs of the parsed header borrow `input`, so `input` must outlive any use of them.
+#[no_mangle]
+pub unsafe extern "C" fn ppp_parse(input: *const u8, length: usize, header: *mut PppHeader) -> i32 {
+    if input.is_null() || header.is_null() {
+        return PPP_NULL;
+    }
+
+    let result = HeaderResult::parse(slice::from_raw_parts(input, length));
+
+    if result.is_incomplete() {
+        return PPP_INCOMPLETE;
+    }
+
+    match result {
+        HeaderResult::V1(Ok(parsed)) => header.write(PppHeader::from(&parsed)),
+        HeaderResult::V2(Ok(parsed)) => header.write(PppHeader::from(&parsed)),
+        HeaderResult::V1(Err(error)) => return i32::from(error.code().number),
+        HeaderResult::V2(Err(error)) => return i32::from(error.code().number),
+    }
+
+    PPP_OK
+}
+
+/// Encodes the header into the start of `buffer`, including any TLVs of a binary header.
+///
+/// Returns the length in bytes of the encoded header, `PPP_NULL` when a pointer is null,
+/// `PPP_INVALID` when a field cannot be encoded in the header's version (e.g. a unix address in a text header)
+/// or the TLVs do not fit in a binary header, or `PPP_CAPACITY` when the encoded header does not fit in `capacity` bytes.
+///
+/// # Safety
+/// `header` must point to a valid `PppHeader`, whose `tlvs` (if not null) point to `tlvs_length` readable bytes.
+/// `buffer` must point to `capacity` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ppp_encode(
+    header: *const PppHeader,
+    buffer: *mut u8,
+    capacity: usize,
+) -> isize {
+    if header.is_null() || buffer.is_null() {
+        return PPP_NULL as isize;
+    }
+
+    let header = &*header;
+    let tlvs = if header.tlvs.is_null() {
+        &[][..]
+    } else {
+        slice::from_raw_parts(header.tlvs, header.tlvs_length)
+    };
+    let encoded = match header.version {
+        PPP_VERSION_1 if tlvs.is_empty() => encode_v1(header),
+        PPP_VERSION_2 => encode_v2(header, tlvs),
+        _ => Err(PPP_INVALID),
+    };
+
+    match encoded {
+        Ok(encoded) if encoded.len() <= capacity => {
+            slice::from_raw_parts_mut(buffer, encoded.len()).copy_from_slice(&encoded);
+            encoded.len() as isize
+        }
+        Ok(..) => PPP_CAPACITY as isize,
+        Err(status) => status as isize,
+    }
+}
+
+/// Encodes a text header, which only supports TCP over IPv4 and IPv6 (or `UNKNOWN`).
+/// `LOCAL` headers have no addresses, so they can only be encoded as `UNKNOWN`.
+fn encode_v1(header: &PppHeader) -> Result<Vec<u8>, i32> {
+    let ports = (header.source_port, header.destination_port);
+    let addresses = match (header.command, header.address_family, header.protocol) {
+        (PPP_COMMAND_LOCAL | PPP_COMMAND_PROXY, PPP_FAMILY_UNSPECIFIED, _) => {
+            v1::Addresses::Unknown
+        }
+        (PPP_COMMAND_PROXY, PPP_FAMILY_IPV4, PPP_PROTOCOL_STREAM) => {
+            let (source, destination) = header.addresses::<4>();
+
+            v1::Addresses::new_tcp4(source, destination, ports.0, ports.1)
+        }
+        (PPP_COMMAND_PROXY, PPP_FAMILY_IPV6, PPP_PROTOCOL_STREAM) => {
+            let (source, destination) = header.addresses::<16>();
+
+            v1::Addresses::new_tcp6(source, destination, ports.0, ports.1)
+        }
+        _ => return Err(PPP_INVALID),
+    };
+
+    Ok(addresses.to_string().into_bytes())
+}
+
+/// Encodes a binary header, appending the TLVs as they are.
+fn encode_v2(header: &PppHeader, tlvs: &[u8]) -> Result<Vec<u8>, i32> {
+    let command = match header.command {
+        PPP_COMMAND_LOCAL => Command::Local,
+        PPP_COMMAND_PROXY => Command::Proxy,
+        _ => return Err(PPP_INVALID),
+    };
+    let protocol = match header.protocol {
+        PPP_PROTOCOL_UNSPECIFIED => Protocol::Unspecified,
+        PPP_PROTOCOL_STREAM => Protocol::Stream,
+        PPP_PROTOCOL_DATAGRAM => Protocol::Datagram,
+        _ => return Err(PPP_INVALID),
+    };
+    let ports = (header.source_port, header.destination_port);
+    let addresses: v2::Addresses = match header.address_family {
+        PPP_FAMILY_UNSPECIFIED => v2::Addresses::Unspecified,
+        PPP_FAMILY_IPV4 => {
+            let (source, destination) = header.addresses::<4>();
+
+            IPv4::new(source, destination, ports.0, ports.1).into()
+        }
+        PPP_FAMILY_IPV6 => {
+            let (source, destination) = header.addresses::<16>();
+
+            IPv6::new(source, destination, ports.0, ports.1).into()
+        }
+        PPP_FAMILY_UNIX => Unix::new(header.source_address, header.destination_address).into(),
+        _ => return Err(PPP_INVALID),
+    };
+
+    Builder::with_addresses(Version::Two | command, protocol, addresses)
+        .write_payload(tlvs)
+        .and_then(Builder::build)
+        .map_err(|_| PPP_INVALID)
+}
+
+/// An iterator over the TLVs of a parsed header.
+///
+/// # Safety
+/// `header` must point to a valid `PppHeader`, whose `tlvs` (if not null) point to `tlvs_length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ppp_tlvs(header: *const PppHeader) -> PppTlvIterator {
+    match header.as_ref() {
+        Some(header) if !header.tlvs.is_null() => PppTlvIterator {
+            next: header.tlvs,
+            remaining: header.tlvs_length,
+        },
+        _ => PppTlvIterator {
+            next: std::ptr::null(),
+            remaining: 0,
+        },
+    }
+}
+
+/// Advances the iterator, writing the next TLV into `tlv`.
+///
+/// Returns 1 when a TLV was written, `PPP_OK` (0) once there are no more TLVs, `PPP_NULL` when a pointer is null,
+/// or the stable error code of an invalid TLV.
+///
+/// # Safety
+/// `iterator` must point to an iterator returned by `ppp_tlvs` (or advanced by this function),
+/// whose header's TLVs are still alive, and `tlv` to a writable `PppTlv`.
+#[no_mangle]
+pub unsafe extern "C" fn ppp_tlv_next(iterator: *mut PppTlvIterator, tlv: *mut PppTlv) -> i32 {
+    if iterator.is_null() || tlv.is_null() {
+        return PPP_NULL;
+    }
+
+    let iterator = &mut *iterator;
+
+    if iterator.next.is_null() || iterator.remaining == 0 {
+        return PPP_OK;
+    }
+
+    let bytes = slice::from_raw_parts(iterator.next, iterator.remaining);
+
+    match TypeLengthValues::from(bytes).next() {
+        Some(Ok(next)) => {
+            let consumed = MINIMUM_TLV_LENGTH + next.value.len();
+
+            tlv.write(PppTlv {
+                kind: next.kind,
+                value: bytes[MINIMUM_TLV_LENGTH..].as_ptr(),
+                length: next.value.len(),
+            });
+
+            iterator.next = iterator.next.add(consumed);
+            iterator.remaining -= consumed;
+
+            1
+        }
+        Some(Err(error)) => {
+            iterator.remaining = 0;
+            i32::from(error.code().number)
+        }
+        None => PPP_OK,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::Type;
+
+    fn parse(input: &[u8]) -> (i32, PppHeader) {
+        let mut header = PppHeader::default();
+        let status = unsafe { ppp_parse(input.as_ptr(), input.len(), &mut header) };
+
+        (status, header)
+    }
+
+    fn encode(header: &PppHeader, capacity: usize) -> (isize, Vec<u8>) {
+        let mut buffer = vec![0; capacity];
+        let length = unsafe { ppp_encode(header, buffer.as_mut_ptr(), buffer.len()) };
+
+        buffer.truncate(length.max(0) as usize);
+
+        (length, buffer)
+    }
+
+    #[test]
+    fn v1_round_trip() {
+        let input = b"PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\nGET /";
+        let (status, header) = parse(input);
+
+        assert_eq!(status, PPP_OK);
+        assert_eq!(header.version, PPP_VERSION_1);
+        assert_eq!(header.address_family, PPP_FAMILY_IPV4);
+        assert_eq!(header.protocol, PPP_PROTOCOL_STREAM);
+        assert_eq!(header.source_address[..4], [127, 0, 0, 1]);
+        assert_eq!(header.destination_port, 443);
+        assert_eq!(header.length, 41);
+        assert!(header.tlvs.is_null());
+
+        assert_eq!(encode(&header, 64), (41, input[..41].to_vec()));
+        assert_eq!(encode(&header, 40).0, PPP_CAPACITY as isize);
+    }
+
+    #[test]
+    fn v2_tlvs() {
+        let input = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Datagram,
+            IPv4::new([127, 0, 0, 1], [192, 168, 1, 1], 80, 443),
+        )
+        .write_tlv(Type::Authority, b"example.com")
+        .unwrap()
+        .write_tlv(Type::NoOp, &[])
+        .unwrap()
+        .build()
+        .unwrap();
+        let (status, header) = parse(&input);
+
+        assert_eq!(status, PPP_OK);
+        assert_eq!(header.version, PPP_VERSION_2);
+        assert_eq!(header.protocol, PPP_PROTOCOL_DATAGRAM);
+        assert_eq!(header.tlvs_length, 17);
+
+        let mut iterator = unsafe { ppp_tlvs(&header) };
+        let mut tlv = PppTlv {
+            kind: 0,
+            value: std::ptr::null(),
+            length: 0,
+        };
+
+        assert_eq!(unsafe { ppp_tlv_next(&mut iterator, &mut tlv) }, 1);
+        assert_eq!(tlv.kind, Type::Authority as u8);
+        assert_eq!(
+            unsafe { slice::from_raw_parts(tlv.value, tlv.length) },
+            b"example.com"
+        );
+        assert_eq!(unsafe { ppp_tlv_next(&mut iterator, &mut tlv) }, 1);
+        assert_eq!((tlv.kind, tlv.length), (Type::NoOp as u8, 0));
+        assert_eq!(unsafe { ppp_tlv_next(&mut iterator, &mut tlv) }, PPP_OK);
+
+        assert_eq!(encode(&header, 64), (input.len() as isize, input));
+    }
+
+    #[test]
+    fn statuses() {
+        assert_eq!(parse(b"PROXY TCP4 127.0.0.1").0, PPP_INCOMPLETE);
+        assert_eq!(parse(b"PROXY TCP5 127.0.0.1 127.0.0.1 80 443\r\n").0, 111);
+        assert_eq!(
+            unsafe { ppp_parse(std::ptr::null(), 0, std::ptr::null_mut()) },
+            PPP_NULL
+        );
+
+        let header = PppHeader {
+            version: PPP_VERSION_1,
+            address_family: PPP_FAMILY_UNIX,
+            ..Default::default()
+        };
+        assert_eq!(encode(&header, 232).0, PPP_INVALID as isize);
+    }
+
+    #[test]
+    fn local_v1() {
+        let mut header = PppHeader {
+            version: PPP_VERSION_1,
+            command: PPP_COMMAND_LOCAL,
+            address_family: PPP_FAMILY_IPV4,
+            protocol: PPP_PROTOCOL_STREAM,
+            ..Default::default()
+        };
+
+        assert_eq!(encode(&header, 64).0, PPP_INVALID as isize);
+
+        header.address_family = PPP_FAMILY_UNSPECIFIED;
+
+        assert_eq!(encode(&header, 64), (15, b"PROXY UNKNOWN\r\n".to_vec()));
+    }
+
+    #[test]
+    fn oversized_tlvs() {
+        let tlvs = vec![0; usize::from(u16::MAX) + 1];
+        let header = PppHeader {
+            version: PPP_VERSION_2,
+            address_family: PPP_FAMILY_IPV4,
+            protocol: PPP_PROTOCOL_STREAM,
+            tlvs: tlvs.as_ptr(),
+            tlvs_length: tlvs.len(),
+            ..Default::default()
+        };
+
+        assert_eq!(encode(&header, 2 * tlvs.len()).0, PPP_INVALID as isize);
+    }
+}
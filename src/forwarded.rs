@@ -0,0 +1,379 @@
+//! Conversions between PROXY protocol headers and the HTTP `Forwarded` (RFC 7239) and `X-Forwarded-For` headers.
+//!
+//! Reverse proxies terminating HTTP can translate the connection information of a PROXY protocol header
+//! into HTTP headers for their upstreams, or the other way around when forwarding HTTP traffic to a TCP backend.
+use crate::prelude::*;
+
+use crate::net::{IpAddr, SocketAddr};
+use crate::parse::{self, Mode};
+use crate::{Header, ProxyInfo};
+
+/// An error in converting an HTTP header value into addresses.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ForwardedError {
+    #[error("Header value does not contain any addresses.")]
+    Empty,
+    #[error("Forwarded element is missing the 'for' parameter.")]
+    MissingFor,
+    #[error("Forwarded parameter {0:?} is not a valid 'name=value' pair.")]
+    InvalidParameter(String),
+    #[error("Node {0:?} is not a valid IP address with an optional port.")]
+    InvalidNode(String),
+    #[error("Node {0:?} is unknown or obfuscated, so it has no address.")]
+    HiddenNode(String),
+}
+
+/// The value of an `X-Forwarded-For` header for the source address of the header.
+/// Returns `None` for headers without IP addresses (e.g. `UNKNOWN` or UNIX sockets).
+///
+/// ## Examples
+/// ```rust
+/// use ppp::forwarded::to_x_forwarded_for;
+/// use ppp::{v1, Header};
+///
+/// let header = v1::Header::try_from("PROXY TCP6 2001:db8::1 2001:db8::2 51234 443\r\n").unwrap();
+///
+/// assert_eq!(to_x_forwarded_for(&Header::V1(header)), Some("2001:db8::1".to_string()));
+/// ```
+pub fn to_x_forwarded_for(header: &Header<'_>) -> Option<String> {
+    ProxyInfo::from(header)
+        .source
+        .map(|source| source.ip().to_string())
+}
+
+/// The value of a `Forwarded` header element for the header, with the source as `for`,
+/// the destination as `by`, and the authority TLV of binary headers (if any) as `host`.
+/// Returns `None` for headers without IP addresses (e.g. `UNKNOWN` or UNIX sockets).
+///
+/// ## Examples
+/// ```rust
+/// use ppp::forwarded::to_forwarded;
+/// use ppp::{v1, Header};
+///
+/// let header = v1::Header::try_from("PROXY TCP4 192.0.2.60 198.51.100.17 47011 443\r\n").unwrap();
+///
+/// assert_eq!(
+///     to_forwarded(&Header::V1(header)),
+///     Some(r#"for="192.0.2.60:47011";by="198.51.100.17:443""#.to_string())
+/// );
+/// ```
+pub fn to_forwarded(header: &Header<'_>) -> Option<String> {
+    let info = ProxyInfo::from(header);
+    let mut forwarded = format!("for={}", node(info.source?));
+
+    if let Some(destination) = info.destination {
+        forwarded.push_str(";by=");
+        forwarded.push_str(&node(destination));
+    }
+
+    if let Some(authority) = info.authority {
+        forwarded.push_str(";host=");
+        forwarded.push_str(&value(&authority));
+    }
+
+    Some(forwarded)
+}
+
+/// Converts an `X-Forwarded-For` value into source and destination addresses for a PROXY protocol header.
+/// The source is the first (i.e. client) address of the list, with a port of 0 since the header has no ports.
+/// The destination is the address the HTTP request was received on.
+///
+/// The addresses can be converted into either version's `Addresses`.
+/// Mismatched address families convert into `UNKNOWN` (version 1) or `Unspecified` (version 2) addresses.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::forwarded::from_x_forwarded_for;
+/// use ppp::v1;
+///
+/// let addresses = from_x_forwarded_for("203.0.113.195, 70.41.3.18", "10.0.0.1:80".parse().unwrap()).unwrap();
+/// let addresses = v1::Addresses::from(addresses);
+///
+/// assert_eq!(addresses.to_string(), "PROXY TCP4 203.0.113.195 10.0.0.1 0 80\r\n");
+/// ```
+pub fn from_x_forwarded_for(
+    value: &str,
+    destination: SocketAddr,
+) -> Result<(SocketAddr, SocketAddr), ForwardedError> {
+    let client = value
+        .split(',')
+        .map(str::trim)
+        .next()
+        .filter(|client| !client.is_empty())
+        .ok_or(ForwardedError::Empty)?;
+    let source = parse::ip(client.as_bytes(), Mode::Strict)
+        .ok_or_else(|| ForwardedError::InvalidNode(client.to_string()))?;
+
+    Ok(((source, 0).into(), destination))
+}
+
+/// Converts a `Forwarded` value into source and destination addresses for a PROXY protocol header.
+/// Uses the first (i.e. closest to the client) element of the value:
+/// its `for` node is the source, and its `by` node is the destination.
+/// When the element has no `by` node, or it is unknown or obfuscated, the given destination is used instead.
+/// Nodes without a port have a port of 0.
+///
+/// The addresses can be converted into either version's `Addresses`.
+/// Mismatched address families convert into `UNKNOWN` (version 1) or `Unspecified` (version 2) addresses.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::forwarded::from_forwarded;
+/// use ppp::v2;
+///
+/// let value = r#"for="[2001:db8:cafe::17]:4711";proto=https, for=192.0.2.43"#;
+/// let addresses = from_forwarded(value, "[2001:db8::1]:443".parse().unwrap()).unwrap();
+///
+/// assert_eq!(addresses, ("[2001:db8:cafe::17]:4711".parse().unwrap(), "[2001:db8::1]:443".parse().unwrap()));
+/// assert_eq!(v2::Addresses::from(addresses).address_family(), v2::AddressFamily::IPv6);
+/// ```
+pub fn from_forwarded(
+    value: &str,
+    destination: SocketAddr,
+) -> Result<(SocketAddr, SocketAddr), ForwardedError> {
+    let element = split(value, ',')
+        .next()
+        .filter(|element| !element.trim().is_empty())
+        .ok_or(ForwardedError::Empty)?;
+
+    let mut source = None;
+    let mut by = None;
+
+    for pair in split(element, ';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        let (name, value) = pair
+            .split_once('=')
+            .ok_or_else(|| ForwardedError::InvalidParameter(pair.to_string()))?;
+        let value = unquote(value.trim())
+            .ok_or_else(|| ForwardedError::InvalidParameter(pair.to_string()))?;
+
+        if name.trim().eq_ignore_ascii_case("for") {
+            source = Some(parse_node(value)?);
+        } else if name.trim().eq_ignore_ascii_case("by") {
+            by = parse_node(value).ok();
+        }
+    }
+
+    let source = source.ok_or(ForwardedError::MissingFor)?;
+
+    Ok((source, by.unwrap_or(destination)))
+}
+
+/// Formats the address as a `Forwarded` node, which must be quoted since it contains a colon.
+fn node(address: SocketAddr) -> String {
+    format!("\"{}\"", address)
+}
+
+/// Formats the value as a token, or as a quoted string when it contains characters other than token characters.
+fn value(value: &str) -> String {
+    let token = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+
+    if !value.is_empty() && value.chars().all(token) {
+        value.to_string()
+    } else {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+
+        format!("\"{}\"", escaped)
+    }
+}
+
+/// Splits the value on the separator, ignoring separators within quoted strings.
+fn split(value: &str, separator: char) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    value.split(move |c: char| {
+        if escaped {
+            escaped = false;
+        } else if quoted && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        }
+
+        !quoted && c == separator
+    })
+}
+
+/// Removes the quotes of a quoted string, if any.
+/// Returns `None` for an unterminated quoted string.
+fn unquote(value: &str) -> Option<&str> {
+    match value.strip_prefix('"') {
+        Some(quoted) => quoted.strip_suffix('"'),
+        None => Some(value),
+    }
+}
+
+/// Parses a node of a `Forwarded` element, which is an IPv4 address or a bracketed IPv6 address with an optional port.
+fn parse_node(node: &str) -> Result<SocketAddr, ForwardedError> {
+    let invalid = || ForwardedError::InvalidNode(node.to_string());
+
+    if node.eq_ignore_ascii_case("unknown") || node.starts_with('_') {
+        return Err(ForwardedError::HiddenNode(node.to_string()));
+    }
+
+    let (address, port) = match node.strip_prefix('[') {
+        Some(rest) => {
+            let (address, rest) = rest.split_once(']').ok_or_else(invalid)?;
+
+            match rest {
+                "" => (address, None),
+                rest => (address, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        }
+        None => match node.split_once(':') {
+            Some((address, port)) => (address, Some(port)),
+            None => (node, None),
+        },
+    };
+
+    let address = match parse::ip(address.as_bytes(), Mode::Strict) {
+        Some(IpAddr::V6(_)) if !node.starts_with('[') => return Err(invalid()),
+        Some(address) => address,
+        None => return Err(invalid()),
+    };
+
+    let port = match port {
+        Some(port) if port.starts_with('_') => {
+            return Err(ForwardedError::HiddenNode(node.to_string()))
+        }
+        // RFC 7239 ports are `1*5DIGIT`, which allows leading zeros.
+        Some(port) => parse::port(port.as_bytes(), Mode::Lenient).ok_or_else(invalid)?,
+        None => 0,
+    };
+
+    Ok((address, port).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Command, Protocol, Type, Version};
+    use crate::{v1, v2};
+
+    fn address(address: &str) -> SocketAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn to_headers() {
+        let header = Builder::with_addresses(
+            Version::Two | Command::Proxy,
+            Protocol::Stream,
+            (address("[2001:db8::1]:4711"), address("[2001:db8::2]:443")),
+        )
+        .write_tlv(Type::Authority, b"example.com:443")
+        .unwrap()
+        .build()
+        .unwrap();
+        let header = Header::V2(v2::Header::try_from(header.as_slice()).unwrap());
+
+        assert_eq!(to_x_forwarded_for(&header).unwrap(), "2001:db8::1");
+        assert_eq!(
+            to_forwarded(&header).unwrap(),
+            r#"for="[2001:db8::1]:4711";by="[2001:db8::2]:443";host="example.com:443""#
+        );
+    }
+
+    #[test]
+    fn to_headers_unknown() {
+        let header = Header::V1(v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap());
+
+        assert_eq!(to_x_forwarded_for(&header), None);
+        assert_eq!(to_forwarded(&header), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let header = Header::V1(
+            v1::Header::try_from("PROXY TCP4 192.0.2.60 198.51.100.17 47011 443\r\n").unwrap(),
+        );
+        let forwarded = to_forwarded(&header).unwrap();
+        let addresses = from_forwarded(&forwarded, address("127.0.0.1:80")).unwrap();
+
+        assert_eq!(
+            v1::Addresses::from(addresses).to_string(),
+            header
+                .as_bytes()
+                .iter()
+                .map(|&b| b as char)
+                .collect::<String>()
+        );
+    }
+
+    #[test]
+    fn from_forwarded_nodes() {
+        let destination = address("10.0.0.1:80");
+
+        assert_eq!(
+            from_forwarded("For=192.0.2.43", destination).unwrap(),
+            (address("192.0.2.43:0"), destination)
+        );
+        assert_eq!(
+            from_forwarded(r#"for="[2001:db8::1]";by=_hidden"#, address("[::1]:80")).unwrap(),
+            (address("[2001:db8::1]:0"), address("[::1]:80"))
+        );
+        assert_eq!(
+            from_forwarded(r#"proto=http;host="a;b,c";for="192.0.2.1:1""#, destination).unwrap(),
+            (address("192.0.2.1:1"), destination)
+        );
+    }
+
+    #[test]
+    fn from_forwarded_errors() {
+        let destination = address("10.0.0.1:80");
+
+        assert_eq!(from_forwarded("", destination), Err(ForwardedError::Empty));
+        assert_eq!(
+            from_forwarded("proto=https", destination),
+            Err(ForwardedError::MissingFor)
+        );
+        assert_eq!(
+            from_forwarded("for", destination),
+            Err(ForwardedError::InvalidParameter("for".to_string()))
+        );
+        assert_eq!(
+            from_forwarded("for=unknown", destination),
+            Err(ForwardedError::HiddenNode("unknown".to_string()))
+        );
+        assert_eq!(
+            from_forwarded(r#"for="192.0.2.1:_port""#, destination),
+            Err(ForwardedError::HiddenNode("192.0.2.1:_port".to_string()))
+        );
+        assert_eq!(
+            from_forwarded(r#"for="2001:db8::1""#, destination),
+            Err(ForwardedError::InvalidNode("2001:db8::1".to_string()))
+        );
+        assert_eq!(
+            from_forwarded(r#"for="[2001:db8::1]80""#, destination),
+            Err(ForwardedError::InvalidNode("[2001:db8::1]80".to_string()))
+        );
+    }
+
+    #[test]
+    fn from_x_forwarded_for_values() {
+        let destination = address("[::1]:443");
+
+        assert_eq!(
+            from_x_forwarded_for(" 2001:db8::7 , 10.0.0.1", destination).unwrap(),
+            (address("[2001:db8::7]:0"), destination)
+        );
+        assert_eq!(
+            from_x_forwarded_for(" ", destination),
+            Err(ForwardedError::Empty)
+        );
+        assert_eq!(
+            from_x_forwarded_for("192.0.2.1:80", destination),
+            Err(ForwardedError::InvalidNode("192.0.2.1:80".to_string()))
+        );
+    }
+
+    #[test]
+    fn quoted_values() {
+        assert_eq!(value("example.com"), "example.com");
+        assert_eq!(value("example.com:443"), "\"example.com:443\"");
+        assert_eq!(value("a\"b"), "\"a\\\"b\"");
+    }
+}
@@ -0,0 +1,159 @@
+//! Parsing of headers from the bytes read so far from a stream, shared by the stream helpers.
+use crate::policy::Policy;
+use crate::sniff::{sniff_proxy, Sniff};
+use crate::{v1, v2, Header, PartialResult};
+
+/// The reasons the bytes read so far cannot be the start of an acceptable header.
+#[derive(Debug)]
+pub(crate) enum Invalid {
+    V1(v1::BinaryParseError),
+    V2(v2::ParseError),
+    MissingHeader,
+}
+
+/// Parses a header from the bytes read so far.
+/// Returns `None` when more bytes are needed.
+pub(crate) fn parse(input: &[u8], policy: Policy) -> Option<Result<Option<Header<'_>>, Invalid>> {
+    Progress::default().parse(input, policy)
+}
+
+/// What is already known about a header whose bytes are still arriving,
+/// so parsing a longer input does not validate the same bytes again.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Progress {
+    /// Not even the prefix is known to be complete.
+    Start,
+    /// The input starts with the text prefix, and none of its first `scanned` bytes is a carriage return.
+    V1 { scanned: usize },
+    /// The fixed part of a binary header is valid, and the whole header is `length` bytes long.
+    V2 { length: usize },
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Progress::Start
+    }
+}
+
+impl Progress {
+    /// Parses a header from the bytes read so far, which extend the input of the previous call.
+    /// An input shorter than the progress made so far starts over.
+    /// Returns `None` when more bytes are needed.
+    pub(crate) fn parse<'a>(
+        &mut self,
+        input: &'a [u8],
+        policy: Policy,
+    ) -> Option<Result<Option<Header<'a>>, Invalid>> {
+        let counted = match *self {
+            Progress::Start => 0,
+            Progress::V1 { scanned } => scanned,
+            Progress::V2 { .. } => v2::MINIMUM_LENGTH,
+        };
+
+        if input.len() < counted {
+            *self = Progress::Start;
+        }
+
+        match *self {
+            Progress::Start => match sniff_proxy(input) {
+                Sniff::Incomplete => None,
+                Sniff::ProxyV2 => self.parse_v2(input),
+                Sniff::ProxyV1 => self.parse_v1(input, 0),
+                _ if policy == Policy::Optional => Some(Ok(None)),
+                _ => Some(Err(Invalid::MissingHeader)),
+            },
+            Progress::V1 { scanned } => self.parse_v1(input, scanned),
+            Progress::V2 { length } if input.len() < length => None,
+            Progress::V2 { .. } => self.parse_v2(input),
+        }
+    }
+
+    /// Parses a binary header, or records its length once its fixed part is valid.
+    fn parse_v2<'a>(&mut self, input: &'a [u8]) -> Option<Result<Option<Header<'a>>, Invalid>> {
+        match v2::Header::try_from(input) {
+            Err(v2::ParseError::Partial(_, length)) => {
+                *self = Progress::V2 {
+                    length: v2::MINIMUM_LENGTH + length,
+                };
+                None
+            }
+            header if header.is_incomplete() => None,
+            header => Some(header.map(|h| Some(h.into())).map_err(Invalid::V2)),
+        }
+    }
+
+    /// Parses a text header once it is terminated, looking for the carriage return from the given offset.
+    fn parse_v1<'a>(
+        &mut self,
+        input: &'a [u8],
+        scanned: usize,
+    ) -> Option<Result<Option<Header<'a>>, Invalid>> {
+        // A partial text header may look invalid (e.g. a port cut short), so only parse terminated lines.
+        let terminated = match input[scanned..].iter().position(|&c| c == b'\r') {
+            Some(offset) => {
+                *self = Progress::V1 {
+                    scanned: scanned + offset,
+                };
+                scanned + offset + 1 < input.len()
+            }
+            None => {
+                *self = Progress::V1 {
+                    scanned: input.len(),
+                };
+                input.len() >= v1::MAX_LENGTH
+            }
+        };
+
+        if terminated {
+            Some(
+                v1::Header::try_from(input)
+                    .map(|h| Some(h.into()))
+                    .map_err(Invalid::V1),
+            )
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumes() {
+        let input = b"PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n";
+        let mut progress = Progress::default();
+
+        assert!(progress.parse(&input[..3], Policy::Require).is_none());
+        assert_eq!(progress, Progress::Start);
+        assert!(progress.parse(&input[..20], Policy::Require).is_none());
+        assert_eq!(progress, Progress::V1 { scanned: 20 });
+        assert!(progress.parse(&input[..38], Policy::Require).is_none());
+        assert_eq!(progress, Progress::V1 { scanned: 37 });
+        assert!(matches!(
+            progress.parse(input, Policy::Require),
+            Some(Ok(Some(Header::V1(..))))
+        ));
+
+        let mut header = Vec::from(v2::PROTOCOL_PREFIX);
+        header.extend([0x20, 0x00, 0x01, 0x00]);
+        header.resize(header.len() + 256, 0);
+
+        let mut progress = Progress::default();
+
+        assert!(progress.parse(&header[..15], Policy::Require).is_none());
+        assert_eq!(progress, Progress::Start);
+        assert!(progress.parse(&header[..16], Policy::Require).is_none());
+        assert_eq!(progress, Progress::V2 { length: 272 });
+        assert!(progress.parse(&header[..271], Policy::Require).is_none());
+        assert!(matches!(
+            progress.parse(&header, Policy::Require),
+            Some(Ok(Some(Header::V2(..))))
+        ));
+
+        // A shorter input starts over.
+        assert!(progress.parse(b"GET", Policy::Require).is_some());
+        assert_eq!(progress, Progress::Start);
+    }
+}
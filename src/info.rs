@@ -0,0 +1,311 @@
+//! A version-independent summary of the connection information in a PROXY protocol header.
+use crate::prelude::*;
+
+use crate::net::SocketAddr;
+use crate::{v1, v2, Version};
+use alloc::sync::Arc;
+use core::fmt::Write;
+use core::str::from_utf8;
+
+/// An owned summary of a PROXY protocol header of either version.
+/// Cloning is cheap, as the variable-length values are reference counted,
+/// which makes it suitable for storing in per-connection state.
+///
+/// Headers without IP addresses (i.e. `UNKNOWN`, `Unspecified`, or `Unix`) have no `source` or `destination`.
+/// The text version of the protocol has no command, so it is always treated as `Command::Proxy`.
+/// For `Command::Local`, the addresses are kept as sent, but receivers should use the real connection endpoints instead.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::{ProxyInfo, Version, v1, v2};
+///
+/// let header = v1::Header::try_from("PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\n").unwrap();
+/// let info = ProxyInfo::from(&header);
+///
+/// assert_eq!(info.version, Version::One);
+/// assert_eq!(info.command, v2::Command::Proxy);
+/// assert_eq!(info.protocol, v2::Protocol::Stream);
+/// assert_eq!(info.source, Some("127.0.0.1:80".parse().unwrap()));
+/// assert_eq!(info.destination, Some("192.168.1.1:443".parse().unwrap()));
+/// assert_eq!(info.authority, None);
+/// assert_eq!(info.unique_id, None);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ProxyInfo {
+    pub version: Version,
+    pub command: v2::Command,
+    pub protocol: v2::Protocol,
+    pub source: Option<SocketAddr>,
+    pub destination: Option<SocketAddr>,
+    pub authority: Option<Arc<str>>,
+    pub unique_id: Option<Arc<[u8]>>,
+}
+
+impl ProxyInfo {
+    /// Formats this summary on a single line for plaintext logs, e.g. `v2 tcp4 203.0.113.7:51234->10.0.0.1:443 id=abc123`.
+    ///
+    /// The format is stable: the version, then `local` for `Command::Local`, then the transport
+    /// (`tcp4`, `tcp6`, `udp4` or `udp6`; `ip4` or `ip6` for IP addresses with `Protocol::Unspecified`;
+    /// or `stream`, `dgram` or `unknown` without IP addresses) and the addresses if any,
+    /// followed by `authority=` and the hexadecimal `id=` when present.
+    /// Whitespace and control characters in the authority are escaped as `\u{..}` and backslashes as `\\`,
+    /// so a line always splits on spaces and the escapes are unambiguous.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::{v1, ProxyInfo};
+    ///
+    /// let header = v1::Header::try_from("PROXY TCP4 203.0.113.7 10.0.0.1 51234 443\r\n").unwrap();
+    ///
+    /// assert_eq!(
+    ///     ProxyInfo::from(&header).to_log_string(),
+    ///     "v1 tcp4 203.0.113.7:51234->10.0.0.1:443"
+    /// );
+    /// ```
+    pub fn to_log_string(&self) -> String {
+        let mut line = String::new();
+
+        let _ = write!(line, "v{}", self.version as u8);
+
+        if self.command == v2::Command::Local {
+            line.push_str(" local");
+        }
+
+        let ipv6 = self.source.map(|source| source.is_ipv6());
+        let transport = match (self.protocol, ipv6) {
+            (v2::Protocol::Stream, Some(false)) => "tcp4",
+            (v2::Protocol::Stream, Some(true)) => "tcp6",
+            (v2::Protocol::Datagram, Some(false)) => "udp4",
+            (v2::Protocol::Datagram, Some(true)) => "udp6",
+            (v2::Protocol::Unspecified, Some(false)) => "ip4",
+            (v2::Protocol::Unspecified, Some(true)) => "ip6",
+            (v2::Protocol::Stream, None) => "stream",
+            (v2::Protocol::Datagram, None) => "dgram",
+            (v2::Protocol::Unspecified, None) => "unknown",
+        };
+
+        line.push(' ');
+        line.push_str(transport);
+
+        if let (Some(source), Some(destination)) = (self.source, self.destination) {
+            let _ = write!(line, " {}->{}", source, destination);
+        }
+
+        if let Some(authority) = self.authority.as_deref() {
+            line.push_str(" authority=");
+
+            for c in authority.chars() {
+                if c == '\\' {
+                    line.push_str("\\\\");
+                } else if c.is_whitespace() || c.is_control() {
+                    let _ = write!(line, "\\u{{{:x}}}", c as u32);
+                } else {
+                    line.push(c);
+                }
+            }
+        }
+
+        if let Some(unique_id) = self.unique_id.as_deref() {
+            line.push_str(" id=");
+
+            for byte in unique_id {
+                let _ = write!(line, "{:02x}", byte);
+            }
+        }
+
+        line
+    }
+}
+
+impl<'a> From<&v1::Header<'a>> for ProxyInfo {
+    fn from(header: &v1::Header<'a>) -> Self {
+        let (protocol, source, destination) = match header.addresses {
+            v1::Addresses::Tcp4(a) => (
+                v2::Protocol::Stream,
+                Some((a.source_address, a.source_port).into()),
+                Some((a.destination_address, a.destination_port).into()),
+            ),
+            v1::Addresses::Tcp6(a) => (
+                v2::Protocol::Stream,
+                Some((a.source_address, a.source_port).into()),
+                Some((a.destination_address, a.destination_port).into()),
+            ),
+            v1::Addresses::Unknown => (v2::Protocol::Unspecified, None, None),
+        };
+
+        ProxyInfo {
+            version: Version::One,
+            command: v2::Command::Proxy,
+            protocol,
+            source,
+            destination,
+            authority: None,
+            unique_id: None,
+        }
+    }
+}
+
+impl<'a> From<&v2::Header<'a>> for ProxyInfo {
+    fn from(header: &v2::Header<'a>) -> Self {
+        let (source, destination) = match header.addresses {
+            v2::Addresses::IPv4(a) => (
+                Some((a.source_address, a.source_port).into()),
+                Some((a.destination_address, a.destination_port).into()),
+            ),
+            v2::Addresses::IPv6(a) => (
+                Some((a.source_address, a.source_port).into()),
+                Some((a.destination_address, a.destination_port).into()),
+            ),
+            v2::Addresses::Unix(..) | v2::Addresses::Unspecified => (None, None),
+        };
+
+        let mut authority = None;
+        let mut unique_id = None;
+
+        for tlv in header.tlvs().map_while(Result::ok) {
+            if tlv.kind == v2::Type::Authority as u8 {
+                authority = from_utf8(tlv.value.as_ref()).ok().map(Arc::from);
+            } else if tlv.kind == v2::Type::UniqueId as u8 {
+                unique_id = Some(Arc::from(tlv.value.as_ref()));
+            }
+        }
+
+        ProxyInfo {
+            version: Version::Two,
+            command: header.command,
+            protocol: header.protocol,
+            source,
+            destination,
+            authority,
+            unique_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Command, Protocol, Type};
+
+    #[test]
+    fn v1_unknown() {
+        let header = v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap();
+        let info = ProxyInfo::from(&header);
+
+        assert_eq!(info.version, Version::One);
+        assert_eq!(info.protocol, Protocol::Unspecified);
+        assert_eq!(info.source, None);
+        assert_eq!(info.destination, None);
+    }
+
+    #[test]
+    fn v2_with_tlvs() {
+        let source: SocketAddr = "[::1]:80".parse().unwrap();
+        let destination: SocketAddr = "[::2]:443".parse().unwrap();
+        let header = Builder::with_addresses(
+            v2::Version::Two | Command::Local,
+            Protocol::Datagram,
+            (source, destination),
+        )
+        .write_tlv(Type::Authority, b"example.com")
+        .unwrap()
+        .write_tlv(Type::UniqueId, &[1, 2, 3])
+        .unwrap()
+        .build()
+        .unwrap();
+        let header = v2::Header::try_from(header.as_slice()).unwrap();
+
+        let info = ProxyInfo::from(&header);
+
+        assert_eq!(
+            info,
+            ProxyInfo {
+                version: Version::Two,
+                command: Command::Local,
+                protocol: Protocol::Datagram,
+                source: Some(source),
+                destination: Some(destination),
+                authority: Some(Arc::from("example.com")),
+                unique_id: Some(Arc::from([1u8, 2, 3].as_slice())),
+            }
+        );
+    }
+
+    #[test]
+    fn v2_unix() {
+        let header = Builder::with_addresses(
+            v2::Version::Two | Command::Proxy,
+            Protocol::Stream,
+            v2::Unix::new([0; 108], [0; 108]),
+        )
+        .build()
+        .unwrap();
+        let header = v2::Header::try_from(header.as_slice()).unwrap();
+
+        let info = ProxyInfo::from(&header);
+
+        assert_eq!(info.source, None);
+        assert_eq!(info.destination, None);
+        assert_eq!(info.authority, None);
+    }
+
+    #[test]
+    fn log_string() {
+        let header = Builder::with_addresses(
+            v2::Version::Two | Command::Local,
+            Protocol::Datagram,
+            (
+                "[2001:db8::1]:80".parse::<SocketAddr>().unwrap(),
+                "[2001:db8::2]:443".parse::<SocketAddr>().unwrap(),
+            ),
+        )
+        .write_tlv(Type::Authority, b"example.com\tx y")
+        .unwrap()
+        .write_tlv(Type::UniqueId, &[0xAB, 0xC1, 0x23])
+        .unwrap()
+        .build()
+        .unwrap();
+        let header = v2::Header::try_from(header.as_slice()).unwrap();
+
+        assert_eq!(
+            ProxyInfo::from(&header).to_log_string(),
+            "v2 local udp6 [2001:db8::1]:80->[2001:db8::2]:443 authority=example.com\\u{9}x\\u{20}y id=abc123"
+        );
+
+        let header = v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap();
+
+        assert_eq!(ProxyInfo::from(&header).to_log_string(), "v1 unknown");
+
+        let header = Builder::with_addresses(
+            v2::Version::Two | Command::Proxy,
+            Protocol::Stream,
+            v2::Unix::new([0; 108], [0; 108]),
+        )
+        .build()
+        .unwrap();
+        let header = v2::Header::try_from(header.as_slice()).unwrap();
+
+        assert_eq!(ProxyInfo::from(&header).to_log_string(), "v2 stream");
+
+        let header = Builder::with_addresses(
+            v2::Version::Two | Command::Proxy,
+            Protocol::Unspecified,
+            (
+                "192.0.2.1:80".parse::<SocketAddr>().unwrap(),
+                "192.0.2.2:443".parse::<SocketAddr>().unwrap(),
+            ),
+        )
+        .write_tlv(Type::Authority, b"a\\u{20}b c")
+        .unwrap()
+        .build()
+        .unwrap();
+        let header = v2::Header::try_from(header.as_slice()).unwrap();
+
+        assert_eq!(
+            ProxyInfo::from(&header).to_log_string(),
+            "v2 ip4 192.0.2.1:80->192.0.2.2:443 authority=a\\\\u{20}b\\u{20}c"
+        );
+    }
+}
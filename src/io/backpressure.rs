@@ -0,0 +1,310 @@
+//! Limits on the number of connections whose headers are read at the same time.
+use std::prelude::v1::*;
+
+use super::Error;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+#[cfg(feature = "tokio")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+/// Bounds the header reads in flight across the clones of an `Acceptor`.
+/// Connections beyond the limit wait for a slot in a queue of bounded length,
+/// and connections beyond the queue fail immediately with `Error::Overloaded`.
+///
+/// Waiting connections are not served in order; whichever is woken first takes the freed slot.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    limit: usize,
+    queue: usize,
+    state: Mutex<State>,
+    available: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: usize,
+    waiting: usize,
+    /// The wakers of the queued `Acquire` futures, by the identifier of each future.
+    #[cfg(feature = "tokio")]
+    wakers: Vec<(u64, Waker)>,
+    /// The identifier of the next `Acquire` future to join the queue.
+    #[cfg(feature = "tokio")]
+    next: u64,
+}
+
+impl Limiter {
+    /// Creates a new `Limiter` allowing `limit` (at least 1) reads in flight, with `queue` more waiting for a slot.
+    pub(crate) fn new(limit: usize, queue: usize) -> Self {
+        Limiter {
+            limit: limit.max(1),
+            queue,
+            state: Mutex::new(State::default()),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Takes a slot, blocking the current thread while the limit is reached and the queue has room.
+    pub(crate) fn acquire(self: &Arc<Self>) -> Result<Permit, Error> {
+        let mut state = self.lock();
+
+        if state.in_flight >= self.limit {
+            if state.waiting >= self.queue {
+                return Err(Error::Overloaded);
+            }
+
+            state.waiting += 1;
+
+            while state.in_flight >= self.limit {
+                state = self
+                    .available
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+
+            state.waiting -= 1;
+        }
+
+        state.in_flight += 1;
+
+        Ok(Permit(Arc::clone(self)))
+    }
+
+    /// Takes a slot like `acquire`, but yields to the executor instead of blocking while waiting.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn acquire_async(self: &Arc<Self>) -> Acquire {
+        Acquire {
+            limiter: Arc::clone(self),
+            queued: None,
+        }
+    }
+
+    /// The number of reads currently in flight.
+    #[cfg(test)]
+    fn in_flight(&self) -> usize {
+        self.lock().in_flight
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A slot for reading one header, which is freed when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit(Arc<Limiter>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+
+        state.in_flight -= 1;
+
+        #[cfg(feature = "tokio")]
+        let wakers = std::mem::take(&mut state.wakers);
+
+        drop(state);
+
+        self.0.available.notify_one();
+
+        #[cfg(feature = "tokio")]
+        wakers.into_iter().for_each(|(_, waker)| waker.wake());
+    }
+}
+
+/// A future for a slot, which leaves the queue and removes its waker when dropped.
+#[cfg(feature = "tokio")]
+pub(crate) struct Acquire {
+    limiter: Arc<Limiter>,
+    /// The identifier of this future's waker while it is in the queue.
+    queued: Option<u64>,
+}
+
+#[cfg(feature = "tokio")]
+impl State {
+    /// Removes the waker of the queued future with the given identifier, if it was not woken yet.
+    fn remove_waker(&mut self, id: u64) {
+        self.wakers.retain(|(queued, _)| *queued != id);
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Future for Acquire {
+    type Output = Result<Permit, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut state = this.limiter.lock();
+
+        if state.in_flight < this.limiter.limit {
+            state.in_flight += 1;
+
+            if let Some(id) = this.queued.take() {
+                state.waiting -= 1;
+                state.remove_waker(id);
+            }
+
+            return Poll::Ready(Ok(Permit(Arc::clone(&this.limiter))));
+        }
+
+        let id = match this.queued {
+            Some(id) => id,
+            None => {
+                if state.waiting >= this.limiter.queue {
+                    return Poll::Ready(Err(Error::Overloaded));
+                }
+
+                let id = state.next;
+
+                state.waiting += 1;
+                state.next += 1;
+                this.queued = Some(id);
+
+                id
+            }
+        };
+
+        // A freed slot takes all the wakers, so a woken future registers again.
+        match state.wakers.iter_mut().find(|(queued, _)| *queued == id) {
+            Some((_, waker)) if waker.will_wake(context.waker()) => (),
+            Some((_, waker)) => *waker = context.waker().clone(),
+            None => state.wakers.push((id, context.waker().clone())),
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if let Some(id) = self.queued {
+            let mut state = self.limiter.lock();
+
+            state.waiting -= 1;
+            state.remove_waker(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn overloaded() {
+        let limiter = Arc::new(Limiter::new(1, 1));
+        let permit = limiter.acquire().unwrap();
+        let waiting = {
+            let limiter = Arc::clone(&limiter);
+            thread::spawn(move || limiter.acquire().map(drop))
+        };
+
+        while limiter.lock().waiting == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(matches!(limiter.acquire(), Err(Error::Overloaded)));
+
+        drop(permit);
+
+        assert!(waiting.join().unwrap().is_ok());
+        assert_eq!(limiter.in_flight(), 0);
+        assert!(limiter.acquire().is_ok());
+    }
+
+    #[test]
+    fn without_queue() {
+        let limiter = Arc::new(Limiter::new(0, 0));
+        let permit = limiter.acquire().unwrap();
+
+        assert!(matches!(limiter.acquire(), Err(Error::Overloaded)));
+
+        drop(permit);
+
+        assert!(limiter.acquire().is_ok());
+    }
+
+    /// A waker that does nothing, for polling futures by hand.
+    #[cfg(feature = "tokio")]
+    fn noop_waker() -> Waker {
+        use std::task::{RawWaker, RawWakerVTable};
+
+        fn noop(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        unsafe { Waker::from_raw(clone(std::ptr::null())) }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn acquire_async() {
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        let limiter = Arc::new(Limiter::new(1, 1));
+        let permit = limiter.acquire().unwrap();
+        let mut queued = Box::pin(limiter.acquire_async());
+
+        assert!(queued.as_mut().poll(&mut context).is_pending());
+        assert!(matches!(
+            Box::pin(limiter.acquire_async())
+                .as_mut()
+                .poll(&mut context),
+            Poll::Ready(Err(Error::Overloaded))
+        ));
+
+        drop(queued);
+
+        let mut queued = Box::pin(limiter.acquire_async());
+
+        assert!(queued.as_mut().poll(&mut context).is_pending());
+
+        drop(permit);
+
+        let acquired = queued.as_mut().poll(&mut context);
+
+        assert!(matches!(acquired, Poll::Ready(Ok(..))));
+        assert_eq!(limiter.in_flight(), 1);
+        assert_eq!(limiter.lock().waiting, 0);
+    }
+
+    /// Cancelled acquires remove their wakers, so a flood of them while the slots are held does not grow the queue.
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn cancelled() {
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        let limiter = Arc::new(Limiter::new(1, 2));
+        let permit = limiter.acquire().unwrap();
+        let mut kept = Box::pin(limiter.acquire_async());
+
+        assert!(kept.as_mut().poll(&mut context).is_pending());
+
+        for _ in 0..10_000 {
+            let mut cancelled = Box::pin(limiter.acquire_async());
+
+            assert!(cancelled.as_mut().poll(&mut context).is_pending());
+            assert!(cancelled.as_mut().poll(&mut context).is_pending());
+            assert_eq!(limiter.lock().wakers.len(), 2);
+        }
+
+        assert_eq!(limiter.lock().wakers.len(), 1);
+        assert_eq!(limiter.lock().waiting, 1);
+
+        drop(permit);
+
+        assert!(limiter.lock().wakers.is_empty());
+        assert!(matches!(
+            kept.as_mut().poll(&mut context),
+            Poll::Ready(Ok(..))
+        ));
+        assert_eq!(limiter.lock().waiting, 0);
+    }
+}
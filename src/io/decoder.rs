@@ -0,0 +1,288 @@
+//! A decoder for caller-owned buffers, without any I/O or internal buffering.
+use std::prelude::v1::*;
+
+use super::{advance, Error};
+use crate::incremental::Progress;
+use crate::outcome;
+use crate::policy::Policy;
+use crate::{Header, ParseOutcome};
+
+/// The result of decoding the filled portion of a caller-owned buffer with the deprecated `Decoder::advance`.
+// The result is returned by value on each call, so boxing the header would only add an allocation.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decoded<'a> {
+    /// The header is not complete yet.
+    /// Read more bytes into the buffer after the filled portion, then pass the whole filled portion to the decoder again.
+    Incomplete,
+    /// The header (if any) is complete.
+    Complete {
+        /// The header borrowed from the buffer, if the stream started with one.
+        header: Option<Header<'a>>,
+        /// The number of bytes at the start of the filled portion that belong to the header.
+        consumed: usize,
+        /// The rest of the filled portion, which belongs to the payload.
+        remaining: &'a [u8],
+    },
+}
+
+/// Decodes PROXY protocol headers from buffers owned by the caller, such as the registered buffers of
+/// completion-based runtimes (`tokio-uring`, `glommio`), where the buffer is handed to the kernel for each read.
+/// The decoder never reads, copies or allocates a buffer; the caller tracks how much of its buffer is filled.
+///
+/// The decoder only needs the buffer to hold the header: at most 107 bytes for version 1,
+/// or 16 bytes plus the advertised length for version 2.
+///
+/// Between calls, the decoder remembers what it has already validated: that the prefix matched,
+/// how many bytes of a text header hold no line ending, or the length of a binary header whose fixed part is valid.
+/// A header trickled in one byte at a time is therefore not revalidated from its first byte on every call.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::io::Decoder;
+/// use ppp::policy::Policy;
+/// use ppp::ParseOutcome;
+///
+/// // Completed reads of a fixed buffer, as delivered by the runtime.
+/// let reads: [&[u8]; 3] = [b"PROXY TCP4 127.0.0.1", b" 127.0.0.2 80 443\r", b"\nGET / HTTP/1.1\r\n"];
+/// let mut buffer = [0; 128];
+/// let mut filled = 0;
+/// let mut decoder = Decoder::new(Policy::Require);
+///
+/// for read in reads {
+///     buffer[filled..filled + read.len()].copy_from_slice(read);
+///     filled += read.len();
+///
+///     match decoder.decode(&buffer[..filled]).unwrap() {
+///         ParseOutcome::Incomplete { needed } => assert!(needed > 0),
+///         ParseOutcome::Complete(header, length) => {
+///             assert_eq!(header.len(), 39);
+///             assert_eq!(&buffer[length..filled], b"GET / HTTP/1.1\r\n");
+///         }
+///         ParseOutcome::NotProxyProtocol => unreachable!(),
+///     }
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Decoder {
+    policy: Policy,
+    progress: Progress,
+}
+
+impl Decoder {
+    /// Creates a new `Decoder` with the given `Policy`.
+    pub fn new(policy: Policy) -> Self {
+        Decoder {
+            policy,
+            progress: Progress::default(),
+        }
+    }
+
+    /// Forgets the progress made on the current stream, so this `Decoder` can be reused for another one.
+    pub fn reset(&mut self) {
+        self.progress = Progress::default();
+    }
+
+    /// The `Policy` applied to each buffer.
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    /// Decodes the filled portion of the buffer, which must start at the first byte of the stream.
+    /// After an `Incomplete` outcome, the filled portion is expected to only grow;
+    /// passing a shorter one starts over as if it were a new stream.
+    ///
+    /// Streams without a header are `ParseOutcome::NotProxyProtocol` unless the `Policy` requires one,
+    /// and always are with `Policy::Ignore`.
+    pub fn decode<'a>(&mut self, filled: &'a [u8]) -> Result<ParseOutcome<'a>, Error> {
+        if self.policy == Policy::Ignore {
+            return Ok(ParseOutcome::NotProxyProtocol);
+        }
+
+        match self.progress.parse(filled, self.policy) {
+            None => Ok(ParseOutcome::Incomplete {
+                needed: outcome::needed(&self.progress, filled),
+            }),
+            Some(header) => header.map(ParseOutcome::from).map_err(Error::from),
+        }
+    }
+
+    /// Decodes the filled portion of the buffer once the stream has ended (i.e. a read returned 0 bytes).
+    /// Unlike `decode`, an incomplete header is an `UnexpectedEof` error, so the outcome is never `Incomplete`.
+    pub fn decode_eof<'a>(&mut self, filled: &'a [u8]) -> Result<ParseOutcome<'a>, Error> {
+        match self.decode(filled)? {
+            ParseOutcome::Incomplete { .. } => advance(filled, 0, self.policy)
+                .unwrap_or(Ok(None))
+                .map(ParseOutcome::from),
+            outcome => Ok(outcome),
+        }
+    }
+
+    /// Decodes the filled portion of the buffer like `decode`, telling a stream without a header apart
+    /// only by the missing header of a `Decoded::Complete`.
+    #[deprecated(note = "use `decode`, which returns a `ParseOutcome`")]
+    pub fn advance<'a>(&mut self, filled: &'a [u8]) -> Result<Decoded<'a>, Error> {
+        self.decode(filled).map(|outcome| decoded(outcome, filled))
+    }
+
+    /// Decodes the filled portion of the buffer once the stream has ended, like `decode_eof`.
+    #[deprecated(note = "use `decode_eof`, which returns a `ParseOutcome`")]
+    pub fn finish<'a>(&mut self, filled: &'a [u8]) -> Result<Decoded<'a>, Error> {
+        self.decode_eof(filled)
+            .map(|outcome| decoded(outcome, filled))
+    }
+}
+
+/// Converts an outcome into the deprecated `Decoded`, splitting the filled portion of the buffer after the header, if any.
+fn decoded<'a>(outcome: ParseOutcome<'a>, filled: &'a [u8]) -> Decoded<'a> {
+    let (header, consumed) = match outcome {
+        ParseOutcome::Incomplete { .. } => return Decoded::Incomplete,
+        ParseOutcome::Complete(header, length) => (Some(header), length),
+        ParseOutcome::NotProxyProtocol => (None, 0),
+    };
+
+    Decoded::Complete {
+        header,
+        consumed,
+        remaining: &filled[consumed..],
+    }
+}
+
+#[cfg(test)]
+// The deprecated `advance` and `finish` are still tested, since they remain public.
+#[allow(deprecated)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    const V2: &[u8] = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBBHello";
+
+    #[test]
+    fn v2_byte_at_a_time() {
+        let mut decoder = Decoder::new(Policy::Require);
+
+        for filled in 0..28 {
+            assert_eq!(decoder.advance(&V2[..filled]).unwrap(), Decoded::Incomplete);
+        }
+
+        match decoder.advance(V2).unwrap() {
+            Decoded::Complete {
+                header,
+                consumed,
+                remaining,
+            } => {
+                assert!(matches!(header, Some(Header::V2(..))));
+                assert_eq!(consumed, 28);
+                assert_eq!(remaining, b"Hello");
+            }
+            Decoded::Incomplete => panic!("expected a complete header"),
+        }
+    }
+
+    #[test]
+    fn reset() {
+        let mut decoder = Decoder::new(Policy::Require);
+
+        assert_eq!(decoder.advance(&V2[..20]).unwrap(), Decoded::Incomplete);
+        assert_ne!(decoder, Decoder::new(Policy::Require));
+
+        decoder.reset();
+        assert_eq!(decoder, Decoder::new(Policy::Require));
+        assert!(matches!(
+            decoder.advance(b"PROXY UNKNOWN\r\n").unwrap(),
+            Decoded::Complete { consumed: 15, .. }
+        ));
+    }
+
+    #[test]
+    fn optional_without_header() {
+        let mut decoder = Decoder::new(Policy::Optional);
+
+        assert_eq!(
+            decoder.advance(b"GET /").unwrap(),
+            Decoded::Complete {
+                header: None,
+                consumed: 0,
+                remaining: b"GET /"
+            }
+        );
+        assert_eq!(decoder.advance(b"").unwrap(), Decoded::Incomplete);
+        assert_eq!(
+            decoder.finish(b"").unwrap(),
+            Decoded::Complete {
+                header: None,
+                consumed: 0,
+                remaining: b""
+            }
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let mut decoder = Decoder::new(Policy::Require);
+
+        assert!(matches!(
+            decoder.advance(b"GET /").unwrap_err(),
+            Error::MissingHeader
+        ));
+        assert!(matches!(
+            decoder.finish(&V2[..20]).unwrap_err(),
+            Error::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof
+        ));
+        assert!(matches!(
+            decoder.finish(b"PROXY UNKNOWN\r\n").unwrap(),
+            Decoded::Complete { consumed: 15, .. }
+        ));
+    }
+
+    #[test]
+    fn ignore() {
+        let mut decoder = Decoder::new(Policy::Ignore);
+
+        assert_eq!(
+            decoder.advance(b"PROXY UNKNOWN\r\n").unwrap(),
+            Decoded::Complete {
+                header: None,
+                consumed: 0,
+                remaining: b"PROXY UNKNOWN\r\n"
+            }
+        );
+    }
+
+    #[test]
+    fn decode() {
+        let mut decoder = Decoder::new(Policy::Optional);
+
+        assert_eq!(
+            decoder.decode(&V2[..20]).unwrap(),
+            ParseOutcome::Incomplete { needed: 8 }
+        );
+        assert!(matches!(
+            decoder.decode(V2).unwrap(),
+            ParseOutcome::Complete(Header::V2(..), 28)
+        ));
+
+        decoder.reset();
+
+        assert_eq!(
+            decoder.decode(b"GET /").unwrap(),
+            ParseOutcome::NotProxyProtocol
+        );
+        assert_eq!(
+            decoder.decode_eof(b"").unwrap(),
+            ParseOutcome::NotProxyProtocol
+        );
+        assert!(matches!(
+            decoder.decode_eof(&V2[..20]).unwrap_err(),
+            Error::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof
+        ));
+        assert!(matches!(
+            Decoder::new(Policy::Require).decode(b"GET /").unwrap_err(),
+            Error::MissingHeader
+        ));
+        assert_eq!(
+            Decoder::new(Policy::Ignore).decode(V2).unwrap(),
+            ParseOutcome::NotProxyProtocol
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Errors for reading PROXY protocol headers from a stream.
+use std::prelude::v1::*;
+
+use crate::incremental::Invalid;
+use crate::policy::Violation;
+use crate::{v1, v2};
+
+/// An error in reading a PROXY protocol header from a stream.
+///
+/// Errors that wrap a cause (e.g. a rejected header's `Violation` or an error with captured bytes) do not repeat it
+/// in their own message, but return it from `source`, so error reports list each cause once.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    V1(#[from] v1::BinaryParseError),
+    #[error(transparent)]
+    V2(#[from] v2::ParseError),
+    #[error("Connection does not start with a PROXY protocol header.")]
+    MissingHeader,
+    #[error("Connection is from a peer that is not trusted to send a PROXY protocol header.")]
+    Untrusted,
+    #[error("Connection header was rejected.")]
+    Rejected(#[source] Violation),
+    #[error("Connection was dropped because too many headers are already being read.")]
+    Overloaded,
+    #[error("Failed to read a PROXY protocol header from {} captured bytes.", .bytes.len())]
+    Captured {
+        #[source]
+        error: Box<Error>,
+        bytes: Vec<u8>,
+    },
+}
+
+impl Error {
+    /// A short name for the kind of error, suitable for logs and metrics.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Error::Io(..) => "io",
+            Error::V1(..) => "v1",
+            Error::V2(..) => "v2",
+            Error::MissingHeader => "missing_header",
+            Error::Untrusted => "untrusted",
+            Error::Rejected(..) => "rejected",
+            Error::Overloaded => "overloaded",
+            Error::Captured { error, .. } => error.kind_name(),
+        }
+    }
+
+    /// The bytes read from the stream before the failure, if they were captured.
+    /// The bytes are limited to the capture size of the `Acceptor`.
+    pub fn captured(&self) -> Option<&[u8]> {
+        match self {
+            Error::Captured { bytes, .. } => Some(bytes.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Attaches up to `limit` of the bytes read before the failure to this error.
+    /// Errors are left as-is when nothing was read or the limit is 0.
+    pub(crate) fn capture(self, bytes: &[u8], limit: usize) -> Self {
+        let length = bytes.len().min(limit);
+
+        if length == 0 {
+            self
+        } else {
+            Error::Captured {
+                error: Box::new(self),
+                bytes: bytes[..length].to_vec(),
+            }
+        }
+    }
+
+    /// The kind of I/O error this error converts into.
+    fn io_kind(&self) -> std::io::ErrorKind {
+        match self {
+            Error::Io(error) => error.kind(),
+            Error::Untrusted => std::io::ErrorKind::PermissionDenied,
+            Error::Overloaded => std::io::ErrorKind::ConnectionRefused,
+            Error::Captured { error, .. } => error.io_kind(),
+            _ => std::io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl From<Invalid> for Error {
+    fn from(invalid: Invalid) -> Self {
+        match invalid {
+            Invalid::V1(error) => Error::V1(error),
+            Invalid::V2(error) => Error::V2(error),
+            Invalid::MissingHeader => Error::MissingHeader,
+        }
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            error => std::io::Error::new(error.io_kind(), error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    /// The messages of the error and each of its causes, in order.
+    fn chain(error: &dyn std::error::Error) -> Vec<String> {
+        let mut messages = vec![error.to_string()];
+        let mut source = error.source();
+
+        while let Some(error) = source {
+            messages.push(error.to_string());
+            source = error.source();
+        }
+
+        messages
+    }
+
+    #[test]
+    fn source_chain() {
+        let error = Error::Rejected(Violation::ZeroPort).capture(b"PROXY", 64);
+
+        assert_eq!(
+            chain(&error),
+            vec![
+                "Failed to read a PROXY protocol header from 5 captured bytes.",
+                "Connection header was rejected.",
+                "Header has a port of 0.",
+            ]
+        );
+
+        let utf8 = std::str::from_utf8(b"PROXY \xFF").unwrap_err();
+        let error = Error::from(v1::BinaryParseError::from(utf8));
+
+        assert_eq!(
+            chain(&error),
+            vec!["Header is not valid UTF-8.", &utf8.to_string()]
+        );
+
+        let error = std::io::Error::from(Error::Rejected(Violation::ZeroPort));
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            chain(&error),
+            vec!["Connection header was rejected.", "Header has a port of 0."]
+        );
+        assert!(error.source().is_some());
+    }
+}
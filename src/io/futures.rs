@@ -0,0 +1,316 @@
+//! Reading a PROXY protocol header from a `futures` stream of byte chunks, for transports other than sockets
+//! (e.g. a websocket tunnel or a message queue) that carry the bytes of a proxied connection.
+use std::prelude::v1::*;
+
+use super::Error;
+use crate::policy::Policy;
+use crate::Header;
+use bytes::Bytes;
+use futures_core::Stream;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// An item of a `HeaderStream`.
+// Only the first item holds a header, so boxing it would only add an allocation.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Frame {
+    /// The header of the connection, if any. Always the first item.
+    Header(Option<Header<'static>>),
+    /// A chunk of the payload that follows the header.
+    Payload(Bytes),
+}
+
+/// Where a `HeaderStream` is in the connection.
+#[derive(Debug)]
+enum State {
+    /// The header is not complete yet, and these are the bytes received so far.
+    Header(Vec<u8>),
+    /// The header was returned, and these bytes received past it have yet to be.
+    Leftover(Bytes),
+    /// The header and any leftover bytes were returned, so chunks are passed through as payload.
+    Payload,
+    /// The inner stream ended, or the header was invalid.
+    Done,
+}
+
+/// Adapts a stream of byte chunks into a `Frame::Header` followed by `Frame::Payload` chunks.
+///
+/// Chunks are buffered until the header is complete, so chunk boundaries may fall anywhere within the header.
+/// The bytes of the last chunk past the header are the first payload chunk; later chunks are passed through unchanged.
+/// An invalid header, or a stream that ends before the header is complete, is returned as the only error,
+/// after which the stream ends.
+///
+/// ## Examples
+/// ```rust
+/// use bytes::Bytes;
+/// use ppp::io::futures::{Frame, HeaderStream};
+/// use ppp::policy::Policy;
+/// use std::task::Poll;
+///
+/// # fn poll<S: futures_core::Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+/// #     use std::task::{Context, RawWaker, RawWakerVTable, Waker};
+/// #     const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RawWaker::new(std::ptr::null(), &VTABLE), |_| (), |_| (), |_| ());
+/// #     let waker = unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) };
+/// #     match std::pin::Pin::new(stream).poll_next(&mut Context::from_waker(&waker)) {
+/// #         Poll::Ready(item) => item,
+/// #         Poll::Pending => unreachable!(),
+/// #     }
+/// # }
+/// # struct Chunks(Vec<Bytes>);
+/// # impl futures_core::Stream for Chunks {
+/// #     type Item = Bytes;
+/// #     fn poll_next(mut self: std::pin::Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<Option<Bytes>> {
+/// #         Poll::Ready(if self.0.is_empty() { None } else { Some(self.0.remove(0)) })
+/// #     }
+/// # }
+/// let chunks = Chunks(vec![Bytes::from(&b"PROXY UNKN"[..]), Bytes::from(&b"OWN\r\nHello"[..])]);
+/// let mut stream = HeaderStream::new(chunks, Policy::Require);
+///
+/// assert!(matches!(poll(&mut stream), Some(Ok(Frame::Header(Some(..))))));
+/// assert_eq!(poll(&mut stream).unwrap().unwrap(), Frame::Payload(Bytes::from(&b"Hello"[..])));
+/// assert!(poll(&mut stream).is_none());
+/// ```
+#[derive(Debug)]
+pub struct HeaderStream<S> {
+    inner: S,
+    policy: Policy,
+    state: State,
+}
+
+impl<S> HeaderStream<S> {
+    /// Creates a new `HeaderStream` that reads a header from the chunks of the inner stream with the given `Policy`.
+    pub fn new(inner: S, policy: Policy) -> Self {
+        HeaderStream {
+            inner,
+            policy,
+            state: State::Header(Vec::new()),
+        }
+    }
+
+    /// Gets a reference to the inner stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consumes this `HeaderStream`, returning the inner stream.
+    /// Any bytes buffered but not yet returned are lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Stream<Item = Bytes> + Unpin> HeaderStream<S> {
+    /// Polls the inner stream for chunks until the header is complete, or the bytes cannot be a header.
+    fn poll_header(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Frame, Error>>> {
+        if self.policy == Policy::Ignore {
+            return self.header(None, 0);
+        }
+
+        loop {
+            let chunk = match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                // An empty chunk is not the end of the stream, so it must not be handed to `advance` as one.
+                Poll::Ready(Some(chunk)) if chunk.is_empty() => continue,
+                Poll::Ready(chunk) => chunk,
+            };
+            let buffer = match &mut self.state {
+                State::Header(buffer) => buffer,
+                _ => unreachable!(),
+            };
+            let read = chunk.as_ref().map_or(0, |chunk| chunk.len());
+
+            buffer.extend_from_slice(chunk.as_deref().unwrap_or_default());
+
+            let result = super::advance(buffer.as_slice(), read, self.policy)
+                .map(|result| result.map(|header| header.map(|header| header.to_owned())));
+
+            match result {
+                None => continue,
+                Some(Ok(header)) if read == 0 => {
+                    self.state = State::Done;
+                    return Poll::Ready(Some(Ok(Frame::Header(header))));
+                }
+                Some(Ok(header)) => {
+                    let length = header.as_ref().map_or(0, Header::len);
+
+                    return self.header(header, length);
+                }
+                Some(Err(error)) => {
+                    self.state = State::Done;
+                    return Poll::Ready(Some(Err(error)));
+                }
+            }
+        }
+    }
+
+    /// Returns the header, keeping the bytes received past its length as the first payload chunk.
+    fn header(
+        &mut self,
+        header: Option<Header<'static>>,
+        length: usize,
+    ) -> Poll<Option<Result<Frame, Error>>> {
+        self.state = match mem::replace(&mut self.state, State::Done) {
+            State::Header(buffer) if buffer.len() > length => {
+                State::Leftover(Bytes::from(buffer[length..].to_vec()))
+            }
+            _ => State::Payload,
+        };
+
+        Poll::Ready(Some(Ok(Frame::Header(header))))
+    }
+}
+
+impl<S: Stream<Item = Bytes> + Unpin> Stream for HeaderStream<S> {
+    type Item = Result<Frame, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        match mem::replace(&mut this.state, State::Payload) {
+            State::Header(buffer) => {
+                this.state = State::Header(buffer);
+                this.poll_header(cx)
+            }
+            State::Leftover(leftover) => Poll::Ready(Some(Ok(Frame::Payload(leftover)))),
+            State::Payload => match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(None) => {
+                    this.state = State::Done;
+                    Poll::Ready(None)
+                }
+                poll => poll.map(|chunk| chunk.map(|chunk| Ok(Frame::Payload(chunk)))),
+            },
+            State::Done => {
+                this.state = State::Done;
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::io;
+    use std::ptr;
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    /// A stream of chunks that is pending before each chunk, waking the task straight away.
+    struct Chunks {
+        chunks: VecDeque<&'static [u8]>,
+        ready: bool,
+    }
+
+    impl Stream for Chunks {
+        type Item = Bytes;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+            self.ready = !self.ready;
+
+            if self.ready {
+                Poll::Ready(self.chunks.pop_front().map(Bytes::from))
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    fn waker() -> Waker {
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(
+            |_| RawWaker::new(ptr::null(), &VTABLE),
+            |_| (),
+            |_| (),
+            |_| (),
+        );
+
+        unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+    }
+
+    /// Polls the adapted stream to its end.
+    fn collect(chunks: &[&'static [u8]], policy: Policy) -> Vec<Result<Frame, Error>> {
+        let waker = waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut stream = HeaderStream::new(
+            Chunks {
+                chunks: chunks.iter().copied().collect(),
+                ready: false,
+            },
+            policy,
+        );
+        let mut frames = Vec::new();
+
+        loop {
+            match Pin::new(&mut stream).poll_next(&mut cx) {
+                Poll::Ready(Some(frame)) => frames.push(frame),
+                Poll::Ready(None) => return frames,
+                Poll::Pending => (),
+            }
+        }
+    }
+
+    fn payload(frame: &Result<Frame, Error>) -> &[u8] {
+        match frame {
+            Ok(Frame::Payload(chunk)) => chunk,
+            _ => panic!("expected a payload chunk, got {:?}", frame),
+        }
+    }
+
+    #[test]
+    fn split_chunks() {
+        let frames = collect(
+            &[
+                b"\r\n\r\n\0\r\nQUIT\n",
+                b"",
+                b"\x21\x11\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBBHel",
+                b"lo",
+            ],
+            Policy::Require,
+        );
+
+        assert_eq!(frames.len(), 3);
+        assert!(matches!(
+            &frames[0],
+            Ok(Frame::Header(Some(Header::V2(..))))
+        ));
+        assert_eq!(payload(&frames[1]), b"Hel");
+        assert_eq!(payload(&frames[2]), b"lo");
+    }
+
+    #[test]
+    fn without_header() {
+        let frames = collect(&[b"GET / HTTP/1.1\r\n"], Policy::Optional);
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].as_ref().unwrap(), &Frame::Header(None));
+        assert_eq!(payload(&frames[1]), b"GET / HTTP/1.1\r\n");
+
+        let frames = collect(&[], Policy::Optional);
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].as_ref().unwrap(), &Frame::Header(None));
+
+        let frames = collect(&[b"PROXY UNKNOWN\r\n"], Policy::Ignore);
+
+        assert_eq!(frames[0].as_ref().unwrap(), &Frame::Header(None));
+        assert_eq!(payload(&frames[1]), b"PROXY UNKNOWN\r\n");
+    }
+
+    #[test]
+    fn errors() {
+        let frames = collect(&[b"PROXY UNKNOWN"], Policy::Require);
+
+        assert_eq!(frames.len(), 1);
+        assert!(matches!(
+            &frames[0],
+            Err(Error::Io(error)) if error.kind() == io::ErrorKind::UnexpectedEof
+        ));
+
+        let frames = collect(&[b"GET / HTTP/1.1\r\n", b"Host: \r\n"], Policy::Require);
+
+        assert_eq!(frames.len(), 1);
+        assert!(matches!(&frames[0], Err(Error::MissingHeader)));
+    }
+}
@@ -0,0 +1,196 @@
+//! A TCP listener that reads the PROXY protocol header of each accepted connection.
+use std::prelude::v1::*;
+
+use super::{Acceptor, Error, ProxiedStream};
+use crate::policy::Policy;
+use crate::Header;
+use std::io::{self, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Wraps a `TcpListener` to read the PROXY protocol header of each connection as it is accepted.
+///
+/// Headers are read on the thread calling `accept`, so a slow peer delays the connections behind it.
+/// Setting a `timeout` bounds the delay.
+///
+/// ## Examples
+/// ```rust,no_run
+/// use ppp::io::{Acceptor, ProxyListener};
+/// use ppp::policy::Policy;
+/// use std::time::Duration;
+///
+/// let listener = ProxyListener::bind("0.0.0.0:8080", Acceptor::new(Policy::Require))
+///     .unwrap()
+///     .timeout(Duration::from_secs(5));
+///
+/// loop {
+///     match listener.accept() {
+///         Ok((stream, header)) => println!("{:?} from {:?}", header, stream.get_ref().peer_addr()),
+///         Err(error) => eprintln!("{}", error),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ProxyListener {
+    inner: TcpListener,
+    acceptor: Acceptor,
+    timeout: Option<Duration>,
+}
+
+impl ProxyListener {
+    /// Creates a new `ProxyListener` that reads headers from the connections of the given listener.
+    pub fn new(inner: TcpListener, acceptor: Acceptor) -> Self {
+        ProxyListener {
+            inner,
+            acceptor,
+            timeout: None,
+        }
+    }
+
+    /// Creates a new `ProxyListener` bound to the given address.
+    pub fn bind<A: ToSocketAddrs>(address: A, acceptor: Acceptor) -> io::Result<Self> {
+        Ok(ProxyListener::new(TcpListener::bind(address)?, acceptor))
+    }
+
+    /// Limits the time spent reading the header of each connection.
+    /// Connections that do not send a complete header in time fail with a `TimedOut` I/O error.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The local address of the inner listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Gets a reference to the inner listener.
+    pub fn get_ref(&self) -> &TcpListener {
+        &self.inner
+    }
+
+    /// Consumes this `ProxyListener`, returning the inner listener.
+    pub fn into_inner(self) -> TcpListener {
+        self.inner
+    }
+
+    /// Accepts a new connection and reads its PROXY protocol header.
+    /// Returns the stream, positioned after the header, along with the header if one was present.
+    pub fn accept(&self) -> Result<(ProxiedStream<TcpStream>, Option<Header<'static>>), Error> {
+        let (stream, peer) = self.inner.accept()?;
+        let result = self.acceptor.permit().and_then(|_permit| {
+            self.acceptor
+                .policy_for(peer.ip())
+                .and_then(|policy| self.read_header(stream, policy))
+                .and_then(|accepted| self.acceptor.validate(accepted))
+        });
+
+        self.acceptor.record(&result);
+
+        result
+    }
+
+    /// Reads the header of an accepted connection, limited by the timeout if any.
+    fn read_header(
+        &self,
+        stream: TcpStream,
+        policy: Policy,
+    ) -> Result<(ProxiedStream<TcpStream>, Option<Header<'static>>), Error> {
+        let (buffer, header) = match self.timeout {
+            Some(timeout) => {
+                let deadline = Deadline {
+                    stream: &stream,
+                    deadline: Instant::now() + timeout,
+                };
+                let result = self.acceptor.read_header(deadline, policy);
+
+                stream.set_read_timeout(None)?;
+
+                let (reader, header) = result?;
+                (reader.into_parts().1, header)
+            }
+            None => {
+                let (reader, header) = self.acceptor.read_header(&stream, policy)?;
+                (reader.into_parts().1, header)
+            }
+        };
+
+        Ok((ProxiedStream::new(stream, buffer), header))
+    }
+}
+
+/// A reader that fails with a `TimedOut` I/O error once the deadline has passed.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl<'a> Read for Deadline<'a> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        self.stream.set_read_timeout(Some(remaining))?;
+
+        match self.stream.read(buffer) {
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                Err(io::ErrorKind::TimedOut.into())
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1;
+    use std::io::Write;
+    use std::thread;
+
+    fn listener(timeout: Duration) -> ProxyListener {
+        ProxyListener::bind("127.0.0.1:0", Acceptor::new(Policy::Require))
+            .unwrap()
+            .timeout(timeout)
+    }
+
+    #[test]
+    fn accept() {
+        let listener = listener(Duration::from_secs(5));
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+
+            stream.write_all(b"PROXY UNKNOWN\r\nHello").unwrap();
+        });
+
+        let (mut stream, header) = listener.accept().unwrap();
+        let mut payload = Vec::new();
+
+        client.join().unwrap();
+        stream.read_to_end(&mut payload).unwrap();
+
+        assert_eq!(
+            header,
+            Some(Header::V1(v1::Header::new(
+                "PROXY UNKNOWN\r\n",
+                v1::Addresses::Unknown
+            )))
+        );
+        assert_eq!(payload, b"Hello");
+        assert_eq!(stream.get_ref().read_timeout().unwrap(), None);
+    }
+
+    #[test]
+    fn accept_timeout() {
+        let listener = listener(Duration::from_millis(50));
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let error = listener.accept().unwrap_err();
+
+        assert!(matches!(error, Error::Io(e) if e.kind() == io::ErrorKind::TimedOut));
+    }
+}
@@ -0,0 +1,712 @@
+//! Helpers for reading a PROXY protocol header from the start of a stream.
+//!
+//! ## Examples
+//! ```rust
+//! use ppp::io::read_header;
+//! use ppp::policy::Policy;
+//! use ppp::{v1, Header};
+//! use std::io::{Cursor, Read};
+//!
+//! let input = Cursor::new("PROXY UNKNOWN\r\nHello, World!");
+//! let (mut stream, header) = read_header(input, Policy::Require).unwrap();
+//! let mut payload = String::new();
+//!
+//! stream.read_to_string(&mut payload).unwrap();
+//!
+//! assert_eq!(header, Some(Header::V1(v1::Header::new("PROXY UNKNOWN\r\n", v1::Addresses::Unknown))));
+//! assert_eq!(payload, "Hello, World!");
+//! ```
+use std::prelude::v1::*;
+
+mod backpressure;
+mod decoder;
+mod error;
+#[cfg(feature = "futures")]
+pub mod futures;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod listener;
+mod nonblocking;
+mod reader;
+mod stream;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+pub use decoder::{Decoded, Decoder};
+pub use error::Error;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use listener::ProxyListener;
+pub use nonblocking::{NonBlockingReader, ReadStatus};
+pub use reader::HeaderReader;
+pub use stream::ProxiedStream;
+
+use crate::metrics::{self, Recorder};
+use crate::policy::{ChecksumPolicy, Decision, Policy, ReservedTypes, TrustedPeers, Validation};
+use crate::{incremental, Header};
+use backpressure::{Limiter, Permit};
+use std::fmt;
+use std::io::{self, Read};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// The number of bytes to read from the stream at a time.
+const READ_SIZE: usize = 256;
+
+/// Accepts connections according to a `Policy`,
+/// only honoring PROXY protocol headers from trusted peers when `TrustedPeers` are configured.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::io::{Acceptor, Error};
+/// use ppp::policy::{Policy, TrustedPeers, Untrusted};
+/// use std::io::Cursor;
+///
+/// let acceptor = Acceptor::new(Policy::Require).trusted_peers(
+///     TrustedPeers::new()
+///         .trust("10.0.0.0/8".parse().unwrap())
+///         .untrusted(Untrusted::Reject),
+/// );
+///
+/// let (_, header) = acceptor
+///     .accept(Cursor::new("PROXY UNKNOWN\r\n"), "10.0.0.1".parse().unwrap())
+///     .unwrap();
+/// assert!(header.is_some());
+///
+/// let error = acceptor
+///     .accept(Cursor::new("PROXY UNKNOWN\r\n"), "192.168.1.1".parse().unwrap())
+///     .unwrap_err();
+/// assert!(matches!(error, Error::Untrusted));
+/// ```
+#[derive(Clone)]
+pub struct Acceptor {
+    policy: Policy,
+    trusted_peers: Option<TrustedPeers>,
+    recorder: Option<Arc<dyn Recorder + Send + Sync>>,
+    capture: usize,
+    validation: Validation,
+    checksum: ChecksumPolicy,
+    reserved_types: ReservedTypes,
+    limiter: Option<Arc<Limiter>>,
+}
+
+impl fmt::Debug for Acceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Acceptor")
+            .field("policy", &self.policy)
+            .field("trusted_peers", &self.trusted_peers)
+            .field("recorder", &self.recorder.is_some())
+            .field("capture", &self.capture)
+            .field("validation", &self.validation)
+            .field("checksum", &self.checksum)
+            .field("reserved_types", &self.reserved_types)
+            .field("limiter", &self.limiter)
+            .finish()
+    }
+}
+
+impl Acceptor {
+    /// Creates a new `Acceptor` with the given `Policy` that trusts all peers.
+    pub fn new(policy: Policy) -> Self {
+        Acceptor {
+            policy,
+            trusted_peers: None,
+            recorder: None,
+            capture: 0,
+            validation: Validation::new(),
+            checksum: ChecksumPolicy::Ignore,
+            reserved_types: ReservedTypes::Allow,
+            limiter: None,
+        }
+    }
+
+    /// Only honors PROXY protocol headers from the given trusted peers.
+    pub fn trusted_peers(mut self, trusted_peers: TrustedPeers) -> Self {
+        self.trusted_peers = Some(trusted_peers);
+        self
+    }
+
+    /// Reports the outcome of each accepted connection to the given `Recorder`.
+    pub fn recorder<R: Recorder + Send + Sync + 'static>(mut self, recorder: Arc<R>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Attaches up to `limit` of the bytes read before a failure to the returned error, as `Error::Captured`.
+    /// Helps reproduce malformed headers without capturing packets. Disabled (a limit of 0) by default.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::io::Acceptor;
+    /// use ppp::policy::Policy;
+    /// use std::io::Cursor;
+    ///
+    /// let error = Acceptor::new(Policy::Require)
+    ///     .capture(16)
+    ///     .accept(Cursor::new("PROXY TCP4 127.0.0.1 127.0.0.1 80 65536\r\n"), "10.0.0.1".parse().unwrap())
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(error.captured(), Some(&b"PROXY TCP4 127.0"[..]));
+    /// ```
+    pub fn capture(mut self, limit: usize) -> Self {
+        self.capture = limit;
+        self
+    }
+
+    /// Rejects headers whose addresses fail the given `Validation` with `Error::Rejected`.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::io::{Acceptor, Error};
+    /// use ppp::policy::{Policy, Validation, Violation};
+    /// use std::io::Cursor;
+    ///
+    /// let error = Acceptor::new(Policy::Require)
+    ///     .validation(Validation::all())
+    ///     .accept(Cursor::new("PROXY TCP4 127.0.0.1 127.0.0.1 0 443\r\n"), "10.0.0.1".parse().unwrap())
+    ///     .unwrap_err();
+    ///
+    /// assert!(matches!(error, Error::Rejected(Violation::ZeroPort)));
+    /// ```
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Rejects binary headers whose CRC32c checksum fails the given `ChecksumPolicy` with `Error::Rejected`.
+    /// Checksums are not verified by default.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::io::{Acceptor, Error};
+    /// use ppp::policy::{ChecksumPolicy, Policy, Violation};
+    /// use std::io::Cursor;
+    ///
+    /// let error = Acceptor::new(Policy::Require)
+    ///     .checksum(ChecksumPolicy::Require)
+    ///     .accept(Cursor::new(b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00"), "10.0.0.1".parse().unwrap())
+    ///     .unwrap_err();
+    ///
+    /// assert!(matches!(error, Error::Rejected(Violation::MissingChecksum)));
+    /// ```
+    pub fn checksum(mut self, checksum: ChecksumPolicy) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Warns about or rejects binary headers with TLVs of types the specification reserves for future use,
+    /// according to the given `ReservedTypes`. Rejected headers fail with `Error::Rejected`.
+    /// All TLV types are allowed by default.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::io::{Acceptor, Error};
+    /// use ppp::policy::{Policy, ReservedTypes, Violation};
+    /// use std::io::Cursor;
+    ///
+    /// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0F\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB\x40\x00\x00";
+    /// let error = Acceptor::new(Policy::Require)
+    ///     .reserved_types(ReservedTypes::Reject)
+    ///     .accept(Cursor::new(&input[..]), "10.0.0.1".parse().unwrap())
+    ///     .unwrap_err();
+    ///
+    /// assert!(matches!(error, Error::Rejected(Violation::ReservedType(0x40))));
+    /// ```
+    pub fn reserved_types(mut self, reserved_types: ReservedTypes) -> Self {
+        self.reserved_types = reserved_types;
+        self
+    }
+
+    /// Limits the headers read at the same time by this `Acceptor` and its clones to `limit` (at least 1).
+    /// Up to `queue` more connections wait for a slot, so a flood of connections that never finish their headers
+    /// cannot hold unbounded memory; connections beyond the queue are dropped with `Error::Overloaded`.
+    /// Reads are unlimited by default.
+    ///
+    /// `accept` blocks the calling thread while waiting for a slot, and the `tokio` `ProxyListener` yields instead.
+    /// Time spent waiting does not count towards a listener's timeout.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::io::Acceptor;
+    /// use ppp::policy::Policy;
+    /// use std::io::Cursor;
+    ///
+    /// let acceptor = Acceptor::new(Policy::Require).max_in_flight(1024, 256);
+    /// let workers: Vec<_> = (0..4)
+    ///     .map(|_| {
+    ///         let acceptor = acceptor.clone();
+    ///         std::thread::spawn(move || acceptor.accept(Cursor::new("PROXY UNKNOWN\r\n"), "10.0.0.1".parse().unwrap()).is_ok())
+    ///     })
+    ///     .collect();
+    ///
+    /// assert!(workers.into_iter().all(|worker| worker.join().unwrap()));
+    /// ```
+    pub fn max_in_flight(mut self, limit: usize, queue: usize) -> Self {
+        self.limiter = Some(Arc::new(Limiter::new(limit, queue)));
+        self
+    }
+
+    /// The `Policy` used for trusted peers.
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    /// Reads the PROXY protocol header of a connection from the given peer address.
+    /// Returns the stream, positioned after the header, along with the header if one was present.
+    pub fn accept<S: Read>(
+        &self,
+        stream: S,
+        peer: IpAddr,
+    ) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error> {
+        let result = self.permit().and_then(|_permit| {
+            self.policy_for(peer)
+                .and_then(|policy| self.read_header(stream, policy))
+                .and_then(|accepted| self.validate(accepted))
+        });
+
+        self.record(&result);
+
+        result
+    }
+
+    /// Reads the header of a connection with the given `Policy`, capturing bytes on failure if configured.
+    pub(crate) fn read_header<S: Read>(
+        &self,
+        stream: S,
+        policy: Policy,
+    ) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error> {
+        read_header_capturing(stream, policy, self.capture)
+    }
+
+    /// Checks the addresses, the checksum and the TLV types of an accepted connection's header
+    /// against the `Validation`, the `ChecksumPolicy` and the `ReservedTypes`.
+    pub(crate) fn validate<S>(
+        &self,
+        accepted: (ProxiedStream<S>, Option<Header<'static>>),
+    ) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error> {
+        if let Some(header) = accepted.1.as_ref() {
+            self.validation.check(header).map_err(Error::Rejected)?;
+            self.checksum.check(header).map_err(Error::Rejected)?;
+            self.reserved_types.check(header).map_err(Error::Rejected)?;
+        }
+
+        Ok(accepted)
+    }
+
+    /// Takes a slot for reading a header, blocking while the in-flight limit is reached, if there is one.
+    pub(crate) fn permit(&self) -> Result<Option<Permit>, Error> {
+        self.limiter.as_ref().map(Limiter::acquire).transpose()
+    }
+
+    /// Takes a slot for reading a header like `permit`, yielding instead of blocking.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn permit_async(&self) -> Result<Option<Permit>, Error> {
+        match self.limiter.as_ref() {
+            Some(limiter) => limiter.acquire_async().await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The number of bytes to attach to errors.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn capture_limit(&self) -> usize {
+        self.capture
+    }
+
+    /// Reports the outcome of accepting a connection to the `Recorder`, if any.
+    pub(crate) fn record<S>(
+        &self,
+        result: &Result<(ProxiedStream<S>, Option<Header<'static>>), Error>,
+    ) {
+        if let Some(recorder) = self.recorder.as_ref() {
+            metrics::record(
+                recorder.as_ref(),
+                result.as_ref().map(|(_, header)| header.as_ref()),
+            );
+        }
+    }
+
+    /// The `Policy` to apply to a connection from the given peer address.
+    pub(crate) fn policy_for(&self, peer: IpAddr) -> Result<Policy, Error> {
+        let decision = self
+            .trusted_peers
+            .as_ref()
+            .map(|trusted_peers| trusted_peers.evaluate(peer))
+            .unwrap_or(Decision::Honor);
+
+        #[cfg(feature = "tracing")]
+        if decision != Decision::Honor {
+            tracing::debug!(%peer, ?decision, "peer is not trusted to send a PROXY protocol header");
+        }
+
+        match decision {
+            Decision::Honor => Ok(self.policy),
+            Decision::Ignore => Ok(Policy::Ignore),
+            Decision::Reject => Err(Error::Untrusted),
+        }
+    }
+}
+
+/// Reads a PROXY protocol header of either version from the start of the stream according to the given `Policy`.
+/// Returns the stream, positioned after the header, along with the header if one was present.
+///
+/// Bytes are read from the stream until a header is complete, so some of the payload may be buffered by the returned stream.
+pub fn read_header<S: Read>(
+    stream: S,
+    policy: Policy,
+) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error> {
+    read_header_capturing(stream, policy, 0)
+}
+
+/// Reads a header like `read_header` from a stream behind a trait object.
+///
+/// Every stream type passed to `read_header` compiles its own copy of the reading code.
+/// Binaries that read headers from many stream types can use this instead to share a single copy between them.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::io::read_header_dyn;
+/// use ppp::policy::Policy;
+/// use std::io::{Cursor, Read};
+///
+/// let mut input = Cursor::new("PROXY UNKNOWN\r\nHello, World!");
+/// let (mut stream, header) = read_header_dyn(&mut input, Policy::Require).unwrap();
+/// let mut payload = String::new();
+///
+/// stream.read_to_string(&mut payload).unwrap();
+///
+/// assert!(header.is_some());
+/// assert_eq!(payload, "Hello, World!");
+/// ```
+pub fn read_header_dyn(
+    stream: &mut dyn Read,
+    policy: Policy,
+) -> Result<(ProxiedStream<&mut dyn Read>, Option<Header<'static>>), Error> {
+    read_header_capturing(stream, policy, 0)
+}
+
+/// Reads a header like `read_header`, attaching up to `capture` of the bytes read to errors.
+fn read_header_capturing<S: Read>(
+    mut stream: S,
+    policy: Policy,
+    capture: usize,
+) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("read_header", ?policy).entered();
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+
+    let mut buffer = Vec::new();
+    let header = read_buffered(&mut stream, policy, &mut buffer);
+
+    #[cfg(feature = "tracing")]
+    record(&header, started.elapsed());
+
+    let header = header.map_err(|error| error.capture(&buffer, capture))?;
+
+    if let Some(header) = header.as_ref() {
+        buffer.drain(..header.len());
+    }
+
+    Ok((ProxiedStream::new(stream, buffer), header))
+}
+
+/// Reads from the stream into the buffer until a header is complete, or the bytes cannot be a header.
+fn read_buffered(
+    stream: &mut dyn Read,
+    policy: Policy,
+    buffer: &mut Vec<u8>,
+) -> Result<Option<Header<'static>>, Error> {
+    if policy == Policy::Ignore {
+        return Ok(None);
+    }
+
+    loop {
+        let length = buffer.len();
+
+        buffer.resize(length + READ_SIZE, 0);
+
+        let read = match stream.read(&mut buffer[length..]) {
+            Ok(read) => read,
+            Err(error) => {
+                buffer.truncate(length);
+
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                } else {
+                    return Err(error.into());
+                }
+            }
+        };
+
+        buffer.truncate(length + read);
+
+        if let Some(result) = advance(buffer.as_slice(), read, policy) {
+            return result.map(|header| header.map(|header| header.to_owned()));
+        }
+    }
+}
+
+/// Emits an event for the outcome of reading a header.
+#[cfg(feature = "tracing")]
+fn record(result: &Result<Option<Header<'static>>, Error>, elapsed: std::time::Duration) {
+    match result {
+        Ok(Some(header)) => tracing::debug!(
+            version = ?header.version(),
+            length = header.len(),
+            ?elapsed,
+            "read PROXY protocol header"
+        ),
+        Ok(None) => tracing::debug!(?elapsed, "connection has no PROXY protocol header"),
+        Err(error) => tracing::warn!(
+            kind = error.kind_name(),
+            code = error.code().name,
+            %error,
+            ?elapsed,
+            "failed to read PROXY protocol header"
+        ),
+    }
+}
+
+/// Handles the bytes read so far after a read of the given length, where a length of 0 is the end of the stream.
+/// Returns `None` when more bytes are needed.
+fn advance(input: &[u8], read: usize, policy: Policy) -> Option<Result<Option<Header<'_>>, Error>> {
+    if read == 0 {
+        Some(if input.is_empty() && policy == Policy::Optional {
+            Ok(None)
+        } else {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        })
+    } else {
+        parse(input, policy)
+    }
+}
+
+/// Parses a header from the bytes read so far.
+/// Returns `None` when more bytes are needed.
+fn parse(input: &[u8], policy: Policy) -> Option<Result<Option<Header<'_>>, Error>> {
+    incremental::parse(input, policy).map(|result| result.map_err(Error::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::Untrusted;
+    use crate::v2::{Builder, Command, Protocol, Type};
+    use crate::{v1, v2};
+    use std::io::Cursor;
+
+    /// A reader that returns a single byte per call to `read`.
+    #[derive(Debug)]
+    struct OneByte<'a>(&'a [u8]);
+
+    impl<'a> Read for OneByte<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buffer.first_mut()) {
+                (Some((byte, rest)), Some(first)) => {
+                    *first = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn read_payload<S: Read>(mut stream: S) -> Vec<u8> {
+        let mut payload = Vec::new();
+
+        stream.read_to_end(&mut payload).unwrap();
+
+        payload
+    }
+
+    #[test]
+    fn v1_one_byte_at_a_time() {
+        let input = b"PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\nHello";
+        let (stream, header) = read_header(OneByte(input), Policy::Require).unwrap();
+
+        assert_eq!(
+            header,
+            Some(Header::V1(v1::Header::new(
+                "PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\n",
+                v1::Addresses::new_tcp4([127, 0, 0, 1], [192, 168, 1, 1], 80, 443),
+            )))
+        );
+        assert_eq!(read_payload(stream), b"Hello");
+    }
+
+    #[test]
+    fn dyn_stream() {
+        let mut input = OneByte(b"PROXY UNKNOWN\r\nHello");
+        let (stream, header) = read_header_dyn(&mut input, Policy::Require).unwrap();
+
+        assert_eq!(
+            header,
+            Some(Header::V1(v1::Header::new(
+                "PROXY UNKNOWN\r\n",
+                v1::Addresses::Unknown
+            )))
+        );
+        assert_eq!(read_payload(stream), b"Hello");
+    }
+
+    #[test]
+    fn v2_with_payload() {
+        let mut input = Builder::with_addresses(
+            v2::Version::Two | Command::Proxy,
+            Protocol::Stream,
+            v2::IPv4::new([127, 0, 0, 1], [192, 168, 1, 1], 80, 443),
+        )
+        .write_tlv(Type::NoOp, &[42])
+        .unwrap()
+        .build()
+        .unwrap();
+        let length = input.len();
+
+        input.extend_from_slice(b"Hello");
+
+        let (stream, header) =
+            read_header(Cursor::new(input.as_slice()), Policy::Optional).unwrap();
+
+        assert_eq!(
+            header,
+            Some(Header::V2(
+                v2::Header::try_from(&input[..length]).unwrap().to_owned()
+            ))
+        );
+        assert_eq!(stream.buffered(), b"Hello");
+        assert_eq!(read_payload(stream), b"Hello");
+    }
+
+    #[test]
+    fn optional_without_header() {
+        let input = b"GET / HTTP/1.1\r\n\r\n";
+        let (stream, header) = read_header(OneByte(input), Policy::Optional).unwrap();
+
+        assert_eq!(header, None);
+        assert_eq!(read_payload(stream), input);
+    }
+
+    #[test]
+    fn optional_empty() {
+        let (stream, header) = read_header(Cursor::new(b""), Policy::Optional).unwrap();
+
+        assert_eq!(header, None);
+        assert_eq!(read_payload(stream), b"");
+    }
+
+    #[test]
+    fn require_without_header() {
+        let error =
+            read_header(Cursor::new(b"GET / HTTP/1.1\r\n\r\n"), Policy::Require).unwrap_err();
+
+        assert!(matches!(error, Error::MissingHeader));
+    }
+
+    #[test]
+    fn require_rejects_first_byte() {
+        let mut input = OneByte(b"GET / HTTP/1.1\r\n\r\n");
+        let error = read_header(&mut input, Policy::Require).unwrap_err();
+
+        assert!(matches!(error, Error::MissingHeader));
+        assert_eq!(input.0, b"ET / HTTP/1.1\r\n\r\n");
+    }
+
+    #[test]
+    fn optional_invalid_header() {
+        let error = read_header(Cursor::new(b"PROXY TCP4\r\n"), Policy::Optional).unwrap_err();
+
+        assert!(matches!(
+            error,
+            Error::V1(v1::BinaryParseError::Parse(
+                v1::ParseError::MissingDestinationAddress
+            ))
+        ));
+    }
+
+    #[test]
+    fn require_partial_header() {
+        let error = read_header(OneByte(b"PROXY TCP4 127.0.0.1"), Policy::Require).unwrap_err();
+
+        assert!(matches!(error, Error::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn ignore() {
+        let input = b"PROXY UNKNOWN\r\n";
+        let (stream, header) = read_header(Cursor::new(input), Policy::Ignore).unwrap();
+
+        assert_eq!(header, None);
+        assert_eq!(read_payload(stream), input);
+    }
+
+    #[test]
+    fn accept_untrusted() {
+        let input = b"PROXY UNKNOWN\r\n";
+        let peers = TrustedPeers::new().trust("127.0.0.1".parse().unwrap());
+        let acceptor = Acceptor::new(Policy::Require).trusted_peers(peers.clone());
+        let local = "127.0.0.1".parse().unwrap();
+        let remote = "192.168.1.1".parse().unwrap();
+
+        let (_, header) = acceptor.accept(Cursor::new(input), local).unwrap();
+        assert!(header.is_some());
+
+        let (stream, header) = acceptor.accept(Cursor::new(input), remote).unwrap();
+        assert_eq!(header, None);
+        assert_eq!(read_payload(stream), input);
+
+        let error = Acceptor::new(Policy::Require)
+            .trusted_peers(peers.untrusted(Untrusted::Reject))
+            .accept(Cursor::new(input), remote)
+            .unwrap_err();
+        assert!(matches!(error, Error::Untrusted));
+    }
+
+    #[test]
+    fn accept_capture() {
+        let input = b"PROXY TCP4 127.0.0.1 192.168.1.1 80 65536\r\nHello";
+        let peer = "127.0.0.1".parse().unwrap();
+
+        let error = Acceptor::new(Policy::Require)
+            .accept(Cursor::new(input), peer)
+            .unwrap_err();
+        assert_eq!(error.captured(), None);
+
+        let error = Acceptor::new(Policy::Require)
+            .capture(1024)
+            .accept(OneByte(input), peer)
+            .unwrap_err();
+        assert_eq!(error.captured(), Some(&input[..43]));
+        assert_eq!(
+            error.code(),
+            v1::ParseError::InvalidDestinationPort(None).code()
+        );
+        assert!(matches!(
+            error,
+            Error::Captured { error, .. } if matches!(*error, Error::V1(..))
+        ));
+
+        let error = Acceptor::new(Policy::Require)
+            .capture(5)
+            .accept(Cursor::new(input), peer)
+            .unwrap_err();
+        assert_eq!(error.captured(), Some(&b"PROXY"[..]));
+        assert_eq!(error.kind_name(), "v1");
+    }
+
+    #[test]
+    fn capture_eof() {
+        let error = Acceptor::new(Policy::Require)
+            .capture(1024)
+            .accept(Cursor::new(b"PROXY TCP4"), "127.0.0.1".parse().unwrap())
+            .unwrap_err();
+
+        assert_eq!(error.captured(), Some(&b"PROXY TCP4"[..]));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
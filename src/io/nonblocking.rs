@@ -0,0 +1,300 @@
+//! A reader for non-blocking streams driven by readiness events (e.g. `mio`).
+use std::prelude::v1::*;
+
+use super::reader::fill;
+use super::{advance, parse, Error};
+use crate::incremental::Progress;
+use crate::outcome;
+use crate::policy::Policy;
+use crate::{Header, ParseOutcome};
+use std::io::{self, Read};
+
+/// The progress of reading a header from a non-blocking stream with the deprecated `NonBlockingReader::poll_read`.
+// The status is returned by value on each poll, so boxing the header would only add an allocation.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReadStatus<'a> {
+    /// The stream has no more bytes available and the header is not complete yet.
+    /// Poll again once the stream is readable.
+    NotReady,
+    /// The header (if any) is complete, along with the number of bytes it consumed from the stream.
+    /// The bytes read past the header are in `NonBlockingReader::payload`.
+    Done(Option<Header<'a>>, usize),
+}
+
+/// Reads a PROXY protocol header from a non-blocking stream across multiple readiness events,
+/// for event loops that cannot use `async`.
+/// Bytes are buffered between calls until the header is complete.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::io::NonBlockingReader;
+/// use ppp::policy::Policy;
+/// use ppp::ParseOutcome;
+/// use std::io::{self, Read};
+///
+/// /// A stream that would block after each chunk.
+/// struct Chunks(Vec<&'static [u8]>, bool);
+///
+/// impl Read for Chunks {
+///     fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+///         self.1 = !self.1;
+///
+///         match self.0.first() {
+///             Some(chunk) if self.1 => {
+///                 buffer[..chunk.len()].copy_from_slice(chunk);
+///                 Ok(self.0.remove(0).len())
+///             }
+///             _ => Err(io::ErrorKind::WouldBlock.into()),
+///         }
+///     }
+/// }
+///
+/// let mut stream = Chunks(vec![b"PROXY UNKNOWN", b"\r\nHello"], false);
+/// let mut reader = NonBlockingReader::new(Policy::Require);
+///
+/// assert_eq!(reader.poll_outcome(&mut stream).unwrap(), ParseOutcome::Incomplete { needed: 2 });
+///
+/// match reader.poll_outcome(&mut stream).unwrap() {
+///     ParseOutcome::Complete(header, length) => {
+///         assert!(header.is_health_check());
+///         assert_eq!(length, 15);
+///     }
+///     outcome => unreachable!("{:?}", outcome),
+/// }
+/// assert_eq!(reader.payload(), b"Hello");
+/// ```
+#[derive(Clone, Debug)]
+pub struct NonBlockingReader {
+    policy: Policy,
+    buffer: Vec<u8>,
+    header: usize,
+    complete: bool,
+}
+
+impl NonBlockingReader {
+    /// Creates a new `NonBlockingReader` with the given `Policy`.
+    pub fn new(policy: Policy) -> Self {
+        NonBlockingReader {
+            policy,
+            buffer: Vec::new(),
+            header: 0,
+            complete: false,
+        }
+    }
+
+    /// Reads all of the bytes available from the stream until the header is complete or the stream would block.
+    /// Returns `ParseOutcome::Incomplete` when the stream would block before the header is complete;
+    /// poll again once the stream is readable. Streams without a header are `ParseOutcome::NotProxyProtocol`
+    /// unless the `Policy` requires one, and always are with `Policy::Ignore`.
+    /// The bytes read past the header are in `payload`.
+    ///
+    /// Once a header is complete (or an error is returned), the next call starts reading a new header,
+    /// so a reader can be reused across connections.
+    pub fn poll_outcome<S: Read>(&mut self, stream: &mut S) -> Result<ParseOutcome<'_>, Error> {
+        if self.complete {
+            self.reset();
+        }
+
+        if self.policy == Policy::Ignore {
+            self.complete = true;
+            return Ok(ParseOutcome::NotProxyProtocol);
+        }
+
+        loop {
+            let read = match fill(&mut self.buffer, stream) {
+                Ok(read) => read,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    return Ok(ParseOutcome::Incomplete {
+                        needed: self.needed(),
+                    })
+                }
+                Err(error) => {
+                    self.complete = true;
+                    return Err(error.into());
+                }
+            };
+
+            match advance(self.buffer.as_slice(), read, self.policy) {
+                None => continue,
+                Some(Ok(Some(header))) => {
+                    self.header = header.len();
+                    break;
+                }
+                Some(Ok(None)) => {
+                    self.complete = true;
+                    return Ok(ParseOutcome::NotProxyProtocol);
+                }
+                Some(Err(error)) => {
+                    self.complete = true;
+                    return Err(error);
+                }
+            }
+        }
+
+        self.complete = true;
+
+        // Parsed again outside of the loop, since a header returned from within would keep the buffer borrowed.
+        let header = parse(&self.buffer[..self.header], self.policy).unwrap_or(Ok(None))?;
+
+        Ok(ParseOutcome::from(header))
+    }
+
+    /// Reads the bytes available from the stream like `poll_outcome`, telling a stream without a header apart
+    /// only by the missing header of a `ReadStatus::Done`.
+    #[deprecated(note = "use `poll_outcome`, which returns a `ParseOutcome`")]
+    pub fn poll_read<S: Read>(&mut self, stream: &mut S) -> Result<ReadStatus<'_>, Error> {
+        Ok(match self.poll_outcome(stream)? {
+            ParseOutcome::Incomplete { .. } => ReadStatus::NotReady,
+            ParseOutcome::Complete(header, length) => ReadStatus::Done(Some(header), length),
+            ParseOutcome::NotProxyProtocol => ReadStatus::Done(None, 0),
+        })
+    }
+
+    /// The lower bound on the number of bytes the buffered start of a header still needs.
+    fn needed(&self) -> usize {
+        let mut progress = Progress::default();
+        let _ = progress.parse(&self.buffer, self.policy);
+
+        outcome::needed(&progress, &self.buffer)
+    }
+
+    /// The bytes read from the stream that are not part of the header.
+    /// Before the header is done, these are all of the bytes read so far.
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer[self.header..]
+    }
+
+    /// Discards any buffered bytes, so the next call to `poll_read` starts reading a new header.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.header = 0;
+        self.complete = false;
+    }
+}
+
+#[cfg(test)]
+// The deprecated `poll_read` is still tested, since it remains public.
+#[allow(deprecated)]
+mod tests {
+    use super::*;
+
+    /// A stream that returns a byte per read and would block after every byte.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        ready: bool,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            self.ready = !self.ready;
+
+            match self.bytes.split_first() {
+                Some((&byte, rest)) if self.ready => {
+                    buffer[0] = byte;
+                    self.bytes = rest;
+                    Ok(1)
+                }
+                None if self.ready => Ok(0),
+                _ => Err(io::ErrorKind::WouldBlock.into()),
+            }
+        }
+    }
+
+    fn poll(reader: &mut NonBlockingReader, stream: &mut Trickle) -> Result<usize, Error> {
+        let mut polls = 1;
+
+        while reader.poll_read(stream)? == ReadStatus::NotReady {
+            polls += 1;
+        }
+
+        Ok(polls)
+    }
+
+    #[test]
+    fn v2_trickle() {
+        let input = b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00Hi";
+        let mut stream = Trickle {
+            bytes: input,
+            ready: false,
+        };
+        let mut reader = NonBlockingReader::new(Policy::Require);
+
+        assert_eq!(poll(&mut reader, &mut stream).unwrap(), 16);
+        assert_eq!(reader.payload(), b"");
+
+        // A completed read starts a new header on the next poll.
+        assert!(matches!(
+            poll(&mut reader, &mut stream).unwrap_err(),
+            Error::MissingHeader
+        ));
+        assert_eq!(reader.payload(), b"H");
+    }
+
+    #[test]
+    fn done_without_header() {
+        let mut stream = Trickle {
+            bytes: b"GET",
+            ready: false,
+        };
+        let mut reader = NonBlockingReader::new(Policy::Optional);
+
+        assert_eq!(
+            reader.poll_read(&mut stream).unwrap(),
+            ReadStatus::Done(None, 0)
+        );
+        assert_eq!(reader.payload(), b"G");
+        assert_eq!(reader.poll_read(&mut stream).unwrap(), ReadStatus::NotReady);
+    }
+
+    #[test]
+    fn end_of_stream() {
+        let mut stream = Trickle {
+            bytes: b"PROXY",
+            ready: false,
+        };
+        let mut reader = NonBlockingReader::new(Policy::Require);
+        let error = poll(&mut reader, &mut stream).unwrap_err();
+
+        assert!(matches!(error, Error::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof));
+        assert_eq!(reader.payload(), b"PROXY");
+    }
+
+    #[test]
+    fn poll_outcome() {
+        let input = b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x02\x00\x00Hi";
+        let mut stream = Trickle {
+            bytes: input,
+            ready: false,
+        };
+        let mut reader = NonBlockingReader::new(Policy::Optional);
+        let mut needed = Vec::new();
+
+        loop {
+            match reader.poll_outcome(&mut stream).unwrap() {
+                ParseOutcome::Incomplete { needed: bytes } => needed.push(bytes),
+                ParseOutcome::Complete(header, length) => {
+                    assert!(matches!(header, Header::V2(..)));
+                    assert_eq!(length, 18);
+                    break;
+                }
+                ParseOutcome::NotProxyProtocol => panic!("expected a header"),
+            }
+        }
+
+        assert_eq!(needed.first(), Some(&15));
+        assert_eq!(needed[15..], [2, 1]);
+
+        let mut stream = Trickle {
+            bytes: b"GET /",
+            ready: false,
+        };
+        let mut reader = NonBlockingReader::new(Policy::Optional);
+
+        assert_eq!(
+            reader.poll_outcome(&mut stream).unwrap(),
+            ParseOutcome::NotProxyProtocol
+        );
+        assert_eq!(reader.payload(), b"G");
+    }
+}
@@ -0,0 +1,197 @@
+//! A reusable reader that keeps its buffer between connections.
+use std::prelude::v1::*;
+
+use super::{advance, parse, Error, READ_SIZE};
+use crate::policy::Policy;
+use crate::Header;
+use std::io::{self, Read};
+
+/// Reads PROXY protocol headers into a buffer that is reused across connections,
+/// so servers accepting many connections avoid an allocation per header.
+/// The returned header borrows the buffer until the next read or reset.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::io::HeaderReader;
+/// use ppp::policy::Policy;
+/// use std::io::Cursor;
+///
+/// let mut reader = HeaderReader::new(Policy::Require);
+///
+/// for input in ["PROXY UNKNOWN\r\nHello", "PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\nWorld"] {
+///     let header = reader.read(&mut Cursor::new(input)).unwrap();
+///
+///     assert!(header.is_some());
+///     assert_eq!(reader.payload().len(), 5);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct HeaderReader {
+    policy: Policy,
+    buffer: Vec<u8>,
+    header: usize,
+}
+
+impl HeaderReader {
+    /// Creates a new `HeaderReader` with the given `Policy` and an empty buffer.
+    pub fn new(policy: Policy) -> Self {
+        Self::with_capacity(policy, 0)
+    }
+
+    /// Creates a new `HeaderReader` with the given `Policy` and a buffer with at least the given capacity.
+    pub fn with_capacity(policy: Policy, capacity: usize) -> Self {
+        HeaderReader {
+            policy,
+            buffer: Vec::with_capacity(capacity),
+            header: 0,
+        }
+    }
+
+    /// The `Policy` applied to each stream.
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    /// The capacity of the underlying buffer.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Reads a PROXY protocol header of either version from the start of the stream.
+    /// Discards the bytes of any previous read, keeping the buffer's capacity.
+    ///
+    /// Bytes are read from the stream until a header is complete,
+    /// so the bytes read past the header must be taken from `payload` before reading from the stream again.
+    pub fn read<S: Read>(&mut self, stream: &mut S) -> Result<Option<Header<'_>>, Error> {
+        self.reset();
+
+        if self.policy == Policy::Ignore {
+            return Ok(None);
+        }
+
+        loop {
+            let read = fill(&mut self.buffer, stream)?;
+
+            match advance(self.buffer.as_slice(), read, self.policy) {
+                None => continue,
+                Some(Ok(Some(header))) => {
+                    self.header = header.len();
+                    break;
+                }
+                Some(Ok(None)) => return Ok(None),
+                Some(Err(error)) => return Err(error),
+            }
+        }
+
+        // Parsed again outside of the loop, since a header returned from within would keep the buffer borrowed.
+        parse(&self.buffer[..self.header], self.policy).unwrap_or(Ok(None))
+    }
+
+    /// The bytes read from the stream by the last read that are not part of the header.
+    /// When no header was read (or there was an error), these are all of the bytes read.
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer[self.header..]
+    }
+
+    /// Discards the bytes of the last read, keeping the buffer's capacity.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.header = 0;
+    }
+}
+
+/// Reads once from the stream into the end of the buffer, retrying on interrupts.
+/// Returns the number of bytes read.
+pub(super) fn fill<S: Read>(buffer: &mut Vec<u8>, stream: &mut S) -> io::Result<usize> {
+    let length = buffer.len();
+
+    loop {
+        buffer.resize(length + READ_SIZE, 0);
+
+        match stream.read(&mut buffer[length..]) {
+            Ok(read) => {
+                buffer.truncate(length + read);
+                return Ok(read);
+            }
+            Err(error) => {
+                buffer.truncate(length);
+
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v1;
+    use std::io::Cursor;
+
+    #[test]
+    fn reuse() {
+        let mut reader = HeaderReader::with_capacity(Policy::Require, 1024);
+
+        let header = reader
+            .read(&mut Cursor::new(
+                b"PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\nHello",
+            ))
+            .unwrap();
+        assert_eq!(
+            header,
+            Some(Header::V1(v1::Header::new(
+                "PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n",
+                v1::Addresses::new_tcp4([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+            )))
+        );
+        assert_eq!(reader.payload(), b"Hello");
+
+        let header = reader
+            .read(&mut Cursor::new(b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00"))
+            .unwrap();
+        assert!(matches!(header, Some(Header::V2(..))));
+        assert_eq!(reader.payload(), b"");
+        assert_eq!(reader.capacity(), 1024);
+    }
+
+    #[test]
+    fn optional() {
+        let mut reader = HeaderReader::new(Policy::Optional);
+
+        assert_eq!(
+            reader
+                .read(&mut Cursor::new(b"GET / HTTP/1.1\r\n"))
+                .unwrap(),
+            None
+        );
+        assert_eq!(reader.payload(), b"GET / HTTP/1.1\r\n");
+    }
+
+    #[test]
+    fn errors() {
+        let mut reader = HeaderReader::new(Policy::Require);
+
+        let error = reader
+            .read(&mut Cursor::new(b"GET / HTTP/1.1\r\n"))
+            .unwrap_err();
+        assert!(matches!(error, Error::MissingHeader));
+
+        let error = reader.read(&mut Cursor::new(b"PROXY TCP4")).unwrap_err();
+        assert!(matches!(error, Error::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof));
+        assert_eq!(reader.payload(), b"PROXY TCP4");
+
+        reader.reset();
+        assert_eq!(reader.payload(), b"");
+    }
+
+    #[test]
+    fn ignore() {
+        let mut reader = HeaderReader::new(Policy::Ignore);
+        let mut input = Cursor::new(b"PROXY UNKNOWN\r\n");
+
+        assert_eq!(reader.read(&mut input).unwrap(), None);
+        assert_eq!(input.position(), 0);
+    }
+}
@@ -0,0 +1,323 @@
+//! A stream that replays the bytes read past a PROXY protocol header.
+use std::prelude::v1::*;
+
+use std::io::{self, Read, Write};
+
+/// A stream positioned after the PROXY protocol header (if any).
+/// Any bytes that were read from the inner stream but are not part of the header are returned first by `read`.
+/// Writes are passed through to the inner stream.
+#[derive(Debug)]
+pub struct ProxiedStream<S> {
+    inner: S,
+    buffer: Vec<u8>,
+    offset: usize,
+}
+
+impl<S> ProxiedStream<S> {
+    /// Creates a new `ProxiedStream` that returns the given buffered bytes before reading from the inner stream.
+    pub fn new(inner: S, buffer: Vec<u8>) -> Self {
+        ProxiedStream {
+            inner,
+            buffer,
+            offset: 0,
+        }
+    }
+
+    /// The bytes read from the inner stream that have not yet been consumed.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer[self.offset..]
+    }
+
+    /// Gets a reference to the inner stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the inner stream.
+    /// Reading directly from the inner stream skips any buffered bytes.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consumes this `ProxiedStream`, returning the inner stream and the bytes that have not yet been consumed.
+    pub fn into_parts(mut self) -> (S, Vec<u8>) {
+        self.buffer.drain(..self.offset);
+
+        (self.inner, self.buffer)
+    }
+}
+
+impl<S: Read> Read for ProxiedStream<S> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let buffered = self.buffered();
+
+        if buffered.is_empty() {
+            return self.inner.read(buffer);
+        }
+
+        let length = std::cmp::min(buffered.len(), buffer.len());
+
+        buffer[..length].copy_from_slice(&buffered[..length]);
+        self.offset += length;
+
+        Ok(length)
+    }
+}
+
+impl<S: Write> Write for ProxiedStream<S> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.inner.write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for ProxiedStream<S> {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        context: &mut std::task::Context<'_>,
+        buffer: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let buffered = this.buffered();
+
+        if buffered.is_empty() {
+            return std::pin::Pin::new(&mut this.inner).poll_read(context, buffer);
+        }
+
+        let length = std::cmp::min(buffered.len(), buffer.remaining());
+
+        buffer.put_slice(&buffered[..length]);
+        this.offset += length;
+
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<S: tokio::io::AsyncRead + Unpin> ProxiedStream<S> {
+    /// Reads a PROXY protocol header of either version from the inner stream according to the given `Policy`,
+    /// keeping every byte read in the buffer of this `ProxiedStream` as soon as it is read.
+    ///
+    /// Unlike `io::tokio::read_header`, this is cancel-safe: when the future is dropped before it completes
+    /// (e.g. by a timeout or in `select!`), no bytes are lost, and calling it again resumes with the bytes already buffered.
+    /// Once a header is read, it is consumed from the buffer, leaving the start of the payload (if any).
+    /// On error, the buffered bytes are left in place.
+    ///
+    /// ## Examples
+    /// ```rust,no_run
+    /// use ppp::io::ProxiedStream;
+    /// use ppp::policy::Policy;
+    /// use std::time::Duration;
+    /// use tokio::net::TcpStream;
+    ///
+    /// # async fn run(stream: TcpStream) -> Result<(), ppp::io::Error> {
+    /// let mut stream = ProxiedStream::new(stream, Vec::new());
+    ///
+    /// let header = loop {
+    ///     match tokio::time::timeout(Duration::from_secs(1), stream.read_header(Policy::Require)).await {
+    ///         Ok(header) => break header?,
+    ///         Err(_) => println!("still waiting for a header"),
+    ///     }
+    /// };
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_header(
+        &mut self,
+        policy: crate::policy::Policy,
+    ) -> Result<Option<crate::Header<'static>>, super::Error> {
+        use tokio::io::AsyncReadExt;
+
+        if policy == crate::policy::Policy::Ignore {
+            return Ok(None);
+        }
+
+        self.buffer.drain(..self.offset);
+        self.offset = 0;
+
+        // Bytes buffered by a cancelled call may already hold a header.
+        let mut result = if self.buffer.is_empty() {
+            None
+        } else {
+            super::parse(&self.buffer, policy).map(owned)
+        };
+        let mut chunk = [0; super::READ_SIZE];
+
+        while result.is_none() {
+            // Reading is cancel-safe, and the bytes are buffered before the next await.
+            let read = match self.inner.read(&mut chunk).await {
+                Ok(read) => read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error.into()),
+            };
+
+            self.buffer.extend_from_slice(&chunk[..read]);
+            result = super::advance(&self.buffer, read, policy).map(owned);
+        }
+
+        let result = result.unwrap_or(Ok(None));
+
+        if let Ok(Some(header)) = result.as_ref() {
+            self.offset = header.len();
+        }
+
+        result
+    }
+}
+
+/// Copies the header of a parse result out of the bytes it was parsed from.
+#[cfg(feature = "tokio")]
+fn owned(
+    result: Result<Option<crate::Header<'_>>, super::Error>,
+) -> Result<Option<crate::Header<'static>>, super::Error> {
+    result.map(|header| header.map(|header| header.to_owned()))
+}
+
+#[cfg(feature = "tokio")]
+impl<S: tokio::io::AsyncWrite + Unpin> tokio::io::AsyncWrite for ProxiedStream<S> {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        context: &mut std::task::Context<'_>,
+        buffer: &[u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        std::pin::Pin::new(&mut self.get_mut().inner).poll_write(context, buffer)
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        context: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::pin::Pin::new(&mut self.get_mut().inner).poll_flush(context)
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        context: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::pin::Pin::new(&mut self.get_mut().inner).poll_shutdown(context)
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::policy::Policy;
+    use crate::{Header, ParseOutcome};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    /// A waker that does nothing, as every test future is polled again right away.
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// A stream that returns a single byte per read, and is pending before each of them.
+    struct Trickle {
+        bytes: Vec<u8>,
+        ready: bool,
+    }
+
+    impl tokio::io::AsyncRead for Trickle {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            context: &mut Context<'_>,
+            buffer: &mut tokio::io::ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+
+            if !this.ready {
+                this.ready = true;
+                context.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            this.ready = false;
+
+            if !this.bytes.is_empty() {
+                buffer.put_slice(&[this.bytes.remove(0)]);
+            }
+
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Reads a header from the stream, cancelling the read each time it is pending and starting a new one.
+    /// Returns the result along with the number of cancelled reads.
+    fn read_cancelled(
+        stream: &mut ProxiedStream<Trickle>,
+        policy: Policy,
+    ) -> (Result<Option<Header<'static>>, crate::io::Error>, usize) {
+        let waker = Waker::from(Arc::new(Noop));
+        let mut context = Context::from_waker(&waker);
+        let mut cancelled = 0;
+
+        loop {
+            let mut read = Box::pin(stream.read_header(policy));
+
+            match read.as_mut().poll(&mut context) {
+                Poll::Ready(result) => return (result, cancelled),
+                Poll::Pending => cancelled += 1,
+            }
+        }
+    }
+
+    #[test]
+    fn cancel_at_every_byte() {
+        let inputs: [&[u8]; 2] = [
+            b"PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\nHello",
+            b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBBHello",
+        ];
+
+        for input in inputs {
+            let header = match ParseOutcome::parse(input) {
+                Ok(ParseOutcome::Complete(header, _)) => header.to_owned(),
+                outcome => panic!("{:?}", outcome),
+            };
+            let mut stream = ProxiedStream::new(
+                Trickle {
+                    bytes: input.to_vec(),
+                    ready: false,
+                },
+                Vec::new(),
+            );
+
+            let (result, cancelled) = read_cancelled(&mut stream, Policy::Require);
+
+            assert_eq!(result.unwrap(), Some(header.clone()));
+            assert_eq!(cancelled, header.len());
+            assert_eq!(stream.buffered(), b"");
+            assert_eq!(stream.get_ref().bytes, b"Hello");
+        }
+    }
+
+    #[test]
+    fn resume_from_buffer() {
+        let input = b"PROXY UNKNOWN\r\nHello";
+        let mut stream = ProxiedStream::new(
+            Trickle {
+                bytes: Vec::new(),
+                ready: true,
+            },
+            input.to_vec(),
+        );
+
+        let (result, cancelled) = read_cancelled(&mut stream, Policy::Require);
+
+        assert!(matches!(result, Ok(Some(Header::V1(..)))));
+        assert_eq!(cancelled, 0);
+        assert_eq!(stream.buffered(), b"Hello");
+
+        let (result, _) = read_cancelled(&mut stream, Policy::Require);
+
+        assert!(result.is_err());
+        assert_eq!(stream.buffered(), b"Hello");
+    }
+}
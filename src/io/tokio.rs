@@ -0,0 +1,530 @@
+//! Asynchronous helpers for reading a PROXY protocol header from the start of a `tokio` stream,
+//! and for writing binary headers with large TLVs to one.
+use std::prelude::v1::*;
+
+use super::{Acceptor, Error, ProxiedStream, READ_SIZE};
+use crate::policy::Policy;
+use crate::{v2, Header};
+use ::tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use ::tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Wraps a `tokio` `TcpListener` to read the PROXY protocol header of each connection as it is accepted.
+///
+/// Headers are read before `accept` returns, so callers wanting concurrent handshakes should accept
+/// plain connections and call `read_header` on a spawned task instead.
+/// Tasks sharing a listener (e.g. behind an `Arc`) also read headers concurrently,
+/// up to the limit set with `Acceptor::max_in_flight`.
+///
+/// ## Examples
+/// ```rust,no_run
+/// use ppp::io::tokio::ProxyListener;
+/// use ppp::io::Acceptor;
+/// use ppp::policy::Policy;
+/// use std::time::Duration;
+///
+/// # async fn run() {
+/// let listener = ProxyListener::bind("0.0.0.0:8080", Acceptor::new(Policy::Require))
+///     .await
+///     .unwrap()
+///     .timeout(Duration::from_secs(5));
+///
+/// loop {
+///     match listener.accept().await {
+///         Ok((stream, header)) => println!("{:?} from {:?}", header, stream.get_ref().peer_addr()),
+///         Err(error) => eprintln!("{}", error),
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ProxyListener {
+    inner: TcpListener,
+    acceptor: Acceptor,
+    timeout: Option<Duration>,
+}
+
+impl ProxyListener {
+    /// Creates a new `ProxyListener` that reads headers from the connections of the given listener.
+    pub fn new(inner: TcpListener, acceptor: Acceptor) -> Self {
+        ProxyListener {
+            inner,
+            acceptor,
+            timeout: None,
+        }
+    }
+
+    /// Creates a new `ProxyListener` bound to the given address.
+    pub async fn bind<A: ToSocketAddrs>(address: A, acceptor: Acceptor) -> io::Result<Self> {
+        Ok(ProxyListener::new(
+            TcpListener::bind(address).await?,
+            acceptor,
+        ))
+    }
+
+    /// Limits the time spent reading the header of each connection.
+    /// Connections that do not send a complete header in time fail with a `TimedOut` I/O error.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The local address of the inner listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Gets a reference to the inner listener.
+    pub fn get_ref(&self) -> &TcpListener {
+        &self.inner
+    }
+
+    /// Consumes this `ProxyListener`, returning the inner listener.
+    pub fn into_inner(self) -> TcpListener {
+        self.inner
+    }
+
+    /// Accepts a new connection and reads its PROXY protocol header.
+    /// Returns the stream, positioned after the header, along with the header if one was present.
+    pub async fn accept(
+        &self,
+    ) -> Result<(ProxiedStream<TcpStream>, Option<Header<'static>>), Error> {
+        let (stream, peer) = self.inner.accept().await?;
+        let result = match self.acceptor.permit_async().await {
+            Ok(_permit) => match self.acceptor.policy_for(peer.ip()) {
+                Ok(policy) => self.read_header(stream, policy).await,
+                Err(error) => Err(error),
+            },
+            Err(error) => Err(error),
+        }
+        .and_then(|accepted| self.acceptor.validate(accepted));
+
+        self.acceptor.record(&result);
+
+        result
+    }
+
+    /// Reads the header of an accepted connection, limited by the timeout if any.
+    async fn read_header(
+        &self,
+        stream: TcpStream,
+        policy: Policy,
+    ) -> Result<(ProxiedStream<TcpStream>, Option<Header<'static>>), Error> {
+        read_header_within(stream, policy, self.timeout, self.acceptor.capture_limit()).await
+    }
+}
+
+/// Reads a PROXY protocol header of either version from the start of the stream according to the given `Policy`.
+/// Returns the stream, positioned after the header, along with the header if one was present.
+///
+/// Bytes are read from the stream until a header is complete, so some of the payload may be buffered by the returned stream.
+/// The bytes read so far are lost if the future is dropped before it completes;
+/// use `ProxiedStream::read_header` when reads may be cancelled (e.g. in `select!`).
+pub async fn read_header<S: AsyncRead + Unpin>(
+    stream: S,
+    policy: Policy,
+) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error> {
+    read_header_within(stream, policy, None, 0).await
+}
+
+/// Reads a header like `read_header` from a stream behind a trait object.
+///
+/// Every stream type passed to `read_header` compiles its own copy of the reading code.
+/// Binaries that read headers from many stream types can use this instead to share a single copy between them.
+pub async fn read_header_dyn(
+    stream: &mut (dyn AsyncRead + Unpin + Send),
+    policy: Policy,
+) -> Result<
+    (
+        ProxiedStream<&mut (dyn AsyncRead + Unpin + Send)>,
+        Option<Header<'static>>,
+    ),
+    Error,
+> {
+    read_header_within(stream, policy, None, 0).await
+}
+
+/// Reads a header like `read_header`, failing with a `TimedOut` I/O error if the header is not complete within the timeout.
+/// Attaches up to `capture` of the bytes read to errors.
+async fn read_header_within<S: AsyncRead + Unpin>(
+    mut stream: S,
+    policy: Policy,
+    timeout: Option<Duration>,
+    capture: usize,
+) -> Result<(ProxiedStream<S>, Option<Header<'static>>), Error> {
+    #[cfg(feature = "tracing")]
+    let started = std::time::Instant::now();
+
+    let mut buffer = Vec::new();
+    let header = read_buffered(&mut stream, policy, &mut buffer);
+
+    #[cfg(feature = "tracing")]
+    let header =
+        tracing::Instrument::instrument(header, tracing::debug_span!("read_header", ?policy));
+
+    let header = match timeout {
+        Some(timeout) => ::tokio::time::timeout(timeout, header)
+            .await
+            .unwrap_or_else(|_| Err(io::Error::from(io::ErrorKind::TimedOut).into())),
+        None => header.await,
+    };
+
+    #[cfg(feature = "tracing")]
+    super::record(&header, started.elapsed());
+
+    let header = header.map_err(|error| error.capture(&buffer, capture))?;
+
+    if let Some(header) = header.as_ref() {
+        buffer.drain(..header.len());
+    }
+
+    Ok((ProxiedStream::new(stream, buffer), header))
+}
+
+/// Reads from the stream into the buffer until a header is complete, or the bytes cannot be a header.
+async fn read_buffered<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    policy: Policy,
+    buffer: &mut Vec<u8>,
+) -> Result<Option<Header<'static>>, Error> {
+    if policy == Policy::Ignore {
+        return Ok(None);
+    }
+
+    let mut chunk = [0; READ_SIZE];
+
+    // Reads into a separate chunk so the buffer only ever holds bytes read from the stream,
+    // even when the future is cancelled by a timeout.
+    loop {
+        let read = match stream.read(&mut chunk).await {
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+
+        buffer.extend_from_slice(&chunk[..read]);
+
+        if let Some(result) = super::advance(buffer.as_slice(), read, policy) {
+            return result.map(|header| header.map(|header| header.to_owned()));
+        }
+    }
+}
+
+/// The progress of reading a header with `poll_read_header`, kept by the caller between polls.
+///
+/// Every byte read from the stream is kept here as soon as it is read, so a future or adapter
+/// that is dropped between polls loses nothing: polling again with the same state picks up where it left off.
+#[derive(Clone, Debug)]
+pub struct ReadState {
+    policy: Policy,
+    buffer: Vec<u8>,
+    header: usize,
+    complete: bool,
+}
+
+impl ReadState {
+    /// Creates a new `ReadState` for reading a header with the given `Policy`.
+    pub fn new(policy: Policy) -> Self {
+        ReadState {
+            policy,
+            buffer: Vec::new(),
+            header: 0,
+            complete: false,
+        }
+    }
+
+    /// The bytes read from the stream that are not part of the header.
+    /// Before the header is done, these are all of the bytes read so far.
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer[self.header..]
+    }
+
+    /// Discards any buffered bytes, so the next call to `poll_read_header` starts reading a new header.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.header = 0;
+        self.complete = false;
+    }
+
+    /// Wraps the stream the header was read from, so the payload read past the header is read first.
+    pub fn into_stream<S>(mut self, stream: S) -> ProxiedStream<S> {
+        self.buffer.drain(..self.header);
+
+        ProxiedStream::new(stream, self.buffer)
+    }
+}
+
+/// Polls the stream for a PROXY protocol header of either version according to the `Policy` of the state,
+/// for hand-written `Future` and `AsyncRead` adapters that cannot use `read_header`.
+///
+/// Returns `Poll::Pending` when the stream has no more bytes available, having registered the context to be woken
+/// once it does. Once the header is done (or an error is returned), the bytes read past it are in `ReadState::payload`,
+/// and the next call starts reading a new header.
+///
+/// ## Examples
+/// ```rust,no_run
+/// use ppp::io::tokio::{poll_read_header, ReadState};
+/// use ppp::io::Error;
+/// use ppp::policy::Policy;
+/// use ppp::Header;
+/// use std::future::Future;
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll};
+/// use tokio::net::TcpStream;
+///
+/// struct ReadHeader {
+///     stream: TcpStream,
+///     state: ReadState,
+/// }
+///
+/// impl Future for ReadHeader {
+///     type Output = Result<Option<Header<'static>>, Error>;
+///
+///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+///         let this = &mut *self;
+///
+///         poll_read_header(cx, &mut this.stream, &mut this.state)
+///     }
+/// }
+///
+/// # async fn run(stream: TcpStream) {
+/// let header = ReadHeader { stream, state: ReadState::new(Policy::Require) }.await;
+/// # }
+/// ```
+pub fn poll_read_header<S: AsyncRead + Unpin + ?Sized>(
+    cx: &mut Context<'_>,
+    stream: &mut S,
+    state: &mut ReadState,
+) -> Poll<Result<Option<Header<'static>>, Error>> {
+    if state.complete {
+        state.reset();
+    }
+
+    if state.policy == Policy::Ignore {
+        state.complete = true;
+        return Poll::Ready(Ok(None));
+    }
+
+    let mut chunk = [0; READ_SIZE];
+
+    loop {
+        let mut read = ReadBuf::new(&mut chunk);
+
+        match Pin::new(&mut *stream).poll_read(cx, &mut read) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Ok(())) => (),
+            Poll::Ready(Err(error)) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Poll::Ready(Err(error)) => {
+                state.complete = true;
+                return Poll::Ready(Err(error.into()));
+            }
+        }
+
+        state.buffer.extend_from_slice(read.filled());
+
+        let result = super::advance(state.buffer.as_slice(), read.filled().len(), state.policy)
+            .map(|result| result.map(|header| header.map(|header| header.to_owned())));
+
+        if let Some(result) = result {
+            if let Ok(Some(header)) = result.as_ref() {
+                state.header = header.len();
+            }
+
+            state.complete = true;
+            return Poll::Ready(result);
+        }
+    }
+}
+
+/// A TLV whose value is copied from a reader as the header is written by `write_streamed`,
+/// so large values (e.g. attestation quotes or certificates) never need to be held in memory at once.
+#[derive(Debug)]
+pub struct StreamedTlv<R> {
+    kind: u8,
+    length: u16,
+    value: R,
+}
+
+impl<R: AsyncRead + Unpin> StreamedTlv<R> {
+    /// Creates a new `StreamedTlv` of the given type, whose value is the next `length` bytes of the reader.
+    pub fn new<T: Into<u8>>(kind: T, length: u16, value: R) -> Self {
+        StreamedTlv {
+            kind: kind.into(),
+            length,
+            value,
+        }
+    }
+}
+
+/// Writes a binary header to the stream, made of the encoded header (e.g. built by a `v2::Builder`)
+/// followed by the streamed TLVs. The payload length of the header is patched to count the streamed TLVs,
+/// whose headers and values are then written one after the other. Bytes of a reader past its TLV's length are left unread.
+///
+/// Returns an `InvalidInput` error, before anything is written, when the header is not a valid binary header,
+/// is followed by other bytes, has a `v2::Type::CRC32C` TLV, or the payload would be longer than `u16::MAX`,
+/// and an `UnexpectedEof` error when a reader ends before its TLV's length.
+/// A checksum cannot be streamed, as it covers the streamed values too.
+///
+/// ## Examples
+/// ```rust,no_run
+/// use ppp::io::tokio::{write_streamed, StreamedTlv};
+/// use ppp::v2::{Builder, Command, Protocol, Version};
+/// use tokio::net::TcpStream;
+///
+/// # async fn run(mut stream: TcpStream) -> std::io::Result<()> {
+/// let header = Builder::with_addresses(
+///     Version::Two | Command::Proxy,
+///     Protocol::Stream,
+///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+/// )
+/// .build()?;
+/// let quote = vec![0; 4096];
+///
+/// write_streamed(&mut stream, &header, &mut [StreamedTlv::new(0xE8, 4096, &quote[..])]).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn write_streamed<W, R>(
+    stream: &mut W,
+    header: &[u8],
+    tlvs: &mut [StreamedTlv<R>],
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+    R: AsyncRead + Unpin,
+{
+    let invalid = || io::Error::from(io::ErrorKind::InvalidInput);
+
+    let parsed = v2::Header::try_from(header).map_err(|_| invalid())?;
+
+    // Trailing bytes would be counted in the payload length and sent as if they were TLVs.
+    if parsed.len() != header.len() {
+        return Err(invalid());
+    }
+
+    if parsed
+        .tlvs()
+        .flatten()
+        .any(|tlv| tlv.kind == u8::from(v2::Type::CRC32C))
+    {
+        return Err(invalid());
+    }
+
+    let length = tlvs
+        .iter()
+        .fold(header.len() - v2::MINIMUM_LENGTH, |length, tlv| {
+            length + v2::MINIMUM_TLV_LENGTH + tlv.length as usize
+        });
+    let length = u16::try_from(length).map_err(|_| invalid())?;
+
+    // The payload length is the last field of the fixed part of the header.
+    let mut prefix = header.to_vec();
+
+    prefix[v2::MINIMUM_LENGTH - 2..v2::MINIMUM_LENGTH].copy_from_slice(&length.to_be_bytes());
+    stream.write_all(&prefix).await?;
+
+    for tlv in tlvs.iter_mut() {
+        let [high, low] = tlv.length.to_be_bytes();
+
+        stream.write_all(&[tlv.kind, high, low]).await?;
+
+        let mut value = (&mut tlv.value).take(u64::from(tlv.length));
+        let copied = ::tokio::io::copy(&mut value, stream).await?;
+
+        if copied < u64::from(tlv.length) {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+
+    stream.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    /// A waker that does nothing, as every test future is polled again right away.
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Polls the future until it completes.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Noop));
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    const HEADER: &[u8] =
+        b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB";
+
+    #[test]
+    fn streamed() {
+        let mut output = Vec::new();
+        let mut tlvs = [StreamedTlv::new(0xE8, 3, &b"abcdef"[..])];
+
+        block_on(write_streamed(&mut output, HEADER, &mut tlvs)).unwrap();
+
+        let header = v2::Header::try_from(output.as_slice()).unwrap();
+
+        assert_eq!(header.len(), output.len());
+        assert_eq!(
+            header.tlvs().next().unwrap().unwrap().value.as_ref(),
+            b"abc"
+        );
+        assert_eq!(tlvs[0].value, b"def");
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        let mut input = HEADER.to_vec();
+        let mut output = Vec::new();
+
+        input.extend_from_slice(b"GET / HTTP/1.1");
+
+        let error = block_on(write_streamed(
+            &mut output,
+            &input,
+            &mut [StreamedTlv::new(0xE8, 3, &b"abc"[..])],
+        ))
+        .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn checksum() {
+        let header = v2::Builder::localhost()
+            .write_tlv(v2::Type::CRC32C, &[0; 4])
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut output = Vec::new();
+
+        let error = block_on(write_streamed(
+            &mut output,
+            &header,
+            &mut [StreamedTlv::new(0xE8, 3, &b"abc"[..])],
+        ))
+        .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(output.is_empty());
+    }
+}
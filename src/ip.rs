@@ -1,10 +1,10 @@
 //! Models for storing IP v4 and v6 addresses and ports.
-use std::prelude::v1::*;
-
-use std::net::{Ipv4Addr, Ipv6Addr};
+use crate::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 /// The source and destination IPv4 addresses and TCP ports of a header.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct IPv4 {
     pub source_address: Ipv4Addr,
     pub source_port: u16,
@@ -27,9 +27,21 @@ impl IPv4 {
             destination_port,
         }
     }
+
+    /// These addresses as IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`), with the same ports.
+    pub fn to_ipv6_mapped(&self) -> IPv6 {
+        IPv6 {
+            source_address: self.source_address.to_ipv6_mapped(),
+            source_port: self.source_port,
+            destination_address: self.destination_address.to_ipv6_mapped(),
+            destination_port: self.destination_port,
+        }
+    }
 }
 /// The source and destination IPv6 addresses and TCP ports of a header.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct IPv6 {
     pub source_address: Ipv6Addr,
     pub source_port: u16,
@@ -52,4 +64,197 @@ impl IPv6 {
             destination_port,
         }
     }
+
+    /// The IPv4 addresses these IPv4-mapped IPv6 addresses embed, with the same ports.
+    /// Returns `None` unless both addresses are IPv4-mapped.
+    pub fn to_ipv4_mapped(&self) -> Option<IPv4> {
+        Some(IPv4 {
+            source_address: mapped(self.source_address)?,
+            source_port: self.source_port,
+            destination_address: mapped(self.destination_address)?,
+            destination_port: self.destination_port,
+        })
+    }
+}
+
+/// The IPv4 address embedded in an IPv4-mapped IPv6 address (`::ffff:a.b.c.d`), if any.
+pub(crate) fn mapped(address: Ipv6Addr) -> Option<Ipv4Addr> {
+    match address.segments() {
+        [0, 0, 0, 0, 0, 0xffff, high, low] => {
+            let [a, b] = high.to_be_bytes();
+            let [c, d] = low.to_be_bytes();
+
+            Some(Ipv4Addr::new(a, b, c, d))
+        }
+        _ => None,
+    }
+}
+
+/// Tests whether the address is in a private range:
+/// the RFC 1918 ranges for IPv4 and unique local addresses (`fc00::/7`) for IPv6.
+pub(crate) fn is_private(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => address.is_private(),
+        IpAddr::V6(address) => match mapped(address) {
+            Some(address) => address.is_private(),
+            None => address.segments()[0] & 0xfe00 == 0xfc00,
+        },
+    }
+}
+
+/// Tests whether the address is a loopback address, including IPv4 loopback addresses mapped to IPv6.
+pub(crate) fn is_loopback(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => address.is_loopback(),
+        IpAddr::V6(address) => match mapped(address) {
+            Some(address) => address.is_loopback(),
+            None => address.is_loopback(),
+        },
+    }
+}
+
+/// Tests whether the address may be reached on the public internet,
+/// i.e. it is not in any of the special-purpose ranges that a real client could not connect from.
+pub(crate) fn is_global(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => is_global_v4(address),
+        IpAddr::V6(address) => match mapped(address) {
+            Some(address) => is_global_v4(address),
+            None => is_global_v6(address),
+        },
+    }
+}
+
+fn is_global_v4(address: Ipv4Addr) -> bool {
+    let [a, b, c, _] = address.octets();
+
+    !(a == 0
+        || address.is_private()
+        || address.is_loopback()
+        || address.is_link_local()
+        || address.is_documentation()
+        || address.is_multicast()
+        // Shared address space (100.64.0.0/10), used for carrier-grade NAT.
+        || (a == 100 && b & 0xc0 == 64)
+        // IETF protocol assignments (192.0.0.0/24).
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking (198.18.0.0/15).
+        || (a == 198 && b & 0xfe == 18)
+        // Reserved (240.0.0.0/4), which includes the broadcast address.
+        || a >= 240)
+}
+
+fn is_global_v6(address: Ipv6Addr) -> bool {
+    let segments = address.segments();
+
+    !(address.is_unspecified()
+        || address.is_loopback()
+        || address.is_multicast()
+        // Unique local (fc00::/7).
+        || segments[0] & 0xfe00 == 0xfc00
+        // Link-local unicast (fe80::/10).
+        || segments[0] & 0xffc0 == 0xfe80
+        // Documentation (2001:db8::/32).
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8)
+        // Discard-only (100::/64).
+        || segments[..4] == [0x100, 0, 0, 0])
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for IPv4 {
+    fn format(&self, f: defmt::Formatter) {
+        let source = self.source_address.octets();
+        let destination = self.destination_address.octets();
+
+        defmt::write!(
+            f,
+            "{}.{}.{}.{}:{} {}.{}.{}.{}:{}",
+            source[0],
+            source[1],
+            source[2],
+            source[3],
+            self.source_port,
+            destination[0],
+            destination[1],
+            destination[2],
+            destination[3],
+            self.destination_port
+        )
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for IPv6 {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "[{=[?]:x}]:{} [{=[?]:x}]:{}",
+            &self.source_address.segments()[..],
+            self.source_port,
+            &self.destination_address.segments()[..],
+            self.destination_port
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes() {
+        let cases: [(&str, bool, bool, bool); 16] = [
+            ("8.8.8.8", false, false, true),
+            ("10.1.2.3", true, false, false),
+            ("172.31.255.255", true, false, false),
+            ("192.168.0.1", true, false, false),
+            ("127.0.0.2", false, true, false),
+            ("169.254.0.1", false, false, false),
+            ("100.64.0.1", false, false, false),
+            ("198.19.0.1", false, false, false),
+            ("255.255.255.255", false, false, false),
+            ("0.0.0.0", false, false, false),
+            ("2001:4860::8888", false, false, true),
+            ("fd12::1", true, false, false),
+            ("::1", false, true, false),
+            ("fe80::1", false, false, false),
+            ("2001:db8::1", false, false, false),
+            ("::ffff:10.0.0.1", true, false, false),
+        ];
+
+        for (address, private, loopback, global) in cases {
+            let address: IpAddr = address.parse().unwrap();
+
+            assert_eq!(is_private(address), private, "{}", address);
+            assert_eq!(is_loopback(address), loopback, "{}", address);
+            assert_eq!(is_global(address), global, "{}", address);
+        }
+
+        assert!(is_loopback("::ffff:127.0.0.1".parse().unwrap()));
+        assert!(is_global("::ffff:8.8.8.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn map_families() {
+        let ipv4 = IPv4::new([192, 0, 2, 1], [10, 0, 0, 1], 51234, 443);
+        let ipv6 = IPv6::new(
+            "::ffff:192.0.2.1".parse::<Ipv6Addr>().unwrap(),
+            "::ffff:10.0.0.1".parse::<Ipv6Addr>().unwrap(),
+            51234,
+            443,
+        );
+
+        assert_eq!(ipv4.to_ipv6_mapped(), ipv6);
+        assert_eq!(ipv6.to_ipv4_mapped(), Some(ipv4));
+
+        let mut partly = ipv6;
+
+        partly.destination_address = "2001:db8::1".parse().unwrap();
+
+        assert_eq!(partly.to_ipv4_mapped(), None);
+        assert_eq!(
+            IPv6::new(Ipv6Addr::LOCALHOST, Ipv6Addr::LOCALHOST, 1, 2).to_ipv4_mapped(),
+            None
+        );
+    }
 }
@@ -1,16 +1,192 @@
 //! A Proxy Protocol Parser written in Rust.
 //! Supports both text and binary versions of the header protocol.
+//!
+//! Parsing never allocates: headers and their TLVs borrow from the input, and addresses are copied by value.
+//! Only conversions to owned values (e.g. `to_owned`) allocate, so latency-critical proxies can parse on the hot path.
+//!
+//! Parsing never panics either: every parse entry point returns an error for any byte sequence it cannot parse,
+//! and the accessors of a parsed header stay within its bytes. The `fuzz` directory holds `cargo fuzz` targets
+//! for text headers, binary headers, TLVs and round trips through the builders that check this contract.
 
-#![cfg_attr(feature = "tstd", no_std)]
+// Tests link `std` for the test harness, so their modules see its prelude even without the `std` feature.
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
 
 #[cfg(feature = "tstd")]
 #[macro_use]
 extern crate sgxlib as std;
 
+// The `alloc` macros are only used by the modules that require both versions.
+#[cfg_attr(
+    all(
+        not(any(feature = "std", feature = "tstd")),
+        feature = "v1",
+        feature = "v2"
+    ),
+    macro_use
+)]
+extern crate alloc;
+
+#[cfg(not(any(feature = "v1", feature = "v2")))]
+compile_error!("At least one of the `v1` and `v2` features must be enabled.");
+
+// Only the modules of the enabled versions, together with `Header`, `HeaderResult` and the error codes,
+// are built with a single version; everything that handles headers of either version requires both.
+#[cfg(any(feature = "python", feature = "wasm"))]
+mod bindings;
+mod code;
+#[cfg(all(feature = "v1", feature = "v2"))]
+mod explain;
+#[cfg(all(feature = "v1", feature = "v2"))]
+mod incremental;
+#[cfg(all(feature = "v1", feature = "v2"))]
+mod info;
 mod ip;
+#[cfg(all(feature = "v1", feature = "v2"))]
+mod outcome;
+// Only traces, which require both versions, observe the parsers.
+#[cfg_attr(not(all(feature = "v1", feature = "v2")), allow(dead_code))]
+mod observe;
+// The lenient parsers are only used for `forwarded`, which requires both versions.
+#[cfg(feature = "v1")]
+#[cfg_attr(not(feature = "v2"), allow(dead_code))]
+mod parse;
+mod proxy;
+mod send_sync;
+#[cfg(any(feature = "std", feature = "tstd"))]
+mod shared;
+#[cfg(all(feature = "v1", feature = "v2"))]
+mod skip;
+#[cfg(all(feature = "v1", feature = "v2"))]
+mod sniff;
+#[cfg(all(feature = "socket2", feature = "std", feature = "v1", feature = "v2"))]
+mod sockaddr;
+#[cfg(all(
+    feature = "bytes",
+    any(feature = "std", feature = "tstd"),
+    feature = "v1",
+    feature = "v2"
+))]
+mod split;
+#[cfg(all(feature = "v1", feature = "v2"))]
+mod strip;
+#[cfg(all(windows, feature = "windows-sys"))]
+mod windows;
 
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub mod batch;
+#[cfg(all(feature = "embedded-io", feature = "v1", feature = "v2"))]
+pub mod embedded;
+#[cfg(feature = "encoding")]
+pub mod encoding;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub mod extensions;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub mod forwarded;
+#[cfg(all(any(feature = "std", feature = "tstd"), feature = "v1", feature = "v2"))]
+pub mod io;
+pub mod low_level;
+#[cfg(all(any(feature = "std", feature = "tstd"), feature = "v1", feature = "v2"))]
+pub mod metrics;
+#[cfg(all(
+    feature = "opentelemetry",
+    feature = "std",
+    feature = "v1",
+    feature = "v2"
+))]
+pub mod otel;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub mod policy;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "relay")]
+pub mod relay;
+#[cfg(all(any(feature = "std", feature = "tstd"), feature = "v1", feature = "v2"))]
+pub mod testing;
+#[cfg(all(
+    any(feature = "openssl", feature = "rustls"),
+    feature = "v1",
+    feature = "v2"
+))]
+pub mod tls;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub mod trace;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub mod translate;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub mod transport;
+#[cfg(feature = "v1")]
 pub mod v1;
+#[cfg(feature = "v2")]
 pub mod v2;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub mod vectors;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use batch::parse_stream;
+pub use code::ErrorCode;
+#[cfg(feature = "encoding")]
+pub use encoding::DecodeError;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use explain::explain;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use info::ProxyInfo;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use outcome::{InvalidHeader, ParseOutcome};
+pub use proxy::ProxyHeader;
+#[cfg(any(feature = "std", feature = "tstd"))]
+pub use shared::SharedHeader;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use skip::{parse_or_skip, Salvage, Skip};
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use sniff::{sniff, Sniff, SNIFF_LENGTH};
+#[cfg(all(
+    feature = "bytes",
+    any(feature = "std", feature = "tstd"),
+    feature = "v1",
+    feature = "v2"
+))]
+pub use split::split_header;
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use strip::{strip_header, HeaderBuf, StripError};
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use trace::parse_with_trace;
+
+/// The parts of the standard prelude used by the crate, taken from `sgx_tstd` in enclave builds and from `alloc` in `no_std` builds.
+mod prelude {
+    #[cfg(any(feature = "std", feature = "tstd"))]
+    pub use std::prelude::v1::*;
+
+    // Builds with a single version do not use all of these.
+    #[cfg(not(any(feature = "std", feature = "tstd")))]
+    #[allow(unused_imports)]
+    pub use alloc::{
+        boxed::Box,
+        string::{String, ToString},
+        vec::Vec,
+    };
+}
+
+/// The IP address types, taken from `sgx_tstd` in enclave builds and from `core` in `no_std` builds.
+mod net {
+    #[cfg(any(feature = "std", feature = "tstd"))]
+    pub use std::net::*;
+
+    #[cfg(not(any(feature = "std", feature = "tstd")))]
+    pub use core::net::*;
+}
+
+/// The version of the PROXY protocol a header was encoded with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Version {
+    One = 1,
+    Two = 2,
+}
 
 /// The canonical way to determine when a streamed header should be retried in a streaming context.
 /// The protocol states that servers may choose to support partial headers or to close the connection if the header is not present all at once.
@@ -37,6 +213,7 @@ impl<'a, T, E: PartialResult> PartialResult for Result<T, E> {
     }
 }
 
+#[cfg(feature = "v1")]
 impl<'a> PartialResult for v1::ParseError {
     fn is_incomplete(&self) -> bool {
         matches!(
@@ -53,6 +230,7 @@ impl<'a> PartialResult for v1::ParseError {
     }
 }
 
+#[cfg(feature = "v1")]
 impl<'a> PartialResult for v1::BinaryParseError {
     fn is_incomplete(&self) -> bool {
         match self {
@@ -62,6 +240,7 @@ impl<'a> PartialResult for v1::BinaryParseError {
     }
 }
 
+#[cfg(feature = "v2")]
 impl<'a> PartialResult for v2::ParseError {
     fn is_incomplete(&self) -> bool {
         matches!(
@@ -75,7 +254,8 @@ impl<'a> PartialResult for v2::ParseError {
 /// Useful for parsing either version 1 or version 2 of the PROXY protocol.
 ///
 /// ## Examples
-/// ```rust
+#[cfg_attr(all(feature = "v1", feature = "v2"), doc = "```rust")]
+#[cfg_attr(not(all(feature = "v1", feature = "v2")), doc = "```ignore")]
 /// use ppp::{HeaderResult, PartialResult, v1, v2};
 ///
 /// let input = "PROXY UNKNOWN\r\n";
@@ -86,41 +266,427 @@ impl<'a> PartialResult for v2::ParseError {
 #[derive(Debug, PartialEq)]
 #[must_use = "this `HeaderResult` may contain a V1 or V2 `Err` variant, which should be handled"]
 pub enum HeaderResult<'a> {
+    #[cfg(feature = "v1")]
     V1(Result<v1::Header<'a>, v1::BinaryParseError>),
+    #[cfg(feature = "v2")]
     V2(Result<v2::Header<'a>, v2::ParseError>),
 }
 
+#[cfg(feature = "v1")]
 impl<'a> From<Result<v1::Header<'a>, v1::BinaryParseError>> for HeaderResult<'a> {
     fn from(result: Result<v1::Header<'a>, v1::BinaryParseError>) -> Self {
         HeaderResult::V1(result)
     }
 }
 
+#[cfg(feature = "v2")]
 impl<'a> From<Result<v2::Header<'a>, v2::ParseError>> for HeaderResult<'a> {
     fn from(result: Result<v2::Header<'a>, v2::ParseError>) -> Self {
         HeaderResult::V2(result)
     }
 }
 
+impl<'a> HeaderResult<'a> {
+    /// Converts this `HeaderResult` into one that owns its header, so it can outlive the input.
+    pub fn into_owned(self) -> HeaderResult<'static> {
+        match self {
+            #[cfg(feature = "v1")]
+            Self::V1(result) => HeaderResult::V1(result.map(|header| header.to_owned())),
+            #[cfg(feature = "v2")]
+            Self::V2(result) => HeaderResult::V2(result.map(|header| header.to_owned())),
+        }
+    }
+}
+
 impl<'a> PartialResult for HeaderResult<'a> {
     fn is_incomplete(&self) -> bool {
         match self {
+            #[cfg(feature = "v1")]
             Self::V1(result) => result.is_incomplete(),
+            #[cfg(feature = "v2")]
             Self::V2(result) => result.is_incomplete(),
         }
     }
 }
 
+/// A PROXY protocol header of either version.
+///
+/// ## Examples
+#[cfg_attr(feature = "v1", doc = "```rust")]
+#[cfg_attr(not(feature = "v1"), doc = "```ignore")]
+/// use ppp::{Header, v1};
+///
+/// let input = "PROXY UNKNOWN\r\n";
+/// let header: Header = v1::Header::try_from(input).unwrap().into();
+///
+/// assert_eq!(header.len(), input.len());
+/// assert_eq!(header.as_bytes(), input.as_bytes());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Header<'a> {
+    #[cfg(feature = "v1")]
+    V1(v1::Header<'a>),
+    #[cfg(feature = "v2")]
+    V2(v2::Header<'a>),
+}
+
+#[cfg(feature = "v1")]
+impl<'a> From<v1::Header<'a>> for Header<'a> {
+    fn from(header: v1::Header<'a>) -> Self {
+        Header::V1(header)
+    }
+}
+
+#[cfg(feature = "v2")]
+impl<'a> From<v2::Header<'a>> for Header<'a> {
+    fn from(header: v2::Header<'a>) -> Self {
+        Header::V2(header)
+    }
+}
+
+#[cfg(all(feature = "v1", feature = "v2"))]
+impl<'a> From<&Header<'a>> for ProxyInfo {
+    fn from(header: &Header<'a>) -> Self {
+        match header {
+            Header::V1(header) => header.into(),
+            Header::V2(header) => header.into(),
+        }
+    }
+}
+
+impl<'a> Header<'a> {
+    /// Creates an owned clone of this [`Header`].
+    pub fn to_owned(&self) -> Header<'static> {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(header) => Header::V1(header.to_owned()),
+            #[cfg(feature = "v2")]
+            Header::V2(header) => Header::V2(header.to_owned()),
+        }
+    }
+
+    /// The `Version` of the PROXY protocol this `Header` was encoded with.
+    pub fn version(&self) -> Version {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(..) => Version::One,
+            #[cfg(feature = "v2")]
+            Header::V2(..) => Version::Two,
+        }
+    }
+
+    /// The total length of this `Header` in bytes.
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    /// Tests whether this `Header`'s underlying bytes are empty.
+    pub fn is_empty(&self) -> bool {
+        self.as_bytes().is_empty()
+    }
+
+    /// The underlying bytes this `Header` is built on.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(header) => header.header.as_bytes(),
+            #[cfg(feature = "v2")]
+            Header::V2(header) => header.as_bytes(),
+        }
+    }
+
+    /// The address family of this `Header`, in the terms of binary headers.
+    /// Text headers are `IPv4` for `TCP4`, `IPv6` for `TCP6` and `Unspecified` for `UNKNOWN`.
+    #[cfg(feature = "v2")]
+    pub fn address_family(&self) -> v2::AddressFamily {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(header) => match header.addresses {
+                v1::Addresses::Tcp4(..) => v2::AddressFamily::IPv4,
+                v1::Addresses::Tcp6(..) => v2::AddressFamily::IPv6,
+                v1::Addresses::Unknown => v2::AddressFamily::Unspecified,
+            },
+            Header::V2(header) => header.address_family(),
+        }
+    }
+
+    /// The transport protocol of this `Header`, in the terms of binary headers.
+    /// Text headers are `Stream` for `TCP4` and `TCP6`, and `Unspecified` for `UNKNOWN`.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::{Header, v2};
+    ///
+    /// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x12\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x35\x00\x35";
+    /// let header: Header = v2::Header::try_from(&input[..]).unwrap().into();
+    ///
+    /// assert_eq!(header.address_family(), v2::AddressFamily::IPv4);
+    /// assert_eq!(header.transport_protocol(), v2::Protocol::Datagram);
+    /// ```
+    #[cfg(feature = "v2")]
+    pub fn transport_protocol(&self) -> v2::Protocol {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(header) => match header.addresses {
+                v1::Addresses::Tcp4(..) | v1::Addresses::Tcp6(..) => v2::Protocol::Stream,
+                v1::Addresses::Unknown => v2::Protocol::Unspecified,
+            },
+            Header::V2(header) => header.transport_protocol(),
+        }
+    }
+
+    /// The values of the TLVs of this `Header` by type, which are always empty for text headers.
+    /// See `v2::Header::tlv_map`.
+    #[cfg(all(any(feature = "std", feature = "tstd"), feature = "v2"))]
+    pub fn tlv_map(
+        &self,
+        duplicates: v2::Duplicates,
+    ) -> Result<std::collections::HashMap<u8, &[u8]>, v2::MapError> {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(..) => Ok(Default::default()),
+            #[cfg(feature = "v2")]
+            Header::V2(header) => header.tlv_map(duplicates),
+        }
+    }
+
+    /// Tests whether this `Header` is a health check by the proxy itself rather than a proxied connection,
+    /// i.e. a binary header with the LOCAL command or a text header with the `UNKNOWN` protocol.
+    ///
+    /// ## Examples
+    #[cfg_attr(feature = "v1", doc = "```rust")]
+    #[cfg_attr(not(feature = "v1"), doc = "```ignore")]
+    /// use ppp::{Header, v1};
+    ///
+    /// let header: Header = v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap().into();
+    ///
+    /// assert!(header.is_health_check());
+    /// assert!(!header.source_is_destination());
+    /// assert!(header.families_match());
+    /// ```
+    pub fn is_health_check(&self) -> bool {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(header) => header.addresses == v1::Addresses::Unknown,
+            #[cfg(feature = "v2")]
+            Header::V2(header) => header.command == v2::Command::Local,
+        }
+    }
+
+    /// Tests whether the source and destination of this `Header` are the same host:
+    /// the same IP address regardless of the ports, or the same UNIX socket path.
+    /// Always false for headers without addresses, including binary LOCAL headers,
+    /// whose addresses are ignored.
+    pub fn source_is_destination(&self) -> bool {
+        match self {
+            #[cfg(feature = "v2")]
+            Header::V2(v2::Header {
+                command,
+                addresses: v2::Addresses::Unix(unix),
+                ..
+            }) if *command != v2::Command::Local => unix.source == unix.destination,
+            header => header
+                .ips()
+                .map_or(false, |(source, destination)| source == destination),
+        }
+    }
+
+    /// Tests whether the source and destination of this `Header` are of the same family.
+    /// IPv6 addresses only match when neither or both are IPv4-mapped (`::ffff:a.b.c.d`);
+    /// headers with IPv4 or UNIX addresses, or without addresses (e.g. binary LOCAL headers),
+    /// always match.
+    pub fn families_match(&self) -> bool {
+        match self.ips() {
+            Some((net::IpAddr::V6(source), net::IpAddr::V6(destination))) => {
+                ip::mapped(source).is_some() == ip::mapped(destination).is_some()
+            }
+            _ => true,
+        }
+    }
+
+    /// The source and destination IP addresses of this `Header`, ignoring those of binary LOCAL headers.
+    fn ips(&self) -> Option<(net::IpAddr, net::IpAddr)> {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(header) => match header.addresses {
+                v1::Addresses::Tcp4(a) => {
+                    Some((a.source_address.into(), a.destination_address.into()))
+                }
+                v1::Addresses::Tcp6(a) => {
+                    Some((a.source_address.into(), a.destination_address.into()))
+                }
+                v1::Addresses::Unknown => None,
+            },
+            #[cfg(feature = "v2")]
+            Header::V2(header) => match header.proxied_addresses() {
+                v2::Addresses::IPv4(a) => {
+                    Some((a.source_address.into(), a.destination_address.into()))
+                }
+                v2::Addresses::IPv6(a) => {
+                    Some((a.source_address.into(), a.destination_address.into()))
+                }
+                v2::Addresses::Unix(..) | v2::Addresses::Unspecified => None,
+            },
+        }
+    }
+}
+
 impl<'a> HeaderResult<'a> {
     /// Parses a PROXY protocol version 2 `Header`.
     /// If the input is not a valid version 2 `Header`, attempts to parse a version 1 `Header`.  
+    /// Neither attempt allocates. With only one of the `v1` and `v2` features enabled, only that version is parsed.
     pub fn parse(input: &'a [u8]) -> HeaderResult<'a> {
-        let header = v2::Header::try_from(input);
+        #[cfg(all(feature = "v1", feature = "v2"))]
+        {
+            let header = v2::Header::try_from(input);
 
-        if header.is_complete() && header.is_err() {
+            if header.is_complete() && header.is_err() {
+                v1::Header::try_from(input).into()
+            } else {
+                header.into()
+            }
+        }
+
+        #[cfg(not(feature = "v2"))]
+        {
             v1::Header::try_from(input).into()
-        } else {
-            header.into()
         }
+
+        #[cfg(not(feature = "v1"))]
+        {
+            v2::Header::try_from(input).into()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "v1", feature = "v2"))]
+mod tests {
+    use super::*;
+    use crate::vectors::VECTORS;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts the allocations of each thread, so tests running in parallel do not interfere.
+    struct Counting;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = Cell::new(0);
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    /// The number of allocations made by the given function on this thread.
+    fn allocations<F: FnOnce()>(f: F) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - before
+    }
+
+    #[test]
+    fn parse_without_allocations() {
+        for vector in VECTORS {
+            let count = allocations(|| {
+                let header = HeaderResult::parse(vector.bytes);
+
+                if let HeaderResult::V2(Ok(header)) = &header {
+                    for tlv in header.tlvs() {
+                        let _ = tlv.map(|tlv| tlv.value.len());
+                    }
+
+                    if let v2::Addresses::Unix(unix) = header.addresses {
+                        let _ = (unix.source_path(), unix.destination_path());
+                    }
+                }
+
+                drop(header);
+                drop(v1::Header::try_from(vector.bytes));
+                drop(v2::Header::try_from(vector.bytes));
+
+                if let Ok(header) = v2::HeaderRef::try_from(vector.bytes) {
+                    let _ = (header.source(), header.destination(), header.tlvs().count());
+
+                    if header.tlvs().count() <= v2::INLINE_TLVS {
+                        let _ = header.tlv_list().map(|tlvs| tlvs.find(v2::HOP));
+                    }
+                }
+            });
+
+            assert_eq!(count, 0, "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn classify_addresses() {
+        let text = |input: &'static str| Header::from(v1::Header::try_from(input).unwrap());
+        let binary = |command, addresses: v2::Addresses| {
+            let header = v2::Builder::with_addresses(
+                v2::Version::Two | command,
+                v2::Protocol::Stream,
+                addresses,
+            )
+            .build()
+            .unwrap();
+
+            Header::from(v2::Header::try_from(header.as_slice()).unwrap().to_owned())
+        };
+        let loopback = text("PROXY TCP4 127.0.0.1 127.0.0.1 80 443\r\n");
+        let mapped = text("PROXY TCP6 ::ffff:192.0.2.1 2001:db8::1 80 443\r\n");
+        let local = binary(
+            v2::Command::Local,
+            v2::IPv4::new([127, 0, 0, 1], [127, 0, 0, 1], 80, 443).into(),
+        );
+        let local_mapped = binary(
+            v2::Command::Local,
+            v2::IPv6::new(
+                [0, 0, 0, 0, 0, 0xFFFF, 0xC000, 0x0201],
+                [0x2001, 0xDB8, 0, 0, 0, 0, 0, 1],
+                80,
+                443,
+            )
+            .into(),
+        );
+        let local_unix = binary(v2::Command::Local, v2::Unix::new([1; 108], [1; 108]).into());
+        let unix = binary(v2::Command::Proxy, v2::Unix::new([1; 108], [1; 108]).into());
+
+        assert!(!loopback.is_health_check());
+        assert!(loopback.source_is_destination());
+        assert!(loopback.families_match());
+        assert!(!mapped.source_is_destination());
+        assert!(!mapped.families_match());
+        assert!(local.is_health_check());
+        assert!(!local.source_is_destination());
+        assert!(local_mapped.families_match());
+        assert!(!local_unix.source_is_destination());
+        assert!(unix.source_is_destination());
+        assert!(unix.families_match());
+
+        let unknown = text("PROXY UNKNOWN\r\n");
+
+        assert_eq!(loopback.address_family(), v2::AddressFamily::IPv4);
+        assert_eq!(loopback.transport_protocol(), v2::Protocol::Stream);
+        assert_eq!(mapped.address_family(), v2::AddressFamily::IPv6);
+        assert_eq!(unknown.address_family(), v2::AddressFamily::Unspecified);
+        assert_eq!(unknown.transport_protocol(), v2::Protocol::Unspecified);
+        assert_eq!(unix.address_family(), v2::AddressFamily::Unix);
+        assert_eq!(unix.transport_protocol(), v2::Protocol::Stream);
+    }
+
+    #[test]
+    fn owned_headers_allocate() {
+        let header = v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap();
+
+        assert_ne!(allocations(|| drop(header.to_owned())), 0);
     }
 }
@@ -0,0 +1,235 @@
+//! The field-level parsers behind the header parsers, for protocols that embed PROXY protocol fields
+//! in a larger grammar of their own.
+//!
+//! Each parser reads from the start of its input and never allocates. The token parsers return how many bytes
+//! they read along with the value, so callers can continue parsing right after the token.
+//! For whole headers, use `v1::Header`, `v2::Header` or `HeaderResult` instead,
+//! and `v1::Addresses::parse_fields` for the address fields of a text header.
+#[cfg(feature = "v1")]
+use crate::net::{Ipv4Addr, Ipv6Addr};
+#[cfg(feature = "v1")]
+use crate::parse::{self, Mode};
+#[cfg(feature = "v1")]
+use crate::v1;
+#[cfg(feature = "v2")]
+use crate::v2;
+use crate::Version;
+
+#[cfg(feature = "v2")]
+pub use crate::v2::{TypeLengthValue, TypeLengthValues};
+
+/// The result of matching the protocol prefix at the start of some input.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Prefix {
+    /// The input starts with the prefix of a header of the given version.
+    Match(Version),
+    /// The input is shorter than the prefixes, but is the start of one of them.
+    Incomplete,
+    /// The input cannot be the start of a header.
+    Mismatch,
+}
+
+/// Matches the prefix of a text (`PROXY`) or binary header at the start of the input.
+/// Only the prefixes of the enabled versions are matched.
+///
+/// ## Examples
+#[cfg_attr(all(feature = "v1", feature = "v2"), doc = "```rust")]
+#[cfg_attr(not(all(feature = "v1", feature = "v2")), doc = "```ignore")]
+/// use ppp::low_level::{prefix, Prefix};
+/// use ppp::Version;
+///
+/// assert_eq!(prefix(b"PROXY TCP4"), Prefix::Match(Version::One));
+/// assert_eq!(prefix(b"\r\n\r\n"), Prefix::Incomplete);
+/// assert_eq!(prefix(b"GET / HTTP/1.1"), Prefix::Mismatch);
+/// ```
+pub fn prefix(input: &[u8]) -> Prefix {
+    let prefixes = [
+        #[cfg(feature = "v1")]
+        (v1::PROTOCOL_PREFIX.as_bytes(), Version::One),
+        #[cfg(feature = "v2")]
+        (v2::PROTOCOL_PREFIX, Version::Two),
+    ];
+
+    if let Some((_, version)) = prefixes
+        .iter()
+        .find(|(prefix, _)| input.starts_with(prefix))
+    {
+        return Prefix::Match(*version);
+    }
+
+    if prefixes.iter().any(|(prefix, _)| prefix.starts_with(input)) {
+        Prefix::Incomplete
+    } else {
+        Prefix::Mismatch
+    }
+}
+
+/// Reads the dotted decimal IPv4 address at the start of the input, as in the `TCP4` addresses of a text header.
+/// Octets with leading zeros are rejected. Returns `None` if the input does not start with an address,
+/// along with where reading stopped.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::low_level::ipv4;
+///
+/// assert_eq!(ipv4(b"127.0.0.1 80"), (9, Some("127.0.0.1".parse().unwrap())));
+/// assert_eq!(ipv4(b"127.0.0.01").1, None);
+/// ```
+#[cfg(feature = "v1")]
+pub fn ipv4(input: &[u8]) -> (usize, Option<Ipv4Addr>) {
+    parse::ipv4(input, Mode::Strict)
+}
+
+/// Reads the IPv6 address at the start of the input, as in the `TCP6` addresses of a text header.
+/// Returns `None` if the input does not start with an address, along with where reading stopped.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::low_level::ipv6;
+///
+/// assert_eq!(ipv6(b"2001:db8::1 443"), (11, Some("2001:db8::1".parse().unwrap())));
+/// ```
+#[cfg(feature = "v1")]
+pub fn ipv6(input: &[u8]) -> (usize, Option<Ipv6Addr>) {
+    parse::ipv6(input, Mode::Strict)
+}
+
+/// Reads the decimal port at the start of the input, as in the ports of a text header.
+/// Ports with leading zeros or larger than `u16::MAX` are rejected. Returns `None` if the input
+/// does not start with a port, along with where reading stopped.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::low_level::port;
+///
+/// assert_eq!(port(b"443\r\n"), (3, Some(443)));
+/// assert_eq!(port(b"65536").1, None);
+/// ```
+#[cfg(feature = "v1")]
+pub fn port(input: &[u8]) -> (usize, Option<u16>) {
+    let (length, port) = parse::u16(input, Mode::Strict);
+
+    (length, port.ok())
+}
+
+/// The fields of the fixed-size first 16 bytes of a binary header.
+#[cfg(feature = "v2")]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedFields {
+    pub version: v2::Version,
+    pub command: v2::Command,
+    pub address_family: v2::AddressFamily,
+    pub protocol: v2::Protocol,
+    /// The length in bytes of the rest of the header: the addresses and TLVs.
+    pub length: u16,
+}
+
+/// Decodes the fixed-size first 16 bytes of a binary header, without requiring the rest of the header.
+/// Fails like `v2::Header::try_from` on an invalid prefix, version, command, address family or protocol,
+/// or a length too short for the addresses.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::low_level::fixed_fields;
+/// use ppp::v2::{AddressFamily, Command};
+///
+/// let fields = fixed_fields(b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C").unwrap();
+///
+/// assert_eq!(fields.command, Command::Proxy);
+/// assert_eq!(fields.address_family, AddressFamily::IPv4);
+/// assert_eq!(fields.length, 12);
+/// ```
+#[cfg(feature = "v2")]
+pub fn fixed_fields(input: &[u8]) -> Result<FixedFields, v2::ParseError> {
+    let fixed = v2::decode_fixed(input, &mut ())?;
+
+    Ok(FixedFields {
+        version: fixed.version,
+        command: fixed.command,
+        address_family: fixed.address_family,
+        protocol: fixed.protocol,
+        length: (fixed.length - v2::MINIMUM_LENGTH) as u16,
+    })
+}
+
+/// Iterates over the TLVs encoded in the input, as in the payload of a binary header after its addresses.
+/// Iteration fails on the first TLV whose length runs past the end of the input.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::low_level::tlvs;
+///
+/// let mut tlvs = tlvs(b"\x02\x00\x03abc\x04\x00\x05");
+///
+/// assert_eq!(tlvs.next().unwrap().unwrap().value.as_ref(), b"abc");
+/// assert!(tlvs.next().unwrap().is_err());
+/// ```
+#[cfg(feature = "v2")]
+pub fn tlvs(input: &[u8]) -> TypeLengthValues<'_> {
+    TypeLengthValues::from(input)
+}
+
+#[cfg(all(test, feature = "v1", feature = "v2"))]
+mod tests {
+    use super::*;
+    use crate::net::IpAddr;
+
+    /// Parses a line of a made-up protocol that embeds an address and port after a keyword, e.g. `CONNECT 127.0.0.1 80`.
+    fn connect(line: &[u8]) -> Option<(IpAddr, u16)> {
+        let rest = line.strip_prefix(b"CONNECT ")?;
+        let (length, address) = match ipv4(rest) {
+            (length, Some(address)) => (length, IpAddr::V4(address)),
+            _ => match ipv6(rest) {
+                (length, Some(address)) => (length, IpAddr::V6(address)),
+                _ => return None,
+            },
+        };
+        let rest = rest[length..].strip_prefix(b" ")?;
+
+        match port(rest) {
+            (length, Some(port)) if length == rest.len() => Some((address, port)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn compose() {
+        assert_eq!(
+            connect(b"CONNECT 127.0.0.1 80"),
+            Some(("127.0.0.1".parse().unwrap(), 80))
+        );
+        assert_eq!(
+            connect(b"CONNECT 2001:db8::1 443"),
+            Some(("2001:db8::1".parse().unwrap(), 443))
+        );
+        assert_eq!(connect(b"CONNECT 127.0.0.1 080"), None);
+        assert_eq!(connect(b"CONNECT 127.0.0.1 80 "), None);
+    }
+
+    #[test]
+    fn match_prefix() {
+        assert_eq!(prefix(b""), Prefix::Incomplete);
+        assert_eq!(prefix(b"PRO"), Prefix::Incomplete);
+        assert_eq!(prefix(b"PROXY"), Prefix::Match(Version::One));
+        assert_eq!(prefix(b"\r\n\r\n\0\r\nQUIT\n"), Prefix::Match(Version::Two));
+        assert_eq!(prefix(b"\r\n\r\n\0\r\nQUIT"), Prefix::Incomplete);
+        assert_eq!(prefix(b"PROXx"), Prefix::Mismatch);
+    }
+
+    #[test]
+    fn fixed_without_payload() {
+        let fields = fixed_fields(b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x05").unwrap();
+
+        assert_eq!(fields.version, v2::Version::Two);
+        assert_eq!(fields.protocol, v2::Protocol::Unspecified);
+        assert_eq!(fields.length, 5);
+        assert_eq!(
+            fixed_fields(b"\r\n\r\n\0\r\nQUIT\n\x21\x21\x00\x0C"),
+            Err(v2::ParseError::InvalidAddresses(12, 36))
+        );
+        assert_eq!(
+            fixed_fields(b"\r\n\r\n\0\r\nQUIT\n\x21"),
+            Err(v2::ParseError::Incomplete(13))
+        );
+    }
+}
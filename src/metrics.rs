@@ -0,0 +1,163 @@
+//! Hooks for recording metrics about the PROXY protocol headers read by an `Acceptor`.
+use std::prelude::v1::*;
+
+use crate::io::Error;
+use crate::{v2, Header, Version};
+
+/// Receives a call for each outcome of reading a connection's PROXY protocol header.
+/// All methods do nothing by default, so implementations only need to override the ones they export.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::io::Acceptor;
+/// use ppp::metrics::Recorder;
+/// use ppp::policy::Policy;
+/// use ppp::Version;
+/// use std::io::Cursor;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// #[derive(Default)]
+/// struct Counter(AtomicUsize);
+///
+/// impl Recorder for Counter {
+///     fn header(&self, _version: Version) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let counter = Arc::new(Counter::default());
+/// let acceptor = Acceptor::new(Policy::Require).recorder(counter.clone());
+///
+/// acceptor.accept(Cursor::new("PROXY UNKNOWN\r\n"), "127.0.0.1".parse().unwrap()).unwrap();
+///
+/// assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+/// ```
+pub trait Recorder {
+    /// Called for each header read, with the header's version.
+    fn header(&self, _version: Version) {}
+
+    /// Called for each binary header with a `LOCAL` command (e.g. a health check from the proxy).
+    fn local(&self) {}
+
+    /// Called for each Type-Length-Value of a binary header, with its raw type.
+    fn tlv(&self, _kind: u8) {}
+
+    /// Called for each connection that was accepted without a header.
+    fn missing(&self) {}
+
+    /// Called for each connection that failed, with the error.
+    /// `Error::kind_name` is suitable as a metric label.
+    fn error(&self, _error: &Error) {}
+}
+
+/// Calls the `Recorder` for the outcome of reading a connection's header.
+pub(crate) fn record<R: Recorder + ?Sized>(recorder: &R, result: Result<Option<&Header>, &Error>) {
+    match result {
+        Ok(Some(header)) => {
+            recorder.header(header.version());
+
+            if let Header::V2(header) = header {
+                if header.command == v2::Command::Local {
+                    recorder.local();
+                }
+
+                header
+                    .tlvs()
+                    .map_while(Result::ok)
+                    .for_each(|tlv| recorder.tlv(tlv.kind));
+            }
+        }
+        Ok(None) => recorder.missing(),
+        Err(error) => recorder.error(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::Acceptor;
+    use crate::policy::{Policy, TrustedPeers, Untrusted};
+    use crate::v2::{Builder, Command, Protocol, Type};
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<String>>);
+
+    impl Recorder for Events {
+        fn header(&self, version: Version) {
+            self.0.lock().unwrap().push(format!("header {:?}", version));
+        }
+
+        fn local(&self) {
+            self.0.lock().unwrap().push("local".to_string());
+        }
+
+        fn tlv(&self, kind: u8) {
+            self.0.lock().unwrap().push(format!("tlv {:#X}", kind));
+        }
+
+        fn missing(&self) {
+            self.0.lock().unwrap().push("missing".to_string());
+        }
+
+        fn error(&self, error: &Error) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("error {}", error.kind_name()));
+        }
+    }
+
+    #[test]
+    fn record_v2() {
+        let events = Arc::new(Events::default());
+        let acceptor = Acceptor::new(Policy::Require).recorder(events.clone());
+        let input = Builder::ipv4(
+            Command::Local,
+            Protocol::Stream,
+            v2::IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        )
+        .write_tlv(Type::NoOp, &[])
+        .unwrap()
+        .write_tlv(Type::Authority, b"example.com")
+        .unwrap()
+        .build()
+        .unwrap();
+
+        acceptor
+            .accept(Cursor::new(input), "127.0.0.1".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            vec!["header Two", "local", "tlv 0x4", "tlv 0x2"]
+        );
+    }
+
+    #[test]
+    fn record_outcomes() {
+        let events = Arc::new(Events::default());
+        let peer = "127.0.0.1".parse().unwrap();
+
+        Acceptor::new(Policy::Optional)
+            .recorder(events.clone())
+            .accept(Cursor::new("GET / HTTP/1.1\r\n"), peer)
+            .unwrap();
+        Acceptor::new(Policy::Require)
+            .recorder(events.clone())
+            .accept(Cursor::new("GET / HTTP/1.1\r\n"), peer)
+            .unwrap_err();
+        Acceptor::new(Policy::Require)
+            .trusted_peers(TrustedPeers::new().untrusted(Untrusted::Reject))
+            .recorder(events.clone())
+            .accept(Cursor::new("PROXY UNKNOWN\r\n"), peer)
+            .unwrap_err();
+
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            vec!["missing", "error missing_header", "error untrusted"]
+        );
+    }
+}
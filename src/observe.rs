@@ -0,0 +1,71 @@
+//! Hooks through which the parsers report each field they decode, so a parse can be traced.
+//!
+//! The parsers are generic over an `Observer` and are called with `()` when nothing is traced,
+//! in which case the hooks compile away.
+use crate::{ErrorCode, PartialResult};
+use core::fmt;
+
+/// The decision taken after a step of parsing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Decision {
+    /// The field was decoded and parsing continued.
+    Continue,
+    /// The header was parsed.
+    Accept,
+    /// More bytes are needed to parse the header.
+    Incomplete,
+    /// The input is not a valid header, for the reason identified by the code.
+    Reject(ErrorCode),
+}
+
+impl Decision {
+    /// The decision for a parse that failed with the error identified by the code.
+    pub(crate) fn failure<E: PartialResult + ?Sized>(error: &E, code: ErrorCode) -> Self {
+        if error.is_incomplete() {
+            Decision::Incomplete
+        } else {
+            Decision::Reject(code)
+        }
+    }
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::Continue => f.write_str("continue"),
+            Decision::Accept => f.write_str("accept"),
+            Decision::Incomplete => f.write_str("incomplete"),
+            Decision::Reject(code) => write!(f, "reject:{}", code),
+        }
+    }
+}
+
+/// The value of a decoded field, as the parser read it.
+#[derive(Copy, Clone)]
+pub(crate) enum Value<'a> {
+    /// The text of a field of a text header.
+    Text(&'a str),
+    /// The raw bytes of a field of a binary header.
+    Bytes(&'a [u8]),
+    /// A byte shown in hexadecimal, e.g. an unknown command.
+    Hex(u8),
+    /// A field decoded into a value, e.g. an address.
+    Display(&'a dyn fmt::Display),
+}
+
+/// Receives each field a parser decodes, in the order the parser decides on them.
+pub(crate) trait Observer {
+    /// Reports the field of the given name, read from a range of the input, and the decision the parser took after it.
+    fn field(
+        &mut self,
+        offset: usize,
+        length: usize,
+        name: &'static str,
+        value: Value<'_>,
+        decision: Decision,
+    );
+}
+
+impl Observer for () {
+    fn field(&mut self, _: usize, _: usize, _: &'static str, _: Value<'_>, _: Decision) {}
+}
@@ -0,0 +1,205 @@
+//! OpenTelemetry semantic convention attributes for the connection described by a PROXY protocol header.
+use std::prelude::v1::*;
+
+use crate::{v1, v2, Header};
+use opentelemetry::KeyValue;
+use std::net::SocketAddr;
+
+/// The client address attribute.
+pub const CLIENT_ADDRESS: &str = "client.address";
+/// The client port attribute.
+pub const CLIENT_PORT: &str = "client.port";
+/// The server address attribute.
+pub const SERVER_ADDRESS: &str = "server.address";
+/// The server port attribute.
+pub const SERVER_PORT: &str = "server.port";
+/// The transport protocol attribute (i.e. `tcp`, `udp` or `unix`).
+pub const NETWORK_TRANSPORT: &str = "network.transport";
+/// The network protocol attribute (i.e. `ipv4` or `ipv6`).
+pub const NETWORK_TYPE: &str = "network.type";
+
+/// The OpenTelemetry attributes of the original client and server of a connection, for connection spans.
+/// The source of the header is the client, and the destination is the server.
+/// Attributes are omitted when the header does not specify them (e.g. `UNKNOWN` or `LOCAL` headers).
+///
+/// ## Examples
+/// ```rust
+/// use opentelemetry::KeyValue;
+/// use ppp::{otel, v1, Header};
+///
+/// let header = v1::Header::try_from("PROXY TCP4 192.0.2.60 198.51.100.17 47011 443\r\n").unwrap();
+///
+/// assert_eq!(
+///     otel::attributes(&Header::V1(header)),
+///     vec![
+///         KeyValue::new(otel::NETWORK_TRANSPORT, "tcp"),
+///         KeyValue::new(otel::NETWORK_TYPE, "ipv4"),
+///         KeyValue::new(otel::CLIENT_ADDRESS, "192.0.2.60"),
+///         KeyValue::new(otel::CLIENT_PORT, 47011),
+///         KeyValue::new(otel::SERVER_ADDRESS, "198.51.100.17"),
+///         KeyValue::new(otel::SERVER_PORT, 443),
+///     ]
+/// );
+/// ```
+pub fn attributes(header: &Header<'_>) -> Vec<KeyValue> {
+    let mut attributes = Vec::with_capacity(6);
+
+    match header {
+        Header::V1(header) => match header.addresses {
+            v1::Addresses::Tcp4(a) => addresses(
+                &mut attributes,
+                "tcp",
+                (a.source_address, a.source_port).into(),
+                (a.destination_address, a.destination_port).into(),
+            ),
+            v1::Addresses::Tcp6(a) => addresses(
+                &mut attributes,
+                "tcp",
+                (a.source_address, a.source_port).into(),
+                (a.destination_address, a.destination_port).into(),
+            ),
+            v1::Addresses::Unknown => (),
+        },
+        Header::V2(header) if header.command == v2::Command::Local => (),
+        Header::V2(header) => {
+            let transport = match header.protocol {
+                v2::Protocol::Stream => "tcp",
+                v2::Protocol::Datagram => "udp",
+                v2::Protocol::Unspecified => return attributes,
+            };
+
+            match header.addresses {
+                v2::Addresses::IPv4(a) => addresses(
+                    &mut attributes,
+                    transport,
+                    (a.source_address, a.source_port).into(),
+                    (a.destination_address, a.destination_port).into(),
+                ),
+                v2::Addresses::IPv6(a) => addresses(
+                    &mut attributes,
+                    transport,
+                    (a.source_address, a.source_port).into(),
+                    (a.destination_address, a.destination_port).into(),
+                ),
+                v2::Addresses::Unix(a) => {
+                    attributes.push(KeyValue::new(NETWORK_TRANSPORT, "unix"));
+
+                    if let Some(path) = path(a.source_path()) {
+                        attributes.push(KeyValue::new(CLIENT_ADDRESS, path));
+                    }
+
+                    if let Some(path) = path(a.destination_path()) {
+                        attributes.push(KeyValue::new(SERVER_ADDRESS, path));
+                    }
+                }
+                v2::Addresses::Unspecified => (),
+            }
+        }
+    }
+
+    attributes
+}
+
+/// Adds the attributes of IP addresses.
+fn addresses(
+    attributes: &mut Vec<KeyValue>,
+    transport: &'static str,
+    source: SocketAddr,
+    destination: SocketAddr,
+) {
+    let network = match source {
+        SocketAddr::V4(..) => "ipv4",
+        SocketAddr::V6(..) => "ipv6",
+    };
+
+    attributes.push(KeyValue::new(NETWORK_TRANSPORT, transport));
+    attributes.push(KeyValue::new(NETWORK_TYPE, network));
+    attributes.push(KeyValue::new(CLIENT_ADDRESS, source.ip().to_string()));
+    attributes.push(KeyValue::new(CLIENT_PORT, i64::from(source.port())));
+    attributes.push(KeyValue::new(SERVER_ADDRESS, destination.ip().to_string()));
+    attributes.push(KeyValue::new(SERVER_PORT, i64::from(destination.port())));
+}
+
+/// The path of a UNIX socket address as a string.
+/// Returns `None` for unnamed sockets and paths that are not valid UTF-8.
+fn path(path: &[u8]) -> Option<String> {
+    std::str::from_utf8(path)
+        .ok()
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Command, Protocol, Unix, Version};
+
+    fn parse(header: &[u8]) -> Header<'_> {
+        Header::V2(v2::Header::try_from(header).unwrap())
+    }
+
+    #[test]
+    fn udp6() {
+        let header = Builder::with_addresses(
+            Version::Two | Command::Proxy,
+            Protocol::Datagram,
+            (
+                "[2001:db8::1]:53".parse().unwrap(),
+                "[2001:db8::2]:5353".parse().unwrap(),
+            ),
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(
+            attributes(&parse(&header)),
+            vec![
+                KeyValue::new(NETWORK_TRANSPORT, "udp"),
+                KeyValue::new(NETWORK_TYPE, "ipv6"),
+                KeyValue::new(CLIENT_ADDRESS, "2001:db8::1"),
+                KeyValue::new(CLIENT_PORT, 53),
+                KeyValue::new(SERVER_ADDRESS, "2001:db8::2"),
+                KeyValue::new(SERVER_PORT, 5353),
+            ]
+        );
+    }
+
+    #[test]
+    fn unix() {
+        let mut source = [0; 108];
+        source[..13].copy_from_slice(b"/run/lb.sock\0");
+        let header = Builder::with_addresses(
+            Version::Two | Command::Proxy,
+            Protocol::Stream,
+            Unix::new(source, [0; 108]),
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(
+            attributes(&parse(&header)),
+            vec![
+                KeyValue::new(NETWORK_TRANSPORT, "unix"),
+                KeyValue::new(CLIENT_ADDRESS, "/run/lb.sock"),
+            ]
+        );
+    }
+
+    #[test]
+    fn without_addresses() {
+        let local = Builder::with_addresses(
+            Version::Two | Command::Local,
+            Protocol::Stream,
+            (
+                "127.0.0.1:80".parse().unwrap(),
+                "127.0.0.1:443".parse().unwrap(),
+            ),
+        )
+        .build()
+        .unwrap();
+        let unknown = v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap();
+
+        assert_eq!(attributes(&parse(&local)), vec![]);
+        assert_eq!(attributes(&Header::V1(unknown)), vec![]);
+    }
+}
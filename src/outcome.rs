@@ -0,0 +1,146 @@
+//! A three-way result for parsing a header from the bytes of a stream received so far.
+use crate::incremental::{Invalid, Progress};
+use crate::policy::Policy;
+use crate::{v1, v2, Header};
+
+/// The length of the shortest text header, `PROXY UNKNOWN\r\n`.
+const SHORTEST_V1: usize = 15;
+
+/// The outcome of parsing the bytes of a stream received so far, when the stream may or may not start with a header.
+/// Unlike the `Result`s of the stream helpers, a stream without a header is not an error to be told apart from the others.
+// Boxing the header would add an allocation to parsing, which otherwise never allocates.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq)]
+pub enum ParseOutcome<'a> {
+    /// The input starts with a complete header of the given length in bytes, which is where the payload starts.
+    Complete(Header<'a>, usize),
+    /// The input is the start of a header, which needs at least `needed` more bytes.
+    /// `needed` is exact once the fixed part of a binary header is complete; before that it is a lower bound.
+    Incomplete { needed: usize },
+    /// The input does not start with a PROXY protocol header, so it is all payload.
+    NotProxyProtocol,
+}
+
+/// A header of either version that is invalid.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum InvalidHeader {
+    #[error(transparent)]
+    V1(#[from] v1::BinaryParseError),
+    #[error(transparent)]
+    V2(#[from] v2::ParseError),
+}
+
+impl<'a> ParseOutcome<'a> {
+    /// Converts this `ParseOutcome` into one that owns its header, so it can outlive the input.
+    pub fn into_owned(self) -> ParseOutcome<'static> {
+        match self {
+            ParseOutcome::Complete(header, length) => {
+                ParseOutcome::Complete(header.to_owned(), length)
+            }
+            ParseOutcome::Incomplete { needed } => ParseOutcome::Incomplete { needed },
+            ParseOutcome::NotProxyProtocol => ParseOutcome::NotProxyProtocol,
+        }
+    }
+
+    /// Parses a header of either version from the bytes of a stream received so far.
+    /// Call again with more bytes after `Incomplete`; only a header that can never become valid is an error.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::ParseOutcome;
+    ///
+    /// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01";
+    ///
+    /// assert_eq!(ParseOutcome::parse(&input[..10]), Ok(ParseOutcome::Incomplete { needed: 6 }));
+    /// assert_eq!(ParseOutcome::parse(input), Ok(ParseOutcome::Incomplete { needed: 8 }));
+    /// assert_eq!(ParseOutcome::parse(b"GET / HTTP/1.1\r\n"), Ok(ParseOutcome::NotProxyProtocol));
+    ///
+    /// match ParseOutcome::parse(b"PROXY UNKNOWN\r\nHello").unwrap() {
+    ///     ParseOutcome::Complete(header, length) => assert_eq!(header.len(), length),
+    ///     outcome => panic!("expected a complete header, got {:?}", outcome),
+    /// }
+    /// ```
+    pub fn parse(input: &'a [u8]) -> Result<Self, InvalidHeader> {
+        let mut progress = Progress::default();
+
+        match progress.parse(input, Policy::Optional) {
+            None => Ok(ParseOutcome::Incomplete {
+                needed: needed(&progress, input),
+            }),
+            Some(Ok(header)) => Ok(ParseOutcome::from(header)),
+            Some(Err(Invalid::V1(error))) => Err(error.into()),
+            Some(Err(Invalid::V2(error))) => Err(error.into()),
+            // Only `Policy::Require` rejects inputs without a header.
+            Some(Err(Invalid::MissingHeader)) => unreachable!(),
+        }
+    }
+}
+
+/// Converts the header of a stream, if it started with one, into a complete outcome.
+impl<'a> From<Option<Header<'a>>> for ParseOutcome<'a> {
+    fn from(header: Option<Header<'a>>) -> Self {
+        match header {
+            Some(header) => {
+                let length = header.len();
+
+                ParseOutcome::Complete(header, length)
+            }
+            None => ParseOutcome::NotProxyProtocol,
+        }
+    }
+}
+
+/// The lower bound on the number of bytes an incomplete header still needs,
+/// given the progress made parsing the input so far.
+pub(crate) fn needed(progress: &Progress, input: &[u8]) -> usize {
+    let shortest = match progress {
+        Progress::V2 { length } => *length,
+        // The input is all or part of the binary prefix.
+        _ if !input.is_empty()
+            && (v2::PROTOCOL_PREFIX.starts_with(input)
+                || input.starts_with(v2::PROTOCOL_PREFIX)) =>
+        {
+            v2::MINIMUM_LENGTH
+        }
+        _ => SHORTEST_V1,
+    };
+
+    shortest.saturating_sub(input.len()).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incomplete() {
+        assert_eq!(
+            ParseOutcome::parse(b""),
+            Ok(ParseOutcome::Incomplete { needed: 15 })
+        );
+        assert_eq!(
+            ParseOutcome::parse(b"PROXY TCP4 127.0.0.1"),
+            Ok(ParseOutcome::Incomplete { needed: 1 })
+        );
+        assert_eq!(
+            ParseOutcome::parse(b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C"),
+            Ok(ParseOutcome::Incomplete { needed: 12 })
+        );
+        assert!(matches!(
+            ParseOutcome::parse(b"PROXY TCP4 127.0.0.1 10.0.0.1 80\r\n"),
+            Err(InvalidHeader::V1(..))
+        ));
+    }
+
+    #[test]
+    fn into_owned() {
+        let input = b"PROXY UNKNOWN\r\nHello".to_vec();
+        let outcome = ParseOutcome::parse(&input).unwrap().into_owned();
+
+        drop(input);
+
+        let outcome = std::thread::spawn(move || outcome).join().unwrap();
+
+        assert!(matches!(outcome, ParseOutcome::Complete(_, 15)));
+    }
+}
@@ -0,0 +1,314 @@
+//! Allocation-free parsers for the ports and IP addresses in text headers.
+//!
+//! Unlike the `FromStr` implementations of the standard library, these never accept a sign or whitespace,
+//! and check for overflow as they read each digit. Each parser reads a value from the start of the bytes
+//! and returns how many bytes it read, so callers can scan a field in the same pass that finds its end.
+use crate::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// How to treat decimal numbers with leading zeros.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Mode {
+    /// Leading zeros are rejected (e.g. the ports and IPv4 octets of a version 1 header).
+    Strict,
+    /// Leading zeros are allowed (e.g. the `1*5DIGIT` ports of RFC 7239).
+    Lenient,
+}
+
+/// Why a decimal number could not be read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum NumberError {
+    /// There are no digits.
+    Empty,
+    /// The number does not fit in the type.
+    Overflow,
+    /// The number has a leading zero in `Mode::Strict`.
+    LeadingZero,
+}
+
+/// Reads the decimal digits at the start of the bytes into a number of at most the given value.
+/// Returns the number of digits read along with the number.
+fn decimal(bytes: &[u8], maximum: u32, mode: Mode) -> (usize, Result<u32, NumberError>) {
+    let count = bytes
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    let mut value: u32 = 0;
+
+    // Stops as soon as the value is too large, so it always fits in a `u32`.
+    for &digit in &bytes[..count] {
+        value = value * 10 + (digit - b'0') as u32;
+
+        if value > maximum {
+            return (count, Err(NumberError::Overflow));
+        }
+    }
+
+    match count {
+        0 => (0, Err(NumberError::Empty)),
+        1 => (1, Ok(value)),
+        _ if mode == Mode::Strict && bytes[0] == b'0' => (count, Err(NumberError::LeadingZero)),
+        _ => (count, Ok(value)),
+    }
+}
+
+/// Reads the decimal `u16` (e.g. a port) at the start of the bytes.
+pub(crate) fn u16(bytes: &[u8], mode: Mode) -> (usize, Result<u16, NumberError>) {
+    let (count, value) = decimal(bytes, u16::MAX as u32, mode);
+
+    (count, value.map(|value| value as u16))
+}
+
+/// Reads the dotted decimal IPv4 address at the start of the bytes.
+/// Returns `None` if the bytes do not start with an address, along with where reading stopped.
+pub(crate) fn ipv4(bytes: &[u8], mode: Mode) -> (usize, Option<Ipv4Addr>) {
+    let mut octets = [0u8; 4];
+    let mut position = 0;
+
+    for (index, octet) in octets.iter_mut().enumerate() {
+        if index > 0 {
+            if bytes.get(position) != Some(&b'.') {
+                return (position, None);
+            }
+
+            position += 1;
+        }
+
+        let (count, value) = decimal(&bytes[position..], u8::MAX as u32, mode);
+
+        position += count;
+
+        match value {
+            Ok(value) => *octet = value as u8,
+            Err(_) => return (position, None),
+        }
+    }
+
+    (position, Some(Ipv4Addr::from(octets)))
+}
+
+/// Reads the IPv6 address at the start of the bytes, in hexadecimal groups with at most one `::`
+/// and optionally ending in an IPv4 address. The `Mode` applies to the IPv4 address.
+/// Returns `None` if the bytes do not start with an address, along with where reading stopped.
+pub(crate) fn ipv6(bytes: &[u8], mode: Mode) -> (usize, Option<Ipv6Addr>) {
+    let mut groups = [0u16; 8];
+    let mut count = 0;
+    let mut gap = None;
+    let mut group: u16 = 0;
+    let mut digits = 0;
+    let mut colons = 0;
+    let mut position = 0;
+
+    while let Some(&byte) = bytes.get(position) {
+        let digit = match byte {
+            b'0'..=b'9' => byte - b'0',
+            b'a'..=b'f' => byte - b'a' + 10,
+            b'A'..=b'F' => byte - b'A' + 10,
+            b':' => {
+                match colons {
+                    // The end of a group.
+                    0 if digits > 0 && count < groups.len() => {
+                        groups[count] = group;
+                        count += 1;
+                        group = 0;
+                        digits = 0;
+                    }
+                    // The start of the address.
+                    0 if digits == 0 => (),
+                    1 if gap.is_none() => gap = Some(count),
+                    _ => return (position, None),
+                }
+
+                colons += 1;
+                position += 1;
+                continue;
+            }
+            // The digits of the current group are the first octet of an IPv4 address, which takes up the last two groups.
+            b'.' if digits > 0 && count + 2 <= groups.len() => {
+                let start = position - digits;
+                let (length, address) = ipv4(&bytes[start..], mode);
+
+                match address {
+                    Some(address) => {
+                        let octets = address.octets();
+
+                        groups[count] = u16::from_be_bytes([octets[0], octets[1]]);
+                        groups[count + 1] = u16::from_be_bytes([octets[2], octets[3]]);
+                        count += 2;
+                        digits = 0;
+                        position = start + length;
+                        break;
+                    }
+                    None => return (start + length, None),
+                }
+            }
+            _ => break,
+        };
+
+        // A single colon may not start an address.
+        if digits == 4 || (colons == 1 && count == 0 && gap.is_none()) {
+            return (position, None);
+        }
+
+        group = (group << 4) | digit as u16;
+        digits += 1;
+        colons = 0;
+        position += 1;
+    }
+
+    if colons == 1 {
+        return (position, None);
+    }
+
+    if digits > 0 {
+        if count == groups.len() {
+            return (position, None);
+        }
+
+        groups[count] = group;
+        count += 1;
+    }
+
+    let address = match gap {
+        None if count == 8 => groups,
+        Some(gap) if count < 8 => {
+            let mut expanded = [0; 8];
+            let tail = count - gap;
+
+            expanded[..gap].copy_from_slice(&groups[..gap]);
+            expanded[8 - tail..].copy_from_slice(&groups[gap..count]);
+            expanded
+        }
+        _ => return (position, None),
+    };
+
+    (position, Some(Ipv6Addr::from(address)))
+}
+
+/// Parses the whole of the bytes with one of the address parsers.
+fn exactly<T>(
+    bytes: &[u8],
+    mode: Mode,
+    parser: impl Fn(&[u8], Mode) -> (usize, Option<T>),
+) -> Option<T> {
+    match parser(bytes, mode) {
+        (length, Some(value)) if length == bytes.len() => Some(value),
+        _ => None,
+    }
+}
+
+/// Parses the whole of the bytes as an IPv4 or IPv6 address.
+pub(crate) fn ip(bytes: &[u8], mode: Mode) -> Option<IpAddr> {
+    exactly(bytes, mode, ipv4)
+        .map(IpAddr::V4)
+        .or_else(|| exactly(bytes, mode, ipv6).map(IpAddr::V6))
+}
+
+/// Parses the whole of the bytes as a decimal `u16`.
+pub(crate) fn port(bytes: &[u8], mode: Mode) -> Option<u16> {
+    match u16(bytes, mode) {
+        (length, Ok(port)) if length == bytes.len() => Some(port),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(u16(b"0", Mode::Strict), (1, Ok(0)));
+        assert_eq!(u16(b"65535 ", Mode::Strict), (5, Ok(65535)));
+        assert_eq!(u16(b"65536", Mode::Strict), (5, Err(NumberError::Overflow)));
+        assert_eq!(
+            u16(b"99999999999999999999", Mode::Strict),
+            (20, Err(NumberError::Overflow))
+        );
+        assert_eq!(
+            u16(b"080", Mode::Strict),
+            (3, Err(NumberError::LeadingZero))
+        );
+        assert_eq!(u16(b"080", Mode::Lenient), (3, Ok(80)));
+        assert_eq!(u16(b"00000", Mode::Lenient), (5, Ok(0)));
+        assert_eq!(u16(b"", Mode::Strict), (0, Err(NumberError::Empty)));
+        assert_eq!(u16(b"+80", Mode::Lenient), (0, Err(NumberError::Empty)));
+        assert_eq!(u16(b" 80", Mode::Lenient), (0, Err(NumberError::Empty)));
+    }
+
+    #[test]
+    fn addresses_agree_with_std() {
+        for input in [
+            "127.0.0.1",
+            "255.255.255.255",
+            "0.0.0.0",
+            "256.0.0.1",
+            "01.0.0.1",
+            "1.2.3",
+            "1.2.3.4.5",
+            "1..2.3",
+            "1.2.3.",
+            "+1.2.3.4",
+            " 1.2.3.4",
+            "",
+        ] {
+            assert_eq!(
+                exactly(input.as_bytes(), Mode::Strict, ipv4),
+                input.parse::<Ipv4Addr>().ok(),
+                "{}",
+                input
+            );
+        }
+
+        for input in [
+            "::",
+            "::1",
+            "1::",
+            "2001:db8::8a2e:370:7334",
+            "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff",
+            "FFFF:0:0:0:0:0:0:1",
+            "1:2:3:4:5:6:7::",
+            "1:2:3:4:5:6:7:8::",
+            "1::2:3:4:5:6:7:8",
+            ":1",
+            "1:",
+            "1:::2",
+            "1::2::3",
+            "12345::",
+            "1:2:3:4:5:6:7",
+            "1:2:3:4:5:6:7:8:9",
+            "gggg::",
+            "::ffff:1.2.3.4",
+            "::1.2.3.4",
+            "1:2:3:4:5:6:1.2.3.4",
+            "1:2:3:4:5:6:7:1.2.3.4",
+            "1:2:3:4:5::1.2.3.4",
+            "1:2:3:4:5:6::1.2.3.4",
+            "1.2.3.4::",
+            "::1.2.3.4:1",
+            "::ffff:01.2.3.4",
+            "::1a.2.3.4",
+            "",
+        ] {
+            assert_eq!(
+                exactly(input.as_bytes(), Mode::Strict, ipv6),
+                input.parse::<Ipv6Addr>().ok(),
+                "{}",
+                input
+            );
+        }
+
+        assert_eq!(
+            ipv4(b"01.002.3.4", Mode::Lenient),
+            (10, Some(Ipv4Addr::new(1, 2, 3, 4)))
+        );
+        assert_eq!(ipv4(b"1.2.3.4 5", Mode::Strict).0, 7);
+        assert_eq!(
+            ip(b"::1", Mode::Strict),
+            Some(IpAddr::V6(Ipv6Addr::LOCALHOST))
+        );
+        assert_eq!(ip(b"1.2.3.4 ", Mode::Strict), None);
+        assert_eq!(port(b"0443", Mode::Lenient), Some(443));
+        assert_eq!(port(b"443x", Mode::Lenient), None);
+        assert_eq!(ipv6(b"::1 2", Mode::Strict).0, 3);
+    }
+}
@@ -0,0 +1,862 @@
+//! Policies for deciding whether a connection's PROXY protocol header should be honored.
+//!
+//! Only peers that are known to speak the PROXY protocol (i.e. load balancers and proxies) should be trusted,
+//! otherwise any client can spoof its source address by sending a header of its own.
+use crate::prelude::*;
+
+use crate::net::{AddrParseError, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use crate::{v1, v2, Header};
+use core::num::ParseIntError;
+use core::str::FromStr;
+
+/// The separator between the address and the prefix length of a network in CIDR notation.
+const PREFIX_SEPARATOR: char = '/';
+
+/// An error in parsing a network in CIDR notation.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum NetworkError {
+    #[error("Network contains an invalid IP address.")]
+    InvalidAddress(#[source] AddrParseError),
+    #[error("Network prefix length must be at most 32 bits for IPv4 and 128 bits for IPv6.")]
+    InvalidPrefixLength(#[source] Option<ParseIntError>),
+}
+
+/// A network of IP addresses, written in CIDR notation as `address/prefix length` (e.g. `10.0.0.0/8`).
+///
+/// ## Examples
+/// ```rust
+/// use ppp::policy::Network;
+///
+/// let network: Network = "10.0.0.0/8".parse().unwrap();
+///
+/// assert!(network.contains("10.1.2.3".parse().unwrap()));
+/// assert!(network.contains("::ffff:10.1.2.3".parse().unwrap()));
+/// assert!(!network.contains("192.168.1.1".parse().unwrap()));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Network {
+    address: IpAddr,
+    prefix_length: u8,
+}
+
+/// Whether connections are expected to start with a PROXY protocol header.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Policy {
+    /// Every connection must start with a PROXY protocol header.
+    Require,
+    /// Connections may start with a PROXY protocol header; otherwise, the bytes read are application data.
+    /// Servers using this policy must wait for the client to send data,
+    /// so it is not suitable for protocols where the server speaks first.
+    Optional,
+    /// Connections never start with a PROXY protocol header; all bytes are application data.
+    Ignore,
+}
+
+/// What to do with a connection from a peer that is not trusted to send a PROXY protocol header.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Untrusted {
+    /// Treat the connection as a plain connection; any header sent by the peer is application data.
+    Ignore,
+    /// Reject the connection.
+    Reject,
+}
+
+/// The outcome of evaluating a peer against the `TrustedPeers`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Decision {
+    /// The peer is trusted, so its PROXY protocol header should be honored.
+    Honor,
+    /// The peer is not trusted; its connection is a plain connection.
+    Ignore,
+    /// The peer is not trusted; its connection should be closed.
+    Reject,
+}
+
+/// The set of networks whose peers are trusted to send PROXY protocol headers.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::policy::{Decision, TrustedPeers, Untrusted};
+///
+/// let peers = TrustedPeers::new()
+///     .trust("10.0.0.0/8".parse().unwrap())
+///     .trust("fd00::/8".parse().unwrap())
+///     .untrusted(Untrusted::Reject);
+///
+/// assert_eq!(peers.evaluate("10.0.0.1".parse().unwrap()), Decision::Honor);
+/// assert_eq!(peers.evaluate("fd00::1".parse().unwrap()), Decision::Honor);
+/// assert_eq!(peers.evaluate("203.0.113.7".parse().unwrap()), Decision::Reject);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrustedPeers {
+    networks: Vec<Network>,
+    untrusted: Untrusted,
+}
+
+impl Network {
+    /// Creates a new `Network` from the given address and prefix length.
+    /// Bits of the address beyond the prefix length are ignored.
+    /// Networks within the IPv4-mapped range (e.g. `::ffff:10.0.0.0/104`) become their IPv4 equivalent (`10.0.0.0/8`),
+    /// as `contains` compares IPv4-mapped addresses as IPv4.
+    pub fn new<T: Into<IpAddr>>(address: T, prefix_length: u8) -> Result<Self, NetworkError> {
+        let address = address.into();
+        let maximum = match address {
+            IpAddr::V4(..) => 32,
+            IpAddr::V6(..) => 128,
+        };
+
+        if prefix_length > maximum {
+            return Err(NetworkError::InvalidPrefixLength(None));
+        }
+
+        Ok(Network::unmapped(address, prefix_length))
+    }
+
+    /// Creates a `Network` from a valid prefix length, converting networks within the IPv4-mapped range to IPv4.
+    fn unmapped(address: IpAddr, prefix_length: u8) -> Self {
+        match (address, canonical(address)) {
+            (IpAddr::V6(..), IpAddr::V4(address)) if prefix_length >= 96 => Network {
+                address: address.into(),
+                prefix_length: prefix_length - 96,
+            },
+            _ => Network {
+                address,
+                prefix_length,
+            },
+        }
+    }
+
+    /// The address of this `Network`.
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    /// The number of leading bits of an address that must match this `Network`'s address.
+    pub fn prefix_length(&self) -> u8 {
+        self.prefix_length
+    }
+
+    /// Tests whether the given address is part of this `Network`.
+    /// IPv4-mapped IPv6 addresses (e.g. `::ffff:10.0.0.1`) are treated as their IPv4 equivalent.
+    pub fn contains(&self, address: IpAddr) -> bool {
+        let suffix = match self.address {
+            IpAddr::V4(..) => 32,
+            IpAddr::V6(..) => 128,
+        } - self.prefix_length as u32;
+
+        match (self.address, canonical(address)) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                u32::from(network).checked_shr(suffix).unwrap_or_default()
+                    == u32::from(address).checked_shr(suffix).unwrap_or_default()
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                u128::from(network).checked_shr(suffix).unwrap_or_default()
+                    == u128::from(address).checked_shr(suffix).unwrap_or_default()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Converts IPv4-mapped IPv6 addresses to IPv4 addresses.
+fn canonical(address: IpAddr) -> IpAddr {
+    match address {
+        IpAddr::V6(ip) => match ip.segments() {
+            [0, 0, 0, 0, 0, 0xFFFF, high, low] => {
+                IpAddr::V4(Ipv4Addr::from(((high as u32) << 16) | low as u32))
+            }
+            _ => IpAddr::V6(ip),
+        },
+        address => address,
+    }
+}
+
+impl FromStr for Network {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_length) = match s.split_once(PREFIX_SEPARATOR) {
+            Some((address, prefix_length)) => (address, Some(prefix_length)),
+            None => (s, None),
+        };
+
+        let address = address
+            .parse::<IpAddr>()
+            .map_err(NetworkError::InvalidAddress)?;
+        let prefix_length = match (prefix_length, address) {
+            (Some(prefix_length), _) => prefix_length
+                .parse::<u8>()
+                .map_err(|e| NetworkError::InvalidPrefixLength(Some(e)))?,
+            (None, IpAddr::V4(..)) => 32,
+            (None, IpAddr::V6(..)) => 128,
+        };
+
+        Network::new(address, prefix_length)
+    }
+}
+
+impl From<Ipv4Addr> for Network {
+    fn from(address: Ipv4Addr) -> Self {
+        Network {
+            address: address.into(),
+            prefix_length: 32,
+        }
+    }
+}
+
+impl From<Ipv6Addr> for Network {
+    fn from(address: Ipv6Addr) -> Self {
+        Network::unmapped(address.into(), 128)
+    }
+}
+
+impl Default for TrustedPeers {
+    fn default() -> Self {
+        TrustedPeers::new()
+    }
+}
+
+impl TrustedPeers {
+    /// Creates an empty set of trusted peers, where connections from untrusted peers are treated as plain connections.
+    pub fn new() -> Self {
+        TrustedPeers {
+            networks: Vec::new(),
+            untrusted: Untrusted::Ignore,
+        }
+    }
+
+    /// Adds the given `Network` to the set of trusted peers.
+    pub fn trust(mut self, network: Network) -> Self {
+        self.networks.push(network);
+        self
+    }
+
+    /// Sets what to do with connections from untrusted peers.
+    pub fn untrusted(mut self, untrusted: Untrusted) -> Self {
+        self.untrusted = untrusted;
+        self
+    }
+
+    /// The trusted networks.
+    pub fn networks(&self) -> &[Network] {
+        self.networks.as_slice()
+    }
+
+    /// Tests whether the given peer address is part of a trusted network.
+    pub fn is_trusted(&self, peer: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(peer))
+    }
+
+    /// Decides how to handle a connection from the given peer address.
+    pub fn evaluate(&self, peer: IpAddr) -> Decision {
+        match (self.is_trusted(peer), self.untrusted) {
+            (true, _) => Decision::Honor,
+            (false, Untrusted::Ignore) => Decision::Ignore,
+            (false, Untrusted::Reject) => Decision::Reject,
+        }
+    }
+}
+
+impl FromIterator<Network> for TrustedPeers {
+    fn from_iter<I: IntoIterator<Item = Network>>(networks: I) -> Self {
+        TrustedPeers {
+            networks: networks.into_iter().collect(),
+            untrusted: Untrusted::Ignore,
+        }
+    }
+}
+
+/// Why an otherwise well-formed header was rejected by a `Validation`, a `ChecksumPolicy` or a `ReservedTypes`.
+#[derive(thiserror::Error, Copy, Clone, Debug, PartialEq)]
+pub enum Violation {
+    #[error("Header has a port of 0.")]
+    ZeroPort,
+    #[error("Header has an unspecified source address.")]
+    UnspecifiedSource,
+    #[error("Header has a multicast source address.")]
+    MulticastSource,
+    #[error("Header has a broadcast source address.")]
+    BroadcastSource,
+    #[error("Header does not carry a CRC32c checksum.")]
+    MissingChecksum,
+    #[error("Header has an invalid CRC32c checksum.")]
+    InvalidChecksum,
+    #[error("Header has an AUTHORITY TLV that is not a valid hostname.")]
+    InvalidAuthority,
+    #[error("Header has a TLV of type {0:#04X}, which the specification reserves for future use.")]
+    ReservedType(u8),
+}
+
+/// Opt-in checks of the addresses in well-formed headers, which reject values that are almost always
+/// sender bugs or spoofing attempts. No checks are enabled by default.
+///
+/// Only headers with IP addresses are checked, and the addresses of `v2::Command::Local` headers are ignored.
+/// The `v2::Type::Authority` TLVs of binary headers can be checked too, as they are routinely used for SNI-based routing.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::policy::{Validation, Violation};
+/// use ppp::{v1, Header};
+///
+/// let validation = Validation::new().zero_ports().unspecified_sources();
+/// let header = v1::Header::try_from("PROXY TCP4 0.0.0.0 127.0.0.1 80 443\r\n").unwrap();
+///
+/// assert_eq!(validation.check(&Header::V1(header)), Err(Violation::UnspecifiedSource));
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Validation {
+    zero_ports: bool,
+    unspecified_sources: bool,
+    multicast_sources: bool,
+    broadcast_sources: bool,
+    hostname_authorities: bool,
+}
+
+impl Validation {
+    /// Creates a new `Validation` with no checks enabled.
+    pub fn new() -> Self {
+        Validation::default()
+    }
+
+    /// Creates a new `Validation` with every check enabled.
+    pub fn all() -> Self {
+        Validation::new()
+            .zero_ports()
+            .unspecified_sources()
+            .multicast_sources()
+            .broadcast_sources()
+            .hostname_authorities()
+    }
+
+    /// Rejects a source or destination port of 0.
+    pub fn zero_ports(mut self) -> Self {
+        self.zero_ports = true;
+        self
+    }
+
+    /// Rejects an unspecified source address (i.e. `0.0.0.0` or `::`).
+    pub fn unspecified_sources(mut self) -> Self {
+        self.unspecified_sources = true;
+        self
+    }
+
+    /// Rejects a multicast source address.
+    pub fn multicast_sources(mut self) -> Self {
+        self.multicast_sources = true;
+        self
+    }
+
+    /// Rejects the IPv4 broadcast address (`255.255.255.255`) as a source address.
+    pub fn broadcast_sources(mut self) -> Self {
+        self.broadcast_sources = true;
+        self
+    }
+
+    /// Rejects binary headers with a `v2::Type::Authority` TLV that is not a syntactically valid DNS hostname:
+    /// dot-separated labels of 1 to 63 ASCII letters, digits and hyphens that neither start nor end with a hyphen,
+    /// 253 bytes at most in total. With the `idna` feature, internationalized names are accepted too,
+    /// as long as they map to such a hostname by IDNA processing (e.g. `bücher.example` to `xn--bcher-kva.example`).
+    pub fn hostname_authorities(mut self) -> Self {
+        self.hostname_authorities = true;
+        self
+    }
+
+    /// Tests whether any check is enabled.
+    pub fn is_enabled(&self) -> bool {
+        *self != Validation::new()
+    }
+
+    /// Checks the addresses of a header, if it has IP addresses, and the authorities of a binary header.
+    pub fn check(&self, header: &Header<'_>) -> Result<(), Violation> {
+        if let Header::V2(header) = header {
+            self.check_authorities(header)?;
+        }
+
+        let (source, destination): (SocketAddr, SocketAddr) = match header {
+            Header::V1(header) => match header.addresses {
+                v1::Addresses::Tcp4(a) => (
+                    (a.source_address, a.source_port).into(),
+                    (a.destination_address, a.destination_port).into(),
+                ),
+                v1::Addresses::Tcp6(a) => (
+                    (a.source_address, a.source_port).into(),
+                    (a.destination_address, a.destination_port).into(),
+                ),
+                v1::Addresses::Unknown => return Ok(()),
+            },
+            Header::V2(header) => match header.proxied_addresses() {
+                v2::Addresses::IPv4(a) => (
+                    (a.source_address, a.source_port).into(),
+                    (a.destination_address, a.destination_port).into(),
+                ),
+                v2::Addresses::IPv6(a) => (
+                    (a.source_address, a.source_port).into(),
+                    (a.destination_address, a.destination_port).into(),
+                ),
+                v2::Addresses::Unix(..) | v2::Addresses::Unspecified => return Ok(()),
+            },
+        };
+
+        self.check_addresses(source, destination)
+    }
+
+    /// Checks the `v2::Type::Authority` TLVs of a binary header.
+    fn check_authorities(&self, header: &v2::Header<'_>) -> Result<(), Violation> {
+        if !self.hostname_authorities {
+            return Ok(());
+        }
+
+        let authorities = header
+            .tlvs()
+            .map_while(Result::ok)
+            .filter(|tlv| tlv.kind == u8::from(v2::Type::Authority));
+
+        for tlv in authorities {
+            match core::str::from_utf8(tlv.value.as_ref()) {
+                Ok(authority) if is_authority(authority) => (),
+                _ => return Err(Violation::InvalidAuthority),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a pair of source and destination addresses.
+    pub fn check_addresses(
+        &self,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) -> Result<(), Violation> {
+        let address = source.ip();
+
+        if self.zero_ports && (source.port() == 0 || destination.port() == 0) {
+            Err(Violation::ZeroPort)
+        } else if self.unspecified_sources && address.is_unspecified() {
+            Err(Violation::UnspecifiedSource)
+        } else if self.multicast_sources && address.is_multicast() {
+            Err(Violation::MulticastSource)
+        } else if self.broadcast_sources
+            && matches!(address, IpAddr::V4(address) if address.is_broadcast())
+        {
+            Err(Violation::BroadcastSource)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Tests whether the value is a syntactically valid DNS hostname, as described by `Validation::hostname_authorities`.
+fn is_hostname(value: &str) -> bool {
+    value.len() <= 253
+        && value.split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+        })
+}
+
+/// Tests whether an authority is a valid hostname.
+#[cfg(not(feature = "idna"))]
+fn is_authority(authority: &str) -> bool {
+    is_hostname(authority)
+}
+
+/// Tests whether an authority is a valid hostname once mapped to ASCII, which also validates its punycode labels.
+#[cfg(feature = "idna")]
+fn is_authority(authority: &str) -> bool {
+    idna::domain_to_ascii_strict(authority).map_or(false, |ascii| is_hostname(&ascii))
+}
+
+/// Whether binary headers must carry a valid CRC32c checksum, in a `v2::Type::CRC32C` TLV.
+/// Text headers cannot carry a checksum, so they are never checked.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::policy::{ChecksumPolicy, Violation};
+/// use ppp::{v2, Header};
+///
+/// let header = v2::Header::try_from(&b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00"[..]).unwrap();
+/// let header = Header::V2(header);
+///
+/// assert_eq!(ChecksumPolicy::VerifyIfPresent.check(&header), Ok(()));
+/// assert_eq!(ChecksumPolicy::Require.check(&header), Err(Violation::MissingChecksum));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ChecksumPolicy {
+    /// Checksums are never verified.
+    Ignore,
+    /// Headers that carry a checksum must carry a valid one.
+    VerifyIfPresent,
+    /// Every binary header must carry a valid checksum.
+    Require,
+}
+
+impl Default for ChecksumPolicy {
+    fn default() -> Self {
+        ChecksumPolicy::Ignore
+    }
+}
+
+impl ChecksumPolicy {
+    /// Checks the checksum of a header against this `ChecksumPolicy`.
+    pub fn check(&self, header: &Header<'_>) -> Result<(), Violation> {
+        let header = match (self, header) {
+            (ChecksumPolicy::Ignore, _) | (_, Header::V1(..)) => return Ok(()),
+            (_, Header::V2(header)) => header,
+        };
+
+        match (self, header.verify_checksum()) {
+            (_, Some(true)) | (ChecksumPolicy::VerifyIfPresent, None) => Ok(()),
+            (_, Some(false)) => Err(Violation::InvalidChecksum),
+            (_, None) => Err(Violation::MissingChecksum),
+        }
+    }
+}
+
+/// Whether binary headers may carry TLVs of types the specification reserves for future use,
+/// i.e. types that are neither assigned by the specification nor in the custom (`0xE0` to `0xEF`)
+/// or experimental (`0xF0` to `0xF7`) ranges. Senders that use them squat on type codes that a future
+/// version of the specification may assign a different meaning to.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::policy::{ReservedTypes, Violation};
+/// use ppp::{v2, Header};
+///
+/// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0F\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB\x40\x00\x00";
+/// let header = Header::V2(v2::Header::try_from(&input[..]).unwrap());
+///
+/// assert_eq!(ReservedTypes::Warn.check(&header), Ok(()));
+/// assert_eq!(ReservedTypes::Reject.check(&header), Err(Violation::ReservedType(0x40)));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReservedTypes {
+    /// TLVs of reserved types are accepted silently.
+    Allow,
+    /// TLVs of reserved types are accepted, but logged as a warning when the `tracing` feature is enabled.
+    Warn,
+    /// Headers with a TLV of a reserved type are rejected.
+    Reject,
+}
+
+impl Default for ReservedTypes {
+    fn default() -> Self {
+        ReservedTypes::Allow
+    }
+}
+
+impl ReservedTypes {
+    /// Checks the types of the TLVs of a header against this `ReservedTypes`.
+    /// TLVs that cannot be parsed, and the TLVs after them, are not checked.
+    pub fn check(&self, header: &Header<'_>) -> Result<(), Violation> {
+        let header = match (self, header) {
+            (ReservedTypes::Allow, _) | (_, Header::V1(..)) => return Ok(()),
+            (_, Header::V2(header)) => header,
+        };
+
+        let reserved = header
+            .tlvs()
+            .map_while(Result::ok)
+            .map(|tlv| tlv.kind)
+            .find(|kind| is_reserved_type(*kind));
+
+        match (self, reserved) {
+            (_, None) => Ok(()),
+            (ReservedTypes::Warn, Some(_kind)) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(kind = _kind, "header has a TLV of a reserved type");
+
+                Ok(())
+            }
+            (_, Some(kind)) => Err(Violation::ReservedType(kind)),
+        }
+    }
+}
+
+/// Tests whether a TLV type is reserved by the specification for future use.
+/// Types assigned by the specification, and the custom and experimental ranges, are not reserved.
+pub fn is_reserved_type(kind: u8) -> bool {
+    !matches!(
+        kind,
+        0x01..=0x05 | 0x20..=0x25 | 0x30 | 0xE0..=0xEF | 0xF0..=0xF7
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        let all = Validation::all();
+        let cases = [
+            ("PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n", Ok(())),
+            (
+                "PROXY TCP4 127.0.0.1 127.0.0.2 80 0\r\n",
+                Err(Violation::ZeroPort),
+            ),
+            (
+                "PROXY TCP6 :: ::1 80 443\r\n",
+                Err(Violation::UnspecifiedSource),
+            ),
+            (
+                "PROXY TCP4 224.0.0.1 127.0.0.2 80 443\r\n",
+                Err(Violation::MulticastSource),
+            ),
+            (
+                "PROXY TCP6 ff02::1 ::1 80 443\r\n",
+                Err(Violation::MulticastSource),
+            ),
+            (
+                "PROXY TCP4 255.255.255.255 127.0.0.2 80 443\r\n",
+                Err(Violation::BroadcastSource),
+            ),
+            ("PROXY TCP4 127.0.0.1 0.0.0.0 80 443\r\n", Ok(())),
+            ("PROXY UNKNOWN\r\n", Ok(())),
+        ];
+
+        assert!(!Validation::new().is_enabled());
+        assert!(all.is_enabled());
+
+        for (text, expected) in cases {
+            let header = Header::V1(v1::Header::try_from(text).unwrap());
+
+            assert_eq!(all.check(&header), expected, "{}", text);
+            assert_eq!(Validation::new().check(&header), Ok(()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn validation_ignores_local() {
+        let mut local = Vec::from(v2::PROTOCOL_PREFIX);
+
+        local.extend([0x20, 0x11, 0, 12, 0, 0, 0, 0, 127, 0, 0, 1, 0, 0, 0, 0]);
+
+        let mut proxy = local.clone();
+
+        proxy[12] = 0x21;
+
+        let local = Header::V2(v2::Header::try_from(local.as_slice()).unwrap());
+        let proxy = Header::V2(v2::Header::try_from(proxy.as_slice()).unwrap());
+
+        assert_eq!(Validation::all().check(&local), Ok(()));
+        assert_eq!(Validation::all().check(&proxy), Err(Violation::ZeroPort));
+    }
+
+    #[test]
+    fn hostname_authorities() {
+        let validation = Validation::new().hostname_authorities();
+        let label = "a".repeat(63);
+        let long = [label.as_str(); 4].join(".");
+        let cases: [(&[u8], bool); 12] = [
+            (b"example.com", true),
+            (b"a-1.EXAMPLE.com", true),
+            (b"xn--bcher-kva.example", true),
+            (label.as_bytes(), true),
+            (&long.as_bytes()[..253], true),
+            (long.as_bytes(), false),
+            (b"", false),
+            (b"example..com", false),
+            (b"-example.com", false),
+            (b"example.com.", false),
+            (b"exa mple.com", false),
+            (b"\xFF.com", false),
+        ];
+
+        for (authority, valid) in cases {
+            let header = v2::Builder::with_addresses(
+                v2::Version::Two | v2::Command::Proxy,
+                v2::Protocol::Stream,
+                v2::IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+            )
+            .write_tlv(v2::Type::Authority, authority)
+            .unwrap()
+            .build()
+            .unwrap();
+            let header = Header::V2(v2::Header::try_from(header.as_slice()).unwrap());
+            let expected = if valid {
+                Ok(())
+            } else {
+                Err(Violation::InvalidAuthority)
+            };
+
+            assert_eq!(validation.check(&header), expected, "{:?}", authority);
+            assert_eq!(Validation::new().check(&header), Ok(()));
+        }
+
+        assert_eq!(is_authority("bücher.example"), cfg!(feature = "idna"));
+    }
+
+    #[test]
+    fn checksum_policy() {
+        let mut signed = Vec::from(v2::PROTOCOL_PREFIX);
+
+        signed.extend([
+            0x21, 0x11, 0x00, 0x13, 127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187,
+        ]);
+        signed.extend([0x03, 0x00, 0x04]);
+        signed.extend(0x8326_5C80u32.to_be_bytes());
+
+        let mut corrupted = signed.clone();
+
+        corrupted[20] ^= 1;
+
+        let unsigned = &signed[..28];
+        let cases = [
+            (&signed[..], Ok(()), Ok(())),
+            (
+                &corrupted[..],
+                Err(Violation::InvalidChecksum),
+                Err(Violation::InvalidChecksum),
+            ),
+            (unsigned, Ok(()), Err(Violation::MissingChecksum)),
+        ];
+
+        for (input, if_present, required) in cases {
+            let mut input = Vec::from(input);
+
+            let length = (input.len() - 16) as u16;
+
+            input[14..16].copy_from_slice(&length.to_be_bytes());
+
+            let header = Header::V2(v2::Header::try_from(input.as_slice()).unwrap());
+
+            assert_eq!(ChecksumPolicy::Ignore.check(&header), Ok(()));
+            assert_eq!(ChecksumPolicy::VerifyIfPresent.check(&header), if_present);
+            assert_eq!(ChecksumPolicy::Require.check(&header), required);
+        }
+
+        let text = Header::V1(v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap());
+
+        assert_eq!(ChecksumPolicy::Require.check(&text), Ok(()));
+    }
+
+    #[test]
+    fn reserved_types() {
+        let cases = [
+            (&[0x01, 0x20, 0x30, 0xE0, 0xEF, 0xF0, 0xF7][..], Ok(())),
+            (&[0xE0, 0x06][..], Err(Violation::ReservedType(0x06))),
+            (&[0x31][..], Err(Violation::ReservedType(0x31))),
+            (&[0xF8, 0x00][..], Err(Violation::ReservedType(0xF8))),
+        ];
+
+        for (kinds, rejected) in cases {
+            let mut input = Vec::from(v2::PROTOCOL_PREFIX);
+
+            input.extend([0x21, 0x11, 0x00, 0x0C + 3 * kinds.len() as u8]);
+            input.extend([127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]);
+
+            for kind in kinds {
+                input.extend([*kind, 0x00, 0x00]);
+            }
+
+            let header = Header::V2(v2::Header::try_from(input.as_slice()).unwrap());
+
+            assert_eq!(ReservedTypes::Allow.check(&header), Ok(()));
+            assert_eq!(ReservedTypes::Warn.check(&header), Ok(()));
+            assert_eq!(ReservedTypes::Reject.check(&header), rejected);
+        }
+
+        let text = Header::V1(v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap());
+
+        assert_eq!(ReservedTypes::Reject.check(&text), Ok(()));
+    }
+
+    #[test]
+    fn parse_network() {
+        assert_eq!(
+            "192.168.0.0/16".parse(),
+            Network::new(Ipv4Addr::new(192, 168, 0, 0), 16)
+        );
+        assert_eq!(
+            "::1".parse(),
+            Network::new(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1), 128)
+        );
+        assert_eq!(
+            "10.0.0.1".parse(),
+            Network::new(Ipv4Addr::new(10, 0, 0, 1), 32)
+        );
+    }
+
+    #[test]
+    fn parse_invalid_network() {
+        assert_eq!(
+            "10.0.0.0/33".parse::<Network>(),
+            Err(NetworkError::InvalidPrefixLength(None))
+        );
+        assert_eq!(
+            "10.0.0.0/".parse::<Network>(),
+            Err(NetworkError::InvalidPrefixLength(Some(
+                "".parse::<u8>().unwrap_err()
+            )))
+        );
+        assert_eq!(
+            "10.0.0/8".parse::<Network>(),
+            Err(NetworkError::InvalidAddress(
+                "".parse::<IpAddr>().unwrap_err()
+            ))
+        );
+    }
+
+    #[test]
+    fn contains() {
+        let network: Network = "172.16.0.0/12".parse().unwrap();
+
+        assert!(network.contains("172.16.0.1".parse().unwrap()));
+        assert!(network.contains("172.31.255.255".parse().unwrap()));
+        assert!(!network.contains("172.32.0.0".parse().unwrap()));
+        assert!(!network.contains("::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn contains_everything() {
+        let ipv4: Network = "0.0.0.0/0".parse().unwrap();
+        let ipv6: Network = "::/0".parse().unwrap();
+
+        assert!(ipv4.contains("255.255.255.255".parse().unwrap()));
+        assert!(ipv6.contains("ffff::1".parse().unwrap()));
+        assert!(!ipv6.contains("127.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn mapped_network() {
+        let network: Network = "::ffff:10.0.0.0/104".parse().unwrap();
+
+        assert_eq!(network, "10.0.0.0/8".parse().unwrap());
+        assert!(network.contains("10.1.2.3".parse().unwrap()));
+        assert!(network.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!network.contains("11.0.0.1".parse().unwrap()));
+
+        let mapped: Network = "::ffff:0:0/96".parse().unwrap();
+
+        assert!(mapped.contains("192.168.1.1".parse().unwrap()));
+        assert!(mapped.contains("::ffff:192.168.1.1".parse().unwrap()));
+        assert!(!mapped.contains("::1".parse().unwrap()));
+
+        let host = Network::from("::ffff:127.0.0.1".parse::<Ipv6Addr>().unwrap());
+
+        assert_eq!(host, Network::from(Ipv4Addr::LOCALHOST));
+    }
+
+    #[test]
+    fn evaluate_peers() {
+        let peers: TrustedPeers = vec![Network::from(Ipv4Addr::LOCALHOST)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(peers.evaluate(Ipv4Addr::LOCALHOST.into()), Decision::Honor);
+        assert_eq!(
+            peers.evaluate(Ipv4Addr::new(127, 0, 0, 2).into()),
+            Decision::Ignore
+        );
+        assert_eq!(
+            peers
+                .untrusted(Untrusted::Reject)
+                .evaluate(Ipv6Addr::LOCALHOST.into()),
+            Decision::Reject
+        );
+    }
+}
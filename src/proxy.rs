@@ -0,0 +1,216 @@
+//! A trait over the headers of both versions, for code that is generic over the version of the protocol.
+use crate::net::SocketAddr;
+#[cfg(feature = "v1")]
+use crate::v1;
+#[cfg(feature = "v2")]
+use crate::v2;
+use crate::{Header, Version};
+
+/// The connection information common to the headers of both versions.
+///
+/// Addresses are as sent, including for local headers; receivers should use the real connection endpoints
+/// when `is_local` is true, as the specification requires.
+///
+/// ## Examples
+#[cfg_attr(all(feature = "v1", feature = "v2"), doc = "```rust")]
+#[cfg_attr(not(all(feature = "v1", feature = "v2")), doc = "```ignore")]
+/// use ppp::{v1, v2, ProxyHeader};
+///
+/// fn describe<H: ProxyHeader>(header: &H) -> Option<String> {
+///     if header.is_local() {
+///         return None;
+///     }
+///
+///     Some(format!("v{} {:?} ({} bytes)", header.version() as u8, header.source()?, header.encoded_len()))
+/// }
+///
+/// let text = v1::Header::try_from("PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\n").unwrap();
+/// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\xC0\xA8\x01\x01\x00\x50\x01\xBB";
+/// let binary = v2::Header::try_from(&input[..]).unwrap();
+///
+/// assert_eq!(describe(&text), Some("v1 127.0.0.1:80 (41 bytes)".to_string()));
+/// assert_eq!(describe(&binary), Some("v2 127.0.0.1:80 (28 bytes)".to_string()));
+/// ```
+pub trait ProxyHeader {
+    /// The address of the original client, if the header has IP addresses.
+    fn source(&self) -> Option<SocketAddr>;
+
+    /// The address the client originally connected to, if the header has IP addresses.
+    fn destination(&self) -> Option<SocketAddr>;
+
+    /// The `Version` of the PROXY protocol the header was encoded with.
+    fn version(&self) -> Version;
+
+    /// Tests whether the header was sent for a connection of the proxy itself rather than a proxied connection,
+    /// i.e. a binary header with the LOCAL command or a text header with the `UNKNOWN` protocol.
+    fn is_local(&self) -> bool;
+
+    /// The total length of the encoded header in bytes, which is where the payload starts.
+    fn encoded_len(&self) -> usize;
+}
+
+#[cfg(feature = "v1")]
+impl<'a> ProxyHeader for v1::Header<'a> {
+    fn source(&self) -> Option<SocketAddr> {
+        self.source_socket_addr()
+    }
+
+    fn destination(&self) -> Option<SocketAddr> {
+        self.destination_socket_addr()
+    }
+
+    fn version(&self) -> Version {
+        Version::One
+    }
+
+    fn is_local(&self) -> bool {
+        self.addresses == v1::Addresses::Unknown
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.header.len()
+    }
+}
+
+#[cfg(feature = "v2")]
+impl<'a> ProxyHeader for v2::Header<'a> {
+    fn source(&self) -> Option<SocketAddr> {
+        match self.addresses {
+            v2::Addresses::IPv4(addresses) => Some(SocketAddr::from((
+                addresses.source_address,
+                addresses.source_port,
+            ))),
+            v2::Addresses::IPv6(addresses) => Some(SocketAddr::from((
+                addresses.source_address,
+                addresses.source_port,
+            ))),
+            v2::Addresses::Unix(..) | v2::Addresses::Unspecified => None,
+        }
+    }
+
+    fn destination(&self) -> Option<SocketAddr> {
+        match self.addresses {
+            v2::Addresses::IPv4(addresses) => Some(SocketAddr::from((
+                addresses.destination_address,
+                addresses.destination_port,
+            ))),
+            v2::Addresses::IPv6(addresses) => Some(SocketAddr::from((
+                addresses.destination_address,
+                addresses.destination_port,
+            ))),
+            v2::Addresses::Unix(..) | v2::Addresses::Unspecified => None,
+        }
+    }
+
+    fn version(&self) -> Version {
+        Version::Two
+    }
+
+    fn is_local(&self) -> bool {
+        self.command == v2::Command::Local
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(feature = "v2")]
+impl<'a> ProxyHeader for v2::HeaderRef<'a> {
+    fn source(&self) -> Option<SocketAddr> {
+        v2::HeaderRef::source(self)
+    }
+
+    fn destination(&self) -> Option<SocketAddr> {
+        v2::HeaderRef::destination(self)
+    }
+
+    fn version(&self) -> Version {
+        Version::Two
+    }
+
+    fn is_local(&self) -> bool {
+        self.command() == v2::Command::Local
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<'a> ProxyHeader for Header<'a> {
+    fn source(&self) -> Option<SocketAddr> {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(header) => header.source(),
+            #[cfg(feature = "v2")]
+            Header::V2(header) => ProxyHeader::source(header),
+        }
+    }
+
+    fn destination(&self) -> Option<SocketAddr> {
+        match self {
+            #[cfg(feature = "v1")]
+            Header::V1(header) => header.destination(),
+            #[cfg(feature = "v2")]
+            Header::V2(header) => ProxyHeader::destination(header),
+        }
+    }
+
+    fn version(&self) -> Version {
+        Header::version(self)
+    }
+
+    fn is_local(&self) -> bool {
+        self.is_health_check()
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(all(test, feature = "v1", feature = "v2"))]
+mod tests {
+    use super::*;
+
+    fn summary<H: ProxyHeader>(
+        header: &H,
+    ) -> (Option<SocketAddr>, Option<SocketAddr>, bool, usize) {
+        (
+            header.source(),
+            header.destination(),
+            header.is_local(),
+            header.encoded_len(),
+        )
+    }
+
+    #[test]
+    fn generic_over_versions() {
+        let text = v1::Header::try_from("PROXY TCP6 2001:db8::1 2001:db8::2 80 443\r\n").unwrap();
+        let source = "[2001:db8::1]:80".parse().ok();
+        let destination = "[2001:db8::2]:443".parse().ok();
+
+        assert_eq!(summary(&text), (source, destination, false, 43));
+        assert_eq!(
+            summary(&Header::from(text)),
+            (source, destination, false, 43)
+        );
+
+        let unknown = v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap();
+
+        assert_eq!(summary(&unknown), (None, None, true, 15));
+
+        let input =
+            b"\r\n\r\n\0\r\nQUIT\n\x20\x11\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB";
+        let binary = v2::Header::try_from(&input[..]).unwrap();
+        let view = v2::HeaderRef::try_from(&input[..]).unwrap();
+        let source = "127.0.0.1:80".parse().ok();
+        let destination = "127.0.0.2:443".parse().ok();
+
+        assert_eq!(summary(&binary), (source, destination, true, 28));
+        assert_eq!(summary(&view), (source, destination, true, 28));
+        assert_eq!(ProxyHeader::version(&view), Version::Two);
+        assert_eq!(ProxyHeader::version(&Header::from(binary)), Version::Two);
+    }
+}
@@ -0,0 +1,173 @@
+//! PyO3 bindings for parsing and encoding headers from Python,
+//! for tooling such as log processors and traffic replayers.
+//!
+//! Addresses are exchanged as strings in `ip:port` form (e.g. `192.168.1.1:443` or `[::1]:443`).
+//! Invalid headers and addresses raise a `ValueError` with the error's code and message.
+//!
+//! ## Examples
+//! ```python
+//! import ppp
+//!
+//! encoded = ppp.encode_v2("127.0.0.1:80", "192.168.1.1:443")
+//! header = ppp.parse(encoded + b"GET / HTTP/1.1\r\n")
+//!
+//! assert header.version == 2
+//! assert header.source == "127.0.0.1:80"
+//! assert header.length == len(encoded)
+//! ```
+use std::prelude::v1::*;
+
+use crate::bindings;
+use crate::v2::{Command, Protocol};
+use crate::ProxyInfo;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// A parsed header of either version.
+#[pyclass(name = "Header", module = "ppp", frozen)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PyHeader {
+    info: ProxyInfo,
+    length: usize,
+}
+
+#[pymethods]
+impl PyHeader {
+    /// The version of the PROXY protocol the header was encoded with (1 or 2).
+    #[getter]
+    fn version(&self) -> u8 {
+        self.info.version as u8
+    }
+
+    /// Whether the header's command is `PROXY`, rather than `LOCAL`.
+    #[getter]
+    fn proxy(&self) -> bool {
+        self.info.command == Command::Proxy
+    }
+
+    /// Whether the proxied connection is a datagram (i.e. UDP) one.
+    #[getter]
+    fn datagram(&self) -> bool {
+        self.info.protocol == Protocol::Datagram
+    }
+
+    /// The address of the original client, if the header has IP addresses.
+    #[getter]
+    fn source(&self) -> Option<String> {
+        self.info.source.map(|address| address.to_string())
+    }
+
+    /// The address the client originally connected to, if the header has IP addresses.
+    #[getter]
+    fn destination(&self) -> Option<String> {
+        self.info.destination.map(|address| address.to_string())
+    }
+
+    /// The authority (i.e. SNI host name) TLV of a binary header.
+    #[getter]
+    fn authority(&self) -> Option<&str> {
+        self.info.authority.as_deref()
+    }
+
+    /// The unique ID TLV of a binary header.
+    #[getter]
+    fn unique_id<'py>(&self, py: Python<'py>) -> Option<&'py PyBytes> {
+        self.info
+            .unique_id
+            .as_deref()
+            .map(|unique_id| PyBytes::new(py, unique_id))
+    }
+
+    /// The length in bytes of the header, after which the payload starts.
+    #[getter]
+    fn length(&self) -> usize {
+        self.length
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Header(version={}, source={:?}, destination={:?}, length={})",
+            self.version(),
+            self.source(),
+            self.destination(),
+            self.length
+        )
+    }
+}
+
+/// Parses a header of either version from the start of the input.
+/// Returns `None` if more bytes are needed.
+#[pyfunction]
+fn parse(input: &[u8]) -> PyResult<Option<PyHeader>> {
+    match bindings::parse(input) {
+        Ok(header) => Ok(header.map(|(info, length)| PyHeader { info, length })),
+        Err(error) => Err(PyValueError::new_err(error)),
+    }
+}
+
+/// Encodes a text header between the given addresses, or an `UNKNOWN` header when both are missing.
+#[pyfunction]
+#[pyo3(signature = (source = None, destination = None))]
+fn encode_v1<'py>(
+    py: Python<'py>,
+    source: Option<&str>,
+    destination: Option<&str>,
+) -> PyResult<&'py PyBytes> {
+    bindings::encode_v1(source, destination)
+        .map(|header| PyBytes::new(py, &header))
+        .map_err(PyValueError::new_err)
+}
+
+/// Encodes a binary header between the given addresses followed by the given TLVs,
+/// or a `LOCAL` header without addresses when both are missing.
+#[pyfunction]
+#[pyo3(signature = (source = None, destination = None, datagram = false, tlvs = None))]
+fn encode_v2<'py>(
+    py: Python<'py>,
+    source: Option<&str>,
+    destination: Option<&str>,
+    datagram: bool,
+    tlvs: Option<&[u8]>,
+) -> PyResult<&'py PyBytes> {
+    bindings::encode_v2(source, destination, datagram, tlvs.unwrap_or_default())
+        .map(|header| PyBytes::new(py, &header))
+        .map_err(PyValueError::new_err)
+}
+
+/// The `ppp` Python module.
+#[pymodule]
+fn ppp(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyHeader>()?;
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(encode_v1, module)?)?;
+    module.add_function(wrap_pyfunction!(encode_v2, module)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mismatched_addresses() {
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let family =
+                encode_v2(py, Some("127.0.0.1:80"), Some("[::1]:443"), false, None).unwrap_err();
+            let pairs = encode_v1(py, None, Some("[::1]:443")).unwrap_err();
+
+            assert!(family.is_instance_of::<PyValueError>(py));
+            assert_eq!(
+                family.value(py).to_string(),
+                "Addresses must be of the same family."
+            );
+            assert_eq!(
+                pairs.value(py).to_string(),
+                "Addresses must be given in pairs."
+            );
+        });
+    }
+}
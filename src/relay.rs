@@ -0,0 +1,494 @@
+//! A minimal TCP relay that forwards each connection to an upstream server behind a PROXY protocol header.
+//!
+//! The relay reads the header of an accepted connection, or synthesizes one from the connection's own addresses
+//! when there is none, connects to the upstream server and sends it the header followed by the connection's payload.
+//! Bytes are then copied in both directions until both sides have closed the connection.
+//! It is a reference for building real proxies and a test bed for the rest of the crate, rather than a production proxy:
+//! each connection is handled on a thread of its own.
+//! With the `splice` feature on Linux, the payload is moved between the sockets with `splice(2)` (see `relay_zero_copy`).
+//!
+//! ## Examples
+//! ```rust,no_run
+//! use ppp::io::Acceptor;
+//! use ppp::policy::Policy;
+//! use ppp::relay::Relay;
+//! use std::net::TcpListener;
+//!
+//! let listener = TcpListener::bind("0.0.0.0:8080").unwrap();
+//! let relay = Relay::new("127.0.0.1:9090".parse().unwrap())
+//!     .acceptor(Acceptor::new(Policy::Optional))
+//!     .version(ppp::Version::Two);
+//!
+//! relay.serve(listener).unwrap();
+//! ```
+use std::prelude::v1::*;
+
+use crate::io::{Acceptor, Error};
+use crate::policy::Policy;
+use crate::v2::{self, Passthrough};
+use crate::{translate, v1, Header, Version};
+use std::io::{self, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+#[cfg(all(target_os = "linux", feature = "splice"))]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(all(target_os = "linux", feature = "splice"))]
+use std::ptr;
+use std::thread;
+
+/// The largest number of bytes moved by a single `splice(2)` call, which is the default capacity of a pipe.
+#[cfg(all(target_os = "linux", feature = "splice"))]
+const SPLICE_LENGTH: usize = 65536;
+
+/// Forwards connections to an upstream server, sending it a PROXY protocol header of the configured version.
+///
+/// Headers of the configured version are re-encoded unchanged, and headers of the other version are translated.
+/// Connections without a header (e.g. because the `Acceptor`'s `Policy` is `Optional` or `Ignore`)
+/// get a header with their own source and destination addresses.
+#[derive(Clone, Debug)]
+pub struct Relay {
+    upstream: SocketAddr,
+    acceptor: Acceptor,
+    version: Version,
+    passthrough: Option<Passthrough<'static>>,
+}
+
+impl Relay {
+    /// Creates a `Relay` to the given upstream server that accepts connections with or without a header,
+    /// and sends version 2 headers.
+    pub fn new(upstream: SocketAddr) -> Self {
+        Relay {
+            upstream,
+            acceptor: Acceptor::new(Policy::Optional),
+            version: Version::Two,
+            passthrough: None,
+        }
+    }
+
+    /// Reads the headers of accepted connections with the given `Acceptor`.
+    pub fn acceptor(mut self, acceptor: Acceptor) -> Self {
+        self.acceptor = acceptor;
+        self
+    }
+
+    /// Sets the version of the headers sent upstream.
+    pub fn version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Selects the TLVs of the version 2 headers sent upstream. By default, every TLV is passed through.
+    pub fn passthrough(mut self, passthrough: Passthrough<'static>) -> Self {
+        self.passthrough = Some(passthrough);
+        self
+    }
+
+    /// Relays every connection accepted by the listener on a new thread.
+    /// Returns an error when a connection cannot be accepted; an error in relaying a connection only closes that connection.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        for client in listener.incoming() {
+            let client = client?;
+            let relay = self.clone();
+
+            thread::spawn(move || relay.handle(client));
+        }
+
+        Ok(())
+    }
+
+    /// Relays a single connection on the current thread, returning once both sides have closed it.
+    pub fn handle(&self, client: TcpStream) -> Result<(), Error> {
+        let source = client.peer_addr()?;
+        let destination = client.local_addr()?;
+        let (client, header) = self.acceptor.accept(client, source.ip())?;
+        let header = self.encode(header.as_ref(), source, destination)?;
+        let (client, buffered) = client.into_parts();
+        let mut upstream = TcpStream::connect(self.upstream)?;
+
+        upstream.write_all(&header)?;
+        upstream.write_all(&buffered)?;
+
+        #[cfg(all(target_os = "linux", feature = "splice"))]
+        relay_zero_copy(client, upstream)?;
+        #[cfg(not(all(target_os = "linux", feature = "splice")))]
+        splice(client, upstream)?;
+
+        Ok(())
+    }
+
+    /// Encodes the header to send upstream for a connection with the given header, if any, and addresses.
+    fn encode(
+        &self,
+        header: Option<&Header<'_>>,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) -> io::Result<Vec<u8>> {
+        if self.version == Version::One {
+            let header = match header {
+                Some(Header::V1(header)) => header.to_string(),
+                Some(Header::V2(header)) => translate::downgrade(header).header.to_string(),
+                None => v1::Addresses::from((source, destination)).to_string(),
+            };
+
+            return Ok(header.into_bytes());
+        }
+
+        let header = match header {
+            Some(Header::V2(header)) => v2::Builder::from_header(header)?.build()?,
+            Some(Header::V1(header)) => {
+                let (command, protocol, addresses) = match header.addresses {
+                    v1::Addresses::Tcp4(addresses) => {
+                        (v2::Command::Proxy, v2::Protocol::Stream, addresses.into())
+                    }
+                    v1::Addresses::Tcp6(addresses) => {
+                        (v2::Command::Proxy, v2::Protocol::Stream, addresses.into())
+                    }
+                    v1::Addresses::Unknown => (
+                        v2::Command::Local,
+                        v2::Protocol::Unspecified,
+                        v2::Addresses::Unspecified,
+                    ),
+                };
+
+                v2::Builder::with_addresses(v2::Version::Two | command, protocol, addresses)
+                    .build()?
+            }
+            None => v2::Builder::with_addresses(
+                v2::Version::Two | v2::Command::Proxy,
+                v2::Protocol::Stream,
+                (source, destination),
+            )
+            .build()?,
+        };
+
+        match self.passthrough.as_ref() {
+            Some(passthrough) => {
+                let header = v2::Header::try_from(header.as_slice())
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+                Ok(passthrough.apply(&header)?.as_bytes().to_vec())
+            }
+            None => Ok(header),
+        }
+    }
+}
+
+/// Copies bytes in both directions, closing each direction of the other side once one side has closed it.
+#[cfg(not(all(target_os = "linux", feature = "splice")))]
+fn splice(mut client: TcpStream, mut upstream: TcpStream) -> io::Result<()> {
+    let mut client_reader = client.try_clone()?;
+    let mut upstream_writer = upstream.try_clone()?;
+    let forward = thread::spawn(move || {
+        let copied = io::copy(&mut client_reader, &mut upstream_writer);
+        let _ = upstream_writer.shutdown(Shutdown::Write);
+
+        copied
+    });
+
+    let backward = io::copy(&mut upstream, &mut client);
+    let _ = client.shutdown(Shutdown::Write);
+    let forward = forward
+        .join()
+        .unwrap_or_else(|_| Err(io::ErrorKind::Other.into()));
+
+    backward.and(forward).map(|_| ())
+}
+
+/// Copies bytes in both directions until both sides have closed the connection, like the relay does once it has sent the header,
+/// but with `splice(2)` so the kernel moves the bytes between the sockets without copying them through userspace.
+/// Each direction of the other side is closed once one side has closed it.
+/// Returns the number of bytes copied from `a` to `b` and from `b` to `a`.
+///
+/// Any bytes read past the header (e.g. those returned by `io::Stream::into_parts`) must be written to the other side first.
+///
+/// ## Examples
+/// ```rust,no_run
+/// use ppp::io::Acceptor;
+/// use ppp::policy::Policy;
+/// use ppp::relay::relay_zero_copy;
+/// use std::io::Write;
+/// use std::net::{TcpListener, TcpStream};
+///
+/// let listener = TcpListener::bind("0.0.0.0:8080").unwrap();
+/// let (client, address) = listener.accept().unwrap();
+/// let (client, header) = Acceptor::new(Policy::Require).accept(client, address.ip()).unwrap();
+/// let (client, buffered) = client.into_parts();
+/// let mut upstream = TcpStream::connect("127.0.0.1:9090").unwrap();
+///
+/// upstream.write_all(&buffered).unwrap();
+///
+/// let (sent, received) = relay_zero_copy(client, upstream).unwrap();
+/// ```
+#[cfg(all(target_os = "linux", feature = "splice"))]
+pub fn relay_zero_copy(a: TcpStream, b: TcpStream) -> io::Result<(u64, u64)> {
+    let a_reader = a.try_clone()?;
+    let b_writer = b.try_clone()?;
+    let forward = thread::spawn(move || {
+        let moved = splice_all(&a_reader, &b_writer);
+        let _ = b_writer.shutdown(Shutdown::Write);
+
+        moved
+    });
+
+    let backward = splice_all(&b, &a);
+    let _ = a.shutdown(Shutdown::Write);
+    let forward = forward
+        .join()
+        .unwrap_or_else(|_| Err(io::ErrorKind::Other.into()));
+
+    Ok((forward?, backward?))
+}
+
+/// A pipe, through which `splice(2)` moves bytes from one socket to another.
+#[cfg(all(target_os = "linux", feature = "splice"))]
+struct Pipe {
+    read: RawFd,
+    write: RawFd,
+}
+
+#[cfg(all(target_os = "linux", feature = "splice"))]
+impl Pipe {
+    fn new() -> io::Result<Self> {
+        let mut descriptors = [0; 2];
+
+        // The array has room for both ends of the pipe.
+        if unsafe { libc::pipe2(descriptors.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Pipe {
+            read: descriptors[0],
+            write: descriptors[1],
+        })
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "splice"))]
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // Both ends of the pipe are owned by this `Pipe`, and closed only here.
+        unsafe {
+            libc::close(self.read);
+            libc::close(self.write);
+        }
+    }
+}
+
+/// Moves up to `length` bytes with a single `splice(2)` call, retrying when interrupted.
+/// Returns 0 once the input has been closed.
+#[cfg(all(target_os = "linux", feature = "splice"))]
+fn splice_once(input: RawFd, output: RawFd, length: usize) -> io::Result<usize> {
+    loop {
+        // Sockets and pipes have no offsets, so both are null.
+        let moved = unsafe {
+            libc::splice(
+                input,
+                ptr::null_mut(),
+                output,
+                ptr::null_mut(),
+                length,
+                libc::SPLICE_F_MOVE | libc::SPLICE_F_MORE,
+            )
+        };
+
+        if moved >= 0 {
+            return Ok(moved as usize);
+        }
+
+        let error = io::Error::last_os_error();
+
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+/// Moves bytes from one socket to the other through a pipe until the first one is closed.
+/// Returns the number of bytes moved.
+#[cfg(all(target_os = "linux", feature = "splice"))]
+fn splice_all(input: &TcpStream, output: &TcpStream) -> io::Result<u64> {
+    let pipe = Pipe::new()?;
+    let mut moved = 0;
+
+    loop {
+        let mut pending = splice_once(input.as_raw_fd(), pipe.write, SPLICE_LENGTH)?;
+
+        if pending == 0 {
+            return Ok(moved);
+        }
+
+        moved += pending as u64;
+
+        while pending > 0 {
+            match splice_once(pipe.read, output.as_raw_fd(), pending)? {
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                written => pending -= written,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::read_header;
+    use crate::v2::Type;
+    use std::io::Read;
+
+    fn addresses() -> (SocketAddr, SocketAddr) {
+        (
+            "192.0.2.1:51234".parse().unwrap(),
+            "10.0.0.1:443".parse().unwrap(),
+        )
+    }
+
+    #[test]
+    fn encode_v1() {
+        let relay = Relay::new(addresses().1).version(Version::One);
+        let (source, destination) = addresses();
+        let text = v1::Header::try_from("PROXY TCP6 0::1 ::2 80 443\r\n").unwrap();
+        let binary = v2::Builder::with_addresses(
+            v2::Version::Two | v2::Command::Proxy,
+            v2::Protocol::Stream,
+            addresses(),
+        )
+        .write_tlv(Type::Authority, b"example.com")
+        .unwrap()
+        .build()
+        .unwrap();
+        let binary = v2::Header::try_from(binary.as_slice()).unwrap();
+
+        assert_eq!(
+            relay
+                .encode(Some(&Header::V1(text)), source, destination)
+                .unwrap(),
+            b"PROXY TCP6 0::1 ::2 80 443\r\n"
+        );
+        assert_eq!(
+            relay
+                .encode(Some(&Header::V2(binary)), source, destination)
+                .unwrap(),
+            b"PROXY TCP4 192.0.2.1 10.0.0.1 51234 443\r\n"
+        );
+        assert_eq!(
+            relay.encode(None, source, destination).unwrap(),
+            b"PROXY TCP4 192.0.2.1 10.0.0.1 51234 443\r\n"
+        );
+    }
+
+    #[test]
+    fn encode_v2() {
+        let relay = Relay::new(addresses().1);
+        let (source, destination) = addresses();
+        let binary = v2::Builder::with_addresses(
+            v2::Version::Two | v2::Command::Proxy,
+            v2::Protocol::Stream,
+            addresses(),
+        )
+        .write_tlv(Type::Authority, b"example.com")
+        .unwrap()
+        .build()
+        .unwrap();
+        let plain = v2::Builder::with_addresses(
+            v2::Version::Two | v2::Command::Proxy,
+            v2::Protocol::Stream,
+            addresses(),
+        )
+        .build()
+        .unwrap();
+        let header = Header::V2(v2::Header::try_from(binary.as_slice()).unwrap());
+        let unknown = Header::V1(v1::Header::try_from("PROXY UNKNOWN\r\n").unwrap());
+
+        assert_eq!(
+            relay.encode(Some(&header), source, destination).unwrap(),
+            binary
+        );
+        assert_eq!(relay.encode(None, source, destination).unwrap(), plain);
+        assert_eq!(
+            relay.encode(Some(&unknown), source, destination).unwrap(),
+            b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00"
+        );
+
+        let stripped = relay
+            .passthrough(Passthrough::strip_all())
+            .encode(Some(&header), source, destination)
+            .unwrap();
+
+        assert_eq!(stripped, plain);
+    }
+
+    #[test]
+    fn relay_connection() {
+        let backend = TcpListener::bind("127.0.0.1:0").unwrap();
+        let frontend = TcpListener::bind("127.0.0.1:0").unwrap();
+        let relay =
+            Relay::new(backend.local_addr().unwrap()).acceptor(Acceptor::new(Policy::Require));
+        let address = frontend.local_addr().unwrap();
+        let relayed = thread::spawn(move || {
+            let (client, _) = frontend.accept().unwrap();
+
+            relay.handle(client)
+        });
+
+        let mut client = TcpStream::connect(address).unwrap();
+
+        client
+            .write_all(b"PROXY TCP4 192.0.2.1 10.0.0.1 51234 443\r\nping")
+            .unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (upstream, _) = backend.accept().unwrap();
+        let (mut upstream, header) = read_header(upstream, Policy::Require).unwrap();
+        let mut payload = Vec::new();
+
+        upstream.read_to_end(&mut payload).unwrap();
+        upstream.write_all(b"pong").unwrap();
+        drop(upstream);
+
+        let mut response = Vec::new();
+
+        client.read_to_end(&mut response).unwrap();
+
+        let info = crate::ProxyInfo::from(&header.unwrap());
+
+        assert_eq!(info.version, Version::Two);
+        assert_eq!(info.source, Some(addresses().0));
+        assert_eq!(info.destination, Some(addresses().1));
+        assert_eq!(payload, b"ping");
+        assert_eq!(response, b"pong");
+        assert!(relayed.join().unwrap().is_ok());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "splice"))]
+    #[test]
+    fn zero_copy() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let connect = || {
+            let client = TcpStream::connect(address).unwrap();
+            let (server, _) = listener.accept().unwrap();
+
+            (client, server)
+        };
+        let (mut client, a) = connect();
+        let (b, mut upstream) = connect();
+        let relayed = thread::spawn(move || relay_zero_copy(a, b));
+        let request = vec![0x5A; 3 * SPLICE_LENGTH + 1];
+
+        client.write_all(&request).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let mut payload = Vec::new();
+
+        upstream.read_to_end(&mut payload).unwrap();
+        upstream.write_all(b"pong").unwrap();
+        drop(upstream);
+
+        let mut response = Vec::new();
+
+        client.read_to_end(&mut response).unwrap();
+
+        assert_eq!(payload, request);
+        assert_eq!(response, b"pong");
+        assert_eq!(relayed.join().unwrap().unwrap(), (request.len() as u64, 4));
+    }
+}
@@ -0,0 +1,109 @@
+//! Compile-time assertions that the public types are `Send` and `Sync`,
+//! so parsed headers (owned with `to_owned` or `into_owned`) can be moved into spawned tasks and worker pools.
+//! A field that is neither (e.g. an `Rc` or a `Cell`) fails to compile here, rather than in the crates that depend on this one.
+
+/// Asserts at compile time that `T` can be sent to and shared between threads.
+// Calls from the anonymous constants below do not count as uses for the lint.
+#[allow(dead_code)]
+fn assert_send_sync<T: Send + Sync>() {}
+
+const _: fn() = || {
+    assert_send_sync::<crate::ErrorCode>();
+    assert_send_sync::<crate::Version>();
+    assert_send_sync::<crate::ip::IPv4>();
+    assert_send_sync::<crate::ip::IPv6>();
+};
+
+#[cfg(feature = "v1")]
+const _: fn() = || {
+    use crate::v1;
+
+    assert_send_sync::<v1::Header<'static>>();
+    assert_send_sync::<v1::Addresses>();
+    assert_send_sync::<v1::ParseError>();
+    assert_send_sync::<v1::BinaryParseError>();
+};
+
+#[cfg(feature = "v2")]
+const _: fn() = || {
+    use crate::v2;
+
+    assert_send_sync::<v2::Header<'static>>();
+    assert_send_sync::<v2::HeaderRef<'static>>();
+    assert_send_sync::<v2::Addresses>();
+    assert_send_sync::<v2::UnixAddress<'static>>();
+    assert_send_sync::<v2::TypeLengthValue<'static>>();
+    assert_send_sync::<v2::TypeLengthValues<'static>>();
+    assert_send_sync::<v2::TlvList<'static>>();
+    assert_send_sync::<v2::Lenient<'static>>();
+    assert_send_sync::<v2::Hop<'static>>();
+    assert_send_sync::<v2::FixedHeader<16>>();
+    assert_send_sync::<v2::FixedBuilder<16>>();
+    assert_send_sync::<v2::CapacityError>();
+    assert_send_sync::<v2::EditError>();
+    assert_send_sync::<v2::ParseError>();
+};
+
+#[cfg(all(any(feature = "std", feature = "tstd"), feature = "v2"))]
+const _: fn() = || {
+    use crate::v2;
+
+    assert_send_sync::<v2::Builder>();
+    assert_send_sync::<v2::Builder<v2::IPv4>>();
+    assert_send_sync::<v2::TlvOrder>();
+    assert_send_sync::<v2::Writer>();
+    assert_send_sync::<v2::Passthrough<'static>>();
+    assert_send_sync::<v2::TlvAction<'static>>();
+    assert_send_sync::<v2::SslInfo<'static>>();
+    assert_send_sync::<v2::LargeTlv<'static>>();
+};
+
+#[cfg(feature = "ring")]
+const _: fn() = || {
+    use crate::v2::signature::ring;
+
+    assert_send_sync::<ring::Ed25519>();
+    assert_send_sync::<ring::HmacSha256>();
+};
+
+#[cfg(feature = "rustcrypto")]
+const _: fn() = || {
+    use crate::v2::signature::rustcrypto;
+
+    assert_send_sync::<rustcrypto::Ed25519>();
+    assert_send_sync::<rustcrypto::HmacSha256>();
+};
+
+#[cfg(any(feature = "std", feature = "tstd"))]
+const _: fn() = || {
+    assert_send_sync::<crate::SharedHeader>();
+};
+
+#[cfg(all(feature = "v1", feature = "v2"))]
+const _: fn() = || {
+    use crate::policy;
+
+    assert_send_sync::<crate::Header<'static>>();
+    assert_send_sync::<crate::HeaderResult<'static>>();
+    assert_send_sync::<crate::HeaderBuf>();
+    assert_send_sync::<crate::ParseOutcome<'static>>();
+    assert_send_sync::<crate::InvalidHeader>();
+    assert_send_sync::<crate::Skip<'static>>();
+    assert_send_sync::<crate::StripError>();
+    assert_send_sync::<crate::ProxyInfo>();
+    assert_send_sync::<crate::Sniff>();
+    assert_send_sync::<crate::extensions::Extensions>();
+    assert_send_sync::<policy::Policy>();
+    assert_send_sync::<policy::TrustedPeers>();
+};
+
+#[cfg(all(any(feature = "std", feature = "tstd"), feature = "v1", feature = "v2"))]
+const _: fn() = || {
+    use crate::io;
+
+    assert_send_sync::<io::Acceptor>();
+    assert_send_sync::<io::Decoder>();
+    assert_send_sync::<io::HeaderReader>();
+    assert_send_sync::<io::NonBlockingReader>();
+    assert_send_sync::<io::Error>();
+};
@@ -0,0 +1,118 @@
+//! A parsed header that is cheap to clone, for handing the same connection information to several consumers.
+use crate::net::SocketAddr;
+use crate::{Header, ProxyHeader, Version};
+use core::ops::Deref;
+use std::sync::Arc;
+
+/// A header of either version that owns its bytes behind an `Arc`, so cloning it only bumps a reference count.
+/// Logging, metrics and routing can each hold a clone of the header of a connection, instead of a copy of its bytes.
+///
+/// Dereferences to the `Header`, so TLVs and addresses are read from the shared bytes as usual.
+///
+/// ## Examples
+#[cfg_attr(feature = "v2", doc = "```rust")]
+#[cfg_attr(not(feature = "v2"), doc = "```ignore")]
+/// use ppp::{v2, Header, SharedHeader};
+///
+/// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\xC0\xA8\x01\x01\x00\x50\x01\xBB";
+/// let header = SharedHeader::from(&Header::V2(v2::Header::try_from(&input[..]).unwrap()));
+/// let logging = header.clone();
+///
+/// std::thread::spawn(move || println!("{:?}", logging.as_bytes())).join().unwrap();
+///
+/// assert!(SharedHeader::ptr_eq(&header, &header.clone()));
+/// assert_eq!(header.len(), input.len());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SharedHeader(Arc<Header<'static>>);
+
+impl SharedHeader {
+    /// Tests whether both `SharedHeader`s are clones of the same header, rather than equal copies.
+    pub fn ptr_eq(this: &SharedHeader, other: &SharedHeader) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    /// The number of clones of this `SharedHeader`, including this one.
+    pub fn clones(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+}
+
+/// Shares an owned header without copying its bytes.
+impl From<Header<'static>> for SharedHeader {
+    fn from(header: Header<'static>) -> Self {
+        SharedHeader(Arc::new(header))
+    }
+}
+
+/// Shares a copy of a header, which may borrow its bytes.
+impl<'a> From<&Header<'a>> for SharedHeader {
+    fn from(header: &Header<'a>) -> Self {
+        SharedHeader(Arc::new(header.to_owned()))
+    }
+}
+
+impl Deref for SharedHeader {
+    type Target = Header<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<Header<'static>> for SharedHeader {
+    fn as_ref(&self) -> &Header<'static> {
+        &self.0
+    }
+}
+
+impl ProxyHeader for SharedHeader {
+    fn source(&self) -> Option<SocketAddr> {
+        ProxyHeader::source(&*self.0)
+    }
+
+    fn destination(&self) -> Option<SocketAddr> {
+        ProxyHeader::destination(&*self.0)
+    }
+
+    fn version(&self) -> Version {
+        ProxyHeader::version(&*self.0)
+    }
+
+    fn is_local(&self) -> bool {
+        ProxyHeader::is_local(&*self.0)
+    }
+
+    fn encoded_len(&self) -> usize {
+        ProxyHeader::encoded_len(&*self.0)
+    }
+}
+
+#[cfg(all(test, feature = "v1", feature = "v2"))]
+mod tests {
+    use super::*;
+    use crate::v1;
+
+    #[test]
+    fn share() {
+        let input = "PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n";
+        let header = Header::V1(v1::Header::try_from(input).unwrap());
+        let copied = SharedHeader::from(&header);
+        let shared = SharedHeader::from(header.to_owned());
+        let clone = shared.clone();
+
+        assert_eq!(copied, shared);
+        assert!(!SharedHeader::ptr_eq(&copied, &shared));
+        assert!(SharedHeader::ptr_eq(&shared, &clone));
+        assert_eq!(shared.clones(), 2);
+        assert_eq!(clone.as_bytes(), input.as_bytes());
+        assert_eq!(
+            ProxyHeader::source(&clone),
+            Some("127.0.0.1:80".parse().unwrap())
+        );
+
+        drop(clone);
+
+        assert_eq!(shared.clones(), 1);
+    }
+}
@@ -0,0 +1,146 @@
+//! Parsing that recovers from connections without a usable header, reporting where the payload starts instead.
+use crate::incremental::{self, Invalid};
+use crate::outcome::InvalidHeader;
+use crate::policy::Policy;
+use crate::{v1, Header};
+
+/// How `parse_or_skip` handles a text header that is malformed but terminated by a CRLF.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Salvage {
+    /// A malformed text header is an error, like any other invalid header.
+    Reject,
+    /// A malformed text header is skipped, and the payload starts after its CRLF.
+    SkipLine,
+}
+
+/// The outcome of `parse_or_skip`.
+// Boxing the header would add an allocation to parsing, which otherwise never allocates.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq)]
+pub enum Skip<'a> {
+    /// The input starts with a valid header.
+    Header(Header<'a>),
+    /// The input does not start with the prefix of either version, so it is all payload.
+    NotProxy { payload_starts_at: usize },
+    /// The input starts with a malformed text header, which was skipped with `Salvage::SkipLine`.
+    Malformed {
+        error: v1::BinaryParseError,
+        payload_starts_at: usize,
+    },
+    /// More bytes are needed to tell.
+    Incomplete,
+}
+
+impl<'a> Skip<'a> {
+    /// The offset of the payload in the input, or `None` if more bytes are needed.
+    pub fn payload_starts_at(&self) -> Option<usize> {
+        match self {
+            Skip::Header(header) => Some(header.len()),
+            Skip::NotProxy { payload_starts_at }
+            | Skip::Malformed {
+                payload_starts_at, ..
+            } => Some(*payload_starts_at),
+            Skip::Incomplete => None,
+        }
+    }
+
+    /// Converts this `Skip` into one that owns its header, so it can outlive the input.
+    pub fn into_owned(self) -> Skip<'static> {
+        match self {
+            Skip::Header(header) => Skip::Header(header.to_owned()),
+            Skip::NotProxy { payload_starts_at } => Skip::NotProxy { payload_starts_at },
+            Skip::Malformed {
+                error,
+                payload_starts_at,
+            } => Skip::Malformed {
+                error,
+                payload_starts_at,
+            },
+            Skip::Incomplete => Skip::Incomplete,
+        }
+    }
+}
+
+/// Parses a header of either version from the start of the input, recovering where the connection can still be served.
+///
+/// Input that clearly is not a PROXY protocol header (i.e. does not start with the prefix of either version) is `Skip::NotProxy`
+/// rather than an error. With `Salvage::SkipLine`, a text header that is malformed but terminated by a CRLF within
+/// the maximum length of a text header is `Skip::Malformed`, with the payload starting just past the CRLF.
+/// Invalid binary headers are always errors, as their length cannot be trusted to find the payload.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::{parse_or_skip, Salvage, Skip};
+///
+/// assert_eq!(
+///     parse_or_skip(b"GET / HTTP/1.1\r\n", Salvage::Reject),
+///     Ok(Skip::NotProxy { payload_starts_at: 0 })
+/// );
+///
+/// let input = b"PROXY TCP4 127.0.0.1 10.0.0.1 80\r\nHello";
+///
+/// assert!(parse_or_skip(input, Salvage::Reject).is_err());
+/// assert_eq!(
+///     parse_or_skip(input, Salvage::SkipLine).unwrap().payload_starts_at(),
+///     Some(34)
+/// );
+/// ```
+pub fn parse_or_skip(input: &[u8], salvage: Salvage) -> Result<Skip<'_>, InvalidHeader> {
+    match incremental::parse(input, Policy::Optional) {
+        None => Ok(Skip::Incomplete),
+        Some(Ok(Some(header))) => Ok(Skip::Header(header)),
+        Some(Ok(None)) => Ok(Skip::NotProxy {
+            payload_starts_at: 0,
+        }),
+        Some(Err(Invalid::V1(error))) => {
+            let line = &input[..input.len().min(v1::MAX_LENGTH)];
+
+            match line.windows(2).position(|pair| pair == b"\r\n") {
+                Some(offset) if salvage == Salvage::SkipLine => Ok(Skip::Malformed {
+                    error,
+                    payload_starts_at: offset + 2,
+                }),
+                _ => Err(error.into()),
+            }
+        }
+        Some(Err(Invalid::V2(error))) => Err(error.into()),
+        // Only `Policy::Require` rejects inputs without a header.
+        Some(Err(Invalid::MissingHeader)) => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2;
+
+    #[test]
+    fn skip() {
+        let header = b"PROXY UNKNOWN\r\nHello";
+
+        assert_eq!(
+            parse_or_skip(header, Salvage::Reject)
+                .unwrap()
+                .payload_starts_at(),
+            Some(15)
+        );
+        assert_eq!(
+            parse_or_skip(b"PROXY TCP4", Salvage::SkipLine),
+            Ok(Skip::Incomplete)
+        );
+        assert_eq!(parse_or_skip(b"", Salvage::SkipLine), Ok(Skip::Incomplete));
+        assert!(matches!(
+            parse_or_skip(b"\r\n\r\n\0\r\nQUIT\n\x13\x00\x00\x00", Salvage::SkipLine),
+            Err(InvalidHeader::V2(v2::ParseError::Version(..)))
+        ));
+
+        let mut long = Vec::from(&b"PROXY UNKNOWN "[..]);
+        long.resize(200, b'a');
+        long.extend_from_slice(b"\r\n");
+
+        assert!(matches!(
+            parse_or_skip(&long, Salvage::SkipLine),
+            Err(InvalidHeader::V1(..))
+        ));
+    }
+}
@@ -0,0 +1,241 @@
+//! Cheap classification of the first bytes of a connection.
+use crate::v2;
+
+/// The number of bytes needed to classify any input, which is the length of the longest prefix.
+pub const SNIFF_LENGTH: usize = longest(&HTTP_PREFIXES, v2::PROTOCOL_PREFIX.len());
+
+/// The prefix of a text header, including the separator after `PROXY`.
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+/// The prefixes of HTTP/1.x request lines (a method and a separator),
+/// and of the HTTP/2 connection preface.
+const HTTP_PREFIXES: [&[u8]; 10] = [
+    b"GET ",
+    b"HEAD ",
+    b"POST ",
+    b"PUT ",
+    b"DELETE ",
+    b"CONNECT ",
+    b"OPTIONS ",
+    b"TRACE ",
+    b"PATCH ",
+    b"PRI * HTTP/2.0",
+];
+
+/// The length of the longest of the prefixes, or of `length` if it is longer.
+const fn longest(prefixes: &[&[u8]], mut length: usize) -> usize {
+    let mut index = 0;
+
+    while index < prefixes.len() {
+        if prefixes[index].len() > length {
+            length = prefixes[index].len();
+        }
+
+        index += 1;
+    }
+
+    length
+}
+
+/// The content type of a TLS handshake record.
+const TLS_HANDSHAKE: u8 = 0x16;
+/// The major version of all SSL 3.0 and TLS record versions.
+const TLS_MAJOR_VERSION: u8 = 0x03;
+/// The highest minor version of a TLS record (TLS 1.3 records claim to be TLS 1.2 or lower).
+const TLS_MAX_MINOR_VERSION: u8 = 0x04;
+/// The handshake message type of a ClientHello.
+const TLS_CLIENT_HELLO: u8 = 0x01;
+
+/// The classification of the first bytes of a connection.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Sniff {
+    /// The input starts with the prefix of a text PROXY protocol header.
+    ProxyV1,
+    /// The input starts with the prefix of a binary PROXY protocol header.
+    ProxyV2,
+    /// The input starts with the record and handshake headers of a TLS ClientHello.
+    TlsClientHello,
+    /// The input starts with an HTTP/1.x request method or the HTTP/2 connection preface.
+    Http,
+    /// The input cannot be the start of any recognized protocol.
+    Unknown,
+    /// More bytes are needed to classify the input.
+    Incomplete,
+}
+
+/// Classifies the first bytes of a connection by their prefix, without parsing a header.
+/// Besides PROXY protocol headers, recognizes TLS ClientHellos and HTTP requests,
+/// so listeners that receive connections both with and without a header can route them.
+/// At most `SNIFF_LENGTH` bytes are inspected.
+/// A matching prefix does not guarantee the rest of the header (or message) is valid.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::{sniff, Sniff};
+///
+/// assert_eq!(sniff(b"PROXY TCP4 127.0.0.1 127.0.0.1 80 443\r\n"), Sniff::ProxyV1);
+/// assert_eq!(sniff(b"\r\n\r\n\0\r\nQUIT\n\x21"), Sniff::ProxyV2);
+/// assert_eq!(sniff(b"GET / HTTP/1.1\r\n"), Sniff::Http);
+/// assert_eq!(sniff(&[0x16, 0x03, 0x01, 0x02, 0x00, 0x01]), Sniff::TlsClientHello);
+/// assert_eq!(sniff(b"SSH-2.0-OpenSSH_8.9\r\n"), Sniff::Unknown);
+/// assert_eq!(sniff(b"P"), Sniff::Incomplete);
+/// ```
+pub fn sniff(input: &[u8]) -> Sniff {
+    let http = HTTP_PREFIXES
+        .iter()
+        .map(|http| (prefix(input, http), Sniff::Http));
+
+    classify(
+        proxy(input)
+            .chain([(tls(input), Sniff::TlsClientHello)])
+            .chain(http),
+    )
+}
+
+/// Classifies the first bytes of a connection as a PROXY protocol header or not.
+/// Unlike `sniff`, inputs that could only be the start of another protocol are `Unknown`,
+/// so readers that require a header can reject them as early as possible.
+pub(crate) fn sniff_proxy(input: &[u8]) -> Sniff {
+    classify(proxy(input))
+}
+
+/// Matches the input against the prefixes of both PROXY protocol versions.
+fn proxy(input: &[u8]) -> impl Iterator<Item = (Prefix, Sniff)> {
+    [
+        (prefix(input, V1_PREFIX), Sniff::ProxyV1),
+        (prefix(input, v2::PROTOCOL_PREFIX), Sniff::ProxyV2),
+    ]
+    .into_iter()
+}
+
+/// Picks the protocol whose prefix the input completely matches,
+/// or `Incomplete` if the input is the start of at least one prefix.
+fn classify(candidates: impl Iterator<Item = (Prefix, Sniff)>) -> Sniff {
+    let mut sniff = Sniff::Unknown;
+
+    for (prefix, candidate) in candidates {
+        match prefix {
+            Prefix::Complete => return candidate,
+            Prefix::Partial => sniff = Sniff::Incomplete,
+            Prefix::Mismatch => (),
+        }
+    }
+
+    sniff
+}
+
+/// How much of a prefix the input matches.
+enum Prefix {
+    Complete,
+    Partial,
+    Mismatch,
+}
+
+/// Tests whether the input starts with the prefix, or is itself the start of the prefix.
+fn prefix(input: &[u8], prefix: &[u8]) -> Prefix {
+    let length = core::cmp::min(input.len(), prefix.len());
+
+    if input[..length] != prefix[..length] {
+        Prefix::Mismatch
+    } else if length == prefix.len() {
+        Prefix::Complete
+    } else {
+        Prefix::Partial
+    }
+}
+
+/// Tests whether the input starts with a TLS handshake record holding a ClientHello,
+/// or is itself the start of one.
+fn tls(input: &[u8]) -> Prefix {
+    let checks = [
+        input.first().map(|&b| b == TLS_HANDSHAKE),
+        input.get(1).map(|&b| b == TLS_MAJOR_VERSION),
+        input.get(2).map(|&b| b <= TLS_MAX_MINOR_VERSION),
+        input.get(5).map(|&b| b == TLS_CLIENT_HELLO),
+    ];
+
+    if checks.contains(&Some(false)) {
+        Prefix::Mismatch
+    } else if input.len() > 5 {
+        Prefix::Complete
+    } else {
+        Prefix::Partial
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_empty() {
+        assert_eq!(sniff(b""), Sniff::Incomplete);
+    }
+
+    #[test]
+    fn sniff_v1() {
+        assert_eq!(sniff(b"PROXY "), Sniff::ProxyV1);
+        assert_eq!(sniff(b"PROXY"), Sniff::Incomplete);
+        assert_eq!(sniff(b"PROXYTCP4"), Sniff::Unknown);
+        assert_eq!(sniff(b"proxy "), Sniff::Unknown);
+    }
+
+    #[test]
+    fn sniff_v2() {
+        assert_eq!(sniff(v2::PROTOCOL_PREFIX), Sniff::ProxyV2);
+        assert_eq!(sniff(&v2::PROTOCOL_PREFIX[..11]), Sniff::Incomplete);
+        assert_eq!(sniff(b"\r\n\r\n\0\r\nQUIT\r"), Sniff::Unknown);
+    }
+
+    #[test]
+    fn sniff_tls() {
+        assert_eq!(
+            sniff(&[0x16, 0x03, 0x01, 0x00, 0xF1, 0x01, 0x00]),
+            Sniff::TlsClientHello
+        );
+        assert_eq!(sniff(&[0x16, 0x03, 0x01]), Sniff::Incomplete);
+        assert_eq!(sniff(&[0x16, 0x03, 0x05]), Sniff::Unknown);
+        assert_eq!(sniff(&[0x16, 0x03, 0x03, 0x00, 0x40, 0x02]), Sniff::Unknown);
+        assert_eq!(sniff(&[0x17, 0x03, 0x03]), Sniff::Unknown);
+    }
+
+    #[test]
+    fn sniff_http() {
+        assert_eq!(sniff(b"POST /api HTTP/1.1\r\n"), Sniff::Http);
+        assert_eq!(sniff(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"), Sniff::Http);
+        assert_eq!(sniff(b"OPTIONS"), Sniff::Incomplete);
+        assert_eq!(sniff(b"GET\t/"), Sniff::Unknown);
+    }
+
+    #[test]
+    fn sniff_length() {
+        assert_eq!(SNIFF_LENGTH, 14);
+        assert_eq!(sniff(&b"PRI * HTTP/2.0\r\n"[..SNIFF_LENGTH]), Sniff::Http);
+        assert_eq!(
+            sniff(&b"\r\n\r\n\0\r\nQUIT\n\x21\x11"[..SNIFF_LENGTH]),
+            Sniff::ProxyV2
+        );
+    }
+
+    #[test]
+    fn sniff_overlapping_prefixes() {
+        assert_eq!(sniff(b"P"), Sniff::Incomplete);
+        assert_eq!(sniff(b"PR"), Sniff::Incomplete);
+        assert_eq!(sniff(b"PROXY"), Sniff::Incomplete);
+        assert_eq!(sniff(b"PUT /"), Sniff::Http);
+    }
+
+    #[test]
+    fn sniff_proxy_only() {
+        assert_eq!(sniff_proxy(b"PROXY "), Sniff::ProxyV1);
+        assert_eq!(sniff_proxy(b"P"), Sniff::Incomplete);
+        assert_eq!(sniff_proxy(b"G"), Sniff::Unknown);
+        assert_eq!(sniff_proxy(b"GET / HTTP/1.1\r\n"), Sniff::Unknown);
+    }
+
+    #[test]
+    fn sniff_garbage() {
+        assert_eq!(sniff(b"\r\n\r\n\r\n"), Sniff::Unknown);
+        assert_eq!(sniff(b"SSH-2.0"), Sniff::Unknown);
+    }
+}
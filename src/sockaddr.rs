@@ -0,0 +1,231 @@
+//! Conversions between the address models and `socket2::SockAddr`, for proxies working at the raw socket level.
+//!
+//! A `SockAddr` holds a `sockaddr_storage`, so addresses returned by raw calls such as `accept` or `getsockname`
+//! convert to and from headers without going through `std::net` types.
+use std::prelude::v1::*;
+
+use crate::{v1, v2};
+use socket2::SockAddr;
+
+// Conversions from `SockAddr`s are constructors rather than `From` implementations for pairs,
+// which would make the type of pairs of parsed addresses ambiguous in code like `Builder::with_addresses`.
+impl v2::Addresses {
+    /// Converts a source and destination of the same family into addresses.
+    /// Pairs of IPv4, IPv6 or named UNIX socket addresses (paths or abstract names) convert to the addresses
+    /// of that family; anything else (e.g. mixed families, or unnamed UNIX sockets) is `Unspecified`.
+    pub fn from_sock_addrs(source: &SockAddr, destination: &SockAddr) -> Self {
+        if let (Some(source), Some(destination)) = (source.as_socket(), destination.as_socket()) {
+            return v2::Addresses::from((source, destination));
+        }
+
+        match (unix_name(source), unix_name(destination)) {
+            (Some(source), Some(destination)) => v2::Unix::new(source, destination).into(),
+            _ => v2::Addresses::Unspecified,
+        }
+    }
+
+    /// The source and destination as `SockAddr`s, if the addresses are of a family with a socket address.
+    /// UNIX socket addresses convert on Unix platforms only.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Addresses, IPv4};
+    /// use socket2::SockAddr;
+    ///
+    /// let addresses = Addresses::from(IPv4::new([192, 0, 2, 1], [10, 0, 0, 1], 51234, 443));
+    /// let (source, destination) = addresses.to_sock_addrs().unwrap();
+    ///
+    /// assert_eq!(source.as_socket(), Some("192.0.2.1:51234".parse().unwrap()));
+    /// assert_eq!(Addresses::from_sock_addrs(&source, &destination), addresses);
+    /// ```
+    pub fn to_sock_addrs(&self) -> Option<(SockAddr, SockAddr)> {
+        match self {
+            v2::Addresses::IPv4(a) => Some((
+                SockAddr::from(std::net::SocketAddr::from((
+                    a.source_address,
+                    a.source_port,
+                ))),
+                SockAddr::from(std::net::SocketAddr::from((
+                    a.destination_address,
+                    a.destination_port,
+                ))),
+            )),
+            v2::Addresses::IPv6(a) => Some((
+                SockAddr::from(std::net::SocketAddr::from((
+                    a.source_address,
+                    a.source_port,
+                ))),
+                SockAddr::from(std::net::SocketAddr::from((
+                    a.destination_address,
+                    a.destination_port,
+                ))),
+            )),
+            v2::Addresses::Unix(a) => Some((
+                unix_address(a.source_address())?,
+                unix_address(a.destination_address())?,
+            )),
+            v2::Addresses::Unspecified => None,
+        }
+    }
+}
+
+impl v1::Addresses {
+    /// Converts a source and destination of the same IP family into addresses, or `Unknown` otherwise.
+    pub fn from_sock_addrs(source: &SockAddr, destination: &SockAddr) -> Self {
+        match (source.as_socket(), destination.as_socket()) {
+            (Some(source), Some(destination)) => v1::Addresses::from((source, destination)),
+            _ => v1::Addresses::Unknown,
+        }
+    }
+
+    /// The source and destination as `SockAddr`s, if the addresses are not `Unknown`.
+    pub fn to_sock_addrs(&self) -> Option<(SockAddr, SockAddr)> {
+        match self {
+            v1::Addresses::Tcp4(a) => v2::Addresses::IPv4(*a).to_sock_addrs(),
+            v1::Addresses::Tcp6(a) => v2::Addresses::IPv6(*a).to_sock_addrs(),
+            v1::Addresses::Unknown => None,
+        }
+    }
+}
+
+/// The path or abstract name of a UNIX socket address, padded with zeros to the size of an address in a binary header.
+/// Abstract names keep the leading zero byte that marks them.
+#[cfg(unix)]
+fn unix_name(address: &SockAddr) -> Option<[u8; 108]> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut padded = [0; 108];
+
+    if let Some(path) = address.as_pathname() {
+        let path = path.as_os_str().as_bytes();
+
+        padded.get_mut(..path.len())?.copy_from_slice(path);
+    } else {
+        let name = address.as_abstract_namespace()?;
+
+        padded.get_mut(1..=name.len())?.copy_from_slice(name);
+    }
+
+    Some(padded)
+}
+
+#[cfg(not(unix))]
+fn unix_name(_: &SockAddr) -> Option<[u8; 108]> {
+    None
+}
+
+/// The UNIX socket address of an address from a binary header.
+#[cfg(unix)]
+fn unix_address(address: v2::UnixAddress<'_>) -> Option<SockAddr> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    match address {
+        v2::UnixAddress::Unnamed => SockAddr::unix(""),
+        v2::UnixAddress::Path(path) => SockAddr::unix(OsStr::from_bytes(path)),
+        v2::UnixAddress::Abstract(name) => {
+            let mut bytes = Vec::with_capacity(1 + name.len());
+
+            bytes.push(0);
+            bytes.extend_from_slice(name);
+
+            SockAddr::unix(OsStr::from_bytes(&bytes))
+        }
+    }
+    .ok()
+}
+
+#[cfg(not(unix))]
+fn unix_address(_: v2::UnixAddress<'_>) -> Option<SockAddr> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    #[test]
+    fn ip_round_trip() {
+        let source: SocketAddr = "[2001:db8::1]:51234".parse().unwrap();
+        let destination: SocketAddr = "[2001:db8::2]:443".parse().unwrap();
+        let sock_addrs = (SockAddr::from(source), SockAddr::from(destination));
+        let addresses = v2::Addresses::from_sock_addrs(&sock_addrs.0, &sock_addrs.1);
+        let text = v1::Addresses::from_sock_addrs(&sock_addrs.0, &sock_addrs.1);
+
+        assert_eq!(addresses, v2::Addresses::from((source, destination)));
+        assert_eq!(text, v1::Addresses::from((source, destination)));
+
+        let (source, destination) = text.to_sock_addrs().unwrap();
+
+        assert_eq!(source.as_socket(), sock_addrs.0.as_socket());
+        assert_eq!(destination.as_socket(), sock_addrs.1.as_socket());
+        assert_eq!(
+            v2::Addresses::from_sock_addrs(
+                &sock_addrs.0,
+                &SockAddr::from(SocketAddr::from(([10, 0, 0, 1], 80)))
+            ),
+            v2::Addresses::Unspecified
+        );
+        assert!(v1::Addresses::Unknown.to_sock_addrs().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_round_trip() {
+        let source = SockAddr::unix("/run/client.sock").unwrap();
+        let destination = SockAddr::unix("/run/server.sock").unwrap();
+        let addresses = v2::Addresses::from_sock_addrs(&source, &destination);
+        let mut expected = ([0; 108], [0; 108]);
+
+        expected.0[..16].copy_from_slice(b"/run/client.sock");
+        expected.1[..16].copy_from_slice(b"/run/server.sock");
+
+        assert_eq!(addresses, v2::Unix::new(expected.0, expected.1).into());
+        assert_eq!(
+            v1::Addresses::from_sock_addrs(&source, &destination),
+            v1::Addresses::Unknown
+        );
+
+        let (source, _) = addresses.to_sock_addrs().unwrap();
+
+        assert_eq!(
+            source.as_pathname(),
+            Some(std::path::Path::new("/run/client.sock"))
+        );
+    }
+
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    #[test]
+    fn abstract_round_trip() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let source = SockAddr::unix(OsStr::from_bytes(b"\0ppp-client")).unwrap();
+        let destination = SockAddr::unix("/run/server.sock").unwrap();
+        let addresses = v2::Addresses::from_sock_addrs(&source, &destination);
+        let mut expected = [0; 108];
+
+        expected[1..11].copy_from_slice(b"ppp-client");
+
+        match addresses {
+            v2::Addresses::Unix(unix) => {
+                assert_eq!(unix.source, expected);
+                assert_eq!(
+                    unix.source_address(),
+                    v2::UnixAddress::Abstract(b"ppp-client")
+                );
+            }
+            _ => panic!("expected unix addresses, got {:?}", addresses),
+        }
+
+        let (source, destination) = addresses.to_sock_addrs().unwrap();
+
+        assert_eq!(source.as_pathname(), None);
+        assert_eq!(source.as_abstract_namespace(), Some(&b"ppp-client"[..]));
+        assert_eq!(
+            destination.as_pathname(),
+            Some(std::path::Path::new("/run/server.sock"))
+        );
+    }
+}
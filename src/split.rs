@@ -0,0 +1,82 @@
+//! Hands a parsed header off from the `BytesMut` buffers of bytes-based codecs.
+use crate::io::Error;
+use crate::policy::Policy;
+use crate::{incremental, Header};
+use bytes::BytesMut;
+
+/// Parses a PROXY protocol header of either version from the front of the buffer and, once it is complete,
+/// splits its bytes off so the buffer starts at the payload. Returns `Ok(None)` when more bytes are needed,
+/// leaving the buffer as-is, which maps directly onto the `decode` method of a `tokio_util::codec::Decoder`.
+///
+/// A buffer that cannot start with a header is an `Error::MissingHeader`.
+/// For connections where the header is optional, use `io::Decoder` with `Policy::Optional` instead.
+///
+/// ## Examples
+/// ```rust
+/// use bytes::BytesMut;
+/// use ppp::split_header;
+///
+/// let mut buffer = BytesMut::from(&b"PROXY TCP4 127.0.0.1 192.168.1.1"[..]);
+///
+/// assert!(split_header(&mut buffer).unwrap().is_none());
+///
+/// buffer.extend_from_slice(b" 80 443\r\nGET / HTTP/1.1\r\n");
+///
+/// let header = split_header(&mut buffer).unwrap().unwrap();
+///
+/// assert_eq!(header.len(), 41);
+/// assert_eq!(&buffer[..], b"GET / HTTP/1.1\r\n");
+/// ```
+pub fn split_header(buffer: &mut BytesMut) -> Result<Option<Header<'static>>, Error> {
+    let header = match incremental::parse(buffer, Policy::Require) {
+        None => return Ok(None),
+        Some(Ok(header)) => header.map(|header| header.to_owned()),
+        Some(Err(invalid)) => return Err(invalid.into()),
+    };
+
+    if let Some(header) = header.as_ref() {
+        let _ = buffer.split_to(header.len());
+    }
+
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_v2() {
+        let header =
+            b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB";
+        let mut buffer = BytesMut::new();
+
+        for &byte in header.iter() {
+            assert!(split_header(&mut buffer).unwrap().is_none());
+            buffer.extend_from_slice(&[byte]);
+        }
+
+        buffer.extend_from_slice(b"Hello");
+
+        assert!(matches!(
+            split_header(&mut buffer).unwrap(),
+            Some(Header::V2(..))
+        ));
+        assert_eq!(&buffer[..], b"Hello");
+    }
+
+    #[test]
+    fn errors() {
+        let mut buffer = BytesMut::from(&b"GET / HTTP/1.1\r\n"[..]);
+
+        assert!(matches!(
+            split_header(&mut buffer),
+            Err(Error::MissingHeader)
+        ));
+        assert_eq!(&buffer[..], b"GET / HTTP/1.1\r\n");
+
+        let mut buffer = BytesMut::from(&b"PROXY TCP4 127.0.0.1 127.0.0.1 80 65536\r\n"[..]);
+
+        assert!(matches!(split_header(&mut buffer), Err(Error::V1(..))));
+    }
+}
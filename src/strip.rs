@@ -0,0 +1,88 @@
+//! Stripping a header from the front of a buffer of the first bytes read from a connection.
+use crate::prelude::*;
+
+use crate::outcome::{InvalidHeader, ParseOutcome};
+use crate::Header;
+
+/// A header that owns its bytes, as returned by `strip_header`.
+pub type HeaderBuf = Header<'static>;
+
+/// An error in stripping a header from a buffer, which leaves the buffer unchanged.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum StripError {
+    #[error("The buffer is the start of a header, which needs at least {needed} more bytes.")]
+    Incomplete { needed: usize },
+    #[error("The buffer does not start with a PROXY protocol header.")]
+    NotProxyProtocol,
+    #[error(transparent)]
+    Invalid(#[from] InvalidHeader),
+}
+
+/// Parses a header of either version from the front of the buffer, removes its bytes from the buffer and returns it.
+/// On error, the buffer is left unchanged, so more bytes can be appended to it after `StripError::Incomplete`.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::{strip_header, StripError};
+///
+/// let mut buffer = b"PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\nGET / HTTP/1.1\r\n".to_vec();
+/// let header = strip_header(&mut buffer).unwrap();
+///
+/// assert_eq!(header.len(), 39);
+/// assert_eq!(buffer, b"GET / HTTP/1.1\r\n");
+/// assert_eq!(strip_header(&mut buffer), Err(StripError::NotProxyProtocol));
+/// assert_eq!(buffer, b"GET / HTTP/1.1\r\n");
+/// ```
+pub fn strip_header(buffer: &mut Vec<u8>) -> Result<HeaderBuf, StripError> {
+    let (header, length) = match ParseOutcome::parse(buffer)? {
+        ParseOutcome::Complete(header, length) => (header.to_owned(), length),
+        ParseOutcome::Incomplete { needed } => return Err(StripError::Incomplete { needed }),
+        ParseOutcome::NotProxyProtocol => return Err(StripError::NotProxyProtocol),
+    };
+
+    buffer.drain(..length);
+
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{v1, v2};
+
+    #[test]
+    fn strip() {
+        let mut buffer = b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00Hello".to_vec();
+        let header = strip_header(&mut buffer).unwrap();
+
+        assert!(matches!(header, Header::V2(..)));
+        assert_eq!(buffer, b"Hello");
+
+        let mut buffer = b"PROXY TCP4 127.0.0.1".to_vec();
+
+        assert_eq!(
+            strip_header(&mut buffer),
+            Err(StripError::Incomplete { needed: 1 })
+        );
+        assert_eq!(buffer, b"PROXY TCP4 127.0.0.1");
+
+        let mut buffer = b"PROXY TCP4 127.0.0.1 127.0.0.2 80 65536\r\n".to_vec();
+
+        assert!(matches!(
+            strip_header(&mut buffer),
+            Err(StripError::Invalid(InvalidHeader::V1(
+                v1::BinaryParseError::Parse(..)
+            )))
+        ));
+        assert_eq!(buffer.len(), 41);
+
+        let mut buffer = b"\r\n\r\n\0\r\nQUIT\n\x13\x00\x00\x00".to_vec();
+
+        assert!(matches!(
+            strip_header(&mut buffer),
+            Err(StripError::Invalid(InvalidHeader::V2(
+                v2::ParseError::Version(..)
+            )))
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+//! In-memory streams for testing code that reads PROXY protocol headers, without real sockets.
+//!
+//! Real connections rarely deliver a header in a single read:
+//! a header may arrive a byte at a time, coalesced with the payload, or be cut short by the peer.
+//! A `MockStream` replays bytes in the segments given to it, so acceptor logic can be tested against each of these.
+//!
+//! ## Examples
+//! ```rust
+//! use ppp::io::read_header;
+//! use ppp::policy::Policy;
+//! use ppp::testing::MockStream;
+//!
+//! let header = b"PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n";
+//!
+//! for mut stream in [
+//!     MockStream::byte_at_a_time(header),
+//!     MockStream::coalesced(header, b"GET / HTTP/1.1\r\n"),
+//!     MockStream::split_at(header, &[5, 11]),
+//! ] {
+//!     assert!(read_header(&mut stream, Policy::Require).unwrap().1.is_some());
+//! }
+//!
+//! let mut truncated = MockStream::truncated(header, 20);
+//! assert!(read_header(&mut truncated, Policy::Require).is_err());
+//! ```
+//!
+//! The `spec` module holds the examples and edge cases of the specification, for testing other parsers.
+use std::prelude::v1::*;
+
+pub mod spec;
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+/// An in-memory stream that returns at most one segment of bytes per read, followed by the end of the stream.
+/// Bytes written to the stream are recorded, so responses can be asserted on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MockStream {
+    segments: VecDeque<Vec<u8>>,
+    written: Vec<u8>,
+}
+
+impl MockStream {
+    /// Creates a new `MockStream` that returns each of the given segments in order, one per read.
+    /// Empty segments are skipped, since an empty read marks the end of the stream.
+    pub fn new<I, B>(segments: I) -> Self
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        MockStream {
+            segments: segments
+                .into_iter()
+                .map(|segment| segment.as_ref().to_vec())
+                .filter(|segment| !segment.is_empty())
+                .collect(),
+            written: Vec::new(),
+        }
+    }
+
+    /// A stream that returns the header and the payload together in a single read.
+    pub fn coalesced(header: &[u8], payload: &[u8]) -> Self {
+        Self::new([[header, payload].concat()])
+    }
+
+    /// A stream that returns a single byte per read.
+    pub fn byte_at_a_time(bytes: &[u8]) -> Self {
+        Self::new(bytes.chunks(1))
+    }
+
+    /// A stream that splits the bytes at each of the given offsets, which must be increasing.
+    /// Offsets past the end of the bytes are ignored.
+    pub fn split_at(bytes: &[u8], offsets: &[usize]) -> Self {
+        let mut start = 0;
+        let mut segments = Vec::with_capacity(offsets.len() + 1);
+
+        for &offset in offsets {
+            let end = offset.clamp(start, bytes.len());
+
+            segments.push(&bytes[start..end]);
+            start = end;
+        }
+
+        segments.push(&bytes[start..]);
+
+        Self::new(segments)
+    }
+
+    /// A stream that ends after the given number of bytes, in a single read.
+    pub fn truncated(bytes: &[u8], length: usize) -> Self {
+        Self::new([&bytes[..length.min(bytes.len())]])
+    }
+
+    /// The number of bytes that have not been read yet.
+    pub fn remaining(&self) -> usize {
+        self.segments.iter().map(Vec::len).sum()
+    }
+
+    /// The bytes written to the stream so far.
+    pub fn written(&self) -> &[u8] {
+        self.written.as_slice()
+    }
+
+    /// Copies as much of the next segment as fits into the buffer.
+    fn next(&mut self, buffer: &mut [u8]) -> usize {
+        let segment = match self.segments.front_mut() {
+            Some(segment) => segment,
+            None => return 0,
+        };
+        let length = segment.len().min(buffer.len());
+
+        buffer[..length].copy_from_slice(&segment[..length]);
+        segment.drain(..length);
+
+        if segment.is_empty() {
+            self.segments.pop_front();
+        }
+
+        length
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        Ok(self.next(buffer))
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buffer);
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncRead for MockStream {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        buffer: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let length = this.next(buffer.initialize_unfilled());
+
+        buffer.advance(length);
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncWrite for MockStream {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        buffer: &[u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        std::task::Poll::Ready(self.get_mut().write(buffer))
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{read_header, Acceptor, Error};
+    use crate::policy::Policy;
+
+    const V2: &[u8] =
+        b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB";
+
+    #[test]
+    fn segments() {
+        let mut stream = MockStream::split_at(b"abcdef", &[2, 2, 5, 10]);
+        let mut buffer = [0; 4];
+
+        assert_eq!(stream.remaining(), 6);
+        assert_eq!(stream.read(&mut buffer).unwrap(), 2);
+        assert_eq!(stream.read(&mut buffer).unwrap(), 3);
+        assert_eq!(&buffer[..3], b"cde");
+        assert_eq!(stream.read(&mut buffer).unwrap(), 1);
+        assert_eq!(stream.read(&mut buffer).unwrap(), 0);
+    }
+
+    #[test]
+    fn partial_segment() {
+        let mut stream = MockStream::coalesced(b"abc", b"def");
+        let mut buffer = [0; 4];
+
+        assert_eq!(stream.read(&mut buffer).unwrap(), 4);
+        assert_eq!(stream.read(&mut buffer).unwrap(), 2);
+        assert_eq!(&buffer[..2], b"ef");
+        assert_eq!(stream.remaining(), 0);
+    }
+
+    #[test]
+    fn accept_segmented() {
+        for stream in [
+            MockStream::byte_at_a_time(V2),
+            MockStream::coalesced(V2, b"Hello"),
+            MockStream::split_at(V2, &[12, 16]),
+        ] {
+            let (_, header) = read_header(stream, Policy::Require).unwrap();
+
+            assert_eq!(header.unwrap().len(), V2.len());
+        }
+
+        let (mut stream, _) =
+            read_header(MockStream::coalesced(V2, b"Hello"), Policy::Require).unwrap();
+        let mut payload = String::new();
+        stream.read_to_string(&mut payload).unwrap();
+        assert_eq!(payload, "Hello");
+
+        let error = Acceptor::new(Policy::Require)
+            .accept(MockStream::truncated(V2, 16), [127, 0, 0, 1].into())
+            .unwrap_err();
+        assert!(matches!(error, Error::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn written() {
+        let mut stream = MockStream::default();
+
+        stream.write_all(b"HTTP/1.1 200 OK\r\n").unwrap();
+        stream.flush().unwrap();
+
+        assert_eq!(stream.written(), b"HTTP/1.1 200 OK\r\n");
+    }
+}
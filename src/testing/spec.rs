@@ -0,0 +1,551 @@
+//! The examples and edge cases of the PROXY protocol specification, along with the outcome a compliant parser
+//! must reach for each, so other implementations can reuse the suite this crate is tested against.
+//!
+//! See <https://haproxy.org/download/1.8/doc/proxy-protocol.txt>
+//!
+//! ## Examples
+//! ```rust
+//! use ppp::testing::spec::{check, Outcome, EXAMPLES};
+//!
+//! assert!(check(Outcome::of).is_empty());
+//!
+//! // A parser that only supports text headers.
+//! let mismatches = check(|input| match ppp::v1::Header::try_from(input) {
+//!     Ok(header) => Outcome::Header(header.header.len()),
+//!     Err(_) => Outcome::Invalid,
+//! });
+//!
+//! assert!(mismatches.len() < EXAMPLES.len());
+//! ```
+use std::prelude::v1::*;
+
+use crate::{v2, Header, HeaderResult, PartialResult, Version};
+
+/// The outcome of parsing the start of an input as a PROXY protocol header.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Outcome {
+    /// The input starts with a valid header of the given length in bytes; the rest of the input is payload.
+    Header(usize),
+    /// More bytes are needed to tell whether the input starts with a valid header.
+    Incomplete,
+    /// The input does not start with a valid header, and never will with more bytes.
+    Invalid,
+}
+
+impl Outcome {
+    /// The outcome of parsing the input with this crate, which treats a binary header with an invalid TLV as invalid.
+    pub fn of(input: &[u8]) -> Outcome {
+        let header: Header = match HeaderResult::parse(input) {
+            HeaderResult::V1(Ok(header)) => header.into(),
+            HeaderResult::V2(Ok(header)) if header.tlvs().all(|tlv| tlv.is_ok()) => header.into(),
+            HeaderResult::V2(Ok(_)) => return Outcome::Invalid,
+            result if result.is_incomplete() => return Outcome::Incomplete,
+            _ => return Outcome::Invalid,
+        };
+
+        Outcome::Header(header.len())
+    }
+}
+
+/// An example or edge case from the specification.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Example {
+    /// A unique name for the example.
+    pub name: &'static str,
+    /// The section of the specification the example comes from.
+    pub section: &'static str,
+    /// The version of the PROXY protocol the example claims to use.
+    pub version: Version,
+    /// The outcome a compliant parser must reach.
+    pub outcome: Outcome,
+    /// The input, which may continue past the header.
+    pub bytes: &'static [u8],
+}
+
+/// An example for which a parser did not reach the expected outcome.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    /// The example that was parsed.
+    pub example: &'static Example,
+    /// The outcome the parser reached instead.
+    pub actual: Outcome,
+}
+
+/// Parses each of the examples with the given parser, returning the examples it got wrong.
+pub fn check<F: FnMut(&[u8]) -> Outcome>(mut parse: F) -> Vec<Mismatch> {
+    EXAMPLES
+        .iter()
+        .map(|example| Mismatch {
+            example,
+            actual: parse(example.bytes),
+        })
+        .filter(|mismatch| mismatch.actual != mismatch.example.outcome)
+        .collect()
+}
+
+/// Copies each part into an array at its offset, leaving the rest of the array zeroed.
+const fn header<const N: usize>(parts: &[(usize, &[u8])]) -> [u8; N] {
+    let mut header = [0; N];
+    let mut part = 0;
+
+    while part < parts.len() {
+        let (offset, bytes) = parts[part];
+        let mut index = 0;
+
+        while index < bytes.len() {
+            header[offset + index] = bytes[index];
+            index += 1;
+        }
+
+        part += 1;
+    }
+
+    header
+}
+
+/// The addresses of the binary IPv4 examples: 192.0.2.1:56324 to 198.51.100.1:443.
+const IPV4: [u8; 12] = [192, 0, 2, 1, 198, 51, 100, 1, 0xDC, 0x04, 0x01, 0xBB];
+
+/// A unix stream header with socket paths, which fill most of the 216 bytes of unix addresses with NULs.
+const UNIX_STREAM: [u8; 232] = header(&[
+    (0, v2::PROTOCOL_PREFIX),
+    (12, &[0x21, 0x31, 0x00, 0xD8]),
+    (16, b"/var/run/source.sock"),
+    (124, b"/var/run/destination.sock"),
+]);
+
+/// A unix datagram header with socket paths.
+const UNIX_DATAGRAM: [u8; 232] = header(&[
+    (0, v2::PROTOCOL_PREFIX),
+    (12, &[0x21, 0x32, 0x00, 0xD8]),
+    (16, b"/var/run/source.sock"),
+    (124, b"/var/run/destination.sock"),
+]);
+
+/// An IPv4 header with a unique ID of the maximum length of 128 bytes.
+const UNIQUE_ID: [u8; 159] = header(&[
+    (0, v2::PROTOCOL_PREFIX),
+    (12, &[0x21, 0x11, 0x00, 0x8F]),
+    (16, &IPV4),
+    (28, &[0x05, 0x00, 0x80]),
+    (31, &[b'u'; 128]),
+]);
+
+/// The examples and edge cases of the specification.
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "spec-v1-tcp4",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Header(47),
+        bytes: b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET / HTTP/1.1\r\nHost: 192.168.0.11\r\n\r\n",
+    },
+    Example {
+        name: "spec-v1-tcp4-worst-case",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Header(56),
+        bytes: b"PROXY TCP4 255.255.255.255 255.255.255.255 65535 65535\r\n",
+    },
+    Example {
+        name: "spec-v1-tcp6-worst-case",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Header(104),
+        bytes: b"PROXY TCP6 ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff 65535 65535\r\n",
+    },
+    Example {
+        name: "spec-v1-unknown-worst-case",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Header(107),
+        bytes: b"PROXY UNKNOWN ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff 65535 65535\r\n",
+    },
+    Example {
+        name: "spec-v1-unknown-shortest",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Header(15),
+        bytes: b"PROXY UNKNOWN\r\n",
+    },
+    Example {
+        name: "spec-v1-unknown-ignores-addresses",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Header(21),
+        bytes: b"PROXY UNKNOWN 1 2 3\r\n",
+    },
+    Example {
+        name: "spec-v1-longer-than-107-bytes",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY UNKNOWN ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff 65535 655350\r\n",
+    },
+    Example {
+        name: "spec-v1-no-crlf-in-107-bytes",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY UNKNOWN ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff 65535 65535xx",
+    },
+    Example {
+        name: "spec-v1-truncated",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Incomplete,
+        bytes: b"PROXY TCP4 192.168.0.1 192.168",
+    },
+    Example {
+        name: "spec-v1-line-feed-only",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\n",
+    },
+    Example {
+        name: "spec-v1-lowercase-prefix",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"proxy TCP4 192.168.0.1 192.168.0.11 56324 443\r\n",
+    },
+    Example {
+        name: "spec-v1-lowercase-protocol",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY tcp4 192.168.0.1 192.168.0.11 56324 443\r\n",
+    },
+    Example {
+        name: "spec-v1-double-space",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY TCP4 192.168.0.1  192.168.0.11 56324 443\r\n",
+    },
+    Example {
+        name: "spec-v1-unsupported-protocol",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY UDP4 192.168.0.1 192.168.0.11 56324 443\r\n",
+    },
+    Example {
+        name: "spec-v1-address-family-mismatch",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY TCP4 2001:db8::1 2001:db8::2 56324 443\r\n",
+    },
+    Example {
+        name: "spec-v1-port-out-of-range",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY TCP4 192.168.0.1 192.168.0.11 65536 443\r\n",
+    },
+    Example {
+        name: "spec-v1-missing-port",
+        section: "2.1",
+        version: Version::One,
+        outcome: Outcome::Invalid,
+        bytes: b"PROXY TCP4 192.168.0.1 192.168.0.11 56324\r\n",
+    },
+    Example {
+        name: "spec-v2-local",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(16),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00",
+    },
+    Example {
+        name: "spec-v2-local-ignores-addresses",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(28),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x20\x11\x00\x0C\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-unspec",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(16),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\x21\x00\x00\x00",
+    },
+    Example {
+        name: "spec-v2-unspec-skips-payload",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(20),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\x21\x00\x00\x04\x01\x02\x03\x04",
+    },
+    Example {
+        name: "spec-v2-tcp4",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(28),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x0C\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB\
+                 GET / HTTP/1.1\r\n",
+    },
+    Example {
+        name: "spec-v2-udp4",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(28),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x12\x00\x0C\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-tcp6",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(52),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x21\x00\x24\
+                 \x20\x01\x0D\xB8\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\
+                 \x20\x01\x0D\xB8\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x02\
+                 \xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-udp6",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(52),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x22\x00\x24\
+                 \x20\x01\x0D\xB8\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\
+                 \x20\x01\x0D\xB8\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x02\
+                 \xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-unix-stream",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(232),
+        bytes: &UNIX_STREAM,
+    },
+    Example {
+        name: "spec-v2-unix-datagram",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(232),
+        bytes: &UNIX_DATAGRAM,
+    },
+    Example {
+        name: "spec-v2-signature-only",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Incomplete,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n",
+    },
+    Example {
+        name: "spec-v2-truncated-addresses",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Incomplete,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0C\xC0\x00\x02\x01",
+    },
+    Example {
+        name: "spec-v2-version-1",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Invalid,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x11\x11\x00\x0C\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-unassigned-command",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Invalid,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x22\x11\x00\x0C\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-unassigned-address-family",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Invalid,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x41\x00\x0C\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-unassigned-protocol",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Invalid,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x13\x00\x0C\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-length-shorter-than-addresses",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Invalid,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x21\x00\x0C\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB",
+    },
+    Example {
+        name: "spec-v2-alpn-authority",
+        section: "2.2.1",
+        version: Version::Two,
+        outcome: Outcome::Header(47),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x1F\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB\
+                 \x01\x00\x02h2\
+                 \x02\x00\x0Bexample.com",
+    },
+    Example {
+        name: "spec-v2-crc32c",
+        section: "2.2.2",
+        version: Version::Two,
+        outcome: Outcome::Header(35),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x13\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB\
+                 \x03\x00\x04\x73\x7E\xE9\x19",
+    },
+    Example {
+        name: "spec-v2-noop-padding",
+        section: "2.2.3",
+        version: Version::Two,
+        outcome: Outcome::Header(37),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x15\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB\
+                 \x04\x00\x00\
+                 \x04\x00\x03\x00\x00\x00",
+    },
+    Example {
+        name: "spec-v2-unique-id-longest",
+        section: "2.2.4",
+        version: Version::Two,
+        outcome: Outcome::Header(159),
+        bytes: &UNIQUE_ID,
+    },
+    Example {
+        name: "spec-v2-ssl",
+        section: "2.2.5",
+        version: Version::Two,
+        outcome: Outcome::Header(104),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x58\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB\
+                 \x20\x00\x49\x07\x00\x00\x00\x00\
+                 \x21\x00\x07TLSv1.3\
+                 \x22\x00\x0Bexample.com\
+                 \x23\x00\x16TLS_AES_128_GCM_SHA256\
+                 \x24\x00\x06SHA256\
+                 \x25\x00\x07RSA2048",
+    },
+    Example {
+        name: "spec-v2-netns",
+        section: "2.2.6",
+        version: Version::Two,
+        outcome: Outcome::Header(34),
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x12\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB\
+                 \x30\x00\x03ns1",
+    },
+    Example {
+        name: "spec-v2-tlv-longer-than-header",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Invalid,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x11\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB\
+                 \x02\x00\x08ex",
+    },
+    Example {
+        name: "spec-v2-tlv-leftovers",
+        section: "2.2",
+        version: Version::Two,
+        outcome: Outcome::Invalid,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x0E\
+                 \xC0\x00\x02\x01\xC6\x33\x64\x01\xDC\x04\x01\xBB\
+                 \x04\x00",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_names() {
+        for (index, example) in EXAMPLES.iter().enumerate() {
+            assert!(
+                EXAMPLES[index + 1..].iter().all(|e| e.name != example.name),
+                "{}",
+                example.name
+            );
+        }
+    }
+
+    #[test]
+    fn examples() {
+        for example in EXAMPLES {
+            assert_eq!(
+                Outcome::of(example.bytes),
+                example.outcome,
+                "{}",
+                example.name
+            );
+
+            if let Outcome::Header(length) = example.outcome {
+                let header: Header = match HeaderResult::parse(example.bytes) {
+                    HeaderResult::V1(header) => header.unwrap().into(),
+                    HeaderResult::V2(header) => header.unwrap().into(),
+                };
+
+                assert_eq!(header.version(), example.version, "{}", example.name);
+                assert_eq!(
+                    header.as_bytes(),
+                    &example.bytes[..length],
+                    "{}",
+                    example.name
+                );
+            }
+        }
+
+        assert_eq!(check(Outcome::of), Vec::new());
+    }
+
+    #[test]
+    fn mismatches() {
+        let mismatches = check(|_| Outcome::Invalid);
+
+        assert!(!mismatches.is_empty());
+        assert!(mismatches
+            .iter()
+            .all(|m| m.example.outcome != Outcome::Invalid && m.actual == Outcome::Invalid));
+    }
+
+    #[test]
+    fn unix_paths() {
+        let header = v2::Header::try_from(&UNIX_STREAM[..]).unwrap();
+
+        match header.addresses {
+            v2::Addresses::Unix(unix) => {
+                assert_eq!(unix.source_path(), &b"/var/run/source.sock"[..]);
+                assert_eq!(unix.destination_path(), &b"/var/run/destination.sock"[..]);
+            }
+            addresses => panic!("{:?}", addresses),
+        }
+    }
+}
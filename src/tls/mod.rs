@@ -0,0 +1,8 @@
+//! Building the SSL Type-Length-Value of binary PROXY protocol headers from TLS connections,
+//! matching the TLV sent by HAProxy's `send-proxy-v2-ssl` and `send-proxy-v2-ssl-cn` options.
+//!
+//! Both build a `v2::SslInfo`, which other TLS libraries can fill in directly.
+#[cfg(feature = "openssl")]
+pub mod openssl;
+#[cfg(feature = "rustls")]
+pub mod rustls;
@@ -0,0 +1,86 @@
+//! Building the SSL Type-Length-Value from an `openssl` connection.
+use std::prelude::v1::*;
+
+use crate::v2::{
+    SslInfo, TypeLengthValue, CLIENT_CERT_CONNECTION, CLIENT_CERT_SESSION, CLIENT_SSL,
+};
+use ::openssl::nid::Nid;
+use ::openssl::pkey::Id;
+use ::openssl::ssl::SslRef;
+use ::openssl::x509::X509Ref;
+use std::io;
+
+/// Builds an SSL `TypeLengthValue` for an `openssl` connection whose handshake is complete
+/// (e.g. the `ssl()` of a `tokio_openssl::SslStream`).
+///
+/// The TLV holds the protocol version, the cipher, the verification result, and the common name,
+/// signature algorithm and key algorithm of the peer's certificate.
+/// Names use OpenSSL's conventions (e.g. `TLSv1.3` and `ECDHE-RSA-AES128-GCM-SHA256`), as HAProxy does.
+///
+/// ## Examples
+/// ```rust,ignore
+/// use ppp::v2::{Builder, Command, Protocol};
+///
+/// let header = Builder::ipv4(Command::Proxy, Protocol::Stream, addresses)
+///     .write_payload(ppp::tls::openssl::ssl_tlv(stream.ssl())?)?
+///     .build()?;
+/// ```
+pub fn ssl_tlv(ssl: &SslRef) -> io::Result<TypeLengthValue<'static>> {
+    let certificate = ssl.peer_certificate();
+
+    let client = match (certificate.is_some(), ssl.session_reused()) {
+        (true, false) => CLIENT_SSL | CLIENT_CERT_CONNECTION | CLIENT_CERT_SESSION,
+        (true, true) => CLIENT_SSL | CLIENT_CERT_SESSION,
+        (false, _) => CLIENT_SSL,
+    };
+    let common_name = certificate.as_deref().and_then(common_name);
+    let signature_algorithm = certificate.as_deref().and_then(|certificate| {
+        certificate
+            .signature_algorithm()
+            .object()
+            .nid()
+            .short_name()
+            .ok()
+    });
+    let key_algorithm = certificate.as_deref().and_then(key_algorithm);
+
+    SslInfo {
+        client,
+        verify: ssl.verify_result().as_raw() as u32,
+        version: Some(ssl.version_str()),
+        common_name: common_name.as_deref(),
+        cipher: ssl.current_cipher().map(|cipher| cipher.name()),
+        signature_algorithm,
+        key_algorithm: key_algorithm.as_deref(),
+    }
+    .to_tlv()
+}
+
+/// The first common name of the certificate's subject.
+/// Names that are not valid UTF-8 or contain a NUL byte are left out rather than truncated.
+fn common_name(certificate: &X509Ref) -> Option<String> {
+    let entry = certificate
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()?;
+    let name = core::str::from_utf8(entry.data().as_slice()).ok()?;
+
+    if name.contains('\0') {
+        return None;
+    }
+
+    Some(name.to_string())
+}
+
+/// The type and size of the certificate's public key (e.g. `RSA2048`).
+fn key_algorithm(certificate: &X509Ref) -> Option<String> {
+    let key = certificate.public_key().ok()?;
+    let name = match key.id() {
+        Id::RSA => "RSA",
+        Id::DSA => "DSA",
+        Id::EC => "EC",
+        _ => return None,
+    };
+
+    Some(format!("{}{}", name, key.bits()))
+}
@@ -0,0 +1,378 @@
+//! Building the SSL Type-Length-Value from a `rustls` connection,
+//! and accepting TLS connections that start with a PROXY protocol header.
+use std::prelude::v1::*;
+
+use crate::io::{Acceptor, ProxiedStream};
+use crate::v2::{
+    SslInfo, TypeLengthValue, CLIENT_CERT_CONNECTION, CLIENT_CERT_SESSION, CLIENT_SSL,
+};
+use crate::ProxyInfo;
+use ::rustls::{CommonState, ProtocolVersion, ServerConfig, ServerConnection, StreamOwned};
+use std::io::{self, Read, Write};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// The object identifier of the common name attribute (2.5.4.3), DER encoded.
+const COMMON_NAME: &[u8] = &[0x06, 0x03, 0x55, 0x04, 0x03];
+
+/// Builds an SSL `TypeLengthValue` for a `rustls` `ServerConnection` or `ClientConnection` whose handshake is complete.
+///
+/// The TLV holds the protocol version, the negotiated cipher suite and the common name of the peer's certificate.
+/// Peers only have certificates once `rustls` has verified them, so the verification result is always 0 (success).
+/// Cipher suites use their IANA names (e.g. `TLS13_AES_128_GCM_SHA256`) rather than OpenSSL's.
+///
+/// ## Examples
+/// ```rust,ignore
+/// use ppp::v2::{Builder, Command, Protocol};
+///
+/// let header = Builder::ipv4(Command::Proxy, Protocol::Stream, addresses)
+///     .write_payload(ppp::tls::rustls::ssl_tlv(&connection)?)?
+///     .build()?;
+/// ```
+pub fn ssl_tlv(connection: &CommonState) -> io::Result<TypeLengthValue<'static>> {
+    let version = connection.protocol_version().map(|version| match version {
+        ProtocolVersion::SSLv2 => "SSLv2".to_string(),
+        ProtocolVersion::SSLv3 => "SSLv3".to_string(),
+        ProtocolVersion::TLSv1_0 => "TLSv1".to_string(),
+        ProtocolVersion::TLSv1_1 => "TLSv1.1".to_string(),
+        ProtocolVersion::TLSv1_2 => "TLSv1.2".to_string(),
+        ProtocolVersion::TLSv1_3 => "TLSv1.3".to_string(),
+        version => format!("{:?}", version),
+    });
+    let cipher = connection
+        .negotiated_cipher_suite()
+        .map(|suite| format!("{:?}", suite.suite()));
+    let certificate = connection
+        .peer_certificates()
+        .and_then(|certificates| certificates.first());
+
+    let client = match certificate {
+        Some(..) => CLIENT_SSL | CLIENT_CERT_CONNECTION | CLIENT_CERT_SESSION,
+        None => CLIENT_SSL,
+    };
+
+    SslInfo {
+        client,
+        verify: 0,
+        version: version.as_deref(),
+        common_name: certificate.and_then(|certificate| common_name(certificate.0.as_slice())),
+        cipher: cipher.as_deref(),
+        ..Default::default()
+    }
+    .to_tlv()
+}
+
+/// An error in accepting a TLS connection behind a PROXY protocol header.
+#[derive(thiserror::Error, Debug)]
+pub enum AcceptError {
+    #[error(transparent)]
+    Header(#[from] crate::io::Error),
+    #[error("Failed to create the TLS connection.")]
+    Tls(#[from] ::rustls::Error),
+    #[error("Failed to complete the TLS handshake.")]
+    Handshake(#[source] io::Error),
+}
+
+/// A TLS stream over a connection whose PROXY protocol header was consumed.
+pub type TlsStream<S> = StreamOwned<ServerConnection, ProxiedStream<S>>;
+
+/// Reads the PROXY protocol header of a connection from the given peer address with the `Acceptor`,
+/// then completes a TLS handshake on the rest of the stream.
+/// Returns the TLS stream along with the `ProxyInfo` of the header if one was present.
+///
+/// Clients commonly send their `ClientHello` without waiting, right behind the header.
+/// Any bytes read past the header are replayed to the TLS connection before the rest of the stream,
+/// so none of the handshake is lost.
+///
+/// ## Examples
+/// ```rust,ignore
+/// use ppp::io::Acceptor;
+/// use ppp::policy::Policy;
+///
+/// let acceptor = Acceptor::new(Policy::Require);
+/// let (stream, peer) = listener.accept()?;
+/// let (mut stream, info) = ppp::tls::rustls::accept(&acceptor, stream, peer.ip(), config.clone())?;
+///
+/// let client = info.and_then(|info| info.source).unwrap_or(peer);
+/// ```
+pub fn accept<S: Read + Write>(
+    acceptor: &Acceptor,
+    stream: S,
+    peer: IpAddr,
+    config: Arc<ServerConfig>,
+) -> Result<(TlsStream<S>, Option<ProxyInfo>), AcceptError> {
+    let (mut stream, header) = acceptor.accept(stream, peer)?;
+    let info = header.as_ref().map(ProxyInfo::from);
+    let mut connection = ServerConnection::new(config)?;
+
+    while connection.is_handshaking() {
+        let (read, written) = connection
+            .complete_io(&mut stream)
+            .map_err(AcceptError::Handshake)?;
+
+        if read == 0 && written == 0 && connection.is_handshaking() {
+            return Err(AcceptError::Handshake(io::ErrorKind::UnexpectedEof.into()));
+        }
+    }
+
+    Ok((StreamOwned::new(connection, stream), info))
+}
+
+/// Finds the first common name of the subject of a DER encoded X.509 certificate.
+fn common_name(certificate: &[u8]) -> Option<&str> {
+    let (certificate, _) = element(certificate, 0x30)?;
+    let (tbs_certificate, _) = element(certificate, 0x30)?;
+
+    // Skip the optional version, the serial number, the signature algorithm, the issuer and the validity.
+    let rest = match element(tbs_certificate, 0xA0) {
+        Some((_, rest)) => rest,
+        None => tbs_certificate,
+    };
+    let (_, rest) = element(rest, 0x02)?;
+    let (_, rest) = element(rest, 0x30)?;
+    let (_, rest) = element(rest, 0x30)?;
+    let (_, rest) = element(rest, 0x30)?;
+    let (mut subject, _) = element(rest, 0x30)?;
+
+    while !subject.is_empty() {
+        let (mut names, rest) = element(subject, 0x31)?;
+
+        while !names.is_empty() {
+            let (name, rest) = element(names, 0x30)?;
+
+            if let Some(value) = name.strip_prefix(COMMON_NAME) {
+                let (tag, value, _) = any_element(value)?;
+
+                // UTF8String, PrintableString, TeletexString or IA5String.
+                if matches!(tag, 0x0C | 0x13 | 0x14 | 0x16) {
+                    return std::str::from_utf8(value).ok();
+                }
+            }
+
+            names = rest;
+        }
+
+        subject = rest;
+    }
+
+    None
+}
+
+/// Splits the contents of a DER element with the expected tag from the bytes that follow it.
+fn element(input: &[u8], expected: u8) -> Option<(&[u8], &[u8])> {
+    match any_element(input)? {
+        (tag, contents, rest) if tag == expected => Some((contents, rest)),
+        _ => None,
+    }
+}
+
+/// Splits a DER element into its tag and contents, and the bytes that follow it.
+fn any_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&length, input) = input.split_first()?;
+
+    let (length, input) = if length & 0x80 == 0 {
+        (length as usize, input)
+    } else {
+        let octets = (length & 0x7F) as usize;
+
+        if octets == 0 || octets > std::mem::size_of::<u32>() || input.len() < octets {
+            return None;
+        }
+
+        let (octets, input) = input.split_at(octets);
+        let length = octets
+            .iter()
+            .fold(0usize, |length, &octet| (length << 8) | octet as usize);
+
+        (length, input)
+    };
+
+    if input.len() < length {
+        return None;
+    }
+
+    let (contents, rest) = input.split_at(length);
+
+    Some((tag, contents, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::Policy;
+    use ::rustls::{Certificate, ClientConfig, ClientConnection, PrivateKey, RootCertStore};
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use std::thread;
+
+    /// A self-signed certificate for `localhost`, valid until 2126, and its PKCS#8 key, for tests only.
+    const CERTIFICATE: &[u8] = include_bytes!("testdata/localhost.crt.der");
+    const KEY: &[u8] = include_bytes!("testdata/localhost.key.der");
+
+    /// One end of an in-memory duplex stream, where each write arrives at the other end as a single read.
+    struct Duplex {
+        incoming: Receiver<Vec<u8>>,
+        outgoing: Sender<Vec<u8>>,
+        buffer: Vec<u8>,
+    }
+
+    fn duplex() -> (Duplex, Duplex) {
+        let (left_sender, right_receiver) = channel();
+        let (right_sender, left_receiver) = channel();
+
+        (
+            Duplex {
+                incoming: left_receiver,
+                outgoing: left_sender,
+                buffer: Vec::new(),
+            },
+            Duplex {
+                incoming: right_receiver,
+                outgoing: right_sender,
+                buffer: Vec::new(),
+            },
+        )
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            if self.buffer.is_empty() {
+                self.buffer = self.incoming.recv().unwrap_or_default();
+            }
+
+            let length = buffer.len().min(self.buffer.len());
+
+            buffer[..length].copy_from_slice(&self.buffer[..length]);
+            self.buffer.drain(..length);
+
+            Ok(length)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+            self.outgoing
+                .send(buffer.to_vec())
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+
+            Ok(buffer.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Encodes a DER element with the given tag and contents.
+    fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+
+        if contents.len() < 0x80 {
+            element.push(contents.len() as u8);
+        } else {
+            element.push(0x82);
+            element.extend_from_slice((contents.len() as u16).to_be_bytes().as_slice());
+        }
+
+        element.extend_from_slice(contents);
+        element
+    }
+
+    fn certificate(subject: &[u8]) -> Vec<u8> {
+        let mut tbs_certificate = der(0xA0, &der(0x02, &[2]));
+
+        tbs_certificate.extend(der(0x02, &[1]));
+        tbs_certificate.extend(der(0x30, &der(0x06, &[0x2A, 0x86, 0x48])));
+        tbs_certificate.extend(der(0x30, &[]));
+        tbs_certificate.extend(der(0x30, &[]));
+        tbs_certificate.extend(der(0x30, subject));
+        tbs_certificate.extend(der(0x30, &[0; 200]));
+
+        let mut certificate = der(0x30, &tbs_certificate);
+
+        certificate.extend(der(0x30, &[]));
+        certificate.extend(der(0x03, &[0]));
+
+        der(0x30, &certificate)
+    }
+
+    fn attribute(oid: &[u8], tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut attribute = oid.to_vec();
+
+        attribute.extend(der(tag, value));
+        der(0x31, &der(0x30, &attribute))
+    }
+
+    #[test]
+    fn find_common_name() {
+        let mut subject = attribute(&[0x06, 0x03, 0x55, 0x04, 0x06], 0x13, b"US");
+
+        subject.extend(attribute(COMMON_NAME, 0x0C, b"example.com"));
+
+        assert_eq!(common_name(&certificate(&subject)), Some("example.com"));
+    }
+
+    #[test]
+    fn accept_client_hello_with_header() {
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![Certificate(CERTIFICATE.to_vec())],
+                PrivateKey(KEY.to_vec()),
+            )
+            .unwrap();
+        let (client_end, server_end) = duplex();
+        let client = thread::spawn(move || {
+            let mut roots = RootCertStore::empty();
+
+            roots.add(&Certificate(CERTIFICATE.to_vec())).unwrap();
+
+            let config = ClientConfig::builder()
+                .with_safe_defaults()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let mut connection =
+                ClientConnection::new(Arc::new(config), "localhost".try_into().unwrap()).unwrap();
+            let mut stream = client_end;
+            let mut hello = b"PROXY TCP4 192.0.2.1 10.0.0.1 51234 443\r\n".to_vec();
+
+            connection.write_tls(&mut hello).unwrap();
+            stream.write_all(&hello).unwrap();
+
+            let mut stream = StreamOwned::new(connection, stream);
+
+            stream.write_all(b"ping").unwrap();
+            stream.flush().unwrap();
+            stream
+        });
+
+        let (mut stream, info) = accept(
+            &Acceptor::new(Policy::Require),
+            server_end,
+            "10.0.0.2".parse().unwrap(),
+            Arc::new(config),
+        )
+        .unwrap();
+        let mut ping = [0; 4];
+
+        stream.read_exact(&mut ping).unwrap();
+
+        assert!(!stream.conn.is_handshaking());
+        assert_eq!(&ping, b"ping");
+        assert_eq!(
+            info.and_then(|info| info.source),
+            "192.0.2.1:51234".parse().ok()
+        );
+        assert!(!client.join().unwrap().conn.is_handshaking());
+    }
+
+    #[test]
+    fn missing_common_name() {
+        let subject = attribute(&[0x06, 0x03, 0x55, 0x04, 0x0A], 0x0C, b"Example");
+
+        assert_eq!(common_name(&certificate(&subject)), None);
+        assert_eq!(common_name(&[0x30, 0x05, 0x30]), None);
+        assert_eq!(common_name(&[]), None);
+    }
+}
@@ -0,0 +1,390 @@
+//! Machine-readable traces of the fields decoded while parsing a header, for attaching to bug reports.
+use crate::prelude::*;
+
+use crate::observe::{Observer, Value};
+use crate::{v1, v2, HeaderResult, PartialResult};
+use core::fmt;
+
+pub use crate::observe::Decision;
+
+/// A step of parsing: the field decoded from a range of the input, or the final decision for the whole header.
+///
+/// Steps are displayed as tab-separated lines of the offset, length, field, value and decision,
+/// e.g. `12\t1\tcommand\tPROXY\tcontinue` or `0\t16\theader\tv2\treject:v2.version`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    /// The offset in the input of the first byte of the field.
+    pub offset: usize,
+    /// The number of bytes of the field.
+    pub length: usize,
+    /// The name of the field, e.g. `source_address`, or `header` for the final step.
+    pub field: &'static str,
+    /// The decoded value of the field, escaped so it holds no tab or control characters.
+    pub value: String,
+    /// The decision the parser took after the step: `Continue` for the fields it went on after,
+    /// the failure for the field it stopped at, and the result of the parse for the final step.
+    pub decision: Decision,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}",
+            self.offset, self.length, self.field, self.value, self.decision
+        )
+    }
+}
+
+/// Receives the steps of a traced parse, in order.
+pub trait TraceSink {
+    /// Records a step.
+    fn record(&mut self, step: Step);
+}
+
+impl TraceSink for Vec<Step> {
+    fn record(&mut self, step: Step) {
+        self.push(step);
+    }
+}
+
+/// Parses a header like `HeaderResult::parse`, recording each field it decodes and the final decision into the sink.
+///
+/// The steps are reported by the parsers as they decide on each field, so a header is rejected at the step
+/// of the field that is invalid. Like `HeaderResult::parse`, the binary parser runs first, and the text parser
+/// only runs on input that is not a binary header, so the steps of text headers follow the rejected binary prefix.
+/// The TLVs of a binary header are traced by iterating them once it is parsed, and an invalid TLV is rejected
+/// without changing the decision for the header, as the parser leaves validating TLVs to their readers.
+///
+/// The final step is always a `header` step, whose decision is the result of the parse: its length is
+/// the length of the header that was parsed, or of the whole input otherwise.
+/// Tracing is deterministic, so the same input always produces the same steps.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::trace::{Decision, Step};
+///
+/// let mut steps = Vec::new();
+/// let header = ppp::parse_with_trace(b"PROXY UNKNOWN\r\n", &mut steps);
+///
+/// assert!(matches!(header, ppp::HeaderResult::V1(Ok(..))));
+/// assert_eq!(
+///     steps.iter().map(ToString::to_string).collect::<Vec<_>>(),
+///     [
+///         "0\t12\tprefix\tPROXY UNKNOW\treject:v2.prefix",
+///         "0\t5\tprefix\tPROXY\tcontinue",
+///         "6\t7\tprotocol\tUNKNOWN\tcontinue",
+///         "13\t2\tterminator\t\\r\\n\tcontinue",
+///         "0\t15\theader\tv1\taccept",
+///     ]
+/// );
+/// ```
+pub fn parse_with_trace<'a, S: TraceSink + ?Sized>(
+    input: &'a [u8],
+    sink: &mut S,
+) -> HeaderResult<'a> {
+    let mut tracer = Tracer { sink };
+    let header = v2::parse_observed(input, &mut tracer);
+
+    let result = if header.is_complete() && header.is_err() {
+        HeaderResult::V1(v1::parse_observed(input, &mut tracer))
+    } else {
+        HeaderResult::V2(header)
+    };
+
+    let (version, length, decision) = match &result {
+        HeaderResult::V1(Ok(header)) => ("v1", header.header.len(), Decision::Accept),
+        HeaderResult::V1(Err(error)) => ("v1", input.len(), Decision::failure(error, error.code())),
+        HeaderResult::V2(Ok(header)) => {
+            tracer.tlvs(header);
+
+            ("v2", header.len(), Decision::Accept)
+        }
+        HeaderResult::V2(Err(error)) => ("v2", input.len(), Decision::failure(error, error.code())),
+    };
+
+    tracer.field(0, length, "header", Value::Text(version), decision);
+
+    result
+}
+
+/// Escapes the control characters of text, including tabs.
+fn escape(text: &str) -> String {
+    text.chars().flat_map(char::escape_default).collect()
+}
+
+/// Formats bytes as text when they are printable, and as lowercase hexadecimal otherwise.
+fn describe(bytes: &[u8]) -> String {
+    match core::str::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(char::is_control) => escape(text),
+        _ => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+    }
+}
+
+/// Records the steps the parsers report into a sink.
+struct Tracer<'s, S: ?Sized> {
+    sink: &'s mut S,
+}
+
+impl<'s, S: TraceSink + ?Sized> Observer for Tracer<'s, S> {
+    fn field(
+        &mut self,
+        offset: usize,
+        length: usize,
+        field: &'static str,
+        value: Value<'_>,
+        decision: Decision,
+    ) {
+        let value = match value {
+            Value::Text(text) => escape(text),
+            Value::Bytes(bytes) => describe(bytes),
+            Value::Hex(byte) => format!("{:#04x}", byte),
+            Value::Display(value) => escape(&value.to_string()),
+        };
+
+        self.sink.record(Step {
+            offset,
+            length,
+            field,
+            value,
+            decision,
+        });
+    }
+}
+
+impl<'s, S: TraceSink + ?Sized> Tracer<'s, S> {
+    /// Records the TLVs of a header as they are decoded, up to the first one that is invalid.
+    fn tlvs(&mut self, header: &v2::Header<'_>) {
+        let mut offset = header.len() - header.tlv_bytes().len();
+
+        for tlv in header.tlvs() {
+            let tlv = match tlv {
+                Ok(tlv) => tlv,
+                Err(error) => {
+                    let length = header.len() - offset;
+                    let value = Value::Display(&error);
+                    let decision = Decision::Reject(error.code());
+
+                    return self.field(offset, length, "invalid_tlvs", value, decision);
+                }
+            };
+            let length = tlv.value.len();
+
+            self.field(
+                offset,
+                1,
+                "tlv_type",
+                Value::Hex(tlv.kind),
+                Decision::Continue,
+            );
+            self.field(
+                offset + 1,
+                2,
+                "tlv_length",
+                Value::Display(&length),
+                Decision::Continue,
+            );
+
+            if length > 0 {
+                let value = Value::Bytes(tlv.value.as_ref());
+
+                self.field(offset + 3, length, "tlv_value", value, Decision::Continue);
+            }
+
+            offset += 3 + length;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Type};
+    use crate::vectors::VECTORS;
+
+    fn trace(input: &[u8]) -> Vec<String> {
+        let mut steps = Vec::new();
+
+        let _ = parse_with_trace(input, &mut steps);
+
+        steps.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn trace_v1() {
+        assert_eq!(
+            trace(b"PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\nGET"),
+            [
+                "0\t12\tprefix\tPROXY TCP4 1\treject:v2.prefix",
+                "0\t5\tprefix\tPROXY\tcontinue",
+                "6\t4\tprotocol\tTCP4\tcontinue",
+                "11\t9\tsource_address\t127.0.0.1\tcontinue",
+                "21\t11\tdestination_address\t192.168.1.1\tcontinue",
+                "33\t2\tsource_port\t80\tcontinue",
+                "36\t3\tdestination_port\t443\tcontinue",
+                "39\t2\tterminator\t\\r\\n\tcontinue",
+                "0\t41\theader\tv1\taccept",
+            ]
+        );
+        assert_eq!(
+            trace(b"PROXY TCP4 127.0.0.1 192.168.1.1 80 0443\r\n")[6..],
+            [
+                "36\t4\tdestination_port\t0443\treject:v1.invalid_destination_port",
+                "0\t42\theader\tv1\treject:v1.invalid_destination_port",
+            ]
+        );
+        assert_eq!(
+            trace(b"PROXY TCP4 127.0.0.1")[3..],
+            [
+                "11\t9\tsource_address\t127.0.0.1\tcontinue",
+                "20\t0\tdestination_address\t\tincomplete",
+                "0\t20\theader\tv1\tincomplete",
+            ]
+        );
+        assert_eq!(
+            trace(b"PROXY UNKNOWN a b\r\n")[2..],
+            [
+                "6\t7\tprotocol\tUNKNOWN\tcontinue",
+                "14\t1\tignored\ta\tcontinue",
+                "16\t1\tignored\tb\tcontinue",
+                "17\t2\tterminator\t\\r\\n\tcontinue",
+                "0\t19\theader\tv1\taccept",
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_rejected_field() {
+        // The destination port is the only invalid field, so the parser stops there rather than at the header.
+        assert_eq!(
+            trace(b"PROXY TCP4 127.0.0.1 192.168.1.1 80 65536\r\n")[1..],
+            [
+                "0\t5\tprefix\tPROXY\tcontinue",
+                "6\t4\tprotocol\tTCP4\tcontinue",
+                "11\t9\tsource_address\t127.0.0.1\tcontinue",
+                "21\t11\tdestination_address\t192.168.1.1\tcontinue",
+                "33\t2\tsource_port\t80\tcontinue",
+                "36\t5\tdestination_port\t65536\treject:v1.invalid_destination_port",
+                "0\t43\theader\tv1\treject:v1.invalid_destination_port",
+            ]
+        );
+        assert_eq!(
+            trace(b"PROXY TCP5 127.0.0.1 192.168.1.1 80 443\r\n")[2..],
+            [
+                "6\t4\tprotocol\tTCP5\treject:v1.invalid_protocol",
+                "0\t41\theader\tv1\treject:v1.invalid_protocol",
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_v2() {
+        let header = Builder::localhost()
+            .write_tlv(Type::Authority, b"example.com")
+            .unwrap()
+            .write_tlv(Type::NoOp, b"")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            trace(&header)[1..],
+            [
+                "12\t1\tversion\t2\tcontinue",
+                "12\t1\tcommand\tPROXY\tcontinue",
+                "13\t1\taddress_family\tINET\tcontinue",
+                "13\t1\tprotocol\tSTREAM\tcontinue",
+                "14\t2\tlength\t29\tcontinue",
+                "16\t4\tsource_address\t127.0.0.1\tcontinue",
+                "20\t4\tdestination_address\t127.0.0.2\tcontinue",
+                "24\t2\tsource_port\t80\tcontinue",
+                "26\t2\tdestination_port\t443\tcontinue",
+                "28\t1\ttlv_type\t0x02\tcontinue",
+                "29\t2\ttlv_length\t11\tcontinue",
+                "31\t11\ttlv_value\texample.com\tcontinue",
+                "42\t1\ttlv_type\t0x04\tcontinue",
+                "43\t2\ttlv_length\t0\tcontinue",
+                "0\t45\theader\tv2\taccept",
+            ]
+        );
+        assert_eq!(trace(&header), trace(&header));
+    }
+
+    #[test]
+    fn trace_rejected() {
+        let mut header = Vec::from(v2::PROTOCOL_PREFIX);
+
+        header.extend([0x10, 0x11, 0, 12]);
+
+        // The text parser stops at the empty field before the binary prefix's first carriage return.
+        assert_eq!(
+            trace(&header)[1..],
+            [
+                "12\t1\tversion\t1\treject:v2.version",
+                "0\t0\tprefix\t\treject:v1.invalid_prefix",
+                "0\t16\theader\tv1\treject:v1.invalid_prefix",
+            ]
+        );
+
+        header[12] = 0x21;
+        header[13] = 0x41;
+
+        assert_eq!(
+            trace(&header)[2..4],
+            [
+                "12\t1\tcommand\tPROXY\tcontinue",
+                "13\t1\taddress_family\t0x04\treject:v2.address_family",
+            ]
+        );
+        assert_eq!(
+            trace(&header[..14]),
+            [
+                "0\t12\tprefix\t0d0a0d0a000d0a515549540a\tcontinue",
+                "0\t14\theader\tv2\tincomplete",
+            ]
+        );
+        assert_eq!(
+            trace(b"GET / HTTP/1.1\r\n")[1..],
+            [
+                "0\t3\tprefix\tGET\treject:v1.invalid_prefix",
+                "0\t16\theader\tv1\treject:v1.invalid_prefix",
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_invalid_tlv() {
+        let mut header = Vec::from(v2::PROTOCOL_PREFIX);
+
+        header.extend([0x21, 0x11, 0, 16, 127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]);
+        header.extend([0x04, 0, 0, 0x02]);
+
+        let steps = trace(&header);
+
+        assert_eq!(
+            steps[10..12],
+            [
+                "28\t1\ttlv_type\t0x04\tcontinue",
+                "29\t2\ttlv_length\t0\tcontinue",
+            ]
+        );
+        // The value of the failed step is the message of the error.
+        assert!(steps[12].starts_with("31\t1\tinvalid_tlvs\t"));
+        assert!(steps[12].ends_with("\treject:v2.leftovers"));
+        assert_eq!(steps[13..], ["0\t32\theader\tv2\taccept"]);
+    }
+
+    #[test]
+    fn same_result() {
+        for vector in VECTORS {
+            let mut steps = Vec::new();
+
+            assert_eq!(
+                parse_with_trace(vector.bytes, &mut steps),
+                HeaderResult::parse(vector.bytes),
+                "{}",
+                vector.name
+            );
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! Translation of binary headers into text headers for backends that only support version 1.
+use crate::prelude::*;
+
+use crate::{v1, v2, Version};
+use alloc::borrow::Cow;
+
+/// Information in a binary header that has no representation in a text header.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Dropped {
+    /// The addresses of a LOCAL command. Text headers express LOCAL connections as `UNKNOWN`.
+    LocalAddresses,
+    /// UNIX socket addresses.
+    UnixAddresses,
+    /// IP addresses of a connection that is not over TCP (i.e. datagram or unspecified protocol).
+    NonTcpAddresses,
+    /// A TLV of the given type.
+    Tlv(u8),
+    /// TLV bytes that could not be parsed.
+    InvalidTlvs,
+}
+
+/// A text header translated from a binary one, along with the information lost in translation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Downgrade {
+    /// The closest text header to the binary one.
+    pub header: v1::Header<'static>,
+    /// The information of the binary header that is not in the text header, in header order.
+    pub dropped: Vec<Dropped>,
+}
+
+impl Downgrade {
+    /// Tests whether the text header represents all of the information of the binary header.
+    pub fn is_lossless(&self) -> bool {
+        self.dropped.is_empty()
+    }
+}
+
+/// Translates a binary header into the closest valid text header.
+/// TCP connections over IPv4 and IPv6 keep their addresses;
+/// all other headers become `UNKNOWN`, which tells the receiver to use the connection's own addresses.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::translate::{downgrade, Dropped};
+/// use ppp::v2::{Builder, Command, Protocol, Type, Version};
+///
+/// let header = Builder::with_addresses(
+///     Version::Two | Command::Proxy,
+///     Protocol::Stream,
+///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+/// )
+/// .write_tlv(Type::Authority, b"example.com")
+/// .unwrap()
+/// .build()
+/// .unwrap();
+/// let header = ppp::v2::Header::try_from(header.as_slice()).unwrap();
+/// let downgrade = downgrade(&header);
+///
+/// assert_eq!(downgrade.header.header, "PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\n");
+/// assert_eq!(downgrade.dropped, vec![Dropped::Tlv(Type::Authority.into())]);
+/// ```
+pub fn downgrade(header: &v2::Header<'_>) -> Downgrade {
+    let mut dropped = Vec::new();
+
+    let addresses = match (header.command, header.protocol, header.addresses) {
+        (_, _, v2::Addresses::Unspecified) => v1::Addresses::Unknown,
+        (v2::Command::Local, _, _) => {
+            dropped.push(Dropped::LocalAddresses);
+            v1::Addresses::Unknown
+        }
+        (_, _, v2::Addresses::Unix(_)) => {
+            dropped.push(Dropped::UnixAddresses);
+            v1::Addresses::Unknown
+        }
+        (_, v2::Protocol::Stream, v2::Addresses::IPv4(addresses)) => addresses.into(),
+        (_, v2::Protocol::Stream, v2::Addresses::IPv6(addresses)) => addresses.into(),
+        _ => {
+            dropped.push(Dropped::NonTcpAddresses);
+            v1::Addresses::Unknown
+        }
+    };
+
+    for tlv in header.tlvs() {
+        match tlv {
+            Ok(tlv) => dropped.push(Dropped::Tlv(tlv.kind)),
+            Err(_) => {
+                dropped.push(Dropped::InvalidTlvs);
+                break;
+            }
+        }
+    }
+
+    Downgrade {
+        header: v1::Header {
+            header: Cow::Owned(addresses.to_string()),
+            addresses,
+        },
+        dropped,
+    }
+}
+
+/// An error in sending a header as version 1, when the text header would lose some of its information.
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("Header cannot be sent as version 1 without dropping {dropped:?}.")]
+pub struct Unrepresentable {
+    /// The information of the binary header that has no representation in a text header, in header order.
+    pub dropped: Vec<Dropped>,
+}
+
+/// Encodes headers in a preferred version, falling back to version 1 when the receiver only supports it.
+///
+/// Headers are given as binary headers, which can hold everything either version can.
+/// Falling back to version 1 is an error rather than a silent loss when the header has information
+/// a text header cannot represent, such as UNIX addresses or TLVs.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::translate::{Dropped, HeaderWriter};
+/// use ppp::v2::{Builder, Command, Protocol, Type};
+/// use ppp::Version;
+///
+/// let header = Builder::with_addresses(
+///     ppp::v2::Version::Two | Command::Proxy,
+///     Protocol::Stream,
+///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+/// )
+/// .build()
+/// .unwrap();
+/// let header = ppp::v2::Header::try_from(header.as_slice()).unwrap();
+///
+/// let writer = HeaderWriter::preferred(Version::Two);
+/// assert_eq!(writer.encode(&header).unwrap(), header.as_bytes());
+///
+/// let writer = writer.v1_only(true);
+/// assert_eq!(writer.version(), Version::One);
+/// assert_eq!(writer.encode(&header).unwrap(), b"PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\n");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeaderWriter {
+    preferred: Version,
+    v1_only: bool,
+}
+
+impl HeaderWriter {
+    /// Creates a `HeaderWriter` that sends headers of the given version unless the receiver only supports version 1.
+    pub fn preferred(version: Version) -> Self {
+        HeaderWriter {
+            preferred: version,
+            v1_only: false,
+        }
+    }
+
+    /// Sets whether the receiver only supports version 1,
+    /// e.g. from a deployment flag or a capability learned from the peer.
+    pub fn v1_only(mut self, v1_only: bool) -> Self {
+        self.v1_only = v1_only;
+        self
+    }
+
+    /// The version of the headers this `HeaderWriter` sends.
+    pub fn version(&self) -> Version {
+        if self.v1_only {
+            Version::One
+        } else {
+            self.preferred
+        }
+    }
+
+    /// Encodes the header in the negotiated version.
+    /// Returns an error when the header must be sent as version 1 but a text header cannot represent all of it.
+    pub fn encode(&self, header: &v2::Header<'_>) -> Result<Vec<u8>, Unrepresentable> {
+        if self.version() == Version::Two {
+            return Ok(header.as_bytes().to_vec());
+        }
+
+        let downgrade = downgrade(header);
+
+        if downgrade.is_lossless() {
+            Ok(downgrade.header.header.into_owned().into_bytes())
+        } else {
+            Err(Unrepresentable {
+                dropped: downgrade.dropped,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{AddressFamily, Builder, Command, Protocol, Type, Unix, Version};
+
+    fn parse(header: &[u8]) -> v2::Header<'_> {
+        v2::Header::try_from(header).unwrap()
+    }
+
+    #[test]
+    fn downgrade_tcp6() {
+        let header = Builder::with_addresses(
+            Version::Two | Command::Proxy,
+            Protocol::Stream,
+            (
+                "[2001:db8::1]:80".parse().unwrap(),
+                "[2001:db8::2]:443".parse().unwrap(),
+            ),
+        )
+        .build()
+        .unwrap();
+        let downgrade = downgrade(&parse(&header));
+
+        assert!(downgrade.is_lossless());
+        assert_eq!(
+            downgrade.header.header,
+            "PROXY TCP6 2001:db8::1 2001:db8::2 80 443\r\n"
+        );
+        assert_eq!(
+            v1::Header::try_from(downgrade.header.header.as_ref()).unwrap(),
+            downgrade.header
+        );
+    }
+
+    #[test]
+    fn downgrade_unspecified() {
+        let header = Builder::new(
+            Version::Two | Command::Local,
+            AddressFamily::Unspecified | Protocol::Unspecified,
+        )
+        .build()
+        .unwrap();
+        let downgrade = downgrade(&parse(&header));
+
+        assert!(downgrade.is_lossless());
+        assert_eq!(downgrade.header.header, "PROXY UNKNOWN\r\n");
+    }
+
+    #[test]
+    fn downgrade_local() {
+        let header = Builder::with_addresses(
+            Version::Two | Command::Local,
+            Protocol::Stream,
+            (
+                "127.0.0.1:80".parse().unwrap(),
+                "127.0.0.2:443".parse().unwrap(),
+            ),
+        )
+        .build()
+        .unwrap();
+        let downgrade = downgrade(&parse(&header));
+
+        assert_eq!(downgrade.header.addresses, v1::Addresses::Unknown);
+        assert_eq!(downgrade.dropped, vec![Dropped::LocalAddresses]);
+    }
+
+    #[test]
+    fn downgrade_unix() {
+        let header = Builder::with_addresses(
+            Version::Two | Command::Proxy,
+            Protocol::Stream,
+            Unix::new([1; 108], [2; 108]),
+        )
+        .write_tlv(Type::NoOp, &[0])
+        .unwrap()
+        .write_tlv(0xE0u8, b"custom")
+        .unwrap()
+        .build()
+        .unwrap();
+        let downgrade = downgrade(&parse(&header));
+
+        assert_eq!(downgrade.header.header, "PROXY UNKNOWN\r\n");
+        assert_eq!(
+            downgrade.dropped,
+            vec![
+                Dropped::UnixAddresses,
+                Dropped::Tlv(Type::NoOp.into()),
+                Dropped::Tlv(0xE0)
+            ]
+        );
+    }
+
+    #[test]
+    fn downgrade_datagram() {
+        let header = Builder::with_addresses(
+            Version::Two | Command::Proxy,
+            Protocol::Datagram,
+            (
+                "127.0.0.1:53".parse().unwrap(),
+                "127.0.0.2:53".parse().unwrap(),
+            ),
+        )
+        .build()
+        .unwrap();
+        let downgrade = downgrade(&parse(&header));
+
+        assert_eq!(downgrade.header.addresses, v1::Addresses::Unknown);
+        assert_eq!(downgrade.dropped, vec![Dropped::NonTcpAddresses]);
+    }
+
+    #[test]
+    fn downgrade_invalid_tlvs() {
+        let header = Builder::localhost()
+            .write_payload(&[Type::NoOp.into(), 0, 8][..])
+            .unwrap()
+            .build()
+            .unwrap();
+        let downgrade = downgrade(&parse(&header));
+
+        assert_eq!(
+            downgrade.header.header,
+            "PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n"
+        );
+        assert_eq!(downgrade.dropped, vec![Dropped::InvalidTlvs]);
+    }
+
+    #[test]
+    fn negotiate_version() {
+        let unix = Builder::with_addresses(
+            Version::Two | Command::Proxy,
+            Protocol::Stream,
+            Unix::new([1; 108], [2; 108]),
+        )
+        .write_tlv(Type::Authority, b"example.com")
+        .unwrap()
+        .build()
+        .unwrap();
+        let local = Builder::new(
+            Version::Two | Command::Local,
+            AddressFamily::Unspecified | Protocol::Unspecified,
+        )
+        .build()
+        .unwrap();
+        let preferred = HeaderWriter::preferred(crate::Version::Two);
+        let fallback = preferred.v1_only(true);
+
+        assert_eq!(preferred.version(), crate::Version::Two);
+        assert_eq!(preferred.encode(&parse(&unix)).unwrap(), unix);
+        assert_eq!(
+            fallback.encode(&parse(&unix)),
+            Err(Unrepresentable {
+                dropped: vec![Dropped::UnixAddresses, Dropped::Tlv(Type::Authority.into())]
+            })
+        );
+        assert_eq!(
+            fallback.encode(&parse(&local)).unwrap(),
+            b"PROXY UNKNOWN\r\n"
+        );
+        assert_eq!(
+            HeaderWriter::preferred(crate::Version::One)
+                .v1_only(false)
+                .version(),
+            crate::Version::One
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Reading and writing PROXY protocol headers over any transport that can read and write some bytes,
+//! for transports without `std::io` or `embedded-io` implementations (e.g. SPDK or DPDK wrappers, or enclave OCALL channels).
+//!
+//! ## Examples
+//! ```rust
+//! use ppp::policy::Policy;
+//! use ppp::transport::{read_header, write_header, HeaderTransport};
+//!
+//! /// A transport over a single message in memory, whose replies are discarded.
+//! struct Message(&'static [u8]);
+//!
+//! impl HeaderTransport for Message {
+//!     type Error = ();
+//!
+//!     fn read(&mut self, buffer: &mut [u8]) -> Result<usize, ()> {
+//!         let length = buffer.len().min(self.0.len());
+//!
+//!         buffer[..length].copy_from_slice(&self.0[..length]);
+//!         self.0 = &self.0[length..];
+//!
+//!         Ok(length)
+//!     }
+//!
+//!     fn write(&mut self, _: &[u8]) -> Result<(), ()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut transport = Message(b"PROXY UNKNOWN\r\nHello");
+//! let (header, payload) = read_header(&mut transport, Policy::Require).unwrap();
+//!
+//! assert_eq!(header.unwrap().as_bytes(), b"PROXY UNKNOWN\r\n");
+//! assert_eq!(payload, b"Hello");
+//! assert_eq!(write_header(&mut transport, b"PROXY UNKNOWN\r\n"), Ok(()));
+//! ```
+use crate::prelude::*;
+
+use crate::incremental::{self, Invalid};
+use crate::policy::Policy;
+use crate::{v1, v2, Header};
+
+/// The number of bytes to read from the transport at a time.
+pub(crate) const READ_SIZE: usize = 256;
+
+/// A transport that headers can be read from and written to.
+pub trait HeaderTransport {
+    /// The error of a failed read or write.
+    type Error;
+
+    /// Reads some bytes into the buffer, returning how many were read.
+    /// Returns 0 only once the transport has ended.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error>;
+
+    /// Writes all of the bytes.
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    /// Sends any bytes written so far. Does nothing by default, for transports that do not buffer writes.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<T: HeaderTransport + ?Sized> HeaderTransport for &mut T {
+    type Error = T::Error;
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        (**self).read(buffer)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        (**self).write(bytes)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        (**self).flush()
+    }
+}
+
+/// An error in reading a PROXY protocol header from a transport with errors of type `E`.
+#[derive(thiserror::Error, Debug)]
+pub enum Error<E> {
+    #[error("Failed to read from the stream: {0:?}")]
+    Io(E),
+    #[error("Stream ended before the PROXY protocol header was complete.")]
+    UnexpectedEof,
+    #[error(transparent)]
+    V1(#[from] v1::BinaryParseError),
+    #[error(transparent)]
+    V2(#[from] v2::ParseError),
+    #[error("Connection does not start with a PROXY protocol header.")]
+    MissingHeader,
+}
+
+impl<E> From<Invalid> for Error<E> {
+    fn from(invalid: Invalid) -> Self {
+        match invalid {
+            Invalid::V1(error) => Error::V1(error),
+            Invalid::V2(error) => Error::V2(error),
+            Invalid::MissingHeader => Error::MissingHeader,
+        }
+    }
+}
+
+/// Reads a PROXY protocol header of either version from the start of the transport according to the given `Policy`.
+/// Returns the header if one was present, along with the bytes read past it, which are the start of the payload.
+#[allow(clippy::type_complexity)]
+pub fn read_header<T: HeaderTransport + ?Sized>(
+    transport: &mut T,
+    policy: Policy,
+) -> Result<(Option<Header<'static>>, Vec<u8>), Error<T::Error>> {
+    read_with(|buffer| transport.read(buffer), policy)
+}
+
+/// Reads a header like `read_header` with the given function for reading some bytes into a buffer,
+/// for the helpers of streams that can only be read from.
+#[allow(clippy::type_complexity)]
+pub(crate) fn read_with<E, F: FnMut(&mut [u8]) -> Result<usize, E>>(
+    mut read: F,
+    policy: Policy,
+) -> Result<(Option<Header<'static>>, Vec<u8>), Error<E>> {
+    let mut buffer = Vec::new();
+
+    if policy == Policy::Ignore {
+        return Ok((None, buffer));
+    }
+
+    let mut chunk = [0; READ_SIZE];
+
+    loop {
+        let length = read(&mut chunk).map_err(Error::Io)?;
+
+        buffer.extend_from_slice(&chunk[..length]);
+
+        let header = advance(buffer.as_slice(), length, policy)
+            .map(|result| result.map(|header| header.map(|header| header.to_owned())));
+
+        if let Some(header) = header {
+            let header = header?;
+
+            buffer.drain(..header.as_ref().map_or(0, Header::len));
+
+            return Ok((header, buffer));
+        }
+    }
+}
+
+/// Writes an encoded header (e.g. from `v2::FixedBuilder` or `Header::as_bytes`) to the start of the transport,
+/// flushing it so the header is sent before any of the payload.
+pub fn write_header<T: HeaderTransport + ?Sized>(
+    transport: &mut T,
+    header: &[u8],
+) -> Result<(), T::Error> {
+    transport.write(header)?;
+    transport.flush()
+}
+
+/// Handles the bytes read so far after a read of the given length, where a length of 0 is the end of the transport.
+/// Returns `None` when more bytes are needed.
+pub(crate) fn advance<E>(
+    input: &[u8],
+    read: usize,
+    policy: Policy,
+) -> Option<Result<Option<Header<'_>>, Error<E>>> {
+    if read == 0 {
+        Some(if input.is_empty() && policy == Policy::Optional {
+            Ok(None)
+        } else {
+            Err(Error::UnexpectedEof)
+        })
+    } else {
+        incremental::parse(input, policy).map(|result| result.map_err(Error::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A transport that reads a byte at a time, or fails once it has no more bytes, and records writes and flushes.
+    #[derive(Default)]
+    struct Trickle {
+        input: &'static [u8],
+        fail: bool,
+        written: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl HeaderTransport for Trickle {
+        type Error = &'static str;
+
+        fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+            match self.input.split_first() {
+                Some((&byte, rest)) => {
+                    buffer[0] = byte;
+                    self.input = rest;
+                    Ok(1)
+                }
+                None if self.fail => Err("disconnected"),
+                None => Ok(0),
+            }
+        }
+
+        fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+            self.written.extend_from_slice(bytes);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_a_byte_at_a_time() {
+        let mut transport = Trickle {
+            input: b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00Hello",
+            ..Default::default()
+        };
+        let (header, payload) = read_header(&mut transport, Policy::Require).unwrap();
+
+        assert_eq!(header.unwrap().len(), 16);
+        assert!(payload.is_empty());
+        assert_eq!(transport.input, b"Hello");
+
+        let (header, payload) = read_header(&mut transport, Policy::Optional).unwrap();
+
+        assert!(header.is_none());
+        assert_eq!(payload, b"H");
+    }
+
+    #[test]
+    fn errors() {
+        let mut transport = Trickle {
+            input: b"PROXY UNKNOWN",
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            read_header(&mut transport, Policy::Require),
+            Err(Error::UnexpectedEof)
+        ));
+
+        let mut transport = Trickle {
+            input: b"PROXY",
+            fail: true,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            read_header(&mut transport, Policy::Require),
+            Err(Error::Io("disconnected"))
+        ));
+        assert!(matches!(
+            read_header(&mut transport, Policy::Ignore),
+            Ok((None, payload)) if payload.is_empty()
+        ));
+    }
+
+    #[test]
+    fn write() {
+        let mut transport = Trickle::default();
+
+        write_header(&mut transport, b"PROXY UNKNOWN\r\n").unwrap();
+
+        assert_eq!(transport.written, b"PROXY UNKNOWN\r\n");
+        assert_eq!(transport.flushes, 1);
+    }
+}
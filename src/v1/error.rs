@@ -1,8 +1,8 @@
 //! Errors for the text proxy protocol.
-use std::prelude::v1::*;
-
 /// An error in parsing a text PROXY protocol header.
+/// New checks of the text format may add variants in minor releases.
 #[derive(thiserror::Error, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum ParseError {
     #[error("Header must start with 'PROXY'.")]
     InvalidPrefix,
@@ -29,13 +29,19 @@ pub enum ParseError {
     #[error("Header must end in '\r\n'.")]
     InvalidSuffix,
     #[error("Header contains invalid IP address for the source.")]
-    InvalidSourceAddress(#[source] std::net::AddrParseError),
+    InvalidSourceAddress(#[source] crate::net::AddrParseError),
     #[error("Header contains invalid IP address for the destination.")]
-    InvalidDestinationAddress(#[source] std::net::AddrParseError),
+    InvalidDestinationAddress(#[source] crate::net::AddrParseError),
     #[error("Header contains invalid TCP port for the source.")]
-    InvalidSourcePort(#[source] Option<std::num::ParseIntError>),
+    InvalidSourcePort(#[source] Option<core::num::ParseIntError>),
     #[error("Header contains invalid TCP port for the destination.")]
-    InvalidDestinationPort(#[source] Option<std::num::ParseIntError>),
+    InvalidDestinationPort(#[source] Option<core::num::ParseIntError>),
+    #[error("Header has more than the four address and port fields.")]
+    ExtraFields,
+    #[error("Header contains an empty field between consecutive separators.")]
+    EmptyField,
+    #[error("Header contains a control character before the terminating '\\r\\n'.")]
+    ControlCharacter,
 }
 
 /// An error in parsing a text PROXY protocol header that is represented as a byte slice.
@@ -44,5 +50,46 @@ pub enum BinaryParseError {
     #[error(transparent)]
     Parse(#[from] ParseError),
     #[error("Header is not valid UTF-8.")]
-    InvalidUtf8(#[from] std::str::Utf8Error),
+    InvalidUtf8(#[from] core::str::Utf8Error),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ParseError {
+    fn format(&self, f: defmt::Formatter) {
+        let name = match self {
+            Self::InvalidPrefix => "InvalidPrefix",
+            Self::Partial => "Partial",
+            Self::MissingPrefix => "MissingPrefix",
+            Self::MissingNewLine => "MissingNewLine",
+            Self::MissingProtocol => "MissingProtocol",
+            Self::MissingSourceAddress => "MissingSourceAddress",
+            Self::MissingDestinationAddress => "MissingDestinationAddress",
+            Self::MissingSourcePort => "MissingSourcePort",
+            Self::MissingDestinationPort => "MissingDestinationPort",
+            Self::HeaderTooLong => "HeaderTooLong",
+            Self::InvalidProtocol => "InvalidProtocol",
+            Self::InvalidSuffix => "InvalidSuffix",
+            Self::InvalidSourceAddress(..) => "InvalidSourceAddress",
+            Self::InvalidDestinationAddress(..) => "InvalidDestinationAddress",
+            Self::InvalidSourcePort(..) => "InvalidSourcePort",
+            Self::InvalidDestinationPort(..) => "InvalidDestinationPort",
+            Self::ExtraFields => "ExtraFields",
+            Self::EmptyField => "EmptyField",
+            Self::ControlCharacter => "ControlCharacter",
+        };
+
+        defmt::write!(f, "{=str}", name)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for BinaryParseError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Parse(error) => defmt::write!(f, "{}", error),
+            Self::InvalidUtf8(error) => {
+                defmt::write!(f, "InvalidUtf8({=usize})", error.valid_up_to())
+            }
+        }
+    }
 }
@@ -1,53 +1,102 @@
 //! Version 1 of the HAProxy protocol (text version).
 //!
 //! See <https://haproxy.org/download/1.8/doc/proxy-protocol.txt>
-use std::prelude::v1::*;
-
 mod error;
 mod model;
+mod scan;
 
 pub use crate::ip::{IPv4, IPv6};
+use crate::net::AddrParseError;
+use crate::observe::{Decision, Observer, Value};
+use crate::parse::NumberError;
+use alloc::borrow::Cow;
+use core::num::ParseIntError;
+use core::str::{from_utf8, FromStr};
 pub use error::{BinaryParseError, ParseError};
 pub use model::{Addresses, Header, SEPARATOR, TCP4, TCP6, UNKNOWN};
 pub use model::{PROTOCOL_PREFIX, PROTOCOL_SUFFIX};
-use std::borrow::Cow;
-use std::net::{AddrParseError, Ipv4Addr, Ipv6Addr};
-use std::str::{from_utf8, FromStr};
+use scan::{Decode, Decoded, Port, PortError, Scanner, V4, V6};
 
-const ZERO: &str = "0";
 const NEWLINE: &str = "\n";
 const CARRIAGE_RETURN: char = '\r';
 
 /// The maximum length of a header in bytes.
-const MAX_LENGTH: usize = 107;
+pub(crate) const MAX_LENGTH: usize = 107;
 /// The total number of parts in the header.
 const PARTS: usize = 7;
 
-/// Parses a text PROXY protocol header.
+/// How closely a header must follow the specification.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Strictness {
+    /// Like `Normal`, but the protocol may be in any case (e.g. `tcp4`).
+    Lenient,
+    /// The protocol must be in uppercase.
+    Normal,
+    /// Like `Normal`, but TCP4 and TCP6 headers must have exactly four address and port fields, each separated by a single space.
+    Strict,
+}
+
+/// Parses a text PROXY protocol header, reporting each field to the observer as it is decided on.
 /// The given string is expected to only include the header and to end in \r\n.
-fn parse_header(header: &str) -> Result<Header, ParseError> {
+fn parse_header<'a, O: Observer>(
+    header: &'a str,
+    strictness: Strictness,
+    observer: &mut O,
+) -> Result<Header<'a>, ParseError> {
     if header.is_empty() {
-        return Err(ParseError::MissingPrefix);
+        let error = ParseError::MissingPrefix;
+
+        return Err(reject(observer, 0, "prefix", header, error));
     } else if header.len() > MAX_LENGTH {
         return Err(ParseError::HeaderTooLong);
     }
 
-    let mut iterator = header
-        .splitn(PARTS, |c| c == SEPARATOR || c == CARRIAGE_RETURN)
-        .peekable();
+    let mut scanner = Scanner::new(header, PARTS);
 
-    let prefix = iterator.next().ok_or(ParseError::MissingPrefix)?;
+    let prefix = scanner.field().ok_or(ParseError::MissingPrefix)?;
 
     if !prefix.is_empty() && PROTOCOL_PREFIX.starts_with(prefix) && header.ends_with(prefix) {
-        return Err(ParseError::Partial);
+        return Err(reject(observer, 0, "prefix", prefix, ParseError::Partial));
     } else if prefix != PROTOCOL_PREFIX {
-        return Err(ParseError::InvalidPrefix);
+        let error = ParseError::InvalidPrefix;
+
+        return Err(reject(observer, 0, "prefix", prefix, error));
     }
 
-    let addresses = match iterator.next() {
+    read(observer, 0, "prefix", prefix);
+
+    // The header ends at its first carriage return, so only the terminating one is left to check for.
+    // A carriage return followed by anything but the line feed is a control character inside the line.
+    let (line, terminator) = header
+        .find(CARRIAGE_RETURN)
+        .map_or((header, ""), |end| header.split_at(end));
+
+    if line.bytes().any(|byte| byte.is_ascii_control())
+        || (terminator.len() > 1 && !terminator.starts_with(PROTOCOL_SUFFIX))
+    {
+        return Err(ParseError::ControlCharacter);
+    }
+
+    let offset = scanner.position();
+    let field = scanner.field();
+    let protocol = field.map(|protocol| match strictness {
+        Strictness::Lenient => [TCP4, TCP6, UNKNOWN]
+            .into_iter()
+            .find(|token| token.eq_ignore_ascii_case(protocol))
+            .unwrap_or(protocol),
+        _ => protocol,
+    });
+
+    let addresses = match protocol {
         Some(TCP4) => {
+            read(observer, offset, "protocol", field.unwrap_or_default());
+
+            if strictness == Strictness::Strict {
+                check_fields(header)?;
+            }
+
             let (source_address, destination_address, source_port, destination_port) =
-                parse_addresses::<Ipv4Addr, _>(&mut iterator)?;
+                parse_addresses::<V4, O>(&mut scanner, observer)?;
 
             Addresses::Tcp4(IPv4 {
                 source_address,
@@ -57,8 +106,14 @@ fn parse_header(header: &str) -> Result<Header, ParseError> {
             })
         }
         Some(TCP6) => {
+            read(observer, offset, "protocol", field.unwrap_or_default());
+
+            if strictness == Strictness::Strict {
+                check_fields(header)?;
+            }
+
             let (source_address, destination_address, source_port, destination_port) =
-                parse_addresses::<Ipv6Addr, _>(&mut iterator)?;
+                parse_addresses::<V6, O>(&mut scanner, observer)?;
 
             Addresses::Tcp6(IPv6 {
                 source_address,
@@ -68,72 +123,155 @@ fn parse_header(header: &str) -> Result<Header, ParseError> {
             })
         }
         Some(UNKNOWN) => {
-            while iterator.next_if(|&s| s != NEWLINE).is_some() {}
-
-            Addresses::Unknown
+            read(observer, offset, "protocol", field.unwrap_or_default());
+
+            // Anything up to the line feed is ignored.
+            loop {
+                let offset = scanner.position();
+
+                match scanner.field() {
+                    Some(NEWLINE) => break read_terminator(observer, header, offset),
+                    Some(field) => read(observer, offset, "ignored", field),
+                    None => {
+                        let error = ParseError::MissingNewLine;
+
+                        return Err(reject(observer, offset, "terminator", "", error));
+                    }
+                }
+            }
+
+            return Ok(Header {
+                header: Cow::Borrowed(header),
+                addresses: Addresses::Unknown,
+            });
         }
-        Some(protocol) if protocol.is_empty() && iterator.peek().is_none() => {
-            return Err(ParseError::MissingProtocol)
+        Some(protocol) if protocol.is_empty() && scanner.is_done() => {
+            let error = ParseError::MissingProtocol;
+
+            return Err(reject(observer, offset, "protocol", protocol, error));
         }
         Some(protocol)
             if !protocol.is_empty()
                 && header.ends_with(protocol)
                 && (TCP4.starts_with(protocol) || UNKNOWN.starts_with(protocol)) =>
         {
-            return Err(ParseError::Partial)
+            let error = ParseError::Partial;
+
+            return Err(reject(observer, offset, "protocol", protocol, error));
+        }
+        Some(protocol) => {
+            let error = ParseError::InvalidProtocol;
+
+            return Err(reject(observer, offset, "protocol", protocol, error));
+        }
+        None => {
+            let error = ParseError::MissingProtocol;
+
+            return Err(reject(observer, offset, "protocol", "", error));
         }
-        Some(_) => return Err(ParseError::InvalidProtocol),
-        None => return Err(ParseError::MissingProtocol),
     };
 
-    let newline = iterator
-        .next()
-        .filter(|s| !s.is_empty())
-        .ok_or(ParseError::MissingNewLine)?;
+    let offset = scanner.position();
+    let newline = match scanner.field().filter(|s| !s.is_empty()) {
+        Some(newline) => newline,
+        None => {
+            let error = ParseError::MissingNewLine;
+
+            return Err(reject(observer, offset, "terminator", "", error));
+        }
+    };
 
     if newline != NEWLINE {
-        return Err(ParseError::InvalidSuffix);
+        let error = ParseError::InvalidSuffix;
+
+        return Err(reject(observer, offset, "terminator", newline, error));
     }
 
+    read_terminator(observer, header, offset);
+
     Ok(Header {
         header: Cow::Borrowed(header),
         addresses,
     })
 }
 
-/// Parses the addresses and ports from a PROXY protocol header for IPv4 and IPv6.
-fn parse_addresses<'a, T: FromStr<Err = AddrParseError>, I: Iterator<Item = &'a str>>(
-    iterator: &mut I,
-) -> Result<(T, T, u16, u16), ParseError> {
-    let source_address = iterator.next().ok_or(ParseError::MissingSourceAddress)?;
-    let destination_address = iterator
-        .next()
-        .ok_or(ParseError::MissingDestinationAddress)?;
-    let source_port = iterator.next().ok_or(ParseError::MissingSourcePort)?;
-    let destination_port = iterator.next().ok_or(ParseError::MissingDestinationPort)?;
+/// Checks that the fields after the protocol are neither empty nor more than the four addresses and ports.
+/// An empty field at the end of a header without a carriage return is still to be read, so it is not an error.
+fn check_fields(header: &str) -> Result<(), ParseError> {
+    let (line, terminated) = match header.find(CARRIAGE_RETURN) {
+        Some(end) => (&header[..end], true),
+        None => (header, false),
+    };
+    let mut fields = line.split(SEPARATOR).skip(2).peekable();
+    let mut count = 0;
 
-    let source_address = source_address
-        .parse::<T>()
-        .map_err(ParseError::InvalidSourceAddress)?;
-    let destination_address = destination_address
-        .parse::<T>()
-        .map_err(ParseError::InvalidDestinationAddress)?;
+    while let Some(field) = fields.next() {
+        count += 1;
 
-    if source_port.starts_with(ZERO) && source_port != ZERO {
-        return Err(ParseError::InvalidSourcePort(None));
+        if field.is_empty() && (terminated || fields.peek().is_some()) {
+            return Err(ParseError::EmptyField);
+        } else if count > 4 {
+            return Err(ParseError::ExtraFields);
+        }
     }
 
-    let source_port = source_port
-        .parse::<u16>()
-        .map_err(|e| ParseError::InvalidSourcePort(Some(e)))?;
+    Ok(())
+}
 
-    if destination_port.starts_with(ZERO) && destination_port != ZERO {
-        return Err(ParseError::InvalidDestinationPort(None));
-    }
+/// Parses the addresses and ports from a PROXY protocol header for IPv4 and IPv6.
+///
+/// All four fields are scanned before any is reported as invalid,
+/// so a missing field takes precedence over an invalid one.
+fn parse_addresses<D, O>(
+    scanner: &mut Scanner<'_>,
+    observer: &mut O,
+) -> Result<(D::Output, D::Output, u16, u16), ParseError>
+where
+    D: Decode<Error = ()>,
+    D::Output: FromStr<Err = AddrParseError>,
+    O: Observer,
+{
+    let source_address = scanner.decode::<D>();
+    let destination_address = scanner.decode::<D>();
+    let source_port = scanner.decode::<Port>();
+    let destination_port = scanner.decode::<Port>();
+
+    let (source_address, destination_address, source_port, destination_port) = match (
+        source_address,
+        destination_address,
+        source_port,
+        destination_port,
+    ) {
+        (Some(a), Some(b), Some(c), Some(d)) => (a, b, c, d),
+        (source_address, destination_address, source_port, _) => {
+            let (field, error) = match (&source_address, &destination_address, &source_port) {
+                (None, ..) => ("source_address", ParseError::MissingSourceAddress),
+                (_, None, _) => ("destination_address", ParseError::MissingDestinationAddress),
+                (.., None) => ("source_port", ParseError::MissingSourcePort),
+                _ => ("destination_port", ParseError::MissingDestinationPort),
+            };
+
+            // The fields before the missing one were read, but none of them is rejected.
+            scanned(observer, "source_address", source_address);
+            scanned(observer, "destination_address", destination_address);
+            scanned(observer, "source_port", source_port);
+
+            return Err(reject(observer, scanner.position(), field, "", error));
+        }
+    };
 
-    let destination_port = destination_port
-        .parse::<u16>()
-        .map_err(|e| ParseError::InvalidDestinationPort(Some(e)))?;
+    let source_address = check(observer, "source_address", source_address, |()| {
+        ParseError::InvalidSourceAddress(address_error::<D::Output>())
+    })?;
+    let destination_address = check(observer, "destination_address", destination_address, |()| {
+        ParseError::InvalidDestinationAddress(address_error::<D::Output>())
+    })?;
+    let source_port = check(observer, "source_port", source_port, |error| {
+        ParseError::InvalidSourcePort(port_error(error))
+    })?;
+    let destination_port = check(observer, "destination_port", destination_port, |error| {
+        ParseError::InvalidDestinationPort(port_error(error))
+    })?;
 
     Ok((
         source_address,
@@ -143,6 +281,96 @@ fn parse_addresses<'a, T: FromStr<Err = AddrParseError>, I: Iterator<Item = &'a
     ))
 }
 
+/// Reports a field the parser continued after to the observer.
+fn read<O: Observer>(observer: &mut O, offset: usize, name: &'static str, field: &str) {
+    observer.field(
+        offset,
+        field.len(),
+        name,
+        Value::Text(field),
+        Decision::Continue,
+    );
+}
+
+/// Reports the line feed that ends a header to the observer, along with the separator before it.
+fn read_terminator<O: Observer>(observer: &mut O, header: &str, offset: usize) {
+    read(
+        observer,
+        offset - 1,
+        "terminator",
+        &header[offset - 1..offset + 1],
+    );
+}
+
+/// Reports a field that was scanned, if there is one, to the observer.
+fn scanned<T, E, O: Observer>(
+    observer: &mut O,
+    name: &'static str,
+    decoded: Option<Decoded<'_, T, E>>,
+) {
+    if let Some(decoded) = decoded {
+        read(observer, decoded.offset, name, decoded.field);
+    }
+}
+
+/// Reports the field the parser failed at to the observer, returning the error.
+fn reject<O: Observer>(
+    observer: &mut O,
+    offset: usize,
+    name: &'static str,
+    field: &str,
+    error: ParseError,
+) -> ParseError {
+    let decision = Decision::failure(&error, error.code());
+
+    observer.field(offset, field.len(), name, Value::Text(field), decision);
+    error
+}
+
+/// Reports a decoded field to the observer, returning its value or the error for it.
+fn check<T, E, O: Observer>(
+    observer: &mut O,
+    name: &'static str,
+    decoded: Decoded<'_, T, E>,
+    error: impl FnOnce(E) -> ParseError,
+) -> Result<T, ParseError> {
+    match decoded.value {
+        Ok(value) => {
+            read(observer, decoded.offset, name, decoded.field);
+            Ok(value)
+        }
+        Err(e) => Err(reject(
+            observer,
+            decoded.offset,
+            name,
+            decoded.field,
+            error(e),
+        )),
+    }
+}
+
+/// The error for an invalid address of type `T`.
+/// The standard library has no constructor for `AddrParseError`, so one is made by parsing an empty string.
+fn address_error<T: FromStr<Err = AddrParseError>>() -> AddrParseError {
+    match "".parse::<T>() {
+        Err(error) => error,
+        Ok(_) => unreachable!("an empty string is not an address"),
+    }
+}
+
+/// The error for an invalid port, which is `None` for ports with leading zeros.
+/// The standard library has no constructor for `ParseIntError`, so one is made by parsing an input with the same problem.
+fn port_error(error: PortError) -> Option<ParseIntError> {
+    let example = match error {
+        PortError::Number(NumberError::LeadingZero) => return None,
+        PortError::Number(NumberError::Empty) => "",
+        PortError::Number(NumberError::Overflow) => "65536",
+        PortError::InvalidDigit => "+",
+    };
+
+    example.parse::<u16>().err()
+}
+
 impl<'a> TryFrom<&'a str> for Header<'a> {
     type Error = ParseError;
 
@@ -152,8 +380,15 @@ impl<'a> TryFrom<&'a str> for Header<'a> {
             None if input.len() >= MAX_LENGTH => return Err(ParseError::HeaderTooLong),
             None => input.len(),
         };
+        // The input may end right after the carriage return, or continue with a multi-byte character,
+        // in which case the carriage return is not followed by the line feed.
+        let header = match input.get(..length) {
+            Some(header) => header,
+            None if input.len() < length => input,
+            None => return Err(ParseError::ControlCharacter),
+        };
 
-        parse_header(&input[..length])
+        parse_header(header, Strictness::Normal, &mut ())
     }
 }
 
@@ -161,14 +396,116 @@ impl<'a> TryFrom<&'a [u8]> for Header<'a> {
     type Error = BinaryParseError;
 
     fn try_from(input: &'a [u8]) -> Result<Self, Self::Error> {
-        let length = match input.iter().position(|&c| CARRIAGE_RETURN == (c as char)) {
-            Some(suffix) => suffix + PROTOCOL_SUFFIX.len(),
-            None if input.len() >= MAX_LENGTH => return Err(ParseError::HeaderTooLong.into()),
-            None => input.len(),
-        };
-        let header = from_utf8(&input[..length])?;
+        parse_bytes(input, Strictness::Normal, &mut ())
+    }
+}
 
-        parse_header(header).map_err(BinaryParseError::Parse)
+/// Parses a text PROXY protocol header from the start of some bytes like `try_from`,
+/// reporting each field to the observer as it is decided on.
+// Only traces, which require both versions, observe the parser.
+#[cfg(feature = "v2")]
+pub(crate) fn parse_observed<'a, O: Observer>(
+    input: &'a [u8],
+    observer: &mut O,
+) -> Result<Header<'a>, BinaryParseError> {
+    parse_bytes(input, Strictness::Normal, observer)
+}
+
+/// Parses a text PROXY protocol header from the start of some bytes.
+fn parse_bytes<'a, O: Observer>(
+    input: &'a [u8],
+    strictness: Strictness,
+    observer: &mut O,
+) -> Result<Header<'a>, BinaryParseError> {
+    let length = match input.iter().position(|&c| CARRIAGE_RETURN == (c as char)) {
+        Some(suffix) => suffix + PROTOCOL_SUFFIX.len(),
+        None if input.len() >= MAX_LENGTH => return Err(ParseError::HeaderTooLong.into()),
+        None => input.len(),
+    };
+    // The input may end right after the carriage return.
+    let header = from_utf8(&input[..length.min(input.len())])?;
+
+    parse_header(header, strictness, observer).map_err(BinaryParseError::Parse)
+}
+
+impl<'a> Header<'a> {
+    /// Parses a text PROXY protocol header from the start of some bytes like `try_from`,
+    /// but rejects TCP4 and TCP6 headers with more than the four address and port fields (`ParseError::ExtraFields`)
+    /// or with consecutive separators (`ParseError::EmptyField`), which usually indicate a bug in the sender.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v1::{Header, ParseError};
+    ///
+    /// let valid = b"PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n";
+    /// let extra = b"PROXY TCP4 127.0.0.1 127.0.0.2 80 443 8080\r\n";
+    ///
+    /// assert!(Header::parse_strict(valid).is_ok());
+    /// assert_eq!(Header::parse_strict(extra), Err(ParseError::ExtraFields.into()));
+    /// ```
+    pub fn parse_strict(input: &'a [u8]) -> Result<Self, BinaryParseError> {
+        parse_bytes(input, Strictness::Strict, &mut ())
+    }
+
+    /// Parses a text PROXY protocol header from the start of some bytes like `try_from`,
+    /// but accepts the protocol in any case (e.g. `tcp4` or `Tcp6`), as sent by some senders that are not compliant.
+    /// The header keeps the bytes as sent.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v1::{Addresses, Header, ParseError};
+    ///
+    /// let lowercase = b"PROXY tcp4 127.0.0.1 127.0.0.2 80 443\r\n";
+    ///
+    /// assert_eq!(Header::try_from(&lowercase[..]), Err(ParseError::InvalidProtocol.into()));
+    /// assert!(matches!(Header::parse_lenient(lowercase).unwrap().addresses, Addresses::Tcp4(..)));
+    /// ```
+    pub fn parse_lenient(input: &'a [u8]) -> Result<Self, BinaryParseError> {
+        parse_bytes(input, Strictness::Lenient, &mut ())
+    }
+}
+
+impl Addresses {
+    /// Parses the addresses from the fields of a header between the prefix and the terminating `\r\n`
+    /// (e.g. `TCP4 127.0.0.1 127.0.0.2 80 443`), as stored in configurations or logs,
+    /// with the same validation as parsing a whole header.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v1::{Addresses, ParseError};
+    /// use std::net::Ipv4Addr;
+    ///
+    /// assert_eq!(
+    ///     Addresses::parse_fields("TCP4 127.0.0.1 127.0.0.2 80 443"),
+    ///     Ok(Addresses::new_tcp4(Ipv4Addr::new(127, 0, 0, 1), Ipv4Addr::new(127, 0, 0, 2), 80, 443))
+    /// );
+    /// assert_eq!(Addresses::parse_fields("UNKNOWN"), Ok(Addresses::Unknown));
+    /// assert_eq!(Addresses::parse_fields("TCP4 127.0.0.1 127.0.0.2 80 443\r\n"), Err(ParseError::ControlCharacter));
+    /// assert!(Addresses::parse_fields("TCP4 127.0.0.1 localhost 80 443").is_err());
+    /// ```
+    pub fn parse_fields(fields: &str) -> Result<Self, ParseError> {
+        // A line feed or carriage return in the fields would end the header early.
+        if fields.bytes().any(|byte| byte.is_ascii_control()) {
+            return Err(ParseError::ControlCharacter);
+        }
+
+        let mut buffer = [0; MAX_LENGTH];
+        let mut length = 0;
+
+        for part in [PROTOCOL_PREFIX, " ", fields, PROTOCOL_SUFFIX] {
+            let end = length + part.len();
+
+            buffer
+                .get_mut(length..end)
+                .ok_or(ParseError::HeaderTooLong)?
+                .copy_from_slice(part.as_bytes());
+            length = end;
+        }
+
+        match from_utf8(&buffer[..length]) {
+            Ok(header) => Ok(parse_header(header, Strictness::Normal, &mut ())?.addresses),
+            Err(_) => unreachable!("the header is made of strings"),
+        }
     }
 }
 
@@ -191,6 +528,7 @@ impl FromStr for Header<'static> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::net::{Ipv4Addr, Ipv6Addr};
 
     #[test]
     fn bytes_invalid_utf8() {
@@ -202,6 +540,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_fields() {
+        let ip: Ipv6Addr = "2001:db8::1".parse().unwrap();
+
+        assert_eq!(
+            Addresses::parse_fields("TCP6 2001:db8::1 2001:db8::1 65535 65535"),
+            Ok(Addresses::new_tcp6(ip, ip, 65535, 65535))
+        );
+        assert_eq!(
+            Addresses::parse_fields("TCP4 127.0.0.1 127.0.0.2 80 443\r\n"),
+            Err(ParseError::ControlCharacter)
+        );
+        assert_eq!(
+            Addresses::parse_fields("PROXY TCP4 127.0.0.1 127.0.0.2 80 443"),
+            Err(ParseError::InvalidProtocol)
+        );
+        assert_eq!(
+            Addresses::parse_fields(""),
+            Err(ParseError::InvalidProtocol)
+        );
+        assert_eq!(
+            Addresses::parse_fields(&"TCP6 ".repeat(20)),
+            Err(ParseError::HeaderTooLong)
+        );
+    }
+
+    #[test]
+    fn socket_addrs() {
+        let header = Header::try_from("PROXY TCP6 ::1 2001:db8::2 65535 1\r\n").unwrap();
+
+        assert_eq!(header.source_socket_addr(), "[::1]:65535".parse().ok());
+        assert_eq!(
+            header.destination_socket_addr(),
+            "[2001:db8::2]:1".parse().ok()
+        );
+
+        let unknown = Header::try_from("PROXY UNKNOWN 127.0.0.1 127.0.0.2 80 443\r\n").unwrap();
+
+        assert_eq!(unknown.source_socket_addr(), None);
+        assert_eq!(unknown.destination_socket_addr(), None);
+    }
+
     #[test]
     fn exact_tcp4() {
         let ip: Ipv4Addr = "255.255.255.255".parse().unwrap();
@@ -213,6 +593,39 @@ mod tests {
         assert_eq!(Header::try_from(text.as_bytes()), Ok(expected));
     }
 
+    #[test]
+    fn as_ref_str() {
+        let text = "PROXY UNKNOWN\r\n";
+        let header = Header::try_from(text).unwrap();
+
+        assert_eq!(AsRef::<str>::as_ref(&header), text);
+    }
+
+    #[test]
+    fn reserialize_unchanged() {
+        let texts = [
+            "PROXY UNKNOWN 1.2.3.4 hello\r\n",
+            "PROXY TCP6 0::1 ::ffff:1.2.3.4 80 443\r\n",
+            "PROXY TCP4 127.0.0.1 127.0.0.2 80 443\r\n",
+        ];
+
+        for text in texts {
+            let header = Header::try_from(text).unwrap();
+
+            assert_eq!(header.to_string(), text);
+            assert_eq!(header.to_owned().to_string(), text);
+        }
+
+        assert_eq!(
+            Header::try_from(texts[0]).unwrap().addresses.to_string(),
+            "PROXY UNKNOWN\r\n"
+        );
+        assert_eq!(
+            Header::try_from(texts[1]).unwrap().addresses.to_string(),
+            "PROXY TCP6 ::1 ::ffff:1.2.3.4 80 443\r\n"
+        );
+    }
+
     #[test]
     fn valid_tcp4() {
         let ip: Ipv4Addr = "255.255.255.255".parse().unwrap();
@@ -273,6 +686,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_tcp4_signed_port() {
+        let text = "PROXY TCP4 255.255.255.255 255.255.255.255 +80 65535\r\n";
+
+        assert_eq!(
+            Header::try_from(text),
+            Err(ParseError::InvalidSourcePort(Some(
+                "+".parse::<u16>().unwrap_err()
+            )))
+        );
+        assert_eq!(
+            Header::try_from("PROXY TCP4 +1.2.3.4 255.255.255.255 80 65535\r\n"),
+            Err(ParseError::InvalidSourceAddress(
+                "".parse::<Ipv4Addr>().unwrap_err()
+            ))
+        );
+    }
+
     #[test]
     fn parse_unknown_connection() {
         let text = "PROXY UNKNOWN\r\nTwo";
@@ -503,6 +934,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_lenient_protocol_family() {
+        let ip: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let text = "PROXY Tcp6 2001:db8::1 2001:db8::1 80 443\r\n";
+
+        assert_eq!(
+            Header::parse_lenient(text.as_bytes()),
+            Ok(Header::new(text, Addresses::new_tcp6(ip, ip, 80, 443)))
+        );
+        assert_eq!(
+            Header::parse_lenient(b"PROXY unknown\r\n"),
+            Ok(Header::new("PROXY unknown\r\n", Addresses::Unknown))
+        );
+        assert_eq!(
+            Header::parse_lenient(b"PROXY tcp5\r\n"),
+            Err(ParseError::InvalidProtocol.into())
+        );
+        assert_eq!(
+            Header::parse_strict(b"PROXY tcp4 127.0.0.1 127.0.0.2 80 443\r\n"),
+            Err(ParseError::InvalidProtocol.into())
+        );
+    }
+
     #[test]
     fn parse_too_long() {
         let text = "PROXY UNKNOWN ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff 65535 65535  \r\n";
@@ -598,6 +1052,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn strict_fields() {
+        let valid = "PROXY TCP6 ::1 ::2 80 443\r\n";
+        let invalid = [
+            (
+                "PROXY TCP4 127.0.0.1 127.0.0.2 80 443 8080\r\n",
+                ParseError::ExtraFields,
+            ),
+            (
+                "PROXY TCP4 127.0.0.1 127.0.0.2 80 443 \r\n",
+                ParseError::EmptyField,
+            ),
+            (
+                "PROXY TCP4 127.0.0.1  127.0.0.2 80 443\r\n",
+                ParseError::EmptyField,
+            ),
+            ("PROXY TCP6 ::1 ::2 80  443\r\n", ParseError::EmptyField),
+        ];
+
+        assert!(Header::parse_strict(valid.as_bytes()).is_ok());
+        assert_eq!(
+            Header::parse_strict(b"PROXY TCP4 127.0.0.1 "),
+            Header::try_from(&b"PROXY TCP4 127.0.0.1 "[..])
+        );
+        assert_eq!(
+            Header::parse_strict(b"PROXY UNKNOWN a  b c d e\r\n"),
+            Header::try_from(&b"PROXY UNKNOWN a  b c d e\r\n"[..])
+        );
+
+        for (text, error) in invalid {
+            assert_eq!(
+                Header::parse_strict(text.as_bytes()),
+                Err(error.into()),
+                "{}",
+                text
+            );
+            assert!(Header::try_from(text).is_err());
+        }
+    }
+
+    #[test]
+    fn control_characters() {
+        for text in [
+            "PROXY UNKNOWN \0\r\n",
+            "PROXY UNKNOWN a\nb\r\n",
+            "PROXY TCP4 127.0.0.1 127.0.0.2\t80 443\r\n",
+            "PROXY TCP4 \x7F",
+            "PROXY TCP4 127.0.0.1\r127.0.0.2 80 443\r\n",
+            "PROXY UNKNOWN a\rb\r\n",
+        ] {
+            assert_eq!(
+                Header::try_from(text),
+                Err(ParseError::ControlCharacter),
+                "{:?}",
+                text
+            );
+            assert_eq!(
+                Header::parse_strict(text.as_bytes()),
+                Err(ParseError::ControlCharacter.into())
+            );
+        }
+    }
+
+    #[test]
+    fn parse_trailing_carriage_return() {
+        let text = "PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r";
+
+        assert_eq!(Header::try_from(text), Err(ParseError::MissingNewLine));
+        assert_eq!(
+            Header::try_from(text.as_bytes()),
+            Err(ParseError::MissingNewLine.into())
+        );
+        assert_eq!(
+            Header::try_from("PROXY UNKNOWN\r\u{e9}"),
+            Err(ParseError::ControlCharacter)
+        );
+    }
+
     #[test]
     fn parse_partial_prefix() {
         let text = "PROX\r\n";
@@ -679,10 +1211,10 @@ mod tests {
     fn parse_no_new_line() {
         let text = "PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\t";
 
-        assert_eq!(Header::try_from(text), Err(ParseError::InvalidSuffix));
+        assert_eq!(Header::try_from(text), Err(ParseError::ControlCharacter));
         assert_eq!(
             Header::try_from(text.as_bytes()),
-            Err(ParseError::InvalidSuffix.into())
+            Err(ParseError::ControlCharacter.into())
         );
     }
 
@@ -1,10 +1,10 @@
 //! The data model to represent the test PROXY protocol header.
-use std::prelude::v1::*;
+use crate::prelude::*;
 
-use crate::ip::{IPv4, IPv6};
-use std::borrow::Cow;
-use std::fmt;
-use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use crate::ip::{self, IPv4, IPv6};
+use crate::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use alloc::borrow::Cow;
+use core::fmt;
 
 /// The prefix of the PROXY protocol header.
 pub const PROTOCOL_PREFIX: &str = "PROXY";
@@ -25,6 +25,9 @@ pub const UNKNOWN: &str = "UNKNOWN";
 pub const SEPARATOR: char = ' ';
 
 /// A text PROXY protocol header that borrows the input string.
+/// Formatting a `Header` writes the input unchanged, including the original spelling of the addresses
+/// and any text after `UNKNOWN`, so a parsed header can be relayed as-is.
+/// Formatting its `Addresses` writes their canonical encoding instead.
 ///
 /// ## Examples
 /// ### Worst Case (from bytes)
@@ -95,6 +98,8 @@ pub const SEPARATOR: char = ' ';
 /// assert_eq!(Err(ParseError::InvalidProtocol), "PROXY tcp4\r\n".parse::<Addresses>());
 /// ```
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Header<'a> {
     pub header: Cow<'a, str>,
     pub addresses: Addresses,
@@ -122,11 +127,54 @@ impl<'a> Header<'a> {
         self.addresses.protocol()
     }
 
+    /// The address of the original client, or `None` for an `UNKNOWN` header.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v1::Header;
+    ///
+    /// let header = Header::try_from("PROXY TCP4 127.0.0.1 192.168.1.1 80 443\r\n").unwrap();
+    ///
+    /// assert_eq!(header.source_socket_addr(), Some("127.0.0.1:80".parse().unwrap()));
+    /// assert_eq!(header.destination_socket_addr(), Some("192.168.1.1:443".parse().unwrap()));
+    /// assert_eq!(Header::try_from("PROXY UNKNOWN\r\n").unwrap().source_socket_addr(), None);
+    /// ```
+    pub fn source_socket_addr(&self) -> Option<SocketAddr> {
+        match self.addresses {
+            Addresses::Tcp4(addresses) => Some(SocketAddr::from((
+                addresses.source_address,
+                addresses.source_port,
+            ))),
+            Addresses::Tcp6(addresses) => Some(SocketAddr::from((
+                addresses.source_address,
+                addresses.source_port,
+            ))),
+            Addresses::Unknown => None,
+        }
+    }
+
+    /// The address the client originally connected to, or `None` for an `UNKNOWN` header.
+    pub fn destination_socket_addr(&self) -> Option<SocketAddr> {
+        match self.addresses {
+            Addresses::Tcp4(addresses) => Some(SocketAddr::from((
+                addresses.destination_address,
+                addresses.destination_port,
+            ))),
+            Addresses::Tcp6(addresses) => Some(SocketAddr::from((
+                addresses.destination_address,
+                addresses.destination_port,
+            ))),
+            Addresses::Unknown => None,
+        }
+    }
+
     /// The source and destination addresses portion of this `Header`.
+    /// Empty when the underlying string is not a header with the protocol of the addresses,
+    /// which can only happen for a `Header` that was not parsed.
     pub fn addresses_str(&self) -> &str {
         let start = PROTOCOL_PREFIX.len() + SEPARATOR.len_utf8() + self.protocol().len();
-        let end = self.header.len() - PROTOCOL_SUFFIX.len();
-        let addresses = &self.header[start..end];
+        let end = self.header.len().saturating_sub(PROTOCOL_SUFFIX.len());
+        let addresses = self.header.get(start..end).unwrap_or_default();
 
         if addresses.starts_with(SEPARATOR) {
             &addresses[SEPARATOR.len_utf8()..]
@@ -198,6 +246,8 @@ impl<'a> Header<'a> {
 /// assert_eq!(Err(ParseError::InvalidProtocol), "PROXY tcp4\r\n".parse::<Addresses>());
 /// ```
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Addresses {
     Unknown,
     Tcp4(IPv4),
@@ -235,6 +285,35 @@ impl Addresses {
         })
     }
 
+    /// Converts `Tcp4` addresses to `Tcp6` with IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`),
+    /// for backends that handle every connection as IPv6. Other addresses are returned unchanged.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v1::Addresses;
+    ///
+    /// let addresses: Addresses = "PROXY TCP4 192.0.2.1 10.0.0.1 51234 443\r\n".parse().unwrap();
+    /// let mapped = addresses.to_ipv6_mapped();
+    ///
+    /// assert_eq!(mapped.to_string(), "PROXY TCP6 ::ffff:192.0.2.1 ::ffff:10.0.0.1 51234 443\r\n");
+    /// assert_eq!(mapped.to_canonical(), addresses);
+    /// ```
+    pub fn to_ipv6_mapped(&self) -> Self {
+        match self {
+            Addresses::Tcp4(a) => Addresses::Tcp6(a.to_ipv6_mapped()),
+            addresses => *addresses,
+        }
+    }
+
+    /// Converts `Tcp6` addresses that are both IPv4-mapped back to `Tcp4`,
+    /// for ACLs that only list IPv4 networks. Other addresses are returned unchanged.
+    pub fn to_canonical(&self) -> Self {
+        match self {
+            Addresses::Tcp6(a) => a.to_ipv4_mapped().map_or(*self, Addresses::Tcp4),
+            addresses => *addresses,
+        }
+    }
+
     /// The protocol portion of this `Addresses`.
     pub fn protocol(&self) -> &str {
         match self {
@@ -243,6 +322,43 @@ impl Addresses {
             Addresses::Unknown => UNKNOWN,
         }
     }
+
+    /// The source IP address, if any.
+    fn source_ip(&self) -> Option<IpAddr> {
+        match self {
+            Addresses::Tcp4(a) => Some(a.source_address.into()),
+            Addresses::Tcp6(a) => Some(a.source_address.into()),
+            Addresses::Unknown => None,
+        }
+    }
+
+    /// Tests whether the source address is in a private range (RFC 1918 for IPv4, `fc00::/7` for IPv6).
+    /// Always false for `Addresses::Unknown`.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v1::Addresses;
+    ///
+    /// let addresses = Addresses::new_tcp4([10, 0, 0, 1], [192, 0, 2, 1], 80, 443);
+    ///
+    /// assert!(addresses.is_source_private());
+    /// assert!(!addresses.is_source_loopback());
+    /// assert!(!addresses.is_source_global());
+    /// ```
+    pub fn is_source_private(&self) -> bool {
+        self.source_ip().map_or(false, ip::is_private)
+    }
+
+    /// Tests whether the source address is a loopback address. Always false for `Addresses::Unknown`.
+    pub fn is_source_loopback(&self) -> bool {
+        self.source_ip().map_or(false, ip::is_loopback)
+    }
+
+    /// Tests whether the source address may be reached on the public internet, i.e. it is not private,
+    /// loopback, link-local, multicast, or in another special-purpose range. Always false for `Addresses::Unknown`.
+    pub fn is_source_global(&self) -> bool {
+        self.source_ip().map_or(false, ip::is_global)
+    }
 }
 
 impl Default for Addresses {
@@ -289,6 +405,12 @@ impl<'a> fmt::Display for Header<'a> {
     }
 }
 
+impl<'a> AsRef<str> for Header<'a> {
+    fn as_ref(&self) -> &str {
+        self.header.as_ref()
+    }
+}
+
 impl fmt::Display for Addresses {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -306,3 +428,14 @@ impl fmt::Display for Addresses {
         }
     }
 }
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Addresses {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Unknown => defmt::write!(f, "UNKNOWN"),
+            Self::Tcp4(a) => defmt::write!(f, "TCP4 {}", a),
+            Self::Tcp6(a) => defmt::write!(f, "TCP6 {}", a),
+        }
+    }
+}
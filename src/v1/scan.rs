@@ -0,0 +1,264 @@
+//! A single forward scan over the fields of a text header, decoding addresses and ports as their bytes are read.
+use crate::net::{Ipv4Addr, Ipv6Addr};
+use crate::parse::{self, Mode, NumberError};
+
+/// Tests whether the byte separates two fields of a header.
+fn is_separator(byte: u8) -> bool {
+    byte == b' ' || byte == b'\r'
+}
+
+/// Splits a header into at most a given number of fields separated by spaces or carriage returns,
+/// like `str::splitn`. The last field is the rest of the header.
+#[derive(Debug)]
+pub(super) struct Scanner<'a> {
+    header: &'a str,
+    position: usize,
+    fields: usize,
+}
+
+/// A field of a header, along with the value a decoder read from it.
+#[derive(Debug)]
+pub(super) struct Decoded<'a, T, E> {
+    /// The offset of the field in the header.
+    pub(super) offset: usize,
+    pub(super) field: &'a str,
+    pub(super) value: Result<T, E>,
+}
+
+impl<'a> Scanner<'a> {
+    /// Creates a new `Scanner` over at most the given number of fields of the header.
+    pub(super) fn new(header: &'a str, fields: usize) -> Self {
+        Scanner {
+            header,
+            position: 0,
+            fields,
+        }
+    }
+
+    /// The offset in the header of the next field.
+    pub(super) fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Tests whether all of the fields have been read.
+    pub(super) fn is_done(&self) -> bool {
+        self.fields == 0
+    }
+
+    /// Reads the next field.
+    pub(super) fn field(&mut self) -> Option<&'a str> {
+        self.decode::<Skip>().map(|decoded| decoded.field)
+    }
+
+    /// Reads the next field, decoding it in the same pass that looks for its end.
+    pub(super) fn decode<D: Decode>(&mut self) -> Option<Decoded<'a, D::Output, D::Error>> {
+        if self.fields == 0 {
+            return None;
+        }
+
+        let start = self.position;
+        let rest = &self.header.as_bytes()[start..];
+
+        self.fields -= 1;
+
+        // The last field is the rest of the header, separators included.
+        let (length, value) = if self.fields == 0 {
+            (rest.len(), D::decode(rest))
+        } else {
+            D::scan(rest)
+        };
+
+        if length < rest.len() {
+            self.position = start + length + 1;
+        } else {
+            self.position = start + length;
+            self.fields = 0;
+        }
+
+        Some(Decoded {
+            offset: start,
+            field: &self.header[start..start + length],
+            value,
+        })
+    }
+}
+
+/// The length of the field at the start of the bytes, searching for its end from the given index.
+fn skip(bytes: &[u8], from: usize) -> usize {
+    bytes[from..]
+        .iter()
+        .position(|&byte| is_separator(byte))
+        .map_or(bytes.len(), |offset| from + offset)
+}
+
+/// Decodes a value from the field at the start of some bytes.
+pub(super) trait Decode {
+    type Output;
+    type Error;
+
+    /// Reads a value from the start of the bytes, returning how many bytes were read.
+    fn read(bytes: &[u8]) -> (usize, Result<Self::Output, Self::Error>);
+
+    /// The error for a field with bytes after its value.
+    fn trailing() -> Self::Error;
+
+    /// Scans the field at the start of the bytes, returning its length and value.
+    fn scan(bytes: &[u8]) -> (usize, Result<Self::Output, Self::Error>) {
+        match Self::read(bytes) {
+            (length, Ok(value)) => match bytes.get(length) {
+                Some(&byte) if !is_separator(byte) => (skip(bytes, length), Err(Self::trailing())),
+                _ => (length, Ok(value)),
+            },
+            (length, Err(error)) => match bytes.get(length) {
+                Some(&byte) if !is_separator(byte) => (skip(bytes, length), Err(Self::trailing())),
+                _ => (length, Err(error)),
+            },
+        }
+    }
+
+    /// Decodes all of the bytes as a single field.
+    fn decode(bytes: &[u8]) -> Result<Self::Output, Self::Error> {
+        match Self::read(bytes) {
+            (length, Ok(_)) if length < bytes.len() => Err(Self::trailing()),
+            (_, value) => value,
+        }
+    }
+}
+
+/// A decoder that only finds the end of the field.
+struct Skip;
+
+impl Decode for Skip {
+    type Output = ();
+    type Error = ();
+
+    fn read(_: &[u8]) -> (usize, Result<(), ()>) {
+        (0, Ok(()))
+    }
+
+    fn trailing() {}
+}
+
+/// Why a port could not be decoded.
+#[derive(Debug, PartialEq)]
+pub(super) enum PortError {
+    Number(NumberError),
+    InvalidDigit,
+}
+
+/// Decodes a port in decimal, without leading zeros.
+pub(super) struct Port;
+
+impl Decode for Port {
+    type Output = u16;
+    type Error = PortError;
+
+    fn read(bytes: &[u8]) -> (usize, Result<u16, PortError>) {
+        let (length, value) = parse::u16(bytes, Mode::Strict);
+
+        (length, value.map_err(PortError::Number))
+    }
+
+    fn trailing() -> PortError {
+        PortError::InvalidDigit
+    }
+}
+
+/// Decodes an IPv4 address in dotted decimal, without leading zeros.
+pub(super) struct V4;
+
+impl Decode for V4 {
+    type Output = Ipv4Addr;
+    type Error = ();
+
+    fn read(bytes: &[u8]) -> (usize, Result<Ipv4Addr, ()>) {
+        let (length, address) = parse::ipv4(bytes, Mode::Strict);
+
+        (length, address.ok_or(()))
+    }
+
+    fn trailing() {}
+}
+
+/// Decodes an IPv6 address, whose optional trailing IPv4 address has no leading zeros.
+pub(super) struct V6;
+
+impl Decode for V6 {
+    type Output = Ipv6Addr;
+    type Error = ();
+
+    fn read(bytes: &[u8]) -> (usize, Result<Ipv6Addr, ()>) {
+        let (length, address) = parse::ipv6(bytes, Mode::Strict);
+
+        (length, address.ok_or(()))
+    }
+
+    fn trailing() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let mut scanner = Scanner::new("PROXY TCP4 a  b\r\nc d", 4);
+
+        assert_eq!(scanner.field(), Some("PROXY"));
+        assert_eq!(scanner.field(), Some("TCP4"));
+        assert_eq!(scanner.field(), Some("a"));
+        assert!(!scanner.is_done());
+        assert_eq!(scanner.field(), Some(" b\r\nc d"));
+        assert!(scanner.is_done());
+        assert_eq!(scanner.field(), None);
+
+        let mut scanner = Scanner::new("PROXY ", 7);
+
+        assert_eq!(scanner.field(), Some("PROXY"));
+        assert_eq!(scanner.field(), Some(""));
+        assert!(scanner.is_done());
+    }
+
+    #[test]
+    fn decoders() {
+        let mut scanner = Scanner::new("80 +80 080 65536 8a 127.0.0.1 1.2.3.4x ::1 ::1 2", 9);
+
+        assert_eq!(scanner.decode::<Port>().unwrap().value, Ok(80));
+        assert_eq!(
+            scanner.decode::<Port>().unwrap().value,
+            Err(PortError::InvalidDigit)
+        );
+        assert_eq!(
+            scanner.decode::<Port>().unwrap().value,
+            Err(PortError::Number(NumberError::LeadingZero))
+        );
+        assert_eq!(
+            scanner.decode::<Port>().unwrap().value,
+            Err(PortError::Number(NumberError::Overflow))
+        );
+
+        let decoded = scanner.decode::<Port>().unwrap();
+        assert_eq!(
+            (decoded.field, decoded.value),
+            ("8a", Err(PortError::InvalidDigit))
+        );
+
+        assert_eq!(
+            scanner.decode::<V4>().unwrap().value,
+            Ok(Ipv4Addr::new(127, 0, 0, 1))
+        );
+
+        let decoded = scanner.decode::<V4>().unwrap();
+        assert_eq!((decoded.field, decoded.value), ("1.2.3.4x", Err(())));
+
+        assert_eq!(
+            scanner.decode::<V6>().unwrap().value,
+            Ok(Ipv6Addr::LOCALHOST)
+        );
+
+        // The last field is the rest of the header.
+        let decoded = scanner.decode::<V6>().unwrap();
+        assert_eq!((decoded.field, decoded.value), ("::1 2", Err(())));
+        assert!(scanner.is_done());
+    }
+}
@@ -0,0 +1,219 @@
+//! Attestation TLVs carrying an SGX DCAP quote, so a header can prove the identity of the enclave proxy that sent it.
+//!
+//! The quote is generated by the sender's quoting enclave (e.g. with `sgx_qe_get_quote`) and verified by the receiver
+//! with a DCAP quote verification library. This module only carries the quote and checks that its size is consistent,
+//! which is all that can be checked without verifying its signature.
+use super::{field, Header, ParseError, TypeLengthValue};
+#[cfg(any(feature = "std", feature = "tstd"))]
+use super::{WriteToHeader, Writer};
+use alloc::borrow::Cow;
+#[cfg(any(feature = "std", feature = "tstd"))]
+use std::io;
+
+/// The custom TLV type used to carry a DCAP quote.
+pub const QUOTE: u8 = 0xE8;
+
+/// The number of bytes of the quote header.
+const QUOTE_HEADER_LENGTH: usize = 48;
+/// The number of bytes of the SGX report body that follows the quote header.
+const REPORT_BODY_LENGTH: usize = 384;
+/// The offset of the 64 bytes of report data within the report body.
+const REPORT_DATA_OFFSET: usize = 320;
+/// The length in bytes of the signature data length, which follows the report body.
+const SIGNATURE_LENGTH_LENGTH: usize = 4;
+/// The number of bytes of the smallest quote, with empty signature data.
+const MINIMUM_QUOTE_LENGTH: usize =
+    QUOTE_HEADER_LENGTH + REPORT_BODY_LENGTH + SIGNATURE_LENGTH_LENGTH;
+
+/// An error in reading the quote carried by a header.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum QuoteError {
+    #[error("Quote TLV of {0} bytes is not a quote of a consistent size.")]
+    Invalid(usize),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for QuoteError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Invalid(length) => defmt::write!(f, "Invalid({=usize})", length),
+            Self::Parse(error) => defmt::write!(f, "Parse({})", error),
+        }
+    }
+}
+
+/// An SGX DCAP quote, as carried in a quote TLV.
+///
+/// A `Quote` only guarantees that the quote is made of a header, a report body and signature data of the
+/// length given in the quote; the signature must still be verified before the quote is trusted.
+/// Senders bind the quote to the connection by choosing its report data, e.g. a hash of the header's addresses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quote<'a> {
+    bytes: Cow<'a, [u8]>,
+}
+
+impl<'a> Quote<'a> {
+    /// Creates a new `Quote` from the bytes returned by the quoting enclave.
+    /// Returns an error if the bytes are not a quote of a consistent size.
+    pub fn new<T: Into<Cow<'a, [u8]>>>(bytes: T) -> Result<Self, QuoteError> {
+        let bytes = bytes.into();
+        let invalid = || QuoteError::Invalid(bytes.len());
+
+        if bytes.len() < MINIMUM_QUOTE_LENGTH {
+            return Err(invalid());
+        }
+
+        let signature_length = u32::from_le_bytes(field::array(
+            &bytes,
+            QUOTE_HEADER_LENGTH + REPORT_BODY_LENGTH,
+        ));
+
+        if bytes.len() - MINIMUM_QUOTE_LENGTH != signature_length as usize {
+            return Err(invalid());
+        }
+
+        Ok(Quote { bytes })
+    }
+
+    /// The version of the quote format (e.g. 3 for SGX DCAP quotes).
+    pub fn version(&self) -> u16 {
+        u16::from_le_bytes(field::array(&self.bytes, 0))
+    }
+
+    /// The 64 bytes of report data chosen by the enclave that requested the quote.
+    pub fn report_data(&self) -> &[u8] {
+        let start = QUOTE_HEADER_LENGTH + REPORT_DATA_OFFSET;
+
+        &self.bytes[start..start + 64]
+    }
+
+    /// The signature data of the quote, including the certification data needed to verify it.
+    pub fn signature_data(&self) -> &[u8] {
+        &self.bytes[MINIMUM_QUOTE_LENGTH..]
+    }
+
+    /// The bytes of the whole quote.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Creates an owned clone of this `Quote`.
+    pub fn to_owned(&self) -> Quote<'static> {
+        Quote {
+            bytes: Cow::Owned(self.bytes.to_vec()),
+        }
+    }
+}
+
+impl<'a> TryFrom<&TypeLengthValue<'a>> for Quote<'a> {
+    type Error = QuoteError;
+
+    fn try_from(tlv: &TypeLengthValue<'a>) -> Result<Self, Self::Error> {
+        if tlv.kind != QUOTE {
+            return Err(QuoteError::Invalid(tlv.len()));
+        }
+
+        Quote::new(tlv.value.clone())
+    }
+}
+
+#[cfg(any(feature = "std", feature = "tstd"))]
+impl<'a> WriteToHeader for Quote<'a> {
+    fn write_to(&self, writer: &mut Writer) -> io::Result<usize> {
+        TypeLengthValue::new(QUOTE, self.as_bytes()).write_to(writer)
+    }
+}
+
+impl<'a> Header<'a> {
+    /// The quote carried by this `Header`, if any.
+    /// Returns an error if any TLV, or the first quote TLV, is invalid.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Builder, Command, Header, Protocol, Quote, Version};
+    ///
+    /// // A quote of a consistent size with empty signature data, standing in for one from the quoting enclave.
+    /// let mut bytes = vec![0; 436];
+    /// bytes[0] = 3;
+    ///
+    /// let header = Builder::with_addresses(
+    ///     Version::Two | Command::Proxy,
+    ///     Protocol::Stream,
+    ///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+    /// )
+    /// .write_payload(Quote::new(bytes).unwrap())
+    /// .unwrap()
+    /// .build()
+    /// .unwrap();
+    /// let header = Header::try_from(header.as_slice()).unwrap();
+    /// let quote = header.quote().unwrap().unwrap();
+    ///
+    /// assert_eq!(quote.version(), 3);
+    /// assert_eq!(quote.report_data(), &[0; 64][..]);
+    /// ```
+    pub fn quote(&self) -> Result<Option<Quote<'_>>, QuoteError> {
+        for tlv in self.tlvs() {
+            let tlv = tlv?;
+
+            if tlv.kind == QUOTE {
+                return Quote::try_from(&tlv).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::Builder;
+
+    /// A quote with the given signature data, and the given signature data length.
+    fn quote(signature: &[u8], length: u32) -> Vec<u8> {
+        let mut bytes = vec![0; MINIMUM_QUOTE_LENGTH];
+
+        bytes[..2].copy_from_slice(&3u16.to_le_bytes());
+        bytes[QUOTE_HEADER_LENGTH + REPORT_DATA_OFFSET] = 0xAB;
+        bytes[MINIMUM_QUOTE_LENGTH - 4..].copy_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(signature);
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = quote(b"signature", 9);
+        let header = Builder::localhost()
+            .write_payload(Quote::new(bytes.as_slice()).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let header = Header::try_from(header.as_slice()).unwrap();
+        let quote = header.quote().unwrap().unwrap();
+
+        assert_eq!(quote.as_bytes(), bytes.as_slice());
+        assert_eq!(quote.version(), 3);
+        assert_eq!(quote.report_data()[0], 0xAB);
+        assert_eq!(quote.signature_data(), b"signature");
+        assert_eq!(quote.to_owned(), quote);
+    }
+
+    #[test]
+    fn invalid_quote() {
+        assert_eq!(
+            Quote::new(quote(b"signature", 8)),
+            Err(QuoteError::Invalid(445))
+        );
+        assert_eq!(Quote::new(&[0; 435][..]), Err(QuoteError::Invalid(435)));
+
+        let tlv = TypeLengthValue::new(QUOTE, &[0; 12]);
+        assert_eq!(Quote::try_from(&tlv), Err(QuoteError::Invalid(12)));
+
+        let input = b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00";
+        let header = Header::try_from(&input[..]).unwrap();
+
+        assert_eq!(header.quote(), Ok(None));
+    }
+}
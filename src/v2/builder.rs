@@ -2,9 +2,10 @@
 use std::prelude::v1::*;
 
 use crate::v2::{
-    Addresses, Protocol, Type, TypeLengthValue, TypeLengthValues, LENGTH, MINIMUM_LENGTH,
-    MINIMUM_TLV_LENGTH, PROTOCOL_PREFIX,
+    Addresses, Command, Header, IPv4, IPv6, Protocol, Type, TypeLengthValue, TypeLengthValues,
+    Unix, Version, LENGTH, MINIMUM_LENGTH, MINIMUM_TLV_LENGTH, PROTOCOL_PREFIX,
 };
+use core::marker::PhantomData;
 use std::io::{self, Write};
 
 /// `Write` interface for the builder's internal buffer.
@@ -30,6 +31,11 @@ pub struct Writer {
 /// Implementation of the builder pattern for PROXY protocol v2 headers.
 /// Supports both valid and invalid headers via the `write_payload` and `write_payloads` functions.
 ///
+/// The type parameter is the address family of the header. The `ipv4`, `ipv6`, `unix`, and `unspecified`
+/// constructors return a `Builder` of that family, which has no way to set the header bytes or length,
+/// so its address family always matches its addresses. `Builder<Unchecked>` takes the header bytes as given,
+/// and any typed `Builder` converts into one with `into`, e.g. to pick the family at runtime.
+///
 /// ## Examples
 /// ```rust
 /// use ppp::v2::{Addresses, AddressFamily, Builder, Command, IPv4, Protocol, PROTOCOL_PREFIX, Type, Version};
@@ -51,14 +57,71 @@ pub struct Writer {
 ///
 /// assert_eq!(header, expected);
 /// ```
+///
+/// A typed `Builder` only accepts addresses of its family:
+/// ```compile_fail
+/// use ppp::v2::{Builder, Command, IPv4, IPv6, Protocol};
+///
+/// let builder: Builder<IPv4> = Builder::ipv6(
+///     Command::Proxy,
+///     Protocol::Stream,
+///     IPv6::new([0, 0, 0, 0, 0, 0, 0, 1], [0, 0, 0, 0, 0, 0, 0, 2], 80, 443),
+/// );
+/// ```
 #[derive(Debug)]
-pub struct Builder {
+pub struct Builder<F = Unchecked> {
     header: Option<Vec<u8>>,
     version_command: u8,
     address_family_protocol: u8,
     addresses: Addresses,
     length: Option<u16>,
     additional_capacity: usize,
+    tlv_order: TlvOrder,
+    family: PhantomData<F>,
+}
+
+/// The address family of a `Builder`: `IPv4`, `IPv6`, `Unix`, `Unspecified`, or `Unchecked`.
+pub trait Family: sealed::Sealed {}
+
+/// The family of a `Builder` whose header bytes are set by the caller,
+/// so the address family is not checked against the addresses written.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Unchecked;
+
+/// The family of a `Builder` for a header without addresses.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Unspecified;
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Unchecked {}
+    impl Sealed for super::Unspecified {}
+    impl Sealed for super::IPv4 {}
+    impl Sealed for super::IPv6 {}
+    impl Sealed for super::Unix {}
+}
+
+impl Family for Unchecked {}
+impl Family for Unspecified {}
+impl Family for IPv4 {}
+impl Family for IPv6 {}
+impl Family for Unix {}
+
+/// The order in which a `Builder` writes the TLVs of a header.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TlvOrder {
+    /// TLVs are written in the order they are written to the `Builder`.
+    Insertion,
+    /// TLVs are sorted by type when the header is built, so headers with the same TLVs are identical byte for byte
+    /// however they were written. TLVs of the same type (e.g. hops or chunks) keep the order they were written in.
+    Sorted,
+}
+
+impl Default for TlvOrder {
+    fn default() -> Self {
+        TlvOrder::Insertion
+    }
 }
 
 impl Writer {
@@ -233,14 +296,11 @@ impl Builder {
     /// No guarantee is made that any address bytes written as a payload will match the header's address family.
     /// The length is determined on `build` unless `set_length` is called to set an explicit value.
     pub fn new(version_command: u8, address_family_protocol: u8) -> Self {
-        Builder {
-            header: None,
+        Builder::from_parts(
             version_command,
             address_family_protocol,
-            addresses: Addresses::Unspecified,
-            length: None,
-            additional_capacity: 0,
-        }
+            Addresses::Unspecified,
+        )
     }
 
     /// Creates an instance of a `Builder` with the given header bytes and `Addresses`.
@@ -253,16 +313,113 @@ impl Builder {
     ) -> Self {
         let addresses = addresses.into();
 
+        Builder::from_parts(
+            version_command,
+            addresses.address_family() | protocol,
+            addresses,
+        )
+    }
+
+    /// Creates an instance of a `Builder` for a version 2 header with IPv4 addresses.
+    /// Unlike `new`, the address family in the header is guaranteed to match the addresses written.
+    pub fn ipv4(command: Command, protocol: Protocol, addresses: IPv4) -> Builder<IPv4> {
+        Builder::typed(command, protocol, addresses.into())
+    }
+
+    /// Creates an instance of a `Builder` for a version 2 header with IPv6 addresses.
+    /// Unlike `new`, the address family in the header is guaranteed to match the addresses written.
+    pub fn ipv6(command: Command, protocol: Protocol, addresses: IPv6) -> Builder<IPv6> {
+        Builder::typed(command, protocol, addresses.into())
+    }
+
+    /// Creates an instance of a `Builder` for a version 2 header with unix socket addresses.
+    /// Unlike `new`, the address family in the header is guaranteed to match the addresses written.
+    pub fn unix(command: Command, protocol: Protocol, addresses: Unix) -> Builder<Unix> {
+        Builder::typed(command, protocol, addresses.into())
+    }
+
+    /// Creates an instance of a `Builder` for a version 2 header without any addresses.
+    pub fn unspecified(command: Command, protocol: Protocol) -> Builder<Unspecified> {
+        Builder::typed(command, protocol, Addresses::Unspecified)
+    }
+
+    /// Creates an instance of a `Builder` that re-encodes the given `Header` from its version, command,
+    /// address family, protocol and addresses, followed by the rest of its payload as-is.
+    /// TLVs therefore keep their order, unknown types included, as do any address bytes of
+    /// `Addresses::Unspecified`. Building without further writes reproduces the header byte for byte;
+    /// later writes are appended to its payload.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Builder, Header};
+    ///
+    /// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x11\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB\xE0\x00\x02\x2A\x2A";
+    /// let header = Header::try_from(&input[..]).unwrap();
+    ///
+    /// assert_eq!(Builder::from_header(&header).and_then(Builder::build).unwrap(), input);
+    /// ```
+    pub fn from_header(header: &Header<'_>) -> io::Result<Self> {
+        let payload = header
+            .as_bytes()
+            .get(MINIMUM_LENGTH + header.addresses.len()..)
+            .unwrap_or_default();
+
+        Builder::from_parts(
+            header.version | header.command,
+            header.address_family() | header.protocol,
+            header.addresses,
+        )
+        .write_payload(payload)
+    }
+
+    /// Overrides the length in the header.
+    /// When set to `Some` value, the length may be smaller or larger than the actual payload in the buffer.
+    pub fn set_length<T: Into<Option<u16>>>(mut self, length: T) -> Self {
+        self.length = length.into();
+        self
+    }
+}
+
+#[cfg(test)]
+impl Builder {
+    /// Creates a `Builder` for a proxied stream from `127.0.0.1:80` to `127.0.0.2:443`,
+    /// the header the tests of the TLV helpers add their TLVs to.
+    pub(crate) fn localhost() -> Self {
+        Builder::with_addresses(
+            Version::Two | Command::Proxy,
+            Protocol::Stream,
+            (
+                "127.0.0.1:80".parse().unwrap(),
+                "127.0.0.2:443".parse().unwrap(),
+            ),
+        )
+    }
+}
+
+impl<F: Family> Builder<F> {
+    /// Creates a `Builder` of any family from its parts, which the caller guarantees to agree.
+    fn from_parts(version_command: u8, address_family_protocol: u8, addresses: Addresses) -> Self {
         Builder {
             header: None,
             version_command,
-            address_family_protocol: addresses.address_family() | protocol,
+            address_family_protocol,
             addresses,
             length: None,
             additional_capacity: 0,
+            tlv_order: TlvOrder::Insertion,
+            family: PhantomData,
         }
     }
 
+    /// Creates a `Builder` for a version 2 header with the address family of the given addresses.
+    fn typed(command: Command, protocol: Protocol, addresses: Addresses) -> Self {
+        Builder::from_parts(
+            Version::Two | command,
+            addresses.address_family() | protocol,
+            addresses,
+        )
+    }
+
     /// Reserves the requested additional capacity in the underlying buffer.
     /// Helps to prevent resizing the underlying buffer when called before `write_payload`, `write_payloads`.
     /// When called after `write_payload`, `write_payloads`, useful as a hint on how to resize the buffer.
@@ -275,10 +432,33 @@ impl Builder {
         self
     }
 
-    /// Overrides the length in the header.
-    /// When set to `Some` value, the length may be smaller or larger than the actual payload in the buffer.
-    pub fn set_length<T: Into<Option<u16>>>(mut self, length: T) -> Self {
-        self.length = length.into();
+    /// Sets the order the TLVs are written in when the header is built.
+    /// The TLVs are the payload after the addresses of this `Builder`, so with `Sorted`,
+    /// `build` returns an `InvalidData` error when the rest of the payload is not made of valid TLVs.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Builder, Command, Protocol, TlvOrder, Type};
+    ///
+    /// let builder = || {
+    ///     Builder::unspecified(Command::Local, Protocol::Unspecified).tlv_order(TlvOrder::Sorted)
+    /// };
+    /// let first = builder()
+    ///     .write_tlv(Type::UniqueId, b"abc")
+    ///     .and_then(|builder| builder.write_tlv(Type::ALPN, b"h2"))
+    ///     .and_then(Builder::build)
+    ///     .unwrap();
+    /// let second = builder()
+    ///     .write_tlv(Type::ALPN, b"h2")
+    ///     .and_then(|builder| builder.write_tlv(Type::UniqueId, b"abc"))
+    ///     .and_then(Builder::build)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(first, second);
+    /// assert_eq!(first[16], u8::from(Type::ALPN));
+    /// ```
+    pub fn tlv_order(mut self, order: TlvOrder) -> Self {
+        self.tlv_order = order;
         self
     }
 
@@ -314,7 +494,7 @@ impl Builder {
 
     /// Writes a Type-Length-Value as a payload.
     /// No surrounding bytes (terminal or otherwise) are added by this `Builder`.
-    /// The length is determined by the length of the slice.
+    /// The length is determined by the length of the slice, so an empty slice writes a TLV with a length of 0.
     /// An error is returned when the length of the slice exceeds `u16::MAX`.
     pub fn write_tlv(self, kind: impl Into<u8>, value: &[u8]) -> io::Result<Self> {
         self.write_payload(TypeLengthValue::new(kind, value))
@@ -363,6 +543,10 @@ impl Builder {
 
         let mut header = self.header.take().unwrap_or_default();
 
+        if self.tlv_order == TlvOrder::Sorted {
+            header = sort_tlvs(header, MINIMUM_LENGTH + self.addresses.len())?;
+        }
+
         if self.length.is_some() {
             return Ok(header);
         }
@@ -377,10 +561,99 @@ impl Builder {
     }
 }
 
+macro_rules! impl_from_typed_builder {
+    ($t:ident) => {
+        impl From<Builder<$t>> for Builder {
+            fn from(builder: Builder<$t>) -> Self {
+                Builder {
+                    header: builder.header,
+                    version_command: builder.version_command,
+                    address_family_protocol: builder.address_family_protocol,
+                    addresses: builder.addresses,
+                    length: builder.length,
+                    additional_capacity: builder.additional_capacity,
+                    tlv_order: builder.tlv_order,
+                    family: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+impl_from_typed_builder!(IPv4);
+impl_from_typed_builder!(IPv6);
+impl_from_typed_builder!(Unix);
+impl_from_typed_builder!(Unspecified);
+
+/// Sorts the TLVs from the given offset of the header by type, keeping the order of TLVs of the same type.
+fn sort_tlvs(header: Vec<u8>, offset: usize) -> io::Result<Vec<u8>> {
+    let mut tlvs = TypeLengthValues::from(header.get(offset..).unwrap_or_default())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    tlvs.sort_by_key(|tlv| tlv.kind);
+
+    let mut writer = Writer::from(Vec::with_capacity(header.len()));
+
+    writer.write_all(&header[..offset.min(header.len())])?;
+
+    for tlv in tlvs {
+        tlv.write_to(&mut writer)?;
+    }
+
+    Ok(writer.finish())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::v2::{AddressFamily, Command, IPv4, IPv6, Protocol, Type, Unix, Version};
+    use crate::v2::AddressFamily;
+
+    #[test]
+    fn sorted_tlvs() {
+        let builder = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        )
+        .tlv_order(TlvOrder::Sorted);
+        let header = builder
+            .write_tlv(0xE7, b"second")
+            .unwrap()
+            .write_tlv(Type::Authority, b"example.com")
+            .unwrap()
+            .write_tlv(0xE7, b"first")
+            .unwrap()
+            .write_tlv(Type::ALPN, b"h2")
+            .unwrap()
+            .build()
+            .unwrap();
+        let parsed = Header::try_from(header.as_slice()).unwrap();
+        let tlvs: Vec<(u8, Vec<u8>)> = parsed
+            .tlvs()
+            .map(|tlv| tlv.map(|tlv| (tlv.kind, tlv.value.to_vec())))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            tlvs,
+            vec![
+                (Type::ALPN.into(), b"h2".to_vec()),
+                (Type::Authority.into(), b"example.com".to_vec()),
+                (0xE7, b"second".to_vec()),
+                (0xE7, b"first".to_vec()),
+            ]
+        );
+
+        let invalid = Builder::unspecified(Command::Local, Protocol::Unspecified)
+            .tlv_order(TlvOrder::Sorted)
+            .write_payload([1u8, 0, 5].as_slice())
+            .unwrap()
+            .build()
+            .unwrap_err();
+
+        assert_eq!(invalid.kind(), io::ErrorKind::InvalidData);
+    }
 
     #[test]
     fn build_length_too_small() {
@@ -624,4 +897,171 @@ mod tests {
 
         assert_eq!(header, expected);
     }
+
+    #[test]
+    fn build_typed_ipv4() {
+        let mut expected = Vec::from(PROTOCOL_PREFIX);
+        expected.extend([
+            0x21, 0x11, 0, 12, 127, 0, 0, 1, 192, 168, 1, 1, 0, 80, 1, 187,
+        ]);
+
+        let header = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [192, 168, 1, 1], 80, 443),
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(header, expected);
+    }
+
+    #[test]
+    fn build_typed_ipv6() {
+        let mut expected = Vec::from(PROTOCOL_PREFIX);
+        expected.extend([0x20, 0x22, 0, 36]);
+        expected.extend([0; 15]);
+        expected.push(1);
+        expected.extend([0; 15]);
+        expected.push(2);
+        expected.extend([0, 80, 1, 187]);
+
+        let header = Builder::ipv6(
+            Command::Local,
+            Protocol::Datagram,
+            IPv6::new([0, 0, 0, 0, 0, 0, 0, 1], [0, 0, 0, 0, 0, 0, 0, 2], 80, 443),
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(header, expected);
+    }
+
+    #[test]
+    fn build_typed_unix() {
+        let source_address = [0xFFu8; 108];
+        let destination_address = [0xAAu8; 108];
+        let mut expected = Vec::from(PROTOCOL_PREFIX);
+        expected.extend([0x21, 0x31, 0, 216]);
+        expected.extend(source_address);
+        expected.extend(destination_address);
+
+        let header = Builder::unix(
+            Command::Proxy,
+            Protocol::Stream,
+            Unix::new(source_address, destination_address),
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(header, expected);
+    }
+
+    #[test]
+    fn build_typed_unspecified() {
+        let mut expected = Vec::from(PROTOCOL_PREFIX);
+        expected.extend([0x20, 0x00, 0, 0]);
+
+        let header = Builder::unspecified(Command::Local, Protocol::Unspecified)
+            .build()
+            .unwrap();
+
+        assert_eq!(header, expected);
+    }
+
+    #[test]
+    fn typed_into_unchecked() {
+        let typed = Builder::unix(
+            Command::Proxy,
+            Protocol::Stream,
+            Unix::new([1; 108], [2; 108]),
+        )
+        .write_tlv(Type::NoOp, b"")
+        .unwrap();
+        let builders: [Builder; 2] = [
+            typed.into(),
+            Builder::unspecified(Command::Local, Protocol::Unspecified).into(),
+        ];
+        let headers: Vec<Vec<u8>> = builders
+            .into_iter()
+            .map(|builder| builder.build().unwrap())
+            .collect();
+
+        assert_eq!(
+            Header::try_from(headers[0].as_slice()).unwrap().addresses,
+            Unix::new([1; 108], [2; 108]).into()
+        );
+        assert_eq!(headers[0][MINIMUM_LENGTH + 216..], [4, 0, 0]);
+        assert_eq!(headers[1][12..], [0x20, 0x00, 0, 0]);
+    }
+
+    #[cfg(feature = "v1")]
+    #[test]
+    fn from_header_unchanged() {
+        let inputs = crate::vectors::VECTORS
+            .iter()
+            .map(|vector| vector.bytes)
+            .chain(
+                crate::testing::spec::EXAMPLES
+                    .iter()
+                    .map(|example| example.bytes),
+            );
+
+        for input in inputs {
+            if let Ok(header) = Header::try_from(input) {
+                let encoded = Builder::from_header(&header)
+                    .and_then(Builder::build)
+                    .unwrap();
+
+                assert_eq!(encoded, header.as_bytes(), "{:?}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn from_header_preserves_payload() {
+        let mut local = Vec::from(PROTOCOL_PREFIX);
+        local.extend([0x20, 0x11, 0, 12, 127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]);
+
+        let mut unspecified = Vec::from(PROTOCOL_PREFIX);
+        unspecified.extend([0x21, 0x00, 0, 5, 1, 2, 3, 4, 5]);
+
+        let tlvs = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        )
+        .write_tlv(0xE1, b"b")
+        .unwrap()
+        .write_tlv(Type::NoOp, b"")
+        .unwrap()
+        .write_tlv(0xE0, b"a")
+        .unwrap()
+        .build()
+        .unwrap();
+
+        for input in [local, unspecified, tlvs.clone()] {
+            let header = Header::try_from(input.as_slice()).unwrap();
+
+            assert_eq!(
+                Builder::from_header(&header)
+                    .and_then(Builder::build)
+                    .unwrap(),
+                input
+            );
+        }
+
+        let header = Header::try_from(tlvs.as_slice()).unwrap();
+        let appended = Builder::from_header(&header)
+            .unwrap()
+            .write_tlv(Type::NoOp, b"c")
+            .unwrap()
+            .build()
+            .unwrap();
+        let appended = Header::try_from(appended.as_slice()).unwrap();
+        let kinds: Vec<u8> = appended.tlvs().map(|tlv| tlv.unwrap().kind).collect();
+
+        assert_eq!(kinds, [0xE1, 0x04, 0xE0, 0x04]);
+        assert_eq!(appended.addresses, header.addresses);
+    }
 }
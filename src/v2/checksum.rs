@@ -0,0 +1,221 @@
+//! Verification of the CRC32c checksum a header may carry in a `Type::CRC32C` TLV.
+use super::{Header, Type, MINIMUM_TLV_LENGTH};
+use core::ops::Range;
+
+/// The Castagnoli polynomial, in reversed bit order.
+const POLYNOMIAL: u32 = 0x82F6_3B78;
+
+/// The CRC of each byte value.
+const TABLE: [u32; 256] = table();
+
+const fn table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut index = 0;
+
+    while index < table.len() {
+        let mut crc = index as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+}
+
+/// Computes the CRC32c checksum of the bytes, as if the bytes in the `zeroed` range were all 0.
+pub(crate) fn crc32c(bytes: &[u8], zeroed: Range<usize>) -> u32 {
+    let crc = bytes
+        .iter()
+        .enumerate()
+        .fold(!0, |crc: u32, (index, &byte)| {
+            let byte = if zeroed.contains(&index) { 0 } else { byte };
+
+            TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+        });
+
+    !crc
+}
+
+/// Sets the value of the first `Type::CRC32C` TLV of the encoded header, if it is 4 bytes long, to its checksum.
+#[cfg(any(feature = "std", feature = "tstd"))]
+pub(crate) fn update_checksum(header: &mut [u8]) {
+    let offset = Header::try_from(&*header).ok().and_then(|parsed| {
+        parsed
+            .checksum_tlv()
+            .filter(|(_, value)| value.len() == 4)
+            .map(|(offset, _)| offset)
+    });
+
+    if let Some(offset) = offset {
+        let checksum = crc32c(header, offset..offset + 4);
+
+        header[offset..offset + 4].copy_from_slice(&checksum.to_be_bytes());
+    }
+}
+
+impl<'a> Header<'a> {
+    /// The offset in the header and the value of the first `Type::CRC32C` TLV, if any.
+    /// TLVs after an invalid TLV are not searched.
+    pub(super) fn checksum_tlv(&self) -> Option<(usize, &[u8])> {
+        self.tlv_value(u8::from(Type::CRC32C))
+    }
+
+    /// The offset in the header and the value of the first TLV of the given kind, if any.
+    /// TLVs after an invalid TLV are not searched.
+    pub(super) fn tlv_value(&self, kind: u8) -> Option<(usize, &[u8])> {
+        let mut offset = self.len() - self.tlv_bytes().len();
+
+        for tlv in self.tlvs() {
+            let tlv = tlv.ok()?;
+            let value = offset + MINIMUM_TLV_LENGTH;
+
+            if tlv.kind == kind {
+                return self
+                    .as_bytes()
+                    .get(value..value + tlv.value.len())
+                    .map(|bytes| (value, bytes));
+            }
+
+            offset = value + tlv.value.len();
+        }
+
+        None
+    }
+
+    /// The CRC32c checksum carried by this `Header`, if it has a `Type::CRC32C` TLV with a 4-byte value.
+    pub fn checksum(&self) -> Option<u32> {
+        let (_, value) = self.checksum_tlv()?;
+
+        <[u8; 4]>::try_from(value).ok().map(u32::from_be_bytes)
+    }
+
+    /// Verifies the CRC32c checksum of this `Header`, which is computed over the whole header
+    /// with the value of the `Type::CRC32C` TLV set to 0.
+    /// Returns `None` when the header carries no checksum, and `Some(false)` when the value is not 4 bytes.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::Header;
+    ///
+    /// let mut input = Vec::from(&b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x13"[..]);
+    ///
+    /// input.extend([127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]);
+    /// input.extend([0x03, 0x00, 0x04]);
+    /// input.extend(0x8326_5C80u32.to_be_bytes());
+    ///
+    /// let header = Header::try_from(input.as_slice()).unwrap();
+    ///
+    /// assert_eq!(header.verify_checksum(), Some(true));
+    /// ```
+    pub fn verify_checksum(&self) -> Option<bool> {
+        let (offset, value) = self.checksum_tlv()?;
+        let checksum = match <[u8; 4]>::try_from(value) {
+            Ok(checksum) => u32::from_be_bytes(checksum),
+            Err(_) => return Some(false),
+        };
+
+        Some(crc32c(self.as_bytes(), offset..offset + value.len()) == checksum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Command, IPv4, Protocol};
+
+    /// A header with the given TLVs and a correct checksum in its first `Type::CRC32C` TLV.
+    fn signed(tlvs: &[(Type, &[u8])]) -> Vec<u8> {
+        let builder = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        );
+        let mut header = tlvs
+            .iter()
+            .try_fold(builder, |builder, (kind, value)| {
+                builder.write_tlv(*kind, value)
+            })
+            .and_then(Builder::build)
+            .unwrap();
+        let (offset, length) = {
+            let parsed = Header::try_from(header.as_slice()).unwrap();
+            let (offset, value) = parsed.checksum_tlv().unwrap();
+
+            (offset, value.len())
+        };
+        let checksum = crc32c(&header, offset..offset + length);
+
+        if length == 4 {
+            header[offset..offset + 4].copy_from_slice(&checksum.to_be_bytes());
+        }
+
+        header
+    }
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc32c(b"123456789", 0..0), 0xE306_9283);
+        assert_eq!(
+            crc32c(b"12345678900000", 9..14),
+            crc32c(b"123456789\0\0\0\0\0", 0..0)
+        );
+        assert_eq!(crc32c(b"", 0..0), 0);
+    }
+
+    #[test]
+    fn verify() {
+        let header = signed(&[(Type::NoOp, b"ab"), (Type::CRC32C, &[0; 4])]);
+        let parsed = Header::try_from(header.as_slice()).unwrap();
+
+        assert_eq!(parsed.verify_checksum(), Some(true));
+        assert_eq!(parsed.checksum(), Some(crc32c(&header, 36..40)));
+
+        for index in 0..header.len() {
+            let mut corrupted = header.clone();
+
+            corrupted[index] ^= 0x01;
+
+            if let Ok(parsed) = Header::try_from(corrupted.as_slice()) {
+                assert_ne!(parsed.verify_checksum(), Some(true), "{}", index);
+            }
+        }
+    }
+
+    #[test]
+    fn missing_or_malformed() {
+        let unsigned = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        )
+        .write_tlv(Type::NoOp, b"ab")
+        .unwrap()
+        .build()
+        .unwrap();
+        let short = signed(&[(Type::CRC32C, &[0; 3])]);
+
+        assert_eq!(
+            Header::try_from(unsigned.as_slice())
+                .unwrap()
+                .verify_checksum(),
+            None
+        );
+        assert_eq!(Header::try_from(short.as_slice()).unwrap().checksum(), None);
+        assert_eq!(
+            Header::try_from(short.as_slice())
+                .unwrap()
+                .verify_checksum(),
+            Some(false)
+        );
+    }
+}
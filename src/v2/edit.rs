@@ -0,0 +1,198 @@
+//! Replacing or appending TLVs in the bytes of a header, without rebuilding the rest of the header.
+#[cfg(any(feature = "std", feature = "tstd"))]
+use crate::prelude::*;
+
+use super::checksum::crc32c;
+use super::{Header, ParseError, LENGTH, MINIMUM_TLV_LENGTH};
+#[cfg(any(feature = "std", feature = "tstd"))]
+use super::{WriteToHeader, Writer, MINIMUM_LENGTH};
+#[cfg(any(feature = "std", feature = "tstd"))]
+use std::io;
+
+/// An error in replacing the value of a TLV, which leaves the header unchanged.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum EditError {
+    #[error("Header has no TLV of type {0:#04X}.")]
+    Missing(u8),
+    #[error("Value of {new} bytes is larger than the current value of {current} bytes.")]
+    Larger { current: usize, new: usize },
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for EditError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Missing(kind) => defmt::write!(f, "Missing({=u8:#x})", kind),
+            Self::Larger { current, new } => {
+                defmt::write!(f, "Larger({=usize}, {=usize})", current, new)
+            }
+            Self::Parse(error) => defmt::write!(f, "Parse({})", error),
+        }
+    }
+}
+
+impl<'a> Header<'a> {
+    /// Replaces the value of the first TLV of the given type with a value of equal or smaller size,
+    /// patching the TLV and payload lengths and the `Type::CRC32C` checksum, if any, in place.
+    /// Headers that borrow their bytes are copied first; owned headers are edited without allocating.
+    ///
+    /// Returns an error, leaving the header unchanged, when there is no such TLV, when the value is larger than
+    /// the current one or when a TLV before it cannot be parsed.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Builder, Command, Header, Protocol, Type, Version};
+    ///
+    /// let bytes = Builder::with_addresses(
+    ///     Version::Two | Command::Proxy,
+    ///     Protocol::Stream,
+    ///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+    /// )
+    /// .write_tlv(Type::Authority, b"internal.example.com")
+    /// .unwrap()
+    /// .write_tlv(Type::CRC32C, &[0; 4])
+    /// .unwrap()
+    /// .build()
+    /// .unwrap();
+    /// let mut header = Header::try_from(bytes.as_slice()).unwrap().to_owned();
+    ///
+    /// header.replace_tlv(Type::Authority, b"example.com").unwrap();
+    ///
+    /// assert_eq!(header.len(), bytes.len() - 9);
+    /// assert_eq!(header.verify_checksum(), Some(true));
+    /// assert_eq!(Header::try_from(header.as_bytes()), Ok(header.clone()));
+    /// ```
+    pub fn replace_tlv<T: Into<u8>>(&mut self, kind: T, value: &[u8]) -> Result<(), EditError> {
+        let kind = kind.into();
+        let (offset, current) = self.find_tlv(kind)?.ok_or(EditError::Missing(kind))?;
+
+        if value.len() > current {
+            return Err(EditError::Larger {
+                current,
+                new: value.len(),
+            });
+        }
+
+        let length = (self.length() - (current - value.len())) as u16;
+        let start = offset + MINIMUM_TLV_LENGTH;
+        let bytes = self.header.to_mut();
+
+        bytes[start..start + value.len()].copy_from_slice(value);
+        bytes.drain(start + value.len()..start + current);
+        bytes[offset + 1..start].copy_from_slice(&(value.len() as u16).to_be_bytes());
+        bytes[LENGTH..LENGTH + 2].copy_from_slice(&length.to_be_bytes());
+
+        let checksum = self
+            .checksum_tlv()
+            .filter(|(_, value)| value.len() == 4)
+            .map(|(offset, _)| offset);
+
+        if let Some(offset) = checksum {
+            let bytes = self.header.to_mut();
+            let checksum = crc32c(bytes, offset..offset + 4);
+
+            bytes[offset..offset + 4].copy_from_slice(&checksum.to_be_bytes());
+        }
+
+        Ok(())
+    }
+
+    /// A copy of the bytes of this `Header` with the TLV appended as the last TLV and the payload length patched.
+    /// Returns an error when the payload would exceed `u16::MAX` bytes.
+    /// The `Type::CRC32C` checksum, if any, is left for the caller to update.
+    #[cfg(any(feature = "std", feature = "tstd"))]
+    pub(super) fn append_tlv<T: WriteToHeader + ?Sized>(&self, tlv: &T) -> io::Result<Vec<u8>> {
+        let mut writer = Writer::from(self.as_bytes().to_vec());
+
+        tlv.write_to(&mut writer)?;
+
+        let mut header = writer.finish();
+        let length = u16::try_from(header.len() - MINIMUM_LENGTH)
+            .map_err(|_| io::Error::from(io::ErrorKind::WriteZero))?
+            .to_be_bytes();
+
+        header[LENGTH..LENGTH + length.len()].copy_from_slice(&length);
+
+        Ok(header)
+    }
+
+    /// The offset in the header and the value length of the first TLV of the given type, if any.
+    fn find_tlv(&self, kind: u8) -> Result<Option<(usize, usize)>, ParseError> {
+        let mut offset = self.len() - self.tlv_bytes().len();
+
+        for tlv in self.tlvs() {
+            let tlv = tlv?;
+
+            if tlv.kind == kind {
+                return Ok(Some((offset, tlv.value.len())));
+            }
+
+            offset += MINIMUM_TLV_LENGTH + tlv.value.len();
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(all(test, any(feature = "std", feature = "tstd")))]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Type};
+
+    fn build() -> Vec<u8> {
+        Builder::localhost()
+            .write_tlv(Type::CRC32C, &[0; 4])
+            .unwrap()
+            .write_tlv(Type::Authority, b"example.com")
+            .unwrap()
+            .write_tlv(Type::UniqueId, b"abc")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn replace_in_place() {
+        let bytes = build();
+        let mut header = Header::try_from(bytes.as_slice()).unwrap();
+
+        header.replace_tlv(Type::Authority, b"example.org").unwrap();
+
+        assert_eq!(header.len(), bytes.len());
+        assert_eq!(header.verify_checksum(), Some(true));
+
+        header.replace_tlv(Type::Authority, b"a.io").unwrap();
+        header.replace_tlv(Type::UniqueId, b"").unwrap();
+
+        let parsed = Header::try_from(header.as_bytes()).unwrap();
+        let tlvs: Vec<(u8, Vec<u8>)> = parsed
+            .tlvs()
+            .map(|tlv| tlv.map(|tlv| (tlv.kind, tlv.value.to_vec())))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(parsed, header);
+        assert_eq!(header.len(), bytes.len() - 7 - 3);
+        assert_eq!(parsed.verify_checksum(), Some(true));
+        assert_eq!(tlvs[1], (Type::Authority.into(), b"a.io".to_vec()));
+        assert_eq!(tlvs[2], (Type::UniqueId.into(), Vec::new()));
+    }
+
+    #[test]
+    fn replace_errors() {
+        let bytes = build();
+        let mut header = Header::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(
+            header.replace_tlv(Type::NoOp, b""),
+            Err(EditError::Missing(Type::NoOp.into()))
+        );
+        assert_eq!(
+            header.replace_tlv(Type::UniqueId, b"abcd"),
+            Err(EditError::Larger { current: 3, new: 4 })
+        );
+        assert_eq!(header.as_bytes(), bytes.as_slice());
+    }
+}
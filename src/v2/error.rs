@@ -1,6 +1,4 @@
 //! Errors for the binary proxy protocol.
-use std::prelude::v1::*;
-
 /// An error in parsing a binary PROXY protocol header.
 #[derive(thiserror::Error, Debug, PartialEq)]
 pub enum ParseError {
@@ -29,3 +27,27 @@ pub enum ParseError {
     #[error("Header contains leftover {0} bytes not accounted for by the address family or TLVs.")]
     Leftovers(usize),
 }
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ParseError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Incomplete(length) => defmt::write!(f, "Incomplete({=usize})", length),
+            Self::Prefix => defmt::write!(f, "Prefix"),
+            Self::Version(version) => defmt::write!(f, "Version({=u8:#x})", version),
+            Self::Command(command) => defmt::write!(f, "Command({=u8:#x})", command),
+            Self::AddressFamily(family) => defmt::write!(f, "AddressFamily({=u8:#x})", family),
+            Self::Protocol(protocol) => defmt::write!(f, "Protocol({=u8:#x})", protocol),
+            Self::Partial(length, expected) => {
+                defmt::write!(f, "Partial({=usize}, {=usize})", length, expected)
+            }
+            Self::InvalidAddresses(length, expected) => {
+                defmt::write!(f, "InvalidAddresses({=usize}, {=usize})", length, expected)
+            }
+            Self::InvalidTLV(kind, length) => {
+                defmt::write!(f, "InvalidTLV({=u8:#x}, {=u16})", kind, length)
+            }
+            Self::Leftovers(length) => defmt::write!(f, "Leftovers({=usize})", length),
+        }
+    }
+}
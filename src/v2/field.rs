@@ -0,0 +1,49 @@
+//! Alignment-safe accessors for the fields of binary headers.
+//!
+//! Headers are parsed from byte slices that may start at any offset of a packed or misaligned buffer,
+//! so multi-byte fields are only ever copied out into byte arrays and decoded from those,
+//! never read through a pointer to a wider type. Callers check the length of the input first.
+
+/// Copies the `N` bytes at the given offset into an array.
+pub(crate) fn array<const N: usize>(bytes: &[u8], offset: usize) -> [u8; N] {
+    let mut array = [0; N];
+
+    array.copy_from_slice(&bytes[offset..offset + N]);
+    array
+}
+
+/// Reads the big-endian `u16` (e.g. a length or port) at the given offset.
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes(array(bytes, offset))
+}
+
+/// Reads the big-endian `u32` (e.g. the verify field of an SSL TLV) at the given offset.
+#[cfg(any(feature = "v1", test))]
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(array(bytes, offset))
+}
+
+/// Reads the big-endian `u64` (e.g. the timestamp of a hop TLV) at the given offset.
+pub(crate) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_be_bytes(array(bytes, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_offset() {
+        let mut buffer = [0u8; 16];
+
+        for offset in 0..8 {
+            buffer.fill(0);
+            buffer[offset..offset + 8].copy_from_slice(&0x0102_0304_0506_0708u64.to_be_bytes());
+
+            assert_eq!(read_u16(&buffer, offset), 0x0102);
+            assert_eq!(read_u32(&buffer, offset), 0x0102_0304);
+            assert_eq!(read_u64(&buffer, offset), 0x0102_0304_0506_0708);
+            assert_eq!(array::<2>(&buffer, offset + 6), [7, 8]);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Encoding of binary headers into fixed-capacity buffers, for senders without an allocator.
+use super::model::{IPV4_ADDRESSES_BYTES, IPV6_ADDRESSES_BYTES, UNIX_ADDRESSES_BYTES};
+use super::{
+    AddressFamily, Command, IPv4, IPv6, Protocol, Unix, Version, LENGTH, MINIMUM_LENGTH,
+    MINIMUM_TLV_LENGTH, PROTOCOL_PREFIX,
+};
+
+/// An error in encoding a header into a fixed-capacity buffer.
+#[derive(thiserror::Error, Copy, Clone, Debug, PartialEq)]
+#[error("Header of {0} bytes exceeds the capacity of {1} bytes.")]
+pub struct CapacityError(pub usize, pub usize);
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for CapacityError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "CapacityError({=usize}, {=usize})", self.0, self.1)
+    }
+}
+
+/// Fails to compile when a buffer of `N` bytes cannot hold the `REQUIRED` bytes.
+struct Capacity<const N: usize, const REQUIRED: usize>;
+
+impl<const N: usize, const REQUIRED: usize> Capacity<N, REQUIRED> {
+    const CHECK: () = assert!(
+        N >= REQUIRED,
+        "the buffer cannot hold the header's addresses"
+    );
+}
+
+/// Builds binary PROXY protocol headers in a buffer of `N` bytes, without allocating.
+/// Intended for senders without an allocator, such as gateways running on microcontrollers.
+///
+/// The constructors check at compile time that `N` can hold the prefix and addresses of the header,
+/// so too small a buffer is a build error rather than a runtime one.
+/// Only TLVs, whose lengths are not known until they are written, are checked at runtime.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::v2::{Command, FixedBuilder, Header, IPv4, Protocol, Type};
+///
+/// let header = FixedBuilder::<32>::ipv4(
+///     Command::Proxy,
+///     Protocol::Stream,
+///     IPv4::new([127, 0, 0, 1], [192, 168, 1, 1], 80, 443),
+/// )
+/// .write_tlv(Type::NoOp, &[42])
+/// .unwrap()
+/// .build();
+///
+/// assert_eq!(header.len(), 32);
+/// assert!(Header::try_from(header.as_bytes()).is_ok());
+/// ```
+///
+/// A buffer too small for the addresses does not compile:
+/// ```rust,compile_fail
+/// use ppp::v2::{Command, FixedBuilder, IPv6, Protocol};
+///
+/// let addresses = IPv6::new([0; 16], [0; 16], 80, 443);
+/// let header = FixedBuilder::<32>::ipv6(Command::Proxy, Protocol::Stream, addresses);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedBuilder<const N: usize> {
+    header: FixedHeader<N>,
+}
+
+/// A binary PROXY protocol header encoded in a buffer of `N` bytes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedHeader<const N: usize> {
+    bytes: [u8; N],
+    length: usize,
+}
+
+impl<const N: usize> FixedBuilder<N> {
+    /// Creates a `FixedBuilder` for a version 2 header with IPv4 addresses.
+    pub fn ipv4(command: Command, protocol: Protocol, addresses: IPv4) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Capacity::<N, { MINIMUM_LENGTH + IPV4_ADDRESSES_BYTES }>::CHECK;
+
+        let mut builder = Self::new(command, AddressFamily::IPv4 | protocol);
+
+        builder.push(&addresses.source_address.octets());
+        builder.push(&addresses.destination_address.octets());
+        builder.push(&addresses.source_port.to_be_bytes());
+        builder.push(&addresses.destination_port.to_be_bytes());
+        builder
+    }
+
+    /// Creates a `FixedBuilder` for a version 2 header with IPv6 addresses.
+    pub fn ipv6(command: Command, protocol: Protocol, addresses: IPv6) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Capacity::<N, { MINIMUM_LENGTH + IPV6_ADDRESSES_BYTES }>::CHECK;
+
+        let mut builder = Self::new(command, AddressFamily::IPv6 | protocol);
+
+        builder.push(&addresses.source_address.octets());
+        builder.push(&addresses.destination_address.octets());
+        builder.push(&addresses.source_port.to_be_bytes());
+        builder.push(&addresses.destination_port.to_be_bytes());
+        builder
+    }
+
+    /// Creates a `FixedBuilder` for a version 2 header with unix socket addresses.
+    pub fn unix(command: Command, protocol: Protocol, addresses: Unix) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Capacity::<N, { MINIMUM_LENGTH + UNIX_ADDRESSES_BYTES }>::CHECK;
+
+        let mut builder = Self::new(command, AddressFamily::Unix | protocol);
+
+        builder.push(&addresses.source);
+        builder.push(&addresses.destination);
+        builder
+    }
+
+    /// Creates a `FixedBuilder` for a version 2 header without any addresses.
+    pub fn unspecified(command: Command, protocol: Protocol) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Capacity::<N, MINIMUM_LENGTH>::CHECK;
+
+        Self::new(command, AddressFamily::Unspecified | protocol)
+    }
+
+    /// Writes the prefix, version, command, address family and protocol, leaving the length for `build`.
+    fn new(command: Command, address_family_protocol: u8) -> Self {
+        let mut builder = FixedBuilder {
+            header: FixedHeader {
+                bytes: [0; N],
+                length: 0,
+            },
+        };
+
+        builder.push(PROTOCOL_PREFIX);
+        builder.push(&[Version::Two | command, address_family_protocol, 0, 0]);
+        builder
+    }
+
+    /// Appends bytes the constructors have already checked the capacity for.
+    fn push(&mut self, bytes: &[u8]) {
+        let FixedHeader {
+            bytes: buffer,
+            length,
+        } = &mut self.header;
+
+        buffer[*length..*length + bytes.len()].copy_from_slice(bytes);
+        *length += bytes.len();
+    }
+
+    /// The number of bytes left in the buffer.
+    pub fn remaining(&self) -> usize {
+        N - self.header.length
+    }
+
+    /// Writes a Type-Length-Value as a payload, which may have an empty value.
+    /// Returns an error, leaving the buffer untouched, when the TLV does not fit in the rest of the buffer
+    /// or the payload would be longer than `u16::MAX`.
+    pub fn write_tlv(mut self, kind: impl Into<u8>, value: &[u8]) -> Result<Self, CapacityError> {
+        let required = self.header.length + MINIMUM_TLV_LENGTH + value.len();
+        let length = match u16::try_from(value.len()) {
+            Ok(length) if required <= N && required - MINIMUM_LENGTH <= u16::MAX as usize => length,
+            _ => return Err(CapacityError(required, N)),
+        };
+
+        self.push(&[kind.into()]);
+        self.push(&length.to_be_bytes());
+        self.push(value);
+
+        Ok(self)
+    }
+
+    /// Sets the length of the header's payload and returns the encoded header.
+    pub fn build(mut self) -> FixedHeader<N> {
+        // The length of the payload is checked as each TLV is written.
+        let payload = (self.header.length - MINIMUM_LENGTH) as u16;
+
+        self.header.bytes[LENGTH..LENGTH + 2].copy_from_slice(&payload.to_be_bytes());
+        self.header
+    }
+}
+
+impl<const N: usize> FixedHeader<N> {
+    /// The bytes of the encoded header.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.length]
+    }
+
+    /// The length of the encoded header in bytes.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Tests whether the encoded header is empty, which is never the case for a built header.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The whole buffer along with the length of the encoded header at its start.
+    pub fn into_inner(self) -> ([u8; N], usize) {
+        (self.bytes, self.length)
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for FixedHeader<N> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> From<FixedHeader<N>> for heapless::Vec<u8, N> {
+    fn from(header: FixedHeader<N>) -> Self {
+        let mut bytes = heapless::Vec::new();
+
+        // The encoded header never exceeds the capacity of its buffer, which matches the vector's.
+        let _ = bytes.extend_from_slice(header.as_bytes());
+
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Header, Type};
+
+    #[test]
+    fn matches_builder() {
+        let addresses = IPv6::new([1; 16], [2; 16], 80, 443);
+        let expected = Builder::ipv6(Command::Proxy, Protocol::Datagram, addresses)
+            .write_tlv(Type::Authority, b"example.com")
+            .unwrap()
+            .build()
+            .unwrap();
+        let header = FixedBuilder::<128>::ipv6(Command::Proxy, Protocol::Datagram, addresses)
+            .write_tlv(Type::Authority, b"example.com")
+            .unwrap()
+            .build();
+
+        assert_eq!(header.as_bytes(), expected.as_slice());
+        assert!(Header::try_from(header.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn unspecified_and_unix() {
+        let header = FixedBuilder::<16>::unspecified(Command::Local, Protocol::Unspecified).build();
+        let expected = Builder::unspecified(Command::Local, Protocol::Unspecified)
+            .build()
+            .unwrap();
+        assert_eq!(header.as_bytes(), expected.as_slice());
+
+        let addresses = Unix::new([1; 108], [2; 108]);
+        let header = FixedBuilder::<232>::unix(Command::Proxy, Protocol::Stream, addresses).build();
+        let expected = Builder::unix(Command::Proxy, Protocol::Stream, addresses)
+            .build()
+            .unwrap();
+        assert_eq!(header.as_bytes(), expected.as_slice());
+        assert_eq!(header.into_inner().1, 232);
+    }
+
+    #[test]
+    fn tlv_capacity() {
+        let builder = FixedBuilder::<32>::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        );
+
+        assert_eq!(builder.remaining(), 4);
+        assert_eq!(
+            builder.write_tlv(Type::NoOp, &[0; 2]).unwrap_err(),
+            CapacityError(33, 32)
+        );
+
+        let header = builder.write_tlv(Type::NoOp, &[0]).unwrap().build();
+        assert_eq!(header.len(), 32);
+        assert_eq!(&header.as_bytes()[LENGTH..LENGTH + 2], &[0, 16]);
+    }
+}
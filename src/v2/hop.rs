@@ -0,0 +1,259 @@
+//! Hop-recording TLVs for tracing a connection through a chain of proxies.
+use crate::prelude::*;
+
+#[cfg(any(feature = "std", feature = "tstd"))]
+use super::{checksum::update_checksum, WriteToHeader, Writer};
+use super::{field, Header, ParseError, TypeLengthValue};
+use alloc::borrow::Cow;
+#[cfg(any(feature = "std", feature = "tstd"))]
+use std::io;
+
+/// The custom TLV type used to record a hop.
+pub const HOP: u8 = 0xE7;
+
+/// The number of bytes of a hop's timestamp.
+const TIMESTAMP_LENGTH: usize = 8;
+
+/// An error in reading the hops recorded in a header.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum HopError {
+    #[error("Hop TLV of {0} bytes does not contain a timestamp followed by a UTF-8 identifier.")]
+    Invalid(usize),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for HopError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Invalid(length) => defmt::write!(f, "Invalid({=usize})", length),
+            Self::Parse(error) => defmt::write!(f, "Parse({})", error),
+        }
+    }
+}
+
+/// A proxy that forwarded a connection, as recorded in a hop TLV.
+/// The TLV value is the big-endian timestamp followed by the UTF-8 identifier.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hop<'a> {
+    /// Identifies the proxy, e.g. its hostname.
+    pub id: Cow<'a, str>,
+    /// When the proxy forwarded the connection, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl<'a> Hop<'a> {
+    /// Creates a new `Hop` for the proxy with the given identifier forwarding at the given time.
+    pub fn new<T: Into<Cow<'a, str>>>(id: T, timestamp: u64) -> Self {
+        Hop {
+            id: id.into(),
+            timestamp,
+        }
+    }
+
+    /// Creates a new `Hop` for the proxy with the given identifier forwarding at the current system time.
+    /// Unavailable on `wasm32-unknown-unknown`, which has no system clock.
+    #[cfg(all(
+        feature = "std",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn now<T: Into<Cow<'a, str>>>(id: T) -> Self {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+
+        Hop::new(id, timestamp)
+    }
+
+    /// Creates an owned clone of this `Hop`.
+    pub fn to_owned(&self) -> Hop<'static> {
+        Hop {
+            id: Cow::Owned(self.id.to_string()),
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+impl<'a> TryFrom<&TypeLengthValue<'a>> for Hop<'a> {
+    type Error = HopError;
+
+    fn try_from(tlv: &TypeLengthValue<'a>) -> Result<Self, Self::Error> {
+        let invalid = || HopError::Invalid(tlv.len());
+
+        if tlv.kind != HOP || tlv.len() < TIMESTAMP_LENGTH {
+            return Err(invalid());
+        }
+
+        let timestamp = field::read_u64(&tlv.value, 0);
+
+        let id = match &tlv.value {
+            Cow::Borrowed(value) => core::str::from_utf8(&value[TIMESTAMP_LENGTH..])
+                .map(Cow::Borrowed)
+                .map_err(|_| invalid())?,
+            Cow::Owned(value) => core::str::from_utf8(&value[TIMESTAMP_LENGTH..])
+                .map(|id| Cow::Owned(id.to_string()))
+                .map_err(|_| invalid())?,
+        };
+
+        Ok(Hop { id, timestamp })
+    }
+}
+
+#[cfg(any(feature = "std", feature = "tstd"))]
+impl<'a> WriteToHeader for Hop<'a> {
+    fn write_to(&self, writer: &mut Writer) -> io::Result<usize> {
+        let mut value = Vec::with_capacity(TIMESTAMP_LENGTH + self.id.len());
+
+        value.extend_from_slice(&self.timestamp.to_be_bytes());
+        value.extend_from_slice(self.id.as_bytes());
+
+        TypeLengthValue::new(HOP, &value).write_to(writer)
+    }
+}
+
+impl<'a> Header<'a> {
+    /// The hops recorded in this `Header`, in the order the proxies forwarded the connection.
+    /// Returns an error if any TLV, or any hop TLV, is invalid.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Builder, Command, Header, Hop, Protocol};
+    ///
+    /// let header = Builder::with_addresses(
+    ///     ppp::v2::Version::Two | Command::Proxy,
+    ///     Protocol::Stream,
+    ///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+    /// )
+    /// .write_payload(Hop::new("edge-1", 1_700_000_000_000))
+    /// .unwrap()
+    /// .build()
+    /// .unwrap();
+    /// let header = Header::try_from(header.as_slice()).unwrap();
+    /// let header = header.with_hop(&Hop::new("mid-1", 1_700_000_000_005)).unwrap();
+    ///
+    /// assert_eq!(
+    ///     header.hops().unwrap(),
+    ///     vec![Hop::new("edge-1", 1_700_000_000_000), Hop::new("mid-1", 1_700_000_000_005)]
+    /// );
+    /// ```
+    pub fn hops(&self) -> Result<Vec<Hop<'_>>, HopError> {
+        let mut hops = Vec::new();
+
+        for tlv in self.tlvs() {
+            let tlv = tlv?;
+
+            if tlv.kind == HOP {
+                hops.push(Hop::try_from(&tlv)?);
+            }
+        }
+
+        Ok(hops)
+    }
+
+    /// Creates a copy of this `Header` with the given hop appended as the last TLV, for forwarding to the next proxy.
+    /// Returns an error when the payload would exceed `u16::MAX` bytes.
+    /// A CRC32C TLV, if any, is updated to match the new header.
+    #[cfg(any(feature = "std", feature = "tstd"))]
+    pub fn with_hop(&self, hop: &Hop<'_>) -> io::Result<Header<'static>> {
+        let mut header = self.append_tlv(hop)?;
+
+        update_checksum(&mut header);
+
+        Ok(Header {
+            header: Cow::Owned(header),
+            version: self.version,
+            command: self.command,
+            protocol: self.protocol,
+            addresses: self.addresses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Type};
+
+    fn header() -> Vec<u8> {
+        Builder::localhost()
+            .write_tlv(Type::Authority, b"example.com")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn append_hops() {
+        let input = header();
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        assert_eq!(header.hops().unwrap(), vec![]);
+
+        let header = header
+            .with_hop(&Hop::new("edge", 1))
+            .unwrap()
+            .with_hop(&Hop::new(String::from("origin"), u64::MAX))
+            .unwrap();
+        let parsed = Header::try_from(header.as_bytes()).unwrap();
+
+        assert_eq!(parsed, header);
+        assert_eq!(parsed.length(), input.len() - 16 + 2 * (3 + 8) + 4 + 6);
+        assert_eq!(
+            parsed.hops().unwrap(),
+            vec![Hop::new("edge", 1), Hop::new("origin", u64::MAX)]
+        );
+        assert_eq!(
+            parsed.tlvs().next().unwrap().unwrap().kind,
+            u8::from(Type::Authority)
+        );
+    }
+
+    #[test]
+    fn checksum_updated() {
+        let input = Builder::localhost()
+            .write_tlv(Type::CRC32C, &[0; 4])
+            .unwrap()
+            .build()
+            .unwrap();
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        assert_eq!(header.verify_checksum(), Some(false));
+
+        let header = header.with_hop(&Hop::new("edge", 1)).unwrap();
+
+        assert_eq!(header.verify_checksum(), Some(true));
+        assert_eq!(
+            header
+                .with_hop(&Hop::new("origin", 2))
+                .unwrap()
+                .verify_checksum(),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn invalid_hop() {
+        let input = Builder::localhost()
+            .write_tlv(HOP, &[0; 7])
+            .unwrap()
+            .build()
+            .unwrap();
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        assert_eq!(header.hops(), Err(HopError::Invalid(7)));
+
+        let tlv = TypeLengthValue::new(HOP, &[0, 0, 0, 0, 0, 0, 0, 1, 0xFF]);
+        assert_eq!(Hop::try_from(&tlv), Err(HopError::Invalid(9)));
+    }
+
+    #[test]
+    fn owned_hop() {
+        let tlv = TypeLengthValue::new(HOP, b"\0\0\0\0\0\0\0\x2Aproxy").to_owned();
+        let hop = Hop::try_from(&tlv).unwrap();
+
+        assert_eq!(hop, Hop::new("proxy", 42));
+        assert_eq!(hop.to_owned(), hop);
+    }
+}
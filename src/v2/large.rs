@@ -0,0 +1,240 @@
+//! Custom TLV values split across sequential TLVs of the same type, for values (e.g. certificates or quotes)
+//! larger than a vendor is willing to put in a single TLV.
+//!
+//! Each chunk TLV starts with a sub-header of two bytes, the index of the chunk and the total number of chunks,
+//! followed by the next bytes of the value. A value therefore spans at most 255 chunks.
+use crate::prelude::*;
+
+use super::{Header, ParseError, TypeLengthValue, WriteToHeader, Writer};
+use std::io;
+
+/// The number of bytes of the sub-header (index and total) at the start of each chunk.
+const CHUNK_HEADER_LENGTH: usize = 2;
+/// The largest number of bytes of the value carried by a single chunk.
+const MAXIMUM_CHUNK_LENGTH: usize = u16::MAX as usize - CHUNK_HEADER_LENGTH;
+
+/// An error in reassembling the value of chunk TLVs.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ChunkError {
+    #[error("Chunk TLVs of type {0:#04X} are missing, out of order or disagree on their total.")]
+    Invalid(u8),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ChunkError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Invalid(kind) => defmt::write!(f, "Invalid({=u8:#x})", kind),
+            Self::Parse(error) => defmt::write!(f, "Parse({})", error),
+        }
+    }
+}
+
+/// A custom TLV value written as one or more sequential chunk TLVs of the same type.
+/// Use `Header::large_tlv` to reassemble the value on the receiving end.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::v2::{Builder, Command, Header, LargeTlv, Protocol, Version};
+///
+/// let certificate = vec![0xAB; 1000];
+/// let header = Builder::with_addresses(
+///     Version::Two | Command::Proxy,
+///     Protocol::Stream,
+///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+/// )
+/// .write_payload(LargeTlv::new(0xE1, &certificate).chunk_length(400))
+/// .unwrap()
+/// .build()
+/// .unwrap();
+/// let header = Header::try_from(header.as_slice()).unwrap();
+///
+/// assert_eq!(header.tlvs().count(), 3);
+/// assert_eq!(header.large_tlv(0xE1), Ok(Some(certificate)));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LargeTlv<'a> {
+    kind: u8,
+    value: &'a [u8],
+    chunk_length: usize,
+}
+
+impl<'a> LargeTlv<'a> {
+    /// Creates a new `LargeTlv` of the given type, with chunks as large as a TLV allows.
+    pub fn new<T: Into<u8>>(kind: T, value: &'a [u8]) -> Self {
+        LargeTlv {
+            kind: kind.into(),
+            value,
+            chunk_length: MAXIMUM_CHUNK_LENGTH,
+        }
+    }
+
+    /// Limits the number of bytes of the value carried by each chunk.
+    /// Lengths of zero or more than a TLV allows are capped when writing.
+    pub fn chunk_length(mut self, length: u16) -> Self {
+        self.chunk_length = (length as usize).clamp(1, MAXIMUM_CHUNK_LENGTH);
+        self
+    }
+
+    /// The chunk TLVs carrying the value, in order.
+    /// An empty value is a single empty chunk.
+    /// Returns an error when the value needs more than 255 chunks.
+    pub fn chunks(&self) -> io::Result<Vec<TypeLengthValue<'static>>> {
+        let total = self.value.len().max(1) + self.chunk_length - 1;
+        let total = total / self.chunk_length;
+
+        if total > u8::MAX as usize {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+
+        let chunks = self.value.chunks(self.chunk_length);
+        let chunks: Vec<&[u8]> = if self.value.is_empty() {
+            vec![&[]]
+        } else {
+            chunks.collect()
+        };
+
+        Ok(chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut value = Vec::with_capacity(CHUNK_HEADER_LENGTH + chunk.len());
+
+                value.push(index as u8);
+                value.push(total as u8);
+                value.extend_from_slice(chunk);
+
+                TypeLengthValue {
+                    kind: self.kind,
+                    value: value.into(),
+                }
+            })
+            .collect())
+    }
+}
+
+impl<'a> WriteToHeader for LargeTlv<'a> {
+    fn write_to(&self, writer: &mut Writer) -> io::Result<usize> {
+        let mut length = 0;
+
+        for chunk in self.chunks()? {
+            length += chunk.write_to(writer)?;
+        }
+
+        Ok(length)
+    }
+}
+
+impl<'a> Header<'a> {
+    /// Reassembles the value of the chunk TLVs of the given type written by `LargeTlv`, if any.
+    /// Returns a `ChunkError::Invalid` when the chunks are missing, out of order or disagree on their total.
+    /// Fails on the first TLV that cannot be parsed, like iterating over `tlvs`.
+    pub fn large_tlv<T: Into<u8>>(&self, kind: T) -> Result<Option<Vec<u8>>, ChunkError> {
+        let kind = kind.into();
+        let mut value = Vec::new();
+        let mut expected = None;
+        let mut next = 0;
+
+        for tlv in self.tlvs() {
+            let tlv = tlv?;
+
+            if tlv.kind != kind {
+                continue;
+            }
+
+            let (index, total) = match tlv.value.as_ref() {
+                [index, total, ..] => (*index, *total),
+                _ => return Err(ChunkError::Invalid(kind)),
+            };
+
+            if index != next || index >= total || *expected.get_or_insert(total) != total {
+                return Err(ChunkError::Invalid(kind));
+            }
+
+            value.extend_from_slice(&tlv.value[CHUNK_HEADER_LENGTH..]);
+            next += 1;
+        }
+
+        match expected {
+            None => Ok(None),
+            Some(total) if total == next => Ok(Some(value)),
+            Some(..) => Err(ChunkError::Invalid(kind)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::Builder;
+
+    fn build<T: WriteToHeader>(payloads: Vec<T>) -> Vec<u8> {
+        Builder::localhost()
+            .write_payloads(payloads)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn chunks() {
+        let large = LargeTlv::new(0xE0, b"abcde").chunk_length(2);
+
+        assert_eq!(
+            large.chunks().unwrap(),
+            vec![
+                TypeLengthValue::new(0xE0, &b"\x00\x03ab"[..]),
+                TypeLengthValue::new(0xE0, &b"\x01\x03cd"[..]),
+                TypeLengthValue::new(0xE0, &b"\x02\x03e"[..]),
+            ]
+        );
+        assert_eq!(
+            LargeTlv::new(0xE0, b"").chunks().unwrap(),
+            vec![TypeLengthValue::new(0xE0, &[0, 1])]
+        );
+        assert!(LargeTlv::new(0xE0, &[0; 256])
+            .chunk_length(1)
+            .chunks()
+            .is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = build(vec![LargeTlv::new(0xE0, b"").chunk_length(0)]);
+        let header = Header::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(header.large_tlv(0xE0), Ok(Some(Vec::new())));
+        assert_eq!(header.large_tlv(0xE1), Ok(None));
+
+        let value = vec![0x5A; 60000];
+        let bytes = build(vec![LargeTlv::new(0xE0, &value).chunk_length(25000)]);
+        let header = Header::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(header.large_tlv(0xE0), Ok(Some(value)));
+    }
+
+    #[test]
+    fn invalid_chunks() {
+        let chunks = [
+            // An index out of order.
+            vec![(0xE0, &b"\x00\x02a"[..]), (0xE0, &b"\x00\x02b"[..])],
+            // A missing last chunk.
+            vec![(0xE0, &b"\x00\x02a"[..])],
+            // Chunks that disagree on their total.
+            vec![(0xE0, &b"\x00\x02a"[..]), (0xE0, &b"\x01\x03b"[..])],
+            // A chunk without a sub-header.
+            vec![(0xE0, &b"\x00"[..])],
+            // A total of zero.
+            vec![(0xE0, &b"\x00\x00"[..])],
+        ];
+
+        for tlvs in chunks {
+            let bytes = build(tlvs);
+            let header = Header::try_from(bytes.as_slice()).unwrap();
+
+            assert_eq!(header.large_tlv(0xE0), Err(ChunkError::Invalid(0xE0)));
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Parsing that skips over headers with address families or protocols this crate does not implement.
+use super::{
+    field, Command, Header, ParseError, ADDRESS_FAMILY_PROTOCOL, LEFT_MASK, LENGTH, MINIMUM_LENGTH,
+    RIGHT_MASK, VERSION_COMMAND,
+};
+
+/// A binary header parsed with `Header::parse_lenient`.
+// Parsing never allocates, so the header is not boxed.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lenient<'a> {
+    /// A header with a supported address family and protocol.
+    Supported(Header<'a>),
+    /// A header with an address family or protocol that is not in the specification, whose payload was skipped.
+    /// The specification allows receivers to accept such connections as if they had no header.
+    Unsupported {
+        /// The bytes of the whole header, payload included.
+        header: &'a [u8],
+        command: Command,
+        /// The address family, in the left 4 bits.
+        family: u8,
+        /// The transport protocol, in the right 4 bits.
+        protocol: u8,
+    },
+}
+
+impl<'a> Lenient<'a> {
+    /// The total length in bytes of the header, which is the number of bytes to skip to reach the payload.
+    pub fn len(&self) -> usize {
+        match self {
+            Lenient::Supported(header) => header.len(),
+            Lenient::Unsupported { header, .. } => header.len(),
+        }
+    }
+
+    /// Tests whether the header's underlying bytes are empty, which is never the case for a parsed header.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The supported header, if any.
+    pub fn supported(self) -> Option<Header<'a>> {
+        match self {
+            Lenient::Supported(header) => Some(header),
+            Lenient::Unsupported { .. } => None,
+        }
+    }
+}
+
+impl<'a> Header<'a> {
+    /// Parses a binary PROXY protocol header like `try_from`, but skips over the advertised length of headers
+    /// with an unknown address family or protocol instead of failing with `ParseError::AddressFamily`
+    /// or `ParseError::Protocol`. The prefix, version and command are still validated.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Command, Header, Lenient};
+    ///
+    /// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x41\x00\x02\xAA\xBBHello";
+    /// let header = Header::parse_lenient(input).unwrap();
+    ///
+    /// assert_eq!(
+    ///     header,
+    ///     Lenient::Unsupported {
+    ///         header: &input[..18],
+    ///         command: Command::Proxy,
+    ///         family: 0x40,
+    ///         protocol: 0x01,
+    ///     }
+    /// );
+    /// assert_eq!(&input[header.len()..], b"Hello");
+    /// ```
+    pub fn parse_lenient(input: &'a [u8]) -> Result<Lenient<'a>, ParseError> {
+        match Header::try_from(input) {
+            Ok(header) => Ok(Lenient::Supported(header)),
+            // Both errors are only reported once the input holds the fixed-size part of a header.
+            Err(ParseError::AddressFamily(..)) | Err(ParseError::Protocol(..)) => {
+                let command = match input[VERSION_COMMAND] & RIGHT_MASK {
+                    0x00 => Command::Local,
+                    _ => Command::Proxy,
+                };
+                let length = field::read_u16(input, LENGTH) as usize;
+
+                if input.len() < MINIMUM_LENGTH + length {
+                    return Err(ParseError::Partial(input.len() - MINIMUM_LENGTH, length));
+                }
+
+                Ok(Lenient::Unsupported {
+                    header: &input[..MINIMUM_LENGTH + length],
+                    command,
+                    family: input[ADDRESS_FAMILY_PROTOCOL] & LEFT_MASK,
+                    protocol: input[ADDRESS_FAMILY_PROTOCOL] & RIGHT_MASK,
+                })
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::PROTOCOL_PREFIX;
+
+    #[test]
+    fn unsupported() {
+        let mut input = Vec::from(PROTOCOL_PREFIX);
+
+        input.extend([0x20, 0x13, 0x00, 0x03, 1, 2, 3]);
+
+        assert_eq!(
+            Header::parse_lenient(&input[..input.len() - 1]),
+            Err(ParseError::Partial(2, 3))
+        );
+        assert_eq!(
+            Header::parse_lenient(&input),
+            Ok(Lenient::Unsupported {
+                header: &input,
+                command: Command::Local,
+                family: 0x10,
+                protocol: 0x03,
+            })
+        );
+
+        input[VERSION_COMMAND] = 0x22;
+
+        assert_eq!(
+            Header::parse_lenient(&input),
+            Err(ParseError::Command(0x02))
+        );
+    }
+
+    #[test]
+    fn supported() {
+        let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x00\x00\x00";
+
+        assert_eq!(
+            Header::parse_lenient(input).unwrap().supported(),
+            Some(Header::try_from(&input[..]).unwrap())
+        );
+        assert_eq!(
+            Header::parse_lenient(&input[..10]),
+            Err(ParseError::Incomplete(10))
+        );
+    }
+}
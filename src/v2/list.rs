@@ -0,0 +1,219 @@
+//! An ordered list of validated TLVs that only allocates when a header has more than a few of them.
+use crate::prelude::*;
+
+use super::{
+    field, Header, HeaderRef, ParseError, TypeLengthValue, TypeLengthValues, MINIMUM_TLV_LENGTH,
+};
+use alloc::borrow::Cow;
+
+/// The number of TLVs a `TlvList` holds without allocating.
+/// Enable the `tlv-inline-8` or `tlv-inline-16` feature to hold more, at the cost of a larger `TlvList`.
+#[cfg(feature = "tlv-inline-16")]
+pub const INLINE_TLVS: usize = 16;
+/// The number of TLVs a `TlvList` holds without allocating.
+/// Enable the `tlv-inline-8` or `tlv-inline-16` feature to hold more, at the cost of a larger `TlvList`.
+#[cfg(all(feature = "tlv-inline-8", not(feature = "tlv-inline-16")))]
+pub const INLINE_TLVS: usize = 8;
+/// The number of TLVs a `TlvList` holds without allocating.
+/// Enable the `tlv-inline-8` or `tlv-inline-16` feature to hold more, at the cost of a larger `TlvList`.
+#[cfg(not(any(feature = "tlv-inline-8", feature = "tlv-inline-16")))]
+pub const INLINE_TLVS: usize = 4;
+
+/// The offsets of each TLV in the `TypeLengthValue` portion of a payload, which is at most `u16::MAX` bytes.
+#[derive(Clone, Debug, PartialEq)]
+enum Offsets {
+    Inline {
+        length: usize,
+        offsets: [u16; INLINE_TLVS],
+    },
+    Spilled(Vec<u16>),
+}
+
+impl Offsets {
+    fn push(&mut self, offset: u16) {
+        match self {
+            Offsets::Inline { length, offsets } if *length < INLINE_TLVS => {
+                offsets[*length] = offset;
+                *length += 1;
+            }
+            Offsets::Inline { offsets, .. } => {
+                let mut spilled = Vec::with_capacity(INLINE_TLVS * 2);
+
+                spilled.extend_from_slice(offsets);
+                spilled.push(offset);
+
+                *self = Offsets::Spilled(spilled);
+            }
+            Offsets::Spilled(offsets) => offsets.push(offset),
+        }
+    }
+
+    fn as_slice(&self) -> &[u16] {
+        match self {
+            Offsets::Inline { length, offsets } => &offsets[..*length],
+            Offsets::Spilled(offsets) => offsets.as_slice(),
+        }
+    }
+}
+
+/// The TLVs of a header in order, validated up front and indexed by their offsets into the header.
+///
+/// Unlike collecting `TypeLengthValues` into a `Vec`, a `TlvList` of at most `INLINE_TLVS` TLVs never allocates,
+/// and decoding a TLV from its offset is only a matter of reading its type and length.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::v2::{Builder, Command, Header, Protocol, Type, Version};
+///
+/// let header = Builder::with_addresses(
+///     Version::Two | Command::Proxy,
+///     Protocol::Stream,
+///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+/// )
+/// .write_tlv(Type::Authority, b"example.com")
+/// .unwrap()
+/// .write_tlv(Type::NoOp, b"")
+/// .unwrap()
+/// .build()
+/// .unwrap();
+/// let header = Header::try_from(header.as_slice()).unwrap();
+/// let tlvs = header.tlv_list().unwrap();
+///
+/// assert_eq!(tlvs.len(), 2);
+/// assert!(tlvs.is_inline());
+/// assert_eq!(tlvs.get(1).unwrap().kind, Type::NoOp as u8);
+/// assert_eq!(tlvs.find(Type::Authority as u8).unwrap().value.as_ref(), b"example.com");
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlvList<'a> {
+    bytes: &'a [u8],
+    offsets: Offsets,
+}
+
+impl<'a> TlvList<'a> {
+    /// Validates each of the TLVs in the `TypeLengthValue` portion of a payload, recording where each one starts.
+    fn parse(bytes: &'a [u8]) -> Result<Self, ParseError> {
+        let mut offsets = Offsets::Inline {
+            length: 0,
+            offsets: [0; INLINE_TLVS],
+        };
+        let mut offset = 0;
+
+        for tlv in TypeLengthValues::from(bytes) {
+            let tlv = tlv?;
+
+            offsets.push(offset as u16);
+            offset += MINIMUM_TLV_LENGTH + tlv.value.len();
+        }
+
+        Ok(TlvList { bytes, offsets })
+    }
+
+    /// The number of TLVs in this `TlvList`.
+    pub fn len(&self) -> usize {
+        self.offsets.as_slice().len()
+    }
+
+    /// Tests whether this `TlvList` has no TLVs.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Tests whether the TLVs fit in the inline capacity, so building this `TlvList` did not allocate.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.offsets, Offsets::Inline { .. })
+    }
+
+    /// The TLV at the given position, if any.
+    pub fn get(&self, index: usize) -> Option<TypeLengthValue<'a>> {
+        let offset = *self.offsets.as_slice().get(index)? as usize;
+        let length = field::read_u16(self.bytes, offset + 1) as usize;
+        let start = offset + MINIMUM_TLV_LENGTH;
+
+        Some(TypeLengthValue {
+            kind: self.bytes[offset],
+            value: Cow::Borrowed(&self.bytes[start..start + length]),
+        })
+    }
+
+    /// The first TLV of the given type, if any.
+    pub fn find(&self, kind: u8) -> Option<TypeLengthValue<'a>> {
+        self.iter().find(|tlv| tlv.kind == kind)
+    }
+
+    /// An `Iterator` of the TLVs in order.
+    pub fn iter(&self) -> impl Iterator<Item = TypeLengthValue<'a>> + '_ {
+        (0..self.len()).filter_map(move |index| self.get(index))
+    }
+}
+
+impl<'a> Header<'a> {
+    /// Validates and indexes the TLVs of this `Header`, without allocating for up to `INLINE_TLVS` TLVs.
+    /// Returns an error if any TLV is invalid.
+    pub fn tlv_list(&self) -> Result<TlvList<'_>, ParseError> {
+        TlvList::parse(self.tlv_bytes())
+    }
+}
+
+impl<'a> HeaderRef<'a> {
+    /// Validates and indexes the TLVs of this `HeaderRef`, without allocating for up to `INLINE_TLVS` TLVs.
+    /// Returns an error if any TLV is invalid.
+    pub fn tlv_list(&self) -> Result<TlvList<'a>, ParseError> {
+        TlvList::parse(self.tlv_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::PROTOCOL_PREFIX;
+
+    fn header(tlvs: usize) -> Vec<u8> {
+        let mut header = Vec::from(PROTOCOL_PREFIX);
+        let length = 12 + tlvs * 4;
+
+        header.extend([0x21, 0x11]);
+        header.extend((length as u16).to_be_bytes());
+        header.extend([127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]);
+
+        for index in 0..tlvs {
+            header.extend([0xE0, 0, 1, index as u8]);
+        }
+
+        header
+    }
+
+    #[test]
+    fn inline_and_spilled() {
+        for count in [0, 1, INLINE_TLVS, INLINE_TLVS + 1, 40] {
+            let input = header(count);
+            let header = Header::try_from(input.as_slice()).unwrap();
+            let list = header.tlv_list().unwrap();
+            let expected: Vec<TypeLengthValue> = header.tlvs().map(Result::unwrap).collect();
+
+            assert_eq!(list.len(), count);
+            assert_eq!(list.is_inline(), count <= INLINE_TLVS);
+            assert_eq!(list.iter().collect::<Vec<_>>(), expected);
+            assert_eq!(list.get(count), None);
+            assert_eq!(
+                HeaderRef::try_from(input.as_slice())
+                    .unwrap()
+                    .tlv_list()
+                    .unwrap(),
+                list
+            );
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let mut input = header(2);
+
+        input[15] += 2;
+        input.extend([0xE1, 0]);
+
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        assert_eq!(header.tlv_list(), Err(ParseError::Leftovers(2)));
+    }
+}
@@ -0,0 +1,94 @@
+//! Collecting the TLVs of a header into a map by type, for consumers that look up TLVs rather than iterate over them.
+use crate::prelude::*;
+
+use super::{Header, ParseError};
+use alloc::borrow::Cow;
+use std::collections::HashMap;
+
+/// How `Header::tlv_map` handles more than one TLV of the same type.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Duplicates {
+    /// The value of the last TLV of a type replaces those before it.
+    LastWins,
+    /// More than one TLV of a type is a `MapError::Duplicate`.
+    Reject,
+}
+
+/// An error in collecting the TLVs of a header into a map.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum MapError {
+    #[error("Header contains more than one TLV of type {0:#04X}.")]
+    Duplicate(u8),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for MapError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Duplicate(kind) => defmt::write!(f, "Duplicate({=u8:#x})", kind),
+            Self::Parse(error) => defmt::write!(f, "Parse({})", error),
+        }
+    }
+}
+
+impl<'a> Header<'a> {
+    /// The values of the TLVs of this `Header` by type, handling duplicate types as given.
+    /// Fails on the first TLV that cannot be parsed, like iterating over `tlvs`.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Duplicates, Header, MapError, Type};
+    ///
+    /// let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x19\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB\x01\x00\x02h2\x04\x00\x00\x01\x00\x02h3";
+    /// let header = Header::try_from(&input[..]).unwrap();
+    /// let tlvs = header.tlv_map(Duplicates::LastWins).unwrap();
+    ///
+    /// assert_eq!(tlvs[&(Type::ALPN as u8)], b"h3");
+    /// assert_eq!(tlvs.len(), 2);
+    /// assert_eq!(
+    ///     header.tlv_map(Duplicates::Reject),
+    ///     Err(MapError::Duplicate(Type::ALPN as u8))
+    /// );
+    /// ```
+    pub fn tlv_map(&self, duplicates: Duplicates) -> Result<HashMap<u8, &[u8]>, MapError> {
+        let mut map = HashMap::new();
+
+        for tlv in self.tlvs() {
+            let tlv = tlv?;
+            let value = match tlv.value {
+                Cow::Borrowed(value) => value,
+                // The TLVs of a header always borrow its bytes.
+                Cow::Owned(..) => unreachable!(),
+            };
+
+            if map.insert(tlv.kind, value).is_some() && duplicates == Duplicates::Reject {
+                return Err(MapError::Duplicate(tlv.kind));
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tlv_map() {
+        let input = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x15\x7F\x00\x00\x01\x7F\x00\x00\x02\x00\x50\x01\xBB\x04\x00\x00\x01\x00\x02h2\x02";
+        let header = Header::try_from(&input[..]).unwrap();
+
+        assert_eq!(
+            header.tlv_map(Duplicates::Reject),
+            Err(MapError::Parse(ParseError::Leftovers(1)))
+        );
+
+        let input = b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00";
+        let header = Header::try_from(&input[..]).unwrap();
+
+        assert!(header.tlv_map(Duplicates::Reject).unwrap().is_empty());
+    }
+}
@@ -1,22 +1,70 @@
 //! Version 2 of the HAProxy protocol (binary version).
 //!
 //! See <https://haproxy.org/download/1.8/doc/proxy-protocol.txt>
-use std::prelude::v1::*;
+//!
+//! Parsing never assumes the input is aligned, so headers may be parsed from packed or misaligned buffers:
+//! multi-byte fields are copied out byte by byte (see `field`), and the module forbids `unsafe` code.
+//!
+//! The TLVs this crate defines itself (`HOP`, `QUOTE` and `SIGNATURE`) use types from the range the specification
+//! reserves for custom types (`0xE0` to `0xEF`), so they never collide with types the specification assigns later.
+#![forbid(unsafe_code)]
 
+#[cfg(feature = "attestation")]
+mod attestation;
+#[cfg(any(feature = "std", feature = "tstd"))]
 mod builder;
+mod checksum;
+mod edit;
 mod error;
+pub(crate) mod field;
+mod fixed;
+mod hop;
+#[cfg(any(feature = "std", feature = "tstd"))]
+mod large;
+mod lenient;
+mod list;
+#[cfg(any(feature = "std", feature = "tstd"))]
+mod map;
 mod model;
+#[cfg(any(feature = "std", feature = "tstd"))]
+mod passthrough;
+pub mod signature;
+#[cfg(any(feature = "std", feature = "tstd"))]
+mod ssl;
+#[cfg(feature = "serde")]
+mod tlv_value;
+mod view;
 
 pub use crate::ip::{IPv4, IPv6};
-pub use builder::{Builder, WriteToHeader, Writer};
+use crate::net::{Ipv4Addr, Ipv6Addr};
+use crate::observe::{Decision, Observer, Value};
+use alloc::borrow::Cow;
+#[cfg(feature = "attestation")]
+pub use attestation::{Quote, QuoteError, QUOTE};
+#[cfg(any(feature = "std", feature = "tstd"))]
+pub use builder::{Builder, Family, TlvOrder, Unchecked, Unspecified, WriteToHeader, Writer};
+use core::fmt;
+pub use edit::EditError;
 pub use error::ParseError;
+pub use fixed::{CapacityError, FixedBuilder, FixedHeader};
+pub use hop::{Hop, HopError, HOP};
+#[cfg(any(feature = "std", feature = "tstd"))]
+pub use large::{ChunkError, LargeTlv};
+pub use lenient::Lenient;
+pub use list::{TlvList, INLINE_TLVS};
+#[cfg(any(feature = "std", feature = "tstd"))]
+pub use map::{Duplicates, MapError};
 pub use model::{
     AddressFamily, Addresses, Command, Header, Protocol, Type, TypeLengthValue, TypeLengthValues,
-    Unix, Version, PROTOCOL_PREFIX,
+    Unix, UnixAddress, Version, PROTOCOL_PREFIX,
 };
-use model::{MINIMUM_LENGTH, MINIMUM_TLV_LENGTH};
-use std::borrow::Cow;
-use std::net::{Ipv4Addr, Ipv6Addr};
+pub(crate) use model::{MINIMUM_LENGTH, MINIMUM_TLV_LENGTH};
+#[cfg(any(feature = "std", feature = "tstd"))]
+pub use passthrough::{Passthrough, TlvAction};
+pub use signature::{SignError, SignatureProvider, SIGNATURE};
+#[cfg(any(feature = "std", feature = "tstd"))]
+pub use ssl::{SslInfo, CLIENT_CERT_CONNECTION, CLIENT_CERT_SESSION, CLIENT_SSL};
+pub use view::HeaderRef;
 
 /// Masks the right 4-bits so only the left 4-bits are present.
 const LEFT_MASK: u8 = 0xF0;
@@ -29,15 +77,37 @@ const ADDRESS_FAMILY_PROTOCOL: usize = VERSION_COMMAND + 1;
 /// The index of the start of the big-endian u16 length.
 const LENGTH: usize = ADDRESS_FAMILY_PROTOCOL + 1;
 
-/// Parses the addresses from the header payload.
-fn parse_addresses(address_family: AddressFamily, bytes: &[u8]) -> Addresses {
+/// The offset, length and name of a field of a header, as reported to an observer.
+type Span = (usize, usize, &'static str);
+
+const VERSION_SPAN: Span = (VERSION_COMMAND, 1, "version");
+const COMMAND_SPAN: Span = (VERSION_COMMAND, 1, "command");
+const ADDRESS_FAMILY_SPAN: Span = (ADDRESS_FAMILY_PROTOCOL, 1, "address_family");
+const PROTOCOL_SPAN: Span = (ADDRESS_FAMILY_PROTOCOL, 1, "protocol");
+const LENGTH_SPAN: Span = (LENGTH, 2, "length");
+
+/// Parses the addresses from the header payload, reporting each field to the observer.
+fn parse_addresses<O: Observer>(
+    address_family: AddressFamily,
+    bytes: &[u8],
+    observer: &mut O,
+) -> Addresses {
     match address_family {
         AddressFamily::Unspecified => Addresses::Unspecified,
         AddressFamily::IPv4 => {
-            let source_address = Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]);
-            let destination_address = Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]);
-            let source_port = u16::from_be_bytes([bytes[8], bytes[9]]);
-            let destination_port = u16::from_be_bytes([bytes[10], bytes[11]]);
+            let source_address = Ipv4Addr::from(field::array::<4>(bytes, 0));
+            let destination_address = Ipv4Addr::from(field::array::<4>(bytes, 4));
+            let source_port = field::read_u16(bytes, 8);
+            let destination_port = field::read_u16(bytes, 10);
+
+            read_ip(
+                observer,
+                4,
+                &source_address,
+                &destination_address,
+                source_port,
+                destination_port,
+            );
 
             Addresses::IPv4(IPv4 {
                 source_address,
@@ -47,16 +117,19 @@ fn parse_addresses(address_family: AddressFamily, bytes: &[u8]) -> Addresses {
             })
         }
         AddressFamily::IPv6 => {
-            let mut address = [0; 16];
-
-            address[..].copy_from_slice(&bytes[..16]);
-            let source_address = Ipv6Addr::from(address);
-
-            address[..].copy_from_slice(&bytes[16..32]);
-            let destination_address = Ipv6Addr::from(address);
-
-            let source_port = u16::from_be_bytes([bytes[32], bytes[33]]);
-            let destination_port = u16::from_be_bytes([bytes[34], bytes[35]]);
+            let source_address = Ipv6Addr::from(field::array::<16>(bytes, 0));
+            let destination_address = Ipv6Addr::from(field::array::<16>(bytes, 16));
+            let source_port = field::read_u16(bytes, 32);
+            let destination_port = field::read_u16(bytes, 34);
+
+            read_ip(
+                observer,
+                16,
+                &source_address,
+                &destination_address,
+                source_port,
+                destination_port,
+            );
 
             Addresses::IPv6(IPv6 {
                 source_address,
@@ -66,96 +139,246 @@ fn parse_addresses(address_family: AddressFamily, bytes: &[u8]) -> Addresses {
             })
         }
         AddressFamily::Unix => {
-            let mut source = [0; 108];
-            let mut destination = [0; 108];
+            let unix = Unix {
+                source: field::array(bytes, 0),
+                destination: field::array(bytes, 108),
+            };
+            let source = (MINIMUM_LENGTH, 108, "source_address");
+            let destination = (MINIMUM_LENGTH + 108, 108, "destination_address");
 
-            source[..].copy_from_slice(&bytes[..108]);
-            destination[..].copy_from_slice(&bytes[108..]);
+            accept(observer, source, Value::Bytes(unix.source_path()));
+            accept(observer, destination, Value::Bytes(unix.destination_path()));
 
-            Addresses::Unix(Unix {
-                source,
-                destination,
-            })
+            Addresses::Unix(unix)
         }
     }
 }
 
-impl<'a> TryFrom<&'a [u8]> for Header<'a> {
-    type Error = ParseError;
+/// Reports the addresses and ports of an IP header, whose addresses have the given length, to the observer.
+fn read_ip<O: Observer>(
+    observer: &mut O,
+    length: usize,
+    source_address: &dyn fmt::Display,
+    destination_address: &dyn fmt::Display,
+    source_port: u16,
+    destination_port: u16,
+) {
+    let ports = MINIMUM_LENGTH + 2 * length;
+    let source = (MINIMUM_LENGTH, length, "source_address");
+    let destination = (MINIMUM_LENGTH + length, length, "destination_address");
+
+    accept(observer, source, Value::Display(source_address));
+    accept(observer, destination, Value::Display(destination_address));
+    accept(
+        observer,
+        (ports, 2, "source_port"),
+        Value::Display(&source_port),
+    );
+    accept(
+        observer,
+        (ports + 2, 2, "destination_port"),
+        Value::Display(&destination_port),
+    );
+}
 
-    fn try_from(input: &'a [u8]) -> Result<Self, Self::Error> {
-        if input.len() < PROTOCOL_PREFIX.len() {
-            if PROTOCOL_PREFIX.starts_with(input) {
-                return Err(ParseError::Incomplete(input.len()));
-            } else {
-                return Err(ParseError::Prefix);
-            }
-        }
+/// Reports a field the parser continued after to the observer.
+fn accept<O: Observer>(observer: &mut O, (offset, length, name): Span, value: Value<'_>) {
+    observer.field(offset, length, name, value, Decision::Continue);
+}
 
-        if &input[..VERSION_COMMAND] != PROTOCOL_PREFIX {
-            return Err(ParseError::Prefix);
-        }
+/// Reports the field the parser failed at to the observer, returning the error.
+fn reject<O: Observer>(
+    observer: &mut O,
+    (offset, length, name): Span,
+    value: Value<'_>,
+    error: ParseError,
+) -> ParseError {
+    let decision = Decision::failure(&error, error.code());
+
+    observer.field(offset, length, name, value, decision);
+    error
+}
 
-        if input.len() < MINIMUM_LENGTH {
-            return Err(ParseError::Incomplete(input.len()));
-        }
+/// The fields of the first 16 bytes of a header.
+pub(crate) struct Fixed {
+    pub(crate) version: Version,
+    pub(crate) command: Command,
+    pub(crate) address_family: AddressFamily,
+    pub(crate) protocol: Protocol,
+    /// The length in bytes of the addresses in the payload.
+    pub(crate) address_bytes: usize,
+    /// The total length in bytes of the header.
+    pub(crate) length: usize,
+}
 
-        let version = match input[VERSION_COMMAND] & LEFT_MASK {
-            0x20 => Version::Two,
-            v => return Err(ParseError::Version(v)),
-        };
-        let command = match input[VERSION_COMMAND] & RIGHT_MASK {
-            0x00 => Command::Local,
-            0x01 => Command::Proxy,
-            c => return Err(ParseError::Command(c)),
-        };
+/// Validates the fixed-size part of a header and checks that the input holds all of its payload.
+fn parse_fixed<O: Observer>(input: &[u8], observer: &mut O) -> Result<Fixed, ParseError> {
+    let fixed = decode_fixed(input, observer)?;
 
-        let address_family = match input[ADDRESS_FAMILY_PROTOCOL] & LEFT_MASK {
-            0x00 => AddressFamily::Unspecified,
-            0x10 => AddressFamily::IPv4,
-            0x20 => AddressFamily::IPv6,
-            0x30 => AddressFamily::Unix,
-            a => return Err(ParseError::AddressFamily(a)),
-        };
-        let protocol = match input[ADDRESS_FAMILY_PROTOCOL] & RIGHT_MASK {
-            0x00 => Protocol::Unspecified,
-            0x01 => Protocol::Stream,
-            0x02 => Protocol::Datagram,
-            p => return Err(ParseError::Protocol(p)),
+    if input.len() < fixed.length {
+        return Err(ParseError::Partial(
+            input.len() - MINIMUM_LENGTH,
+            fixed.length - MINIMUM_LENGTH,
+        ));
+    }
+
+    Ok(fixed)
+}
+
+/// Validates the fixed-size part of a header, without checking that the input holds its payload,
+/// reporting each field to the observer as it is decided on.
+pub(crate) fn decode_fixed<O: Observer>(
+    input: &[u8],
+    observer: &mut O,
+) -> Result<Fixed, ParseError> {
+    let prefix = &input[..input.len().min(VERSION_COMMAND)];
+    let span = (0, prefix.len(), "prefix");
+
+    if input.len() < PROTOCOL_PREFIX.len() {
+        let error = if PROTOCOL_PREFIX.starts_with(input) {
+            ParseError::Incomplete(input.len())
+        } else {
+            ParseError::Prefix
         };
 
-        let length = u16::from_be_bytes([input[LENGTH], input[LENGTH + 1]]) as usize;
-        let address_family_bytes = address_family.byte_length().unwrap_or_default();
+        return Err(reject(observer, span, Value::Bytes(prefix), error));
+    }
+
+    if prefix != PROTOCOL_PREFIX {
+        return Err(reject(
+            observer,
+            span,
+            Value::Bytes(prefix),
+            ParseError::Prefix,
+        ));
+    }
+
+    accept(observer, span, Value::Bytes(prefix));
 
-        if length < address_family_bytes {
-            return Err(ParseError::InvalidAddresses(length, address_family_bytes));
+    if input.len() < MINIMUM_LENGTH {
+        return Err(ParseError::Incomplete(input.len()));
+    }
+
+    let version = match input[VERSION_COMMAND] & LEFT_MASK {
+        0x20 => Version::Two,
+        v => {
+            let version = v >> 4;
+            let error = ParseError::Version(v);
+
+            return Err(reject(
+                observer,
+                VERSION_SPAN,
+                Value::Display(&version),
+                error,
+            ));
         }
+    };
+
+    accept(observer, VERSION_SPAN, Value::Display(&2));
 
-        let full_length = MINIMUM_LENGTH + length;
+    let (command, name) = match input[VERSION_COMMAND] & RIGHT_MASK {
+        0x00 => (Command::Local, "LOCAL"),
+        0x01 => (Command::Proxy, "PROXY"),
+        c => {
+            let error = ParseError::Command(c);
 
-        if input.len() < full_length {
-            return Err(ParseError::Partial(input.len() - MINIMUM_LENGTH, length));
+            return Err(reject(observer, COMMAND_SPAN, Value::Hex(c), error));
         }
+    };
+
+    accept(observer, COMMAND_SPAN, Value::Text(name));
+
+    let (address_family, name) = match input[ADDRESS_FAMILY_PROTOCOL] & LEFT_MASK {
+        0x00 => (AddressFamily::Unspecified, "UNSPEC"),
+        0x10 => (AddressFamily::IPv4, "INET"),
+        0x20 => (AddressFamily::IPv6, "INET6"),
+        0x30 => (AddressFamily::Unix, "UNIX"),
+        a => {
+            let error = ParseError::AddressFamily(a);
+
+            return Err(reject(
+                observer,
+                ADDRESS_FAMILY_SPAN,
+                Value::Hex(a >> 4),
+                error,
+            ));
+        }
+    };
 
-        let header = &input[..full_length];
-        let addresses = parse_addresses(
-            address_family,
-            &header[MINIMUM_LENGTH..MINIMUM_LENGTH + address_family_bytes],
-        );
+    accept(observer, ADDRESS_FAMILY_SPAN, Value::Text(name));
+
+    let (protocol, name) = match input[ADDRESS_FAMILY_PROTOCOL] & RIGHT_MASK {
+        0x00 => (Protocol::Unspecified, "UNSPEC"),
+        0x01 => (Protocol::Stream, "STREAM"),
+        0x02 => (Protocol::Datagram, "DGRAM"),
+        p => {
+            let error = ParseError::Protocol(p);
 
-        Ok(Header {
-            header: Cow::Borrowed(header),
-            version,
-            command,
-            protocol,
-            addresses,
-        })
+            return Err(reject(observer, PROTOCOL_SPAN, Value::Hex(p), error));
+        }
+    };
+
+    accept(observer, PROTOCOL_SPAN, Value::Text(name));
+
+    let length = field::read_u16(input, LENGTH) as usize;
+    let address_bytes = address_family.byte_length().unwrap_or_default();
+
+    if length < address_bytes {
+        let error = ParseError::InvalidAddresses(length, address_bytes);
+
+        return Err(reject(
+            observer,
+            LENGTH_SPAN,
+            Value::Display(&length),
+            error,
+        ));
     }
+
+    accept(observer, LENGTH_SPAN, Value::Display(&length));
+
+    Ok(Fixed {
+        version,
+        command,
+        address_family,
+        protocol,
+        address_bytes,
+        length: MINIMUM_LENGTH + length,
+    })
+}
+
+/// Parses a binary header like `try_from`, reporting each field to the observer as it is decided on.
+pub(crate) fn parse_observed<'a, O: Observer>(
+    input: &'a [u8],
+    observer: &mut O,
+) -> Result<Header<'a>, ParseError> {
+    let fixed = parse_fixed(input, observer)?;
+    let header = &input[..fixed.length];
+    let addresses = parse_addresses(
+        fixed.address_family,
+        &header[MINIMUM_LENGTH..MINIMUM_LENGTH + fixed.address_bytes],
+        observer,
+    );
+
+    Ok(Header {
+        header: Cow::Borrowed(header),
+        version: fixed.version,
+        command: fixed.command,
+        protocol: fixed.protocol,
+        addresses,
+    })
 }
 
+impl<'a> TryFrom<&'a [u8]> for Header<'a> {
+    type Error = ParseError;
+
+    fn try_from(input: &'a [u8]) -> Result<Self, Self::Error> {
+        parse_observed(input, &mut ())
+    }
+}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::PartialResult;
     use model::{Type, TypeLengthValue};
 
     #[test]
@@ -188,8 +411,9 @@ mod tests {
             actual.address_bytes(),
             &[127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]
         );
-        assert_eq!(actual.tlv_bytes(), &[]);
+        assert_eq!(actual.tlv_bytes(), &[] as &[u8]);
         assert_eq!(actual.as_bytes(), input.as_slice());
+        assert_eq!(AsRef::<[u8]>::as_ref(&actual), input.as_slice());
     }
 
     #[test]
@@ -222,7 +446,7 @@ mod tests {
             actual.address_bytes(),
             &[127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]
         );
-        assert_eq!(actual.tlv_bytes(), &[]);
+        assert_eq!(actual.tlv_bytes(), &[] as &[u8]);
         assert_eq!(actual.as_bytes(), input.as_slice());
     }
 
@@ -251,7 +475,7 @@ mod tests {
         assert_eq!(actual.length(), 8);
         assert_eq!(actual.address_family(), AddressFamily::Unspecified);
         assert_eq!(actual.address_bytes(), &[127, 0, 0, 1, 127, 0, 0, 2]);
-        assert_eq!(actual.tlv_bytes(), &[]);
+        assert_eq!(actual.tlv_bytes(), &[] as &[u8]);
         assert_eq!(actual.as_bytes(), input.as_slice());
     }
 
@@ -375,7 +599,7 @@ mod tests {
             actual.address_bytes(),
             &[127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]
         );
-        assert_eq!(actual.tlv_bytes(), &[]);
+        assert_eq!(actual.tlv_bytes(), &[] as &[u8]);
         assert_eq!(actual.as_bytes(), header);
     }
 
@@ -669,8 +893,8 @@ mod tests {
         assert_eq!(actual_tlvs, vec![]);
         assert_eq!(actual.length(), 0);
         assert_eq!(actual.address_family(), AddressFamily::Unspecified);
-        assert_eq!(actual.address_bytes(), &[]);
-        assert_eq!(actual.tlv_bytes(), &[]);
+        assert_eq!(actual.address_bytes(), &[] as &[u8]);
+        assert_eq!(actual.tlv_bytes(), &[] as &[u8]);
         assert_eq!(actual.as_bytes(), header);
     }
 
@@ -704,7 +928,7 @@ mod tests {
             actual.address_bytes(),
             &[127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 0xbb, 1]
         );
-        assert_eq!(actual.tlv_bytes(), &[]);
+        assert_eq!(actual.tlv_bytes(), &[] as &[u8]);
         assert_eq!(actual.as_bytes(), input.as_slice());
     }
 
@@ -724,6 +948,150 @@ mod tests {
         );
     }
 
+    #[test]
+    fn length_boundaries() {
+        let families = [
+            (0x00, AddressFamily::Unspecified),
+            (0x11, AddressFamily::IPv4),
+            (0x21, AddressFamily::IPv6),
+            (0x31, AddressFamily::Unix),
+        ];
+
+        for (family, address_family) in families {
+            let address_bytes = address_family.byte_length().unwrap_or_default();
+            let lengths = [0, 1, 2, 3, 4]
+                .into_iter()
+                .chain((address_bytes.max(1) - 1)..address_bytes + 5)
+                .chain([u16::MAX as usize]);
+
+            for length in lengths {
+                let mut input = Vec::from(PROTOCOL_PREFIX);
+
+                input.extend([0x21, family]);
+                input.extend((length as u16).to_be_bytes());
+                input.resize(MINIMUM_LENGTH + length, 0);
+
+                if length > address_bytes {
+                    // A NoOp TLV of zero length for every full 3 bytes after the addresses.
+                    for byte in input[MINIMUM_LENGTH + address_bytes..]
+                        .iter_mut()
+                        .step_by(3)
+                    {
+                        *byte = Type::NoOp as u8;
+                    }
+                }
+
+                for available in [0, length / 2, length.max(1) - 1, length, length + 3] {
+                    let mut bytes = input.clone();
+
+                    bytes.resize(MINIMUM_LENGTH + available, 0);
+
+                    let actual = Header::try_from(bytes.as_slice());
+
+                    let view = HeaderRef::try_from(bytes.as_slice());
+
+                    assert_eq!(view.as_ref().err(), actual.as_ref().err());
+                    assert_eq!(
+                        view.map(|header| header.len()).ok(),
+                        actual.as_ref().ok().map(|header| header.as_bytes().len())
+                    );
+
+                    if length < address_bytes {
+                        assert_eq!(
+                            actual,
+                            Err(ParseError::InvalidAddresses(length, address_bytes))
+                        );
+                        assert!(actual.is_complete());
+                    } else if available < length {
+                        assert_eq!(actual, Err(ParseError::Partial(available, length)));
+                        assert!(actual.is_incomplete());
+                    } else {
+                        // The whole payload of an unspecified address family is its address.
+                        let address_bytes = address_family.byte_length().unwrap_or(length);
+                        let header = actual.unwrap();
+                        let leftovers = (length - address_bytes) % MINIMUM_TLV_LENGTH;
+                        let tlvs: Vec<_> = header.tlvs().collect();
+
+                        assert_eq!(header.as_bytes().len(), MINIMUM_LENGTH + length);
+                        assert_eq!(header.address_bytes().len(), address_bytes);
+                        assert_eq!(
+                            tlvs.iter().filter(|tlv| tlv.is_ok()).count(),
+                            (length - address_bytes) / MINIMUM_TLV_LENGTH
+                        );
+
+                        if leftovers == 0 {
+                            assert!(tlvs.iter().all(Result::is_ok));
+                        } else {
+                            assert_eq!(tlvs.last(), Some(&Err(ParseError::Leftovers(leftovers))));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_tlvs() {
+        let mut input = Vec::from(PROTOCOL_PREFIX);
+
+        input.extend([0x21, 0x11, 0, 22]);
+        input.extend([127, 0, 0, 1, 127, 0, 0, 2, 0, 80, 1, 187]);
+        input.extend([0x04, 0, 0]);
+        input.extend([0x02, 0, 1, b'a']);
+        input.extend([0xE0, 0, 0]);
+
+        let expected = vec![
+            TypeLengthValue::new(Type::NoOp, &[]),
+            TypeLengthValue::new(Type::Authority, b"a"),
+            TypeLengthValue::new(0xE0, &[]),
+        ];
+        let header = Header::try_from(input.as_slice()).unwrap();
+        let view = HeaderRef::try_from(input.as_slice()).unwrap();
+
+        assert!(expected[0].is_empty());
+        assert_eq!(
+            header.tlvs().collect::<Result<Vec<_>, _>>(),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            view.tlvs().collect::<Result<Vec<_>, _>>(),
+            Ok(expected.clone())
+        );
+        assert_eq!(
+            header.tlv_list().unwrap().iter().collect::<Vec<_>>(),
+            expected
+        );
+
+        let built = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        )
+        .write_tlv(Type::NoOp, &[])
+        .unwrap()
+        .write_tlv(Type::Authority, b"a")
+        .unwrap()
+        .write_tlv(0xE0, &[])
+        .unwrap()
+        .build()
+        .unwrap();
+        let fixed = FixedBuilder::<38>::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        )
+        .write_tlv(Type::NoOp, &[])
+        .unwrap()
+        .write_tlv(Type::Authority, b"a")
+        .unwrap()
+        .write_tlv(0xE0, &[])
+        .unwrap()
+        .build();
+
+        assert_eq!(built, input);
+        assert_eq!(fixed.as_bytes(), input.as_slice());
+    }
+
     #[test]
     fn not_prefixed() {
         assert_eq!(
@@ -747,4 +1115,84 @@ mod tests {
             ParseError::Incomplete(PROTOCOL_PREFIX.len())
         );
     }
+
+    #[test]
+    fn unix_paths() {
+        let mut source = [0; 108];
+        source[..9].copy_from_slice(b"/tmp/sock");
+        let unix = Unix::new(source, [b'a'; 108]);
+
+        assert_eq!(unix.source_path(), b"/tmp/sock");
+        assert_eq!(unix.destination_path(), [b'a'; 108].as_slice());
+        assert_eq!(Unix::new([0; 108], [0; 108]).source_path(), b"");
+    }
+
+    #[test]
+    fn unix_addresses() {
+        let mut abstract_name = [0; 108];
+        abstract_name[1..8].copy_from_slice(b"a\0b\0\0c\0");
+        let mut source = [0; 108];
+        source[..9].copy_from_slice(b"/tmp/sock");
+        let unix = Unix::new(source, abstract_name);
+
+        assert_eq!(unix.source_address(), UnixAddress::Path(b"/tmp/sock"));
+        assert_eq!(
+            unix.destination_address(),
+            UnixAddress::Abstract(b"a\0b\0\0c")
+        );
+        assert_eq!(
+            UnixAddress::new(&[b'a'; 108]).as_bytes(),
+            [b'a'; 108].as_slice()
+        );
+        assert_eq!(UnixAddress::new(&[0; 108]), UnixAddress::Unnamed);
+        assert_eq!(UnixAddress::new(&[]).as_bytes(), b"");
+
+        #[cfg(unix)]
+        assert_eq!(
+            unix.source_address().as_os_str(),
+            std::ffi::OsStr::new("/tmp/sock")
+        );
+    }
+
+    #[test]
+    fn misaligned() {
+        #[repr(C, packed)]
+        struct Packed {
+            tag: u8,
+            header: [u8; 68],
+        }
+
+        let addresses = IPv6::new([1; 16], [2; 16], 80, 443);
+        let header = FixedBuilder::<68>::ipv6(Command::Proxy, Protocol::Stream, addresses)
+            .write_tlv(Type::UniqueId, &[3; 13])
+            .unwrap()
+            .build();
+        let expected = Header::try_from(header.as_bytes()).unwrap();
+
+        let mut buffer = [0u8; 68 + 8];
+
+        for offset in 0..8 {
+            buffer[offset..offset + 68].copy_from_slice(header.as_bytes());
+
+            let parsed = Header::try_from(&buffer[offset..offset + 68]).unwrap();
+
+            assert_eq!(parsed.addresses, expected.addresses);
+            assert_eq!(
+                parsed.tlvs().collect::<Vec<_>>(),
+                expected.tlvs().collect::<Vec<_>>()
+            );
+        }
+
+        let packed = Packed {
+            tag: 0,
+            header: header.into_inner().0,
+        };
+        let bytes = &packed.header;
+
+        assert_eq!(packed.tag, 0);
+        assert_eq!(
+            Header::try_from(&bytes[..]).unwrap().addresses,
+            expected.addresses
+        );
+    }
 }
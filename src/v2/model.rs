@@ -1,11 +1,10 @@
-use std::prelude::v1::*;
-
-use crate::ip::{IPv4, IPv6};
+use crate::ip::{self, IPv4, IPv6};
+use crate::net::{IpAddr, SocketAddr};
 use crate::v2::error::ParseError;
-use std::borrow::Cow;
-use std::fmt;
-use std::net::SocketAddr;
-use std::ops::BitOr;
+use crate::v2::field;
+use alloc::borrow::Cow;
+use core::fmt;
+use core::ops::BitOr;
 
 /// The prefix of the PROXY protocol header.
 pub const PROTOCOL_PREFIX: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
@@ -15,11 +14,11 @@ pub const MINIMUM_LENGTH: usize = 16;
 pub const MINIMUM_TLV_LENGTH: usize = 3;
 
 /// The number of bytes for an IPv4 addresses payload.
-const IPV4_ADDRESSES_BYTES: usize = 12;
+pub(super) const IPV4_ADDRESSES_BYTES: usize = 12;
 /// The number of bytes for an IPv6 addresses payload.
-const IPV6_ADDRESSES_BYTES: usize = 36;
+pub(super) const IPV6_ADDRESSES_BYTES: usize = 36;
 /// The number of bytes for a unix addresses payload.
-const UNIX_ADDRESSES_BYTES: usize = 216;
+pub(super) const UNIX_ADDRESSES_BYTES: usize = 216;
 
 /// A proxy protocol version 2 header.
 ///
@@ -45,6 +44,8 @@ const UNIX_ADDRESSES_BYTES: usize = 216;
 /// assert_eq!(actual.tlvs().collect::<Vec<Result<TypeLengthValue<'_>, ParseError>>>(), vec![Ok(TypeLengthValue::new(Type::NoOp, &[42]))]);
 /// ```
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Header<'a> {
     pub header: Cow<'a, [u8]>,
     pub version: Version,
@@ -55,12 +56,16 @@ pub struct Header<'a> {
 
 /// The supported `Version`s for binary headers.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Version {
     Two = 0x20,
 }
 
 /// The supported `Command`s for a PROXY protocol header.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Command {
     Local = 0,
     Proxy,
@@ -68,6 +73,8 @@ pub enum Command {
 
 /// The supported `AddressFamily` for a PROXY protocol header.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum AddressFamily {
     Unspecified = 0x00,
     IPv4 = 0x10,
@@ -77,6 +84,8 @@ pub enum AddressFamily {
 
 /// The supported `Protocol`s for a PROXY protocol header.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Protocol {
     Unspecified = 0,
     Stream,
@@ -95,6 +104,8 @@ pub enum Protocol {
 /// assert_eq!(addresses.address_family(), AddressFamily::IPv4);
 /// ```
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Addresses {
     Unspecified,
     IPv4(IPv4),
@@ -104,11 +115,40 @@ pub enum Addresses {
 
 /// The source and destination addresses of UNIX sockets.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Unix {
+    #[cfg_attr(feature = "serde", serde(with = "unix_address"))]
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<u8>"))]
     pub source: [u8; 108],
+    #[cfg_attr(feature = "serde", serde(with = "unix_address"))]
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<u8>"))]
     pub destination: [u8; 108],
 }
 
+/// Serializes the fixed-size unix socket addresses as byte sequences,
+/// since `serde` only supports arrays of up to 32 elements.
+#[cfg(feature = "serde")]
+mod unix_address {
+    use crate::prelude::*;
+
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(address: &[u8; 108], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(address.as_slice())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 108], D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+
+        bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(bytes.len(), &"108 bytes"))
+    }
+}
+
 /// An `Iterator` of `TypeLengthValue`s stored in a byte slice.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct TypeLengthValues<'a> {
@@ -117,14 +157,21 @@ pub struct TypeLengthValues<'a> {
 }
 
 /// A Type-Length-Value payload.
+/// The value may be empty, e.g. for a `Type::NoOp` with a length of 0.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct TypeLengthValue<'a> {
     pub kind: u8,
+    #[cfg_attr(feature = "serde", serde(with = "super::tlv_value"))]
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
     pub value: Cow<'a, [u8]>,
 }
 
 /// Supported types for `TypeLengthValue` payloads.
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Type {
     ALPN = 0x01,
     Authority,
@@ -167,7 +214,7 @@ impl<'a> Header<'a> {
 
     /// The length of this `Header`'s payload in bytes.
     pub fn length(&self) -> usize {
-        self.header[MINIMUM_LENGTH..].len()
+        self.header.len().saturating_sub(MINIMUM_LENGTH)
     }
 
     /// The total length of this `Header` in bytes.
@@ -185,22 +232,66 @@ impl<'a> Header<'a> {
         self.addresses.address_family()
     }
 
+    /// The transport `Protocol` of this `Header`.
+    pub fn transport_protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// Tests whether this `Header` was sent for a connection of the proxy itself (`Command::Local`),
+    /// in which case the receiver must use the real connection endpoints.
+    pub fn is_local(&self) -> bool {
+        self.command == Command::Local
+    }
+
+    /// The `Addresses` the receiver should use for the connection, as the specification requires.
+    /// These are `Addresses::Unspecified` for `Command::Local`, even when the sender included an address block,
+    /// which remains available as-is in the `addresses` field and from `address_bytes`.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Addresses, Builder, Command, Header, Protocol, Version};
+    ///
+    /// let header = Builder::with_addresses(
+    ///     Version::Two | Command::Local,
+    ///     Protocol::Stream,
+    ///     ("127.0.0.1:80".parse().unwrap(), "127.0.0.1:443".parse().unwrap()),
+    /// )
+    /// .build()
+    /// .unwrap();
+    /// let header = Header::try_from(header.as_slice()).unwrap();
+    ///
+    /// assert_eq!(header.proxied_addresses(), Addresses::Unspecified);
+    /// assert!(matches!(header.addresses, Addresses::IPv4(..)));
+    /// ```
+    pub fn proxied_addresses(&self) -> Addresses {
+        if self.is_local() {
+            Addresses::Unspecified
+        } else {
+            self.addresses
+        }
+    }
+
     /// The length in bytes of the address portion of the payload.
     fn address_bytes_end(&self) -> usize {
         let length = self.length();
         let address_bytes = self.address_family().byte_length().unwrap_or(length);
 
-        MINIMUM_LENGTH + std::cmp::min(address_bytes, length)
+        MINIMUM_LENGTH + core::cmp::min(address_bytes, length)
     }
 
     /// The bytes of the address portion of the payload.
+    /// Empty when the underlying bytes are shorter than a header, which can only happen for a `Header` that was not parsed.
     pub fn address_bytes(&self) -> &[u8] {
-        &self.header[MINIMUM_LENGTH..self.address_bytes_end()]
+        self.header
+            .get(MINIMUM_LENGTH..self.address_bytes_end())
+            .unwrap_or_default()
     }
 
     /// The bytes of the `TypeLengthValue` portion of the payload.
     pub fn tlv_bytes(&self) -> &[u8] {
-        &self.header[self.address_bytes_end()..]
+        self.header
+            .get(self.address_bytes_end()..)
+            .unwrap_or_default()
     }
 
     /// An `Iterator` of `TypeLengthValue`s.
@@ -217,6 +308,12 @@ impl<'a> Header<'a> {
     }
 }
 
+impl<'a> AsRef<[u8]> for Header<'a> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
 impl<'a> TypeLengthValues<'a> {
     /// The underlying byte slice of the `TypeLengthValue`s portion of the `Header` payload.
     pub fn as_bytes(&self) -> &[u8] {
@@ -245,11 +342,11 @@ impl<'a> Iterator for TypeLengthValues<'a> {
 
         if remaining.len() < MINIMUM_TLV_LENGTH {
             self.offset = self.bytes.len();
-            return Some(Err(ParseError::Leftovers(self.bytes.len())));
+            return Some(Err(ParseError::Leftovers(remaining.len())));
         }
 
         let tlv_type = remaining[0];
-        let length = u16::from_be_bytes([remaining[1], remaining[2]]);
+        let length = field::read_u16(remaining, 1);
         let tlv_length = MINIMUM_TLV_LENGTH + length as usize;
 
         if remaining.len() < tlv_length {
@@ -380,6 +477,80 @@ impl Addresses {
     pub fn is_empty(&self) -> bool {
         self.address_family().byte_length().is_none()
     }
+
+    /// Converts `IPv4` addresses to `IPv6` with IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`),
+    /// for backends that handle every connection as IPv6. Other addresses are returned unchanged.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Addresses, IPv4, IPv6};
+    /// use std::net::Ipv6Addr;
+    ///
+    /// let addresses = Addresses::IPv4(IPv4::new([192, 0, 2, 1], [10, 0, 0, 1], 51234, 443));
+    /// let mapped = addresses.to_ipv6_mapped();
+    ///
+    /// assert_eq!(
+    ///     mapped,
+    ///     Addresses::IPv6(IPv6::new(
+    ///         "::ffff:192.0.2.1".parse::<Ipv6Addr>().unwrap(),
+    ///         "::ffff:10.0.0.1".parse::<Ipv6Addr>().unwrap(),
+    ///         51234,
+    ///         443
+    ///     ))
+    /// );
+    /// assert_eq!(mapped.to_canonical(), addresses);
+    /// ```
+    pub fn to_ipv6_mapped(&self) -> Self {
+        match self {
+            Addresses::IPv4(a) => Addresses::IPv6(a.to_ipv6_mapped()),
+            addresses => *addresses,
+        }
+    }
+
+    /// Converts `IPv6` addresses that are both IPv4-mapped back to `IPv4`,
+    /// for ACLs that only list IPv4 networks. Other addresses are returned unchanged.
+    pub fn to_canonical(&self) -> Self {
+        match self {
+            Addresses::IPv6(a) => a.to_ipv4_mapped().map_or(*self, Addresses::IPv4),
+            addresses => *addresses,
+        }
+    }
+
+    /// The source IP address, if any.
+    fn source_ip(&self) -> Option<IpAddr> {
+        match self {
+            Addresses::IPv4(a) => Some(a.source_address.into()),
+            Addresses::IPv6(a) => Some(a.source_address.into()),
+            Addresses::Unix(..) | Addresses::Unspecified => None,
+        }
+    }
+
+    /// Tests whether the source address is in a private range (RFC 1918 for IPv4, `fc00::/7` for IPv6).
+    /// Always false for addresses without IPs.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::{Addresses, IPv6};
+    ///
+    /// let addresses = Addresses::from(IPv6::new([0xfd00, 0, 0, 0, 0, 0, 0, 1], [0x2001, 0x4860, 0, 0, 0, 0, 0, 0x8888], 80, 443));
+    ///
+    /// assert!(addresses.is_source_private());
+    /// assert!(!addresses.is_source_global());
+    /// ```
+    pub fn is_source_private(&self) -> bool {
+        self.source_ip().map_or(false, ip::is_private)
+    }
+
+    /// Tests whether the source address is a loopback address. Always false for addresses without IPs.
+    pub fn is_source_loopback(&self) -> bool {
+        self.source_ip().map_or(false, ip::is_loopback)
+    }
+
+    /// Tests whether the source address may be reached on the public internet, i.e. it is not private,
+    /// loopback, link-local, multicast, or in another special-purpose range. Always false for addresses without IPs.
+    pub fn is_source_global(&self) -> bool {
+        self.source_ip().map_or(false, ip::is_global)
+    }
 }
 
 impl Unix {
@@ -390,6 +561,101 @@ impl Unix {
             destination,
         }
     }
+
+    /// The path of the source address, up to the first null byte, borrowed from the address.
+    pub fn source_path(&self) -> &[u8] {
+        path(&self.source)
+    }
+
+    /// The path of the destination address, up to the first null byte, borrowed from the address.
+    pub fn destination_path(&self) -> &[u8] {
+        path(&self.destination)
+    }
+
+    /// The source address, distinguishing paths from names in the abstract namespace.
+    pub fn source_address(&self) -> UnixAddress<'_> {
+        UnixAddress::new(&self.source)
+    }
+
+    /// The destination address, distinguishing paths from names in the abstract namespace.
+    pub fn destination_address(&self) -> UnixAddress<'_> {
+        UnixAddress::new(&self.destination)
+    }
+}
+
+/// A unix socket address, decoded from the 108 null-padded bytes of a `Unix` address block.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::v2::{Unix, UnixAddress};
+///
+/// let mut source = [0; 108];
+/// let mut destination = [0; 108];
+///
+/// source[..9].copy_from_slice(b"/tmp/sock");
+/// destination[1..5].copy_from_slice(b"ppp\0");
+///
+/// let unix = Unix::new(source, destination);
+///
+/// assert_eq!(unix.source_address(), UnixAddress::Path(b"/tmp/sock"));
+/// assert_eq!(unix.destination_address(), UnixAddress::Abstract(b"ppp"));
+/// assert_eq!(unix.destination_path(), b"");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum UnixAddress<'a> {
+    /// An address of only null bytes, as sent for unnamed sockets.
+    Unnamed,
+    /// A path in the filesystem, up to the first null byte.
+    Path(&'a [u8]),
+    /// A name in the abstract namespace of Linux, which starts with a null byte.
+    /// The name excludes the leading null byte and any trailing null padding, but may contain other null bytes.
+    Abstract(&'a [u8]),
+}
+
+impl<'a> UnixAddress<'a> {
+    /// Decodes a null-padded unix socket address.
+    pub fn new(address: &'a [u8]) -> Self {
+        match address.split_first() {
+            Some((0, name)) => {
+                let length = name
+                    .iter()
+                    .rposition(|&b| b != 0)
+                    .map_or(0, |last| last + 1);
+
+                if length == 0 {
+                    UnixAddress::Unnamed
+                } else {
+                    UnixAddress::Abstract(&name[..length])
+                }
+            }
+            Some(_) => UnixAddress::Path(path(address)),
+            None => UnixAddress::Unnamed,
+        }
+    }
+
+    /// The bytes of the path or abstract name, which are empty for unnamed sockets.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        match self {
+            UnixAddress::Unnamed => &[],
+            UnixAddress::Path(bytes) | UnixAddress::Abstract(bytes) => bytes,
+        }
+    }
+
+    /// The path or abstract name as an `OsStr`, for use with the unix socket APIs of the standard library.
+    #[cfg(all(feature = "std", unix))]
+    pub fn as_os_str(&self) -> &'a std::ffi::OsStr {
+        std::os::unix::ffi::OsStrExt::from_bytes(self.as_bytes())
+    }
+}
+
+/// Trims a unix socket address at the first null byte.
+fn path(address: &[u8]) -> &[u8] {
+    let length = address
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(address.len());
+
+    &address[..length]
 }
 
 impl BitOr<AddressFamily> for Protocol {
@@ -443,3 +709,38 @@ impl From<Type> for u8 {
         kind as u8
     }
 }
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Addresses {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Unspecified => defmt::write!(f, "Unspecified"),
+            Self::IPv4(a) => defmt::write!(f, "IPv4 {}", a),
+            Self::IPv6(a) => defmt::write!(f, "IPv6 {}", a),
+            Self::Unix(a) => defmt::write!(f, "Unix {}", a),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Unix {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "{=[u8]:a} {=[u8]:a}",
+            self.source.as_slice(),
+            self.destination.as_slice()
+        )
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for UnixAddress<'a> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Unnamed => defmt::write!(f, "Unnamed"),
+            Self::Path(path) => defmt::write!(f, "Path {=[u8]:a}", path),
+            Self::Abstract(name) => defmt::write!(f, "Abstract {=[u8]:a}", name),
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Selecting the TLVs to pass through, strip or overwrite when re-emitting a header to the next hop,
+//! like the TLV pass-through options of Envoy's PROXY protocol listener filter and upstream transport socket.
+use std::prelude::v1::*;
+
+use super::checksum::update_checksum;
+use super::{Builder, Header, TypeLengthValue};
+use alloc::borrow::Cow;
+use std::io;
+
+/// What to do with the TLVs of a type when re-emitting a header.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TlvAction<'a> {
+    /// Copy the TLVs unchanged.
+    Pass,
+    /// Drop the TLVs.
+    Strip,
+    /// Replace the TLVs with a single TLV holding the value, in place of the first one.
+    /// The TLV is appended when the header has none of the type.
+    Overwrite(Cow<'a, [u8]>),
+}
+
+/// A configuration of the TLVs to keep when re-emitting a binary header.
+/// TLV types without a rule are either all passed through or all stripped.
+/// The default strips every TLV, as Envoy does when no types are configured.
+///
+/// A CRC32C TLV that is kept has its checksum recomputed over the re-emitted header.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::v2::{Builder, Command, Header, Passthrough, Protocol, Type, TypeLengthValue};
+///
+/// let input = Builder::with_addresses(
+///     ppp::v2::Version::Two | Command::Proxy,
+///     Protocol::Stream,
+///     ("127.0.0.1:80".parse().unwrap(), "127.0.0.2:443".parse().unwrap()),
+/// )
+/// .write_tlv(Type::Authority, b"example.com")
+/// .unwrap()
+/// .write_tlv(0xE0, b"internal")
+/// .unwrap()
+/// .build()
+/// .unwrap();
+/// let header = Header::try_from(input.as_slice()).unwrap();
+///
+/// let passthrough = Passthrough::default()
+///     .pass(Type::Authority)
+///     .overwrite(Type::UniqueId, b"abc123".as_slice());
+/// let relayed = passthrough.apply(&header).unwrap();
+///
+/// assert_eq!(relayed.addresses, header.addresses);
+/// assert_eq!(
+///     relayed.tlvs().collect::<Result<Vec<_>, _>>().unwrap(),
+///     vec![
+///         TypeLengthValue::new(Type::Authority, b"example.com"),
+///         TypeLengthValue::new(Type::UniqueId, b"abc123"),
+///     ]
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Passthrough<'a> {
+    pass_unlisted: bool,
+    rules: Vec<(u8, TlvAction<'a>)>,
+}
+
+impl<'a> Passthrough<'a> {
+    /// Creates a `Passthrough` that passes the TLVs of every type without a rule.
+    pub fn pass_all() -> Self {
+        Passthrough {
+            pass_unlisted: true,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Creates a `Passthrough` that strips the TLVs of every type without a rule.
+    pub fn strip_all() -> Self {
+        Passthrough::default()
+    }
+
+    /// Sets the action for TLVs of the given type, replacing any earlier rule for the type.
+    pub fn rule<T: Into<u8>>(mut self, kind: T, action: TlvAction<'a>) -> Self {
+        let kind = kind.into();
+
+        match self.rules.iter_mut().find(|(rule, _)| *rule == kind) {
+            Some((_, existing)) => *existing = action,
+            None => self.rules.push((kind, action)),
+        }
+
+        self
+    }
+
+    /// Passes the TLVs of the given type through unchanged.
+    pub fn pass<T: Into<u8>>(self, kind: T) -> Self {
+        self.rule(kind, TlvAction::Pass)
+    }
+
+    /// Strips the TLVs of the given type.
+    pub fn strip<T: Into<u8>>(self, kind: T) -> Self {
+        self.rule(kind, TlvAction::Strip)
+    }
+
+    /// Replaces the TLVs of the given type with a single TLV holding the value, adding it if the header has none.
+    pub fn overwrite<T: Into<u8>, V: Into<Cow<'a, [u8]>>>(self, kind: T, value: V) -> Self {
+        self.rule(kind, TlvAction::Overwrite(value.into()))
+    }
+
+    /// The action taken for TLVs of the given type.
+    pub fn action(&self, kind: u8) -> &TlvAction<'a> {
+        let default = if self.pass_unlisted {
+            &TlvAction::Pass
+        } else {
+            &TlvAction::Strip
+        };
+
+        self.rules
+            .iter()
+            .find(|(rule, _)| *rule == kind)
+            .map_or(default, |(_, action)| action)
+    }
+
+    /// Re-emits the header with its version, command, protocol and addresses unchanged,
+    /// and its TLVs passed, stripped or overwritten in their original order.
+    /// TLVs added by `overwrite` that the header did not have follow in the order of their rules.
+    ///
+    /// Headers with `Addresses::Unspecified` have no TLVs this crate can tell apart from the address bytes,
+    /// so their payload is kept as-is before any TLVs added by `overwrite`.
+    /// Returns an error when a TLV of the header is invalid, or when the payload would exceed `u16::MAX` bytes.
+    pub fn apply(&self, header: &Header<'_>) -> io::Result<Header<'static>> {
+        let mut tlvs = Vec::new();
+        let mut overwritten = Vec::new();
+
+        for tlv in header.tlvs() {
+            let tlv = tlv.map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+            match self.action(tlv.kind) {
+                TlvAction::Pass => tlvs.push(tlv),
+                TlvAction::Strip => (),
+                TlvAction::Overwrite(..) if overwritten.contains(&tlv.kind) => (),
+                TlvAction::Overwrite(value) => {
+                    overwritten.push(tlv.kind);
+                    tlvs.push(TypeLengthValue::new(tlv.kind, value.as_ref()));
+                }
+            }
+        }
+
+        for (kind, action) in &self.rules {
+            if let TlvAction::Overwrite(value) = action {
+                if !overwritten.contains(kind) {
+                    tlvs.push(TypeLengthValue::new(*kind, value.as_ref()));
+                }
+            }
+        }
+
+        let mut bytes = Builder::new(
+            header.version | header.command,
+            header.address_family() | header.protocol,
+        )
+        .write_payload(header.address_bytes())?
+        .write_payloads(tlvs)?
+        .build()?;
+
+        update_checksum(&mut bytes);
+
+        Ok(Header {
+            header: Cow::Owned(bytes),
+            version: header.version,
+            command: header.command,
+            protocol: header.protocol,
+            addresses: header.addresses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Addresses, Command, IPv4, Protocol, Type, Version};
+
+    fn encoded(tlvs: &[(u8, &[u8])]) -> Vec<u8> {
+        let builder = Builder::ipv4(
+            Command::Proxy,
+            Protocol::Stream,
+            IPv4::new([127, 0, 0, 1], [127, 0, 0, 2], 80, 443),
+        );
+
+        tlvs.iter()
+            .try_fold(builder, |builder, (kind, value)| {
+                builder.write_tlv(*kind, value)
+            })
+            .and_then(Builder::build)
+            .unwrap()
+    }
+
+    fn kinds(header: &Header<'_>) -> Vec<(u8, Vec<u8>)> {
+        header
+            .tlvs()
+            .map(|tlv| tlv.map(|tlv| (tlv.kind, tlv.value.to_vec())))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn pass_all() {
+        let input = encoded(&[(0xE1, b"b"), (0x04, b""), (0xE0, b"a")]);
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        assert_eq!(
+            Passthrough::pass_all().apply(&header).unwrap().as_bytes(),
+            &input[..]
+        );
+        assert_eq!(
+            Passthrough::strip_all().apply(&header).unwrap().as_bytes(),
+            &encoded(&[])[..]
+        );
+    }
+
+    #[test]
+    fn rules() {
+        let input = encoded(&[(0xE1, b"b"), (0x02, b"host"), (0xE1, b"c"), (0x05, b"id")]);
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        let relayed = Passthrough::pass_all()
+            .strip(Type::UniqueId)
+            .overwrite(0xE1, b"x".as_slice())
+            .overwrite(0xE2, b"y".as_slice())
+            .apply(&header)
+            .unwrap();
+
+        assert_eq!(
+            kinds(&relayed),
+            vec![
+                (0xE1, b"x".to_vec()),
+                (0x02, b"host".to_vec()),
+                (0xE2, b"y".to_vec())
+            ]
+        );
+        assert_eq!(Header::try_from(relayed.as_bytes()).unwrap(), relayed);
+
+        let passthrough = Passthrough::default().pass(0xE1).strip(0xE1);
+
+        assert_eq!(passthrough.action(0xE1), &TlvAction::Strip);
+        assert_eq!(passthrough.action(0xE2), &TlvAction::Strip);
+        assert_eq!(Passthrough::pass_all().action(0xE2), &TlvAction::Pass);
+    }
+
+    #[test]
+    fn recompute_checksum() {
+        let input = encoded(&[(0x03, &[0; 4]), (0xE0, b"a")]);
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        let relayed = Passthrough::default()
+            .pass(Type::CRC32C)
+            .overwrite(Type::Authority, b"example.com".as_slice())
+            .apply(&header)
+            .unwrap();
+
+        assert_eq!(relayed.verify_checksum(), Some(true));
+        assert_eq!(kinds(&relayed)[1], (0x02, b"example.com".to_vec()));
+    }
+
+    #[test]
+    fn unspecified() {
+        let mut input = Vec::from(crate::v2::PROTOCOL_PREFIX);
+        input.extend([0x20, 0x00, 0, 3, 1, 2, 3]);
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        let relayed = Passthrough::default()
+            .overwrite(0xE0, b"a".as_slice())
+            .apply(&header)
+            .unwrap();
+
+        assert_eq!(relayed.addresses, Addresses::Unspecified);
+        assert_eq!(relayed.version, Version::Two);
+        assert_eq!(relayed.as_bytes()[16..], [1, 2, 3, 0xE0, 0, 1, b'a']);
+    }
+
+    #[test]
+    fn invalid_tlv() {
+        let mut input = encoded(&[(0xE0, b"a")]);
+
+        input.push(0xE1);
+        input[15] += 1;
+
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        assert_eq!(
+            Passthrough::pass_all().apply(&header).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! Signature TLVs authenticating the sender of a header, generic over the provider of the signature algorithm.
+//!
+//! The signature covers the whole header, with the signature itself and the value of the `Type::CRC32C` TLV,
+//! if any, set to 0, so the checksum can be updated after signing. Any other change to the header,
+//! such as appending a hop, invalidates the signature.
+//!
+//! Ed25519 and HMAC-SHA256 providers are built on `ring` (see `ring`) and on the RustCrypto crates
+//! (see `rustcrypto`). Other primitives are used by implementing `SignatureProvider`.
+use crate::prelude::*;
+
+use super::Header;
+#[cfg(any(feature = "std", feature = "tstd"))]
+use super::{checksum::update_checksum, TypeLengthValue};
+#[cfg(any(feature = "std", feature = "tstd"))]
+use alloc::borrow::Cow;
+use core::ops::Range;
+
+#[cfg(feature = "ring")]
+pub mod ring;
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
+
+/// The custom TLV type used to carry a signature.
+pub const SIGNATURE: u8 = 0xE9;
+
+/// Identifies signatures made with Ed25519.
+pub const ED25519: u8 = 0x01;
+/// Identifies signatures made with HMAC-SHA256.
+pub const HMAC_SHA256: u8 = 0x02;
+
+/// The number of bytes of the algorithm identifier that precedes the signature in the TLV value.
+const ALGORITHM_LENGTH: usize = 1;
+
+/// An error in signing a header, which leaves the header unchanged.
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum SignError {
+    #[error("Header already carries a signature TLV.")]
+    AlreadySigned,
+    #[error("Provider did not return a signature of its signature length.")]
+    CannotSign,
+    #[error("Header with the signature TLV would exceed the maximum payload length.")]
+    TooLong,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for SignError {
+    fn format(&self, f: defmt::Formatter) {
+        let name = match self {
+            Self::AlreadySigned => "AlreadySigned",
+            Self::CannotSign => "CannotSign",
+            Self::TooLong => "TooLong",
+        };
+
+        defmt::write!(f, "{=str}", name)
+    }
+}
+
+/// Signs and verifies headers with a signature algorithm.
+///
+/// The signature TLV value is the algorithm identifier followed by the signature,
+/// so a header signed with one algorithm is never verified with another.
+pub trait SignatureProvider {
+    /// Identifies the algorithm in the signature TLV, e.g. `ED25519`.
+    /// Providers of algorithms without a constant in this module should use values from `0x80`.
+    fn algorithm(&self) -> u8;
+
+    /// The number of bytes of every signature made by this provider.
+    fn signature_length(&self) -> usize;
+
+    /// Signs the message, returning `signature_length` bytes,
+    /// or `None` when the provider cannot sign, e.g. because it only holds a public key.
+    fn sign(&self, message: &[u8]) -> Option<Vec<u8>>;
+
+    /// Tests whether the signature is valid for the message.
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// The bytes of the header a signature covers, with the bytes in the signature and checksum ranges set to 0.
+fn message(header: &[u8], signature: Range<usize>, checksum: Option<Range<usize>>) -> Vec<u8> {
+    let mut message = header.to_vec();
+
+    for range in core::iter::once(signature).chain(checksum) {
+        message[range].iter_mut().for_each(|byte| *byte = 0);
+    }
+
+    message
+}
+
+impl<'a> Header<'a> {
+    /// The range of the value of the `Type::CRC32C` TLV in the header, if any.
+    fn checksum_range(&self) -> Option<Range<usize>> {
+        self.checksum_tlv()
+            .map(|(offset, value)| offset..offset + value.len())
+    }
+
+    /// The algorithm identifier and the signature of the first signature TLV of this `Header`, if any.
+    /// Returns `None` when the TLV value is empty.
+    pub fn signature(&self) -> Option<(u8, &[u8])> {
+        let (_, value) = self.tlv_value(SIGNATURE)?;
+        let (&algorithm, signature) = value.split_first()?;
+
+        Some((algorithm, signature))
+    }
+
+    /// Verifies the signature of this `Header` with the provider.
+    /// Returns `None` when the header carries no signature, and `Some(false)` when it was made with another algorithm.
+    pub fn verify_signature<P: SignatureProvider + ?Sized>(&self, provider: &P) -> Option<bool> {
+        let (offset, value) = self.tlv_value(SIGNATURE)?;
+        let (algorithm, signature) = match value.split_first() {
+            Some((&algorithm, signature)) => (algorithm, signature),
+            None => return Some(false),
+        };
+
+        if algorithm != provider.algorithm() || signature.len() != provider.signature_length() {
+            return Some(false);
+        }
+
+        let start = offset + ALGORITHM_LENGTH;
+        let message = message(
+            self.as_bytes(),
+            start..start + signature.len(),
+            self.checksum_range(),
+        );
+
+        Some(provider.verify(&message, signature))
+    }
+
+    /// Creates a copy of this `Header` signed by the provider, with the signature TLV appended as the last TLV.
+    /// Returns an error when the header is already signed, when the provider cannot sign,
+    /// or when the payload would exceed `u16::MAX` bytes.
+    /// A CRC32C TLV, if any, is updated to match the new header.
+    #[cfg(any(feature = "std", feature = "tstd"))]
+    pub fn with_signature<P: SignatureProvider + ?Sized>(
+        &self,
+        provider: &P,
+    ) -> Result<Header<'static>, SignError> {
+        if self.tlv_value(SIGNATURE).is_some() {
+            return Err(SignError::AlreadySigned);
+        }
+
+        let length = provider.signature_length();
+        let mut value = vec![0; ALGORITHM_LENGTH + length];
+
+        value[0] = provider.algorithm();
+
+        let mut header = self
+            .append_tlv(&TypeLengthValue::new(SIGNATURE, &value))
+            .map_err(|_| SignError::TooLong)?;
+        let range = header.len() - length..header.len();
+        let signature = provider
+            .sign(&message(&header, range.clone(), self.checksum_range()))
+            .filter(|signature| signature.len() == length)
+            .ok_or(SignError::CannotSign)?;
+
+        header[range].copy_from_slice(&signature);
+        update_checksum(&mut header);
+
+        Ok(Header {
+            header: Cow::Owned(header),
+            version: self.version,
+            command: self.command,
+            protocol: self.protocol,
+            addresses: self.addresses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::checksum::crc32c;
+    use crate::v2::{Builder, Hop, Type};
+
+    /// Stands in for a keyed algorithm: the CRC32c of the key followed by the message.
+    struct Keyed {
+        key: u8,
+        signer: bool,
+    }
+
+    impl SignatureProvider for Keyed {
+        fn algorithm(&self) -> u8 {
+            0xFF
+        }
+
+        fn signature_length(&self) -> usize {
+            4
+        }
+
+        fn sign(&self, message: &[u8]) -> Option<Vec<u8>> {
+            if !self.signer {
+                return None;
+            }
+
+            Some(self.mac(message))
+        }
+
+        fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+            self.mac(message) == signature
+        }
+    }
+
+    impl Keyed {
+        fn mac(&self, message: &[u8]) -> Vec<u8> {
+            let mut keyed = vec![self.key];
+
+            keyed.extend_from_slice(message);
+
+            crc32c(&keyed, 0..0).to_be_bytes().to_vec()
+        }
+    }
+
+    const SIGNER: Keyed = Keyed {
+        key: 7,
+        signer: true,
+    };
+
+    fn header(tlvs: &[(Type, &[u8])]) -> Vec<u8> {
+        let builder = Builder::localhost();
+
+        tlvs.iter()
+            .try_fold(builder, |builder, (kind, value)| {
+                builder.write_tlv(*kind, value)
+            })
+            .and_then(Builder::build)
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = header(&[(Type::Authority, b"example.com"), (Type::CRC32C, &[0; 4])]);
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        assert_eq!(header.signature(), None);
+        assert_eq!(header.verify_signature(&SIGNER), None);
+
+        let signed = header.with_signature(&SIGNER).unwrap();
+        let parsed = Header::try_from(signed.as_bytes()).unwrap();
+        let verifier = Keyed {
+            key: 7,
+            signer: false,
+        };
+
+        assert_eq!(parsed, signed);
+        assert_eq!(parsed.length(), input.len() - 16 + 3 + 1 + 4);
+        assert_eq!(parsed.verify_signature(&verifier), Some(true));
+        assert_eq!(parsed.verify_checksum(), Some(true));
+        assert_eq!(
+            parsed.signature().map(|(algorithm, _)| algorithm),
+            Some(0xFF)
+        );
+        assert_eq!(
+            parsed.verify_signature(&Keyed {
+                key: 8,
+                signer: false
+            }),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn tampered() {
+        let input = header(&[(Type::NoOp, b"ab")]);
+        let signed = Header::try_from(input.as_slice())
+            .unwrap()
+            .with_signature(&SIGNER)
+            .unwrap();
+
+        for index in 0..signed.len() {
+            let mut corrupted = signed.as_bytes().to_vec();
+
+            corrupted[index] ^= 0x01;
+
+            if let Ok(parsed) = Header::try_from(corrupted.as_slice()) {
+                assert_ne!(parsed.verify_signature(&SIGNER), Some(true), "{}", index);
+            }
+        }
+
+        let forwarded = signed.with_hop(&Hop::new("edge", 1)).unwrap();
+
+        assert_eq!(forwarded.verify_signature(&SIGNER), Some(false));
+    }
+
+    #[test]
+    fn cannot_sign() {
+        let input = header(&[]);
+        let header = Header::try_from(input.as_slice()).unwrap();
+        let verifier = Keyed {
+            key: 7,
+            signer: false,
+        };
+
+        assert_eq!(header.with_signature(&verifier), Err(SignError::CannotSign));
+        assert_eq!(
+            header
+                .with_signature(&SIGNER)
+                .unwrap()
+                .with_signature(&SIGNER),
+            Err(SignError::AlreadySigned)
+        );
+
+        let input = header(&[(Type::NoOp, &[0; 65515])]);
+        let header = Header::try_from(input.as_slice()).unwrap();
+
+        assert_eq!(header.with_signature(&SIGNER), Err(SignError::TooLong));
+    }
+
+    #[test]
+    fn other_algorithm() {
+        let input = header(&[(Type::NoOp, b"ab")]);
+        let mut signed = Header::try_from(input.as_slice())
+            .unwrap()
+            .with_signature(&SIGNER)
+            .unwrap()
+            .as_bytes()
+            .to_vec();
+        let algorithm = signed.len() - 5;
+
+        signed[algorithm] = ED25519;
+
+        let parsed = Header::try_from(signed.as_slice()).unwrap();
+
+        assert_eq!(parsed.verify_signature(&SIGNER), Some(false));
+
+        let empty = Builder::localhost()
+            .write_tlv(SIGNATURE, b"")
+            .unwrap()
+            .build()
+            .unwrap();
+        let parsed = Header::try_from(empty.as_slice()).unwrap();
+
+        assert_eq!(parsed.signature(), None);
+        assert_eq!(parsed.verify_signature(&SIGNER), Some(false));
+    }
+}
@@ -0,0 +1,181 @@
+//! Ed25519 and HMAC-SHA256 signature providers built on `ring`.
+//!
+//! ## Examples
+//! ```rust
+//! use ppp::v2::signature::ring::Ed25519;
+//! use ppp::v2::{Builder, Command, Header, Protocol, Version};
+//! use ring::signature::{Ed25519KeyPair, KeyPair};
+//!
+//! let key_pair = Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+//! let verifier = Ed25519::verifier(key_pair.public_key().as_ref());
+//! let signer = Ed25519::new(key_pair);
+//!
+//! let header = Builder::with_addresses(
+//!     Version::Two | Command::Proxy,
+//!     Protocol::Stream,
+//!     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+//! )
+//! .build()
+//! .unwrap();
+//! let header = Header::try_from(header.as_slice()).unwrap();
+//! let signed = header.with_signature(&signer).unwrap();
+//!
+//! assert_eq!(signed.verify_signature(&verifier), Some(true));
+//! ```
+use super::{SignatureProvider, ED25519, HMAC_SHA256};
+use crate::prelude::*;
+use core::fmt;
+use ring::hmac;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+
+/// The number of bytes of an Ed25519 signature.
+const ED25519_LENGTH: usize = 64;
+/// The number of bytes of an HMAC-SHA256 tag.
+const HMAC_SHA256_LENGTH: usize = 32;
+
+/// Signs headers with an Ed25519 key pair, or only verifies them with a public key.
+#[derive(Debug)]
+pub struct Ed25519 {
+    key_pair: Option<Ed25519KeyPair>,
+    public_key: Vec<u8>,
+}
+
+impl Ed25519 {
+    /// Creates a new `Ed25519` provider that signs with the key pair and verifies with its public key.
+    pub fn new(key_pair: Ed25519KeyPair) -> Self {
+        Ed25519 {
+            public_key: key_pair.public_key().as_ref().to_vec(),
+            key_pair: Some(key_pair),
+        }
+    }
+
+    /// Creates a new `Ed25519` provider that only verifies signatures, with the 32-byte public key.
+    pub fn verifier<T: Into<Vec<u8>>>(public_key: T) -> Self {
+        Ed25519 {
+            key_pair: None,
+            public_key: public_key.into(),
+        }
+    }
+}
+
+impl SignatureProvider for Ed25519 {
+    fn algorithm(&self) -> u8 {
+        ED25519
+    }
+
+    fn signature_length(&self) -> usize {
+        ED25519_LENGTH
+    }
+
+    fn sign(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let key_pair = self.key_pair.as_ref()?;
+
+        Some(key_pair.sign(message).as_ref().to_vec())
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        UnparsedPublicKey::new(&ring::signature::ED25519, &self.public_key)
+            .verify(message, signature)
+            .is_ok()
+    }
+}
+
+/// Signs and verifies headers with HMAC-SHA256, under a key shared by the sender and the receiver.
+pub struct HmacSha256 {
+    key: hmac::Key,
+}
+
+impl HmacSha256 {
+    /// Creates a new `HmacSha256` provider with the shared key.
+    pub fn new(key: &[u8]) -> Self {
+        HmacSha256 {
+            key: hmac::Key::new(hmac::HMAC_SHA256, key),
+        }
+    }
+}
+
+// Leaves the key out, so it is not logged.
+impl fmt::Debug for HmacSha256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacSha256").finish_non_exhaustive()
+    }
+}
+
+impl SignatureProvider for HmacSha256 {
+    fn algorithm(&self) -> u8 {
+        HMAC_SHA256
+    }
+
+    fn signature_length(&self) -> usize {
+        HMAC_SHA256_LENGTH
+    }
+
+    fn sign(&self, message: &[u8]) -> Option<Vec<u8>> {
+        Some(hmac::sign(&self.key, message).as_ref().to_vec())
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        hmac::verify(&self.key, message, signature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Header, Type};
+
+    fn header() -> Vec<u8> {
+        Builder::localhost()
+            .write_tlv(Type::Authority, b"example.com")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    fn key_pair(seed: u8) -> Ed25519KeyPair {
+        Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap()
+    }
+
+    #[test]
+    fn ed25519() {
+        let input = header();
+        let header = Header::try_from(input.as_slice()).unwrap();
+        let signer = Ed25519::new(key_pair(1));
+        let verifier = Ed25519::verifier(key_pair(1).public_key().as_ref());
+        let signed = header.with_signature(&signer).unwrap();
+        let (algorithm, signature) = signed.signature().unwrap();
+
+        assert_eq!(algorithm, ED25519);
+        assert_eq!(signature.len(), ED25519_LENGTH);
+        assert_eq!(signed.verify_signature(&signer), Some(true));
+        assert_eq!(signed.verify_signature(&verifier), Some(true));
+        assert_eq!(
+            signed.verify_signature(&Ed25519::verifier(key_pair(2).public_key().as_ref())),
+            Some(false)
+        );
+        assert!(header.with_signature(&verifier).is_err());
+    }
+
+    #[test]
+    fn hmac_sha256() {
+        let input = header();
+        let header = Header::try_from(input.as_slice()).unwrap();
+        let signed = header.with_signature(&HmacSha256::new(b"secret")).unwrap();
+        let (algorithm, signature) = signed.signature().unwrap();
+
+        assert_eq!(algorithm, HMAC_SHA256);
+        assert_eq!(signature.len(), HMAC_SHA256_LENGTH);
+        assert_eq!(
+            signed.verify_signature(&HmacSha256::new(b"secret")),
+            Some(true)
+        );
+        assert_eq!(
+            signed.verify_signature(&HmacSha256::new(b"other")),
+            Some(false)
+        );
+        assert_eq!(
+            signed.verify_signature(&Ed25519::new(key_pair(1))),
+            Some(false)
+        );
+    }
+}
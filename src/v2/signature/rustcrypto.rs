@@ -0,0 +1,218 @@
+//! Ed25519 and HMAC-SHA256 signature providers built on the RustCrypto `ed25519-dalek`, `hmac` and `sha2` crates.
+//!
+//! The signatures are interchangeable with those of the `ring` providers.
+//!
+//! ## Examples
+//! ```rust
+//! use ed25519_dalek::SigningKey;
+//! use ppp::v2::signature::rustcrypto::Ed25519;
+//! use ppp::v2::{Builder, Command, Header, Protocol, Version};
+//!
+//! let signing_key = SigningKey::from_bytes(&[7; 32]);
+//! let verifier = Ed25519::verifier(signing_key.verifying_key());
+//! let signer = Ed25519::new(signing_key);
+//!
+//! let header = Builder::with_addresses(
+//!     Version::Two | Command::Proxy,
+//!     Protocol::Stream,
+//!     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+//! )
+//! .build()
+//! .unwrap();
+//! let header = Header::try_from(header.as_slice()).unwrap();
+//! let signed = header.with_signature(&signer).unwrap();
+//!
+//! assert_eq!(signed.verify_signature(&verifier), Some(true));
+//! ```
+use super::{SignatureProvider, ED25519, HMAC_SHA256};
+use crate::prelude::*;
+use core::fmt;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// The number of bytes of an HMAC-SHA256 tag.
+const HMAC_SHA256_LENGTH: usize = 32;
+
+/// Signs headers with an Ed25519 signing key, or only verifies them with a verifying key.
+#[derive(Debug)]
+pub struct Ed25519 {
+    signing_key: Option<SigningKey>,
+    verifying_key: VerifyingKey,
+}
+
+impl Ed25519 {
+    /// Creates a new `Ed25519` provider that signs with the key and verifies with its verifying key.
+    pub fn new(signing_key: SigningKey) -> Self {
+        Ed25519 {
+            verifying_key: signing_key.verifying_key(),
+            signing_key: Some(signing_key),
+        }
+    }
+
+    /// Creates a new `Ed25519` provider that only verifies signatures.
+    pub fn verifier(verifying_key: VerifyingKey) -> Self {
+        Ed25519 {
+            signing_key: None,
+            verifying_key,
+        }
+    }
+}
+
+impl SignatureProvider for Ed25519 {
+    fn algorithm(&self) -> u8 {
+        ED25519
+    }
+
+    fn signature_length(&self) -> usize {
+        Signature::BYTE_SIZE
+    }
+
+    fn sign(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let signing_key = self.signing_key.as_ref()?;
+
+        Some(signing_key.sign(message).to_bytes().to_vec())
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        Signature::from_slice(signature).map_or(false, |signature| {
+            self.verifying_key.verify(message, &signature).is_ok()
+        })
+    }
+}
+
+/// Signs and verifies headers with HMAC-SHA256, under a key shared by the sender and the receiver.
+#[derive(Clone)]
+pub struct HmacSha256 {
+    mac: Hmac<Sha256>,
+}
+
+impl HmacSha256 {
+    /// Creates a new `HmacSha256` provider with the shared key.
+    pub fn new(key: &[u8]) -> Self {
+        HmacSha256 {
+            mac: Hmac::new_from_slice(key).expect("HMAC accepts keys of any length"),
+        }
+    }
+}
+
+// Leaves the key out, so it is not logged.
+impl fmt::Debug for HmacSha256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacSha256").finish_non_exhaustive()
+    }
+}
+
+impl SignatureProvider for HmacSha256 {
+    fn algorithm(&self) -> u8 {
+        HMAC_SHA256
+    }
+
+    fn signature_length(&self) -> usize {
+        HMAC_SHA256_LENGTH
+    }
+
+    fn sign(&self, message: &[u8]) -> Option<Vec<u8>> {
+        let mut mac = self.mac.clone();
+
+        mac.update(message);
+
+        Some(mac.finalize().into_bytes().to_vec())
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        let mut mac = self.mac.clone();
+
+        mac.update(message);
+        mac.verify_slice(signature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::v2::{Builder, Header, Type};
+
+    fn header() -> Vec<u8> {
+        Builder::localhost()
+            .write_tlv(Type::Authority, b"example.com")
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn ed25519() {
+        let input = header();
+        let header = Header::try_from(input.as_slice()).unwrap();
+        let signer = Ed25519::new(SigningKey::from_bytes(&[1; 32]));
+        let verifier = Ed25519::verifier(SigningKey::from_bytes(&[1; 32]).verifying_key());
+        let signed = header.with_signature(&signer).unwrap();
+        let (algorithm, signature) = signed.signature().unwrap();
+
+        assert_eq!(algorithm, ED25519);
+        assert_eq!(signature.len(), Signature::BYTE_SIZE);
+        assert_eq!(signed.verify_signature(&signer), Some(true));
+        assert_eq!(signed.verify_signature(&verifier), Some(true));
+        assert_eq!(
+            signed.verify_signature(&Ed25519::verifier(
+                SigningKey::from_bytes(&[2; 32]).verifying_key()
+            )),
+            Some(false)
+        );
+        assert!(header.with_signature(&verifier).is_err());
+    }
+
+    #[test]
+    fn hmac_sha256() {
+        let input = header();
+        let header = Header::try_from(input.as_slice()).unwrap();
+        let signed = header.with_signature(&HmacSha256::new(b"secret")).unwrap();
+        let (algorithm, signature) = signed.signature().unwrap();
+
+        assert_eq!(algorithm, HMAC_SHA256);
+        assert_eq!(signature.len(), HMAC_SHA256_LENGTH);
+        assert_eq!(
+            signed.verify_signature(&HmacSha256::new(b"secret")),
+            Some(true)
+        );
+        assert_eq!(
+            signed.verify_signature(&HmacSha256::new(b"other")),
+            Some(false)
+        );
+    }
+
+    /// Headers signed by one provider verify with the other, so senders and receivers can each choose theirs.
+    #[cfg(feature = "ring")]
+    #[test]
+    fn interchangeable_with_ring() {
+        use crate::v2::signature::ring;
+        use ::ring::signature::{Ed25519KeyPair, KeyPair};
+
+        let input = header();
+        let header = Header::try_from(input.as_slice()).unwrap();
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(&[1; 32]).unwrap();
+        let signing_key = SigningKey::from_bytes(&[1; 32]);
+
+        assert_eq!(
+            key_pair.public_key().as_ref(),
+            signing_key.verifying_key().as_bytes()
+        );
+
+        let signed = header
+            .with_signature(&ring::Ed25519::new(key_pair))
+            .unwrap();
+
+        assert_eq!(
+            signed.verify_signature(&Ed25519::new(signing_key)),
+            Some(true)
+        );
+
+        let signed = header.with_signature(&HmacSha256::new(b"secret")).unwrap();
+
+        assert_eq!(
+            signed.verify_signature(&ring::HmacSha256::new(b"secret")),
+            Some(true)
+        );
+    }
+}
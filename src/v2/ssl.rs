@@ -0,0 +1,144 @@
+//! The SSL Type-Length-Value, describing the TLS connection of the client to the proxy.
+//!
+//! The SSL TLV value is a client flags byte and a 32-bit verification result,
+//! followed by sub-TLVs describing the TLS connection.
+use std::prelude::v1::*;
+
+use super::{Type, TypeLengthValue, WriteToHeader, Writer};
+use std::io::{self, Write};
+
+/// The client connected over SSL/TLS.
+pub const CLIENT_SSL: u8 = 0x01;
+/// The client provided a certificate over the current connection.
+pub const CLIENT_CERT_CONNECTION: u8 = 0x02;
+/// The client provided a certificate at least once over the TLS session the connection belongs to.
+pub const CLIENT_CERT_SESSION: u8 = 0x04;
+
+/// The attributes of a TLS connection carried by the SSL TLV, as captured from any TLS library.
+/// Attributes that are `None` are left out of the TLV.
+///
+/// The `tls` module fills these in from `rustls` and `openssl` connections;
+/// other TLS libraries (or a proxy that terminated TLS elsewhere) can fill them in directly.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::v2::{Builder, Command, Header, Protocol, SslInfo, Type, Version, CLIENT_SSL};
+///
+/// let ssl = SslInfo {
+///     client: CLIENT_SSL,
+///     version: Some("TLSv1.3"),
+///     cipher: Some("TLS_AES_128_GCM_SHA256"),
+///     ..Default::default()
+/// };
+/// let header = Builder::with_addresses(
+///     Version::Two | Command::Proxy,
+///     Protocol::Stream,
+///     ("127.0.0.1:80".parse().unwrap(), "192.168.1.1:443".parse().unwrap()),
+/// )
+/// .write_payload(&ssl)
+/// .unwrap()
+/// .build()
+/// .unwrap();
+/// let header = Header::try_from(header.as_slice()).unwrap();
+///
+/// assert_eq!(header.tlvs().next().unwrap().unwrap(), ssl.to_tlv().unwrap());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SslInfo<'a> {
+    /// The client flags, a combination of `CLIENT_SSL`, `CLIENT_CERT_CONNECTION` and `CLIENT_CERT_SESSION`.
+    pub client: u8,
+    /// The result of verifying the client's certificate, where 0 is success.
+    pub verify: u32,
+    /// The protocol version (e.g. `TLSv1.3`).
+    pub version: Option<&'a str>,
+    /// The common name of the subject of the client's certificate.
+    pub common_name: Option<&'a str>,
+    /// The cipher suite (e.g. `ECDHE-RSA-AES128-GCM-SHA256`).
+    pub cipher: Option<&'a str>,
+    /// The algorithm that signed the client's certificate (e.g. `SHA256`).
+    pub signature_algorithm: Option<&'a str>,
+    /// The type and size of the key of the client's certificate (e.g. `RSA2048`).
+    pub key_algorithm: Option<&'a str>,
+}
+
+impl<'a> SslInfo<'a> {
+    /// Encodes these attributes as an SSL `TypeLengthValue`.
+    /// Returns an error when the value would exceed `u16::MAX` bytes.
+    pub fn to_tlv(&self) -> io::Result<TypeLengthValue<'static>> {
+        let mut writer = Writer::default();
+
+        writer.write_all([self.client].as_slice())?;
+        writer.write_all(self.verify.to_be_bytes().as_slice())?;
+
+        let sub_tlvs = [
+            (Type::SSLVersion, self.version),
+            (Type::SSLCommonName, self.common_name),
+            (Type::SSLCipher, self.cipher),
+            (Type::SSLSignatureAlgorithm, self.signature_algorithm),
+            (Type::SSLKeyAlgorithm, self.key_algorithm),
+        ];
+
+        for (kind, value) in sub_tlvs {
+            if let Some(value) = value {
+                (kind, value.as_bytes()).write_to(&mut writer)?;
+            }
+        }
+
+        let value = writer.finish();
+
+        if value.len() > u16::MAX as usize {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+
+        Ok(TypeLengthValue {
+            kind: Type::SSL.into(),
+            value: value.into(),
+        })
+    }
+}
+
+impl<'a> WriteToHeader for SslInfo<'a> {
+    fn write_to(&self, writer: &mut Writer) -> io::Result<usize> {
+        self.to_tlv()?.write_to(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let ssl = SslInfo {
+            client: CLIENT_SSL | CLIENT_CERT_CONNECTION,
+            verify: 0,
+            version: Some("TLSv1.3"),
+            common_name: Some("example.com"),
+            ..Default::default()
+        };
+
+        let mut expected = vec![0x03, 0, 0, 0, 0, 0x21, 0, 7];
+        expected.extend_from_slice(b"TLSv1.3");
+        expected.extend_from_slice(&[0x22, 0, 11]);
+        expected.extend_from_slice(b"example.com");
+
+        assert_eq!(
+            ssl.to_tlv().unwrap(),
+            TypeLengthValue::new(Type::SSL, expected.as_slice())
+        );
+    }
+
+    #[test]
+    fn encode_empty() {
+        let ssl = SslInfo {
+            client: CLIENT_SSL,
+            verify: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ssl.to_tlv().unwrap(),
+            TypeLengthValue::new(Type::SSL, &[0x01, 0, 0, 0, 1])
+        );
+    }
+}
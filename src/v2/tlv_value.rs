@@ -0,0 +1,154 @@
+//! Serializes TLV values as byte strings rather than sequences of bytes,
+//! so binary formats (e.g. bincode or CBOR) write them in one piece, and as base64 strings in human-readable formats (e.g. JSON).
+//!
+//! Deserializing also accepts sequences of bytes, as written before values were serialized as byte strings.
+use crate::prelude::*;
+
+use alloc::borrow::Cow;
+use core::fmt;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+
+/// The alphabet of standard, padded base64 (RFC 4648).
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// The byte appended to base64 strings whose length is not a multiple of 4.
+const PADDING: u8 = b'=';
+
+pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&encode(value))
+    } else {
+        serializer.serialize_bytes(value)
+    }
+}
+
+pub fn deserialize<'de, 'a, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Cow<'a, [u8]>, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(ValueVisitor)
+    } else {
+        deserializer.deserialize_byte_buf(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Cow<'static, [u8]>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte string or a base64 string")
+    }
+
+    fn visit_bytes<E: Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E: Error>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(value))
+    }
+
+    fn visit_str<E: Error>(self, value: &str) -> Result<Self::Value, E> {
+        decode(value)
+            .map(Cow::Owned)
+            .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut value = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+        while let Some(byte) = seq.next_element()? {
+            value.push(byte);
+        }
+
+        Ok(Cow::Owned(value))
+    }
+}
+
+/// Encodes the bytes as standard, padded base64.
+fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | (*byte as u32) << (16 - 8 * index)
+        });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (group >> (18 - 6 * index)) & 0x3F;
+
+                encoded.push(ALPHABET[sextet as usize] as char);
+            } else {
+                encoded.push(PADDING as char);
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes standard, padded base64, or `None` if the string is not valid base64.
+fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.as_bytes();
+
+    if encoded.len() % 4 != 0 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    let chunks = encoded.len() / 4;
+
+    for (chunk_index, chunk) in encoded.chunks(4).enumerate() {
+        let padding = chunk
+            .iter()
+            .rev()
+            .take_while(|byte| **byte == PADDING)
+            .count();
+
+        // Only the last group may be padded, and by at most two bytes.
+        if padding > 2 || (padding > 0 && chunk_index + 1 != chunks) {
+            return None;
+        }
+
+        let mut group = 0u32;
+
+        for byte in &chunk[..4 - padding] {
+            let sextet = ALPHABET.iter().position(|letter| letter == byte)?;
+
+            group = group << 6 | sextet as u32;
+        }
+
+        group <<= 6 * padding;
+        bytes.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"\xFF\x00proxy", "/wBwcm94eQ=="),
+        ];
+
+        for (bytes, encoded) in cases {
+            assert_eq!(encode(bytes), encoded);
+            assert_eq!(decode(encoded).as_deref(), Some(bytes));
+        }
+
+        assert_eq!(decode("Zg="), None);
+        assert_eq!(decode("Zg==Zm8="), None);
+        assert_eq!(decode("Z==="), None);
+        assert_eq!(decode("Zm9*"), None);
+    }
+}
@@ -0,0 +1,234 @@
+//! A borrowed view of a binary header that decodes its fields on demand.
+use super::{
+    field, parse_addresses, parse_fixed, AddressFamily, Addresses, Command, Header, ParseError,
+    Protocol, TypeLengthValues, Version, MINIMUM_LENGTH,
+};
+use crate::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use alloc::borrow::Cow;
+
+/// A validated version 2 header borrowed from the input, whose addresses and TLVs are only decoded when asked for.
+///
+/// Parsing a `HeaderRef` checks the same things as parsing a `Header`, but skips copying the addresses,
+/// so callers that only need, say, the source address do no more work than that.
+/// Convert it into a `Header` for the fully decoded representation.
+///
+/// ## Examples
+/// ```rust
+/// use ppp::v2::{Command, HeaderRef, PROTOCOL_PREFIX};
+///
+/// let mut input = Vec::from(PROTOCOL_PREFIX);
+/// input.extend([
+///    0x21, 0x11, 0, 12, 127, 0, 0, 1, 192, 168, 1, 1, 0, 80, 1, 187,
+/// ]);
+///
+/// let header = HeaderRef::try_from(input.as_slice()).unwrap();
+///
+/// assert_eq!(header.command(), Command::Proxy);
+/// assert_eq!(header.source(), Some("127.0.0.1:80".parse().unwrap()));
+/// assert_eq!(header.tlvs().count(), 0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeaderRef<'a> {
+    bytes: &'a [u8],
+    command: Command,
+    address_family: AddressFamily,
+    protocol: Protocol,
+}
+
+impl<'a> TryFrom<&'a [u8]> for HeaderRef<'a> {
+    type Error = ParseError;
+
+    fn try_from(input: &'a [u8]) -> Result<Self, Self::Error> {
+        let fixed = parse_fixed(input, &mut ())?;
+
+        Ok(HeaderRef {
+            bytes: &input[..fixed.length],
+            command: fixed.command,
+            address_family: fixed.address_family,
+            protocol: fixed.protocol,
+        })
+    }
+}
+
+impl<'a> HeaderRef<'a> {
+    /// The `Version` of this `HeaderRef`.
+    pub fn version(&self) -> Version {
+        Version::Two
+    }
+
+    /// The `Command` of this `HeaderRef`.
+    pub fn command(&self) -> Command {
+        self.command
+    }
+
+    /// The `AddressFamily` of this `HeaderRef`.
+    pub fn address_family(&self) -> AddressFamily {
+        self.address_family
+    }
+
+    /// The `Protocol` of this `HeaderRef`.
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// The length of this `HeaderRef`'s payload in bytes.
+    pub fn length(&self) -> usize {
+        self.bytes.len() - MINIMUM_LENGTH
+    }
+
+    /// The total length of this `HeaderRef` in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Tests whether this `HeaderRef`'s underlying byte slice is empty, which is never the case for a parsed header.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The underlying byte slice this `HeaderRef` borrows.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The bytes of the address portion of the payload.
+    pub fn address_bytes(&self) -> &'a [u8] {
+        &self.bytes[MINIMUM_LENGTH..self.address_bytes_end()]
+    }
+
+    /// The bytes of the `TypeLengthValue` portion of the payload.
+    pub fn tlv_bytes(&self) -> &'a [u8] {
+        &self.bytes[self.address_bytes_end()..]
+    }
+
+    /// An `Iterator` of `TypeLengthValue`s, decoded as it advances.
+    pub fn tlvs(&self) -> TypeLengthValues<'a> {
+        TypeLengthValues::from(self.tlv_bytes())
+    }
+
+    /// Decodes the `Addresses` of this `HeaderRef`, as sent regardless of the `Command`.
+    pub fn addresses(&self) -> Addresses {
+        parse_addresses(self.address_family, self.address_bytes(), &mut ())
+    }
+
+    /// Decodes the `Addresses` the receiver should use for the connection,
+    /// which are `Addresses::Unspecified` for `Command::Local` as the specification requires.
+    pub fn proxied_addresses(&self) -> Addresses {
+        if self.command == Command::Local {
+            Addresses::Unspecified
+        } else {
+            self.addresses()
+        }
+    }
+
+    /// Decodes the address of the original client, if the header has IP addresses.
+    pub fn source(&self) -> Option<SocketAddr> {
+        let bytes = self.address_bytes();
+
+        match self.address_family {
+            AddressFamily::IPv4 => Some(SocketAddr::from((
+                Ipv4Addr::from(field::array::<4>(bytes, 0)),
+                field::read_u16(bytes, 8),
+            ))),
+            AddressFamily::IPv6 => Some(SocketAddr::from((
+                Ipv6Addr::from(field::array::<16>(bytes, 0)),
+                field::read_u16(bytes, 32),
+            ))),
+            AddressFamily::Unix | AddressFamily::Unspecified => None,
+        }
+    }
+
+    /// Decodes the address the client originally connected to, if the header has IP addresses.
+    pub fn destination(&self) -> Option<SocketAddr> {
+        let bytes = self.address_bytes();
+
+        match self.address_family {
+            AddressFamily::IPv4 => Some(SocketAddr::from((
+                Ipv4Addr::from(field::array::<4>(bytes, 4)),
+                field::read_u16(bytes, 10),
+            ))),
+            AddressFamily::IPv6 => Some(SocketAddr::from((
+                Ipv6Addr::from(field::array::<16>(bytes, 16)),
+                field::read_u16(bytes, 34),
+            ))),
+            AddressFamily::Unix | AddressFamily::Unspecified => None,
+        }
+    }
+
+    /// The end of the address portion of the payload.
+    /// Like `Header`, the payload of a header with an unspecified address family is all addresses.
+    fn address_bytes_end(&self) -> usize {
+        let length = self.length();
+        let address_bytes = self.address_family.byte_length().unwrap_or(length);
+
+        MINIMUM_LENGTH + core::cmp::min(address_bytes, length)
+    }
+}
+
+impl<'a> From<HeaderRef<'a>> for Header<'a> {
+    fn from(header: HeaderRef<'a>) -> Self {
+        Header {
+            header: Cow::Borrowed(header.bytes),
+            version: header.version(),
+            command: header.command,
+            protocol: header.protocol,
+            addresses: header.addresses(),
+        }
+    }
+}
+
+impl<'a> AsRef<[u8]> for HeaderRef<'a> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+// The test vectors include text headers, so they require both versions.
+#[cfg(all(test, feature = "v1"))]
+mod tests {
+    use super::*;
+    use crate::v2::PROTOCOL_PREFIX;
+    use crate::vectors::VECTORS;
+
+    #[test]
+    fn matches_header() {
+        for vector in VECTORS
+            .iter()
+            .filter(|v| v.bytes.starts_with(PROTOCOL_PREFIX))
+        {
+            let header = Header::try_from(vector.bytes);
+            let view = HeaderRef::try_from(vector.bytes);
+
+            match (header, view) {
+                (Ok(header), Ok(view)) => {
+                    assert_eq!(Header::from(view), header, "{}", vector.name);
+                    assert_eq!(view.tlv_bytes(), header.tlv_bytes(), "{}", vector.name);
+                    assert_eq!(
+                        view.proxied_addresses(),
+                        header.proxied_addresses(),
+                        "{}",
+                        vector.name
+                    );
+
+                    let addresses = header.addresses;
+                    let (source, destination) = match addresses {
+                        Addresses::IPv4(a) => (
+                            Some((a.source_address, a.source_port).into()),
+                            Some((a.destination_address, a.destination_port).into()),
+                        ),
+                        Addresses::IPv6(a) => (
+                            Some((a.source_address, a.source_port).into()),
+                            Some((a.destination_address, a.destination_port).into()),
+                        ),
+                        _ => (None, None),
+                    };
+
+                    assert_eq!(view.source(), source, "{}", vector.name);
+                    assert_eq!(view.destination(), destination, "{}", vector.name);
+                }
+                (Err(header), Err(view)) => assert_eq!(header, view, "{}", vector.name),
+                (header, view) => panic!("{}: {:?} != {:?}", vector.name, header, view),
+            }
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Test vectors of headers modelled after common PROXY protocol implementations, both valid and invalid.
+//!
+//! The parser is tested against every vector, and downstream integration tests can reuse the corpus.
+//! Every vector is currently `Synthetic`: until captures from the implementations are recorded
+//! (the `interop` CI job uploads them), the corpus checks the parser against their documented output,
+//! not their observed traffic.
+//!
+//! Each vector records its `Provenance`. `Captured` vectors are the bytes an implementation sent on the wire,
+//! as recorded by the interop tests (see `tests/interop.rs`). `Synthetic` vectors are reconstructed from
+//! the implementation's documented output (addresses and TLVs included), so they only show what the
+//! implementation is documented to send; filter them out to test against observed traffic only.
+//!
+//! ## Examples
+//! ```rust
+//! use ppp::vectors::{Source, VECTORS};
+//!
+//! for vector in VECTORS.iter().filter(|v| v.valid && v.source == Source::AwsNlb) {
+//!     assert!(ppp::v2::Header::try_from(vector.bytes).is_ok(), "{}", vector.name);
+//! }
+//! ```
+use crate::Version;
+
+/// The implementation a test vector is modelled after.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Source {
+    /// HAProxy's `send-proxy` and `send-proxy-v2` server options, including the examples of the specification.
+    HAProxy,
+    /// NGINX's `proxy_protocol on` directive, which sends text headers.
+    Nginx,
+    /// AWS Network Load Balancers, which send binary headers with a VPC endpoint TLV for PrivateLink connections.
+    AwsNlb,
+    /// Traefik's `proxyProtocol` middleware.
+    Traefik,
+    /// Malformed headers that a compliant parser must reject.
+    Invalid,
+}
+
+/// Where the bytes of a test vector come from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Provenance {
+    /// Recorded from the implementation's output on the wire, e.g. by running `tests/interop.rs`
+    /// with `PPP_INTEROP_CAPTURE` set.
+    Captured,
+    /// Reconstructed from the implementation's documentation or specification, rather than observed.
+    Synthetic,
+}
+
+/// A header modelled after one a PROXY protocol implementation sends.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vector {
+    /// A unique name for the vector.
+    pub name: &'static str,
+    /// The implementation the vector is modelled after.
+    pub source: Source,
+    /// Whether the bytes were captured from the implementation or reconstructed.
+    pub provenance: Provenance,
+    /// The version of the PROXY protocol the vector claims to use.
+    pub version: Version,
+    /// Whether the parser must accept the vector.
+    pub valid: bool,
+    /// The complete header.
+    pub bytes: &'static [u8],
+}
+
+/// The corpus of test vectors.
+pub const VECTORS: &[Vector] = &[
+    Vector {
+        name: "haproxy-v1-tcp4",
+        source: Source::HAProxy,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: true,
+        bytes: b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\n",
+    },
+    Vector {
+        name: "haproxy-v1-tcp6-longest",
+        source: Source::HAProxy,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: true,
+        bytes: b"PROXY TCP6 ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff 65535 65535\r\n",
+    },
+    Vector {
+        name: "haproxy-v1-unknown",
+        source: Source::HAProxy,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: true,
+        bytes: b"PROXY UNKNOWN\r\n",
+    },
+    Vector {
+        name: "haproxy-v1-unknown-longest",
+        source: Source::HAProxy,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: true,
+        bytes: b"PROXY UNKNOWN ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff 65535 65535\r\n",
+    },
+    Vector {
+        name: "haproxy-v2-local",
+        source: Source::HAProxy,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: true,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x20\x00\x00\x00",
+    },
+    Vector {
+        name: "haproxy-v2-tcp4",
+        source: Source::HAProxy,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: true,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x0C\
+                 \xC0\xA8\x00\x01\xC0\xA8\x00\x0B\xDC\x04\x01\xBB",
+    },
+    Vector {
+        name: "haproxy-v2-tcp4-ssl",
+        source: Source::HAProxy,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: true,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x33\
+                 \xC0\xA8\x00\x01\xC0\xA8\x00\x0B\xDC\x04\x01\xBB\
+                 \x20\x00\x24\x07\x00\x00\x00\x00\
+                 \x21\x00\x07TLSv1.3\
+                 \x22\x00\x12client.example.com",
+    },
+    Vector {
+        name: "haproxy-v2-tcp6-tlvs",
+        source: Source::HAProxy,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: true,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x21\x00\x4A\
+                 \x20\x01\x0D\xB8\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\
+                 \x20\x01\x0D\xB8\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x02\
+                 \xDC\x04\x01\xBB\
+                 \x01\x00\x02h2\
+                 \x02\x00\x0Bexample.com\
+                 \x05\x00\x100123456789abcdef",
+    },
+    Vector {
+        name: "nginx-v1-tcp4",
+        source: Source::Nginx,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: true,
+        bytes: b"PROXY TCP4 203.0.113.7 10.0.0.5 51234 80\r\n",
+    },
+    Vector {
+        name: "nginx-v1-tcp6",
+        source: Source::Nginx,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: true,
+        bytes: b"PROXY TCP6 2001:db8::7 2001:db8::5 51234 443\r\n",
+    },
+    Vector {
+        name: "aws-nlb-v2-tcp4",
+        source: Source::AwsNlb,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: true,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x0C\
+                 \xAC\x1F\x07\x71\xAC\x1F\x0A\x1F\x9F\xEA\x00\x50",
+    },
+    Vector {
+        name: "aws-nlb-v2-tcp4-vpc-endpoint",
+        source: Source::AwsNlb,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: true,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x2C\
+                 \xAC\x1F\x07\x71\xAC\x1F\x0A\x1F\x9F\xEA\x00\x50\
+                 \xEA\x00\x17\x01vpce-08d2bf15fac5001c9\
+                 \x04\x00\x03\x00\x00\x00",
+    },
+    Vector {
+        name: "traefik-v1-tcp4",
+        source: Source::Traefik,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: true,
+        bytes: b"PROXY TCP4 10.0.0.1 10.0.0.2 53000 8080\r\n",
+    },
+    Vector {
+        name: "traefik-v2-tcp6",
+        source: Source::Traefik,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: true,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x21\x00\x24\
+                 \xFD\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x10\
+                 \xFD\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x20\
+                 \xC9\x38\x20\xFB",
+    },
+    Vector {
+        name: "traefik-v2-udp4",
+        source: Source::Traefik,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: true,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x12\x00\x0C\
+                 \x0A\x00\x00\x01\x0A\x00\x00\x02\xCF\x08\x00\x35",
+    },
+    Vector {
+        name: "invalid-v1-line-feed",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: false,
+        bytes: b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\n",
+    },
+    Vector {
+        name: "invalid-v1-lowercase",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: false,
+        bytes: b"proxy TCP4 192.168.0.1 192.168.0.11 56324 443\r\n",
+    },
+    Vector {
+        name: "invalid-v1-port",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: false,
+        bytes: b"PROXY TCP4 192.168.0.1 192.168.0.11 65536 443\r\n",
+    },
+    Vector {
+        name: "invalid-v1-address",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: false,
+        bytes: b"PROXY TCP4 192.168.0.256 192.168.0.11 56324 443\r\n",
+    },
+    Vector {
+        name: "invalid-v1-address-family",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: false,
+        bytes: b"PROXY TCP4 ::1 ::1 56324 443\r\n",
+    },
+    Vector {
+        name: "invalid-v1-protocol",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::One,
+        valid: false,
+        bytes: b"PROXY UDP4 192.168.0.1 192.168.0.11 56324 443\r\n",
+    },
+    Vector {
+        name: "invalid-v2-version",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: false,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x11\x11\x00\x0C\
+                 \xC0\xA8\x00\x01\xC0\xA8\x00\x0B\xDC\x04\x01\xBB",
+    },
+    Vector {
+        name: "invalid-v2-command",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: false,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x2F\x11\x00\x0C\
+                 \xC0\xA8\x00\x01\xC0\xA8\x00\x0B\xDC\x04\x01\xBB",
+    },
+    Vector {
+        name: "invalid-v2-address-family",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: false,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x41\x00\x0C\
+                 \xC0\xA8\x00\x01\xC0\xA8\x00\x0B\xDC\x04\x01\xBB",
+    },
+    Vector {
+        name: "invalid-v2-short-addresses",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: false,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x21\x00\x0C\
+                 \xC0\xA8\x00\x01\xC0\xA8\x00\x0B\xDC\x04\x01\xBB",
+    },
+    Vector {
+        name: "invalid-v2-tlv-overflow",
+        source: Source::Invalid,
+        provenance: Provenance::Synthetic,
+        version: Version::Two,
+        valid: false,
+        bytes: b"\r\n\r\n\0\r\nQUIT\n\
+                 \x21\x11\x00\x1A\
+                 \xC0\xA8\x00\x01\xC0\xA8\x00\x0B\xDC\x04\x01\xBB\
+                 \x02\x00\x20example.com",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{v1, v2, Header};
+
+    /// Parses the vector, including the TLVs of binary headers.
+    fn parse(vector: &Vector) -> Result<Header<'static>, String> {
+        match vector.version {
+            Version::One => v1::Header::try_from(vector.bytes)
+                .map(|header| header.to_owned().into())
+                .map_err(|error| format!("{:?}", error)),
+            Version::Two => {
+                let header = v2::Header::try_from(vector.bytes).map_err(|e| format!("{:?}", e))?;
+
+                if let Some(Err(error)) = header.tlvs().find(Result::is_err) {
+                    return Err(format!("{:?}", error));
+                }
+
+                Ok(header.to_owned().into())
+            }
+        }
+    }
+
+    #[test]
+    fn unique_names() {
+        for (index, vector) in VECTORS.iter().enumerate() {
+            assert!(
+                VECTORS[index + 1..].iter().all(|v| v.name != vector.name),
+                "{}",
+                vector.name
+            );
+        }
+    }
+
+    #[test]
+    fn valid() {
+        for vector in VECTORS.iter().filter(|v| v.valid) {
+            let header = parse(vector).unwrap_or_else(|e| panic!("{}: {}", vector.name, e));
+
+            assert_eq!(header.version(), vector.version, "{}", vector.name);
+            assert_eq!(header.len(), vector.bytes.len(), "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        for vector in VECTORS.iter().filter(|v| !v.valid) {
+            assert!(parse(vector).is_err(), "{}", vector.name);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! `wasm-bindgen` bindings for parsing and encoding headers from JavaScript,
+//! for edge platforms that run Rust compiled to WebAssembly.
+//!
+//! Addresses are exchanged as strings in `ip:port` form (e.g. `192.168.1.1:443` or `[::1]:443`).
+use std::prelude::v1::*;
+
+use crate::bindings;
+use crate::v2::{Command, Protocol};
+use crate::ProxyInfo;
+use wasm_bindgen::prelude::*;
+
+/// A parsed header of either version.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedHeader {
+    info: ProxyInfo,
+    length: usize,
+}
+
+#[wasm_bindgen]
+impl ParsedHeader {
+    /// The version of the PROXY protocol the header was encoded with (1 or 2).
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u8 {
+        self.info.version as u8
+    }
+
+    /// Whether the header's command is `PROXY`, rather than `LOCAL`.
+    #[wasm_bindgen(getter)]
+    pub fn proxy(&self) -> bool {
+        self.info.command == Command::Proxy
+    }
+
+    /// Whether the proxied connection is a datagram (i.e. UDP) one.
+    #[wasm_bindgen(getter)]
+    pub fn datagram(&self) -> bool {
+        self.info.protocol == Protocol::Datagram
+    }
+
+    /// The address of the original client, if the header has IP addresses.
+    #[wasm_bindgen(getter)]
+    pub fn source(&self) -> Option<String> {
+        self.info.source.map(|address| address.to_string())
+    }
+
+    /// The address the client originally connected to, if the header has IP addresses.
+    #[wasm_bindgen(getter)]
+    pub fn destination(&self) -> Option<String> {
+        self.info.destination.map(|address| address.to_string())
+    }
+
+    /// The authority (i.e. SNI host name) TLV of a binary header.
+    #[wasm_bindgen(getter)]
+    pub fn authority(&self) -> Option<String> {
+        self.info.authority.as_deref().map(str::to_string)
+    }
+
+    /// The unique ID TLV of a binary header.
+    #[wasm_bindgen(getter, js_name = uniqueId)]
+    pub fn unique_id(&self) -> Option<Vec<u8>> {
+        self.info.unique_id.as_deref().map(<[u8]>::to_vec)
+    }
+
+    /// The length in bytes of the header, after which the payload starts.
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
+/// Parses a header of either version from the start of the input.
+/// Returns `undefined` if more bytes are needed, and throws the error's code and message for invalid headers.
+#[wasm_bindgen(js_name = parseHeader)]
+pub fn parse_header(input: &[u8]) -> Result<Option<ParsedHeader>, JsValue> {
+    match bindings::parse(input) {
+        Ok(header) => Ok(header.map(|(info, length)| ParsedHeader { info, length })),
+        Err(error) => Err(JsValue::from_str(&error)),
+    }
+}
+
+/// Encodes a text header between the given addresses, or an `UNKNOWN` header when both are missing.
+#[wasm_bindgen(js_name = encodeV1)]
+pub fn encode_v1(source: Option<String>, destination: Option<String>) -> Result<Vec<u8>, JsValue> {
+    bindings::encode_v1(source.as_deref(), destination.as_deref())
+        .map_err(|error| JsValue::from_str(&error))
+}
+
+/// Encodes a binary header between the given addresses followed by the given TLVs,
+/// or a `LOCAL` header without addresses when both are missing.
+#[wasm_bindgen(js_name = encodeV2)]
+pub fn encode_v2(
+    source: Option<String>,
+    destination: Option<String>,
+    datagram: bool,
+    tlvs: &[u8],
+) -> Result<Vec<u8>, JsValue> {
+    bindings::encode_v2(source.as_deref(), destination.as_deref(), datagram, tlvs)
+        .map_err(|error| JsValue::from_str(&error))
+}
+
+// Errors are JavaScript values, which only exist when running as WebAssembly.
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn mismatched_addresses() {
+        let family = encode_v2(
+            Some("127.0.0.1:80".to_string()),
+            Some("[::1]:443".to_string()),
+            false,
+            &[],
+        )
+        .unwrap_err();
+        let pairs = encode_v1(None, Some("[::1]:443".to_string())).unwrap_err();
+
+        assert_eq!(
+            family.as_string().as_deref(),
+            Some("Addresses must be of the same family.")
+        );
+        assert_eq!(
+            pairs.as_string().as_deref(),
+            Some("Addresses must be given in pairs.")
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Conversions between the IP address models and the WinSock `SOCKADDR_IN` and `SOCKADDR_IN6` structures,
+//! for Windows proxies calling WinSock directly (e.g. with `AcceptEx` or `getsockname`).
+//!
+//! Ports are converted to and from network byte order. Flow information and scope identifiers are not part of a header,
+//! so they are zero in the converted structures and are dropped from converted addresses.
+use core::mem;
+
+use crate::ip::{IPv4, IPv6};
+use crate::net::{Ipv4Addr, Ipv6Addr};
+use windows_sys::Win32::Networking::WinSock::{AF_INET, AF_INET6, SOCKADDR_IN, SOCKADDR_IN6};
+
+impl IPv4 {
+    /// Converts a source and destination `SOCKADDR_IN` into addresses. The address family fields are not checked.
+    pub fn from_sockaddr_in(source: &SOCKADDR_IN, destination: &SOCKADDR_IN) -> Self {
+        let (source_address, source_port) = from_sockaddr_in(source);
+        let (destination_address, destination_port) = from_sockaddr_in(destination);
+
+        IPv4::new(
+            source_address,
+            destination_address,
+            source_port,
+            destination_port,
+        )
+    }
+
+    /// The source and destination as `SOCKADDR_IN`s.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use ppp::v2::IPv4;
+    ///
+    /// let addresses = IPv4::new([192, 0, 2, 1], [10, 0, 0, 1], 51234, 443);
+    /// let (source, destination) = addresses.to_sockaddr_in();
+    ///
+    /// assert_eq!(u16::from_be(source.sin_port), 51234);
+    /// assert_eq!(IPv4::from_sockaddr_in(&source, &destination), addresses);
+    /// ```
+    pub fn to_sockaddr_in(&self) -> (SOCKADDR_IN, SOCKADDR_IN) {
+        (
+            to_sockaddr_in(self.source_address, self.source_port),
+            to_sockaddr_in(self.destination_address, self.destination_port),
+        )
+    }
+}
+
+impl IPv6 {
+    /// Converts a source and destination `SOCKADDR_IN6` into addresses. The address family fields are not checked.
+    pub fn from_sockaddr_in6(source: &SOCKADDR_IN6, destination: &SOCKADDR_IN6) -> Self {
+        let (source_address, source_port) = from_sockaddr_in6(source);
+        let (destination_address, destination_port) = from_sockaddr_in6(destination);
+
+        IPv6::new(
+            source_address,
+            destination_address,
+            source_port,
+            destination_port,
+        )
+    }
+
+    /// The source and destination as `SOCKADDR_IN6`s.
+    pub fn to_sockaddr_in6(&self) -> (SOCKADDR_IN6, SOCKADDR_IN6) {
+        (
+            to_sockaddr_in6(self.source_address, self.source_port),
+            to_sockaddr_in6(self.destination_address, self.destination_port),
+        )
+    }
+}
+
+fn from_sockaddr_in(address: &SOCKADDR_IN) -> (Ipv4Addr, u16) {
+    // Every field of the address union is a view of the same 4 bytes, in network byte order.
+    let octets = unsafe { address.sin_addr.S_un.S_addr }.to_ne_bytes();
+
+    (Ipv4Addr::from(octets), u16::from_be(address.sin_port))
+}
+
+fn to_sockaddr_in(ip: Ipv4Addr, port: u16) -> SOCKADDR_IN {
+    // WinSock structures are plain data, for which all zeros is the unspecified address.
+    let mut address: SOCKADDR_IN = unsafe { mem::zeroed() };
+
+    address.sin_family = AF_INET;
+    address.sin_port = port.to_be();
+    address.sin_addr.S_un.S_addr = u32::from_ne_bytes(ip.octets());
+
+    address
+}
+
+fn from_sockaddr_in6(address: &SOCKADDR_IN6) -> (Ipv6Addr, u16) {
+    // Every field of the address union is a view of the same 16 bytes, in network byte order.
+    let octets = unsafe { address.sin6_addr.u.Byte };
+
+    (Ipv6Addr::from(octets), u16::from_be(address.sin6_port))
+}
+
+fn to_sockaddr_in6(ip: Ipv6Addr, port: u16) -> SOCKADDR_IN6 {
+    let mut address: SOCKADDR_IN6 = unsafe { mem::zeroed() };
+
+    address.sin6_family = AF_INET6;
+    address.sin6_port = port.to_be();
+    address.sin6_addr.u.Byte = ip.octets();
+
+    address
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv4_round_trip() {
+        let addresses = IPv4::new([192, 0, 2, 1], [10, 0, 0, 1], 51234, 443);
+        let (source, destination) = addresses.to_sockaddr_in();
+
+        assert_eq!(source.sin_family, AF_INET);
+        assert_eq!(source.sin_port, 51234u16.to_be());
+        assert_eq!(
+            unsafe { destination.sin_addr.S_un.S_addr }.to_ne_bytes(),
+            [10, 0, 0, 1]
+        );
+        assert_eq!(IPv4::from_sockaddr_in(&source, &destination), addresses);
+    }
+
+    #[test]
+    fn ipv6_round_trip() {
+        let source: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let destination: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let addresses = IPv6::new(source, destination, 51234, 443);
+        let (mut source, destination) = addresses.to_sockaddr_in6();
+
+        assert_eq!(source.sin6_family, AF_INET6);
+        assert_eq!(source.sin6_flowinfo, 0);
+        assert_eq!(unsafe { source.Anonymous.sin6_scope_id }, 0);
+        assert_eq!(destination.sin6_port, 443u16.to_be());
+
+        source.sin6_flowinfo = 7;
+        source.Anonymous.sin6_scope_id = 3;
+
+        assert_eq!(IPv6::from_sockaddr_in6(&source, &destination), addresses);
+    }
+
+    /// Reads and writes a header over a loopback TCP connection made with the Windows socket stack.
+    #[cfg(feature = "std")]
+    #[test]
+    fn tcp_loopback() {
+        use crate::io::read_header;
+        use crate::policy::Policy;
+        use crate::{v2, Header};
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let (source, destination) =
+            IPv4::new([192, 0, 2, 1], [10, 0, 0, 1], 51234, 443).to_sockaddr_in();
+        let addresses = IPv4::from_sockaddr_in(&source, &destination);
+        let header = v2::Builder::with_addresses(
+            v2::Version::Two | v2::Command::Proxy,
+            v2::Protocol::Stream,
+            addresses,
+        )
+        .build()
+        .unwrap();
+
+        client.write_all(&header).unwrap();
+        client.write_all(b"Hello").unwrap();
+        drop(client);
+
+        let (mut stream, received) = read_header(server, Policy::Require).unwrap();
+        let mut payload = Vec::new();
+
+        stream.read_to_end(&mut payload).unwrap();
+
+        match received {
+            Some(Header::V2(received)) => {
+                assert_eq!(received.addresses, v2::Addresses::IPv4(addresses))
+            }
+            header => panic!("expected a binary header, got {:?}", header),
+        }
+        assert_eq!(payload, b"Hello");
+    }
+
+    /// Reads a header written to a named pipe, which Windows services use for local connections.
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn named_pipe() {
+        use crate::io::tokio::read_header;
+        use crate::policy::Policy;
+        use crate::{v2, Header};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::windows::named_pipe::{ClientOptions, ServerOptions};
+        use tokio::runtime::Builder;
+
+        let name = format!(r"\\.\pipe\ppp-named-pipe-{}", std::process::id());
+        let runtime = Builder::new_current_thread().enable_io().build().unwrap();
+        let addresses = IPv6::new(
+            "2001:db8::1".parse::<Ipv6Addr>().unwrap(),
+            "2001:db8::2".parse::<Ipv6Addr>().unwrap(),
+            51234,
+            443,
+        );
+        let header = v2::Builder::with_addresses(
+            v2::Version::Two | v2::Command::Proxy,
+            v2::Protocol::Stream,
+            addresses,
+        )
+        .build()
+        .unwrap();
+
+        runtime.block_on(async {
+            let server = ServerOptions::new()
+                .first_pipe_instance(true)
+                .create(&name)
+                .unwrap();
+            let mut client = ClientOptions::new().open(&name).unwrap();
+
+            server.connect().await.unwrap();
+            client.write_all(&header).await.unwrap();
+            client.write_all(b"Hello").await.unwrap();
+
+            let (mut stream, received) = read_header(server, Policy::Require).await.unwrap();
+            let mut payload = [0; 5];
+
+            // The client keeps the pipe open, so the payload is read by length rather than to the end.
+            stream.read_exact(&mut payload).await.unwrap();
+
+            match received {
+                Some(Header::V2(received)) => {
+                    assert_eq!(received.addresses, v2::Addresses::IPv6(addresses))
+                }
+                header => panic!("expected a binary header, got {:?}", header),
+            }
+            assert_eq!(&payload, b"Hello");
+        });
+    }
+}
@@ -0,0 +1,293 @@
+//! Replays headers encoded by this crate through local HAProxy and NGINX processes, and parses the headers
+//! they emit in turn, to catch incompatibilities with the reference implementations that unit tests cannot.
+//!
+//! Each proxy accepts a PROXY protocol header on its frontend and sends one of its own to a backend
+//! run by the test, so both the encoder and the parser are exercised against it.
+//! Run with `cargo test --features interop --test interop`.
+//! The proxies are found on the `PATH`, or at the paths in the `PPP_HAPROXY` and `PPP_NGINX` environment variables;
+//! a proxy that cannot be started is skipped, unless `PPP_INTEROP_REQUIRED` is set.
+//! When `PPP_INTEROP_CAPTURE` names a directory, the raw bytes of every header a proxy sends are written to it,
+//! for recording as `Captured` test vectors in `src/vectors.rs`.
+use ppp::io::read_header;
+use ppp::policy::Policy;
+use ppp::{v1, v2, Header};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a proxy to start listening, or for a connection to be forwarded.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The payload sent after each header, which the backend must receive intact.
+const PAYLOAD: &[u8] = b"ping";
+
+/// The client and server addresses sent to and expected back from the proxies.
+const ADDRESSES: &[(&str, &str)] = &[
+    ("192.0.2.1:56324", "198.51.100.1:443"),
+    ("10.0.0.1:1", "10.0.0.2:65535"),
+    ("[2001:db8::1]:56324", "[2001:db8::2]:443"),
+];
+
+/// A reference implementation of the PROXY protocol, running for the duration of a test.
+struct Proxy {
+    name: String,
+    child: Child,
+    directory: PathBuf,
+    frontend: SocketAddr,
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// A free port on the loopback interface.
+fn free_address() -> SocketAddr {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .unwrap()
+}
+
+/// Reports a proxy that could not be started, failing the test if the proxies are required.
+fn skip(name: &str, reason: &str) -> Option<Proxy> {
+    assert!(
+        std::env::var_os("PPP_INTEROP_REQUIRED").is_none(),
+        "failed to start {}: {}",
+        name,
+        reason
+    );
+    eprintln!("skipping {}: {}", name, reason);
+
+    None
+}
+
+/// Starts the proxy with the configuration returned for its frontend address and working directory,
+/// or returns `None` when it cannot be started.
+fn start<F>(name: &str, variable: &str, arguments: &[&str], configure: F) -> Option<Proxy>
+where
+    F: FnOnce(SocketAddr, &str) -> String,
+{
+    let program = std::env::var(variable).unwrap_or_else(|_| name.to_string());
+    let frontend = free_address();
+    let directory = std::env::temp_dir().join(format!("ppp-interop-{}-{}", name, frontend.port()));
+
+    fs::create_dir_all(&directory).unwrap();
+
+    let configuration = directory.join(format!("{}.conf", name));
+
+    fs::write(
+        &configuration,
+        configure(frontend, directory.to_str().unwrap()),
+    )
+    .unwrap();
+
+    let child = Command::new(&program)
+        .args(arguments)
+        .arg(&configuration)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut proxy = Proxy {
+        name: name.to_string(),
+        child: match child {
+            Ok(child) => child,
+            Err(error) => {
+                let _ = fs::remove_dir_all(&directory);
+                return skip(name, &error.to_string());
+            }
+        },
+        directory,
+        frontend,
+    };
+    let started = Instant::now();
+
+    while TcpStream::connect(proxy.frontend).is_err() {
+        // E.g. a build without a module the configuration needs, or a port that was taken in the meantime.
+        if let Ok(Some(status)) = proxy.child.try_wait() {
+            return skip(name, &format!("exited with {}", status));
+        }
+
+        assert!(
+            started.elapsed() < TIMEOUT,
+            "{} did not start listening",
+            name
+        );
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    Some(proxy)
+}
+
+/// Writes the bytes of a header the proxy sent to the directory in `PPP_INTEROP_CAPTURE`, if it is set.
+/// The file is named after the proxy, the version and the source address of the header.
+fn capture(proxy: &Proxy, header: &Header) {
+    let directory = match std::env::var_os("PPP_INTEROP_CAPTURE") {
+        Some(directory) => PathBuf::from(directory),
+        None => return,
+    };
+    let version = match header.version() {
+        ppp::Version::One => "v1",
+        ppp::Version::Two => "v2",
+    };
+    let source = ppp::ProxyInfo::from(header)
+        .source
+        .map(|source| {
+            source
+                .to_string()
+                .replace(|c| c == ':' || c == '[' || c == ']', "_")
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(
+        directory.join(format!("{}-{}-{}.bin", proxy.name, version, source)),
+        header.as_bytes(),
+    )
+    .unwrap();
+}
+
+/// Sends the header and the payload through the proxy, returning the header the backend received from it.
+fn forward(proxy: &Proxy, backend: &TcpListener, header: &[u8]) -> Header<'static> {
+    let mut client = TcpStream::connect(proxy.frontend).unwrap();
+
+    client.write_all(header).unwrap();
+    client.write_all(PAYLOAD).unwrap();
+
+    let (stream, _) = backend.accept().unwrap();
+
+    stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+    let (mut stream, forwarded) = read_header(stream, Policy::Require).unwrap();
+    let mut payload = [0; PAYLOAD.len()];
+
+    stream.read_exact(&mut payload).unwrap();
+
+    assert_eq!(payload, PAYLOAD);
+
+    let forwarded = forwarded.unwrap();
+
+    capture(proxy, &forwarded);
+
+    forwarded
+}
+
+/// The headers of both versions this crate encodes for the given addresses.
+fn encode(source: SocketAddr, destination: SocketAddr) -> [Vec<u8>; 2] {
+    let text = v1::Addresses::from((source, destination)).to_string();
+    let binary = v2::Builder::with_addresses(
+        v2::Version::Two | v2::Command::Proxy,
+        v2::Protocol::Stream,
+        (source, destination),
+    )
+    .build()
+    .unwrap();
+
+    [text.into_bytes(), binary]
+}
+
+/// Checks that the proxy forwards the source address of each header it accepts, in the expected version.
+/// When `destination` is set, the proxy must forward the destination address of the header too,
+/// rather than its own address.
+fn round_trip(proxy: &Proxy, backend: &TcpListener, version: ppp::Version, destination: bool) {
+    for (source, target) in ADDRESSES {
+        let source: SocketAddr = source.parse().unwrap();
+        let target: SocketAddr = target.parse().unwrap();
+
+        for header in encode(source, target) {
+            let forwarded = forward(proxy, backend, &header);
+            let info = ppp::ProxyInfo::from(&forwarded);
+
+            assert_eq!(forwarded.version(), version, "{:?}", header);
+            assert_eq!(info.source, Some(source), "{:?}", header);
+
+            if !destination {
+                continue;
+            }
+
+            assert_eq!(info.destination, Some(target), "{:?}", header);
+
+            // The proxy's text headers should be exactly what this crate would send for the same addresses.
+            if let Header::V1(forwarded) = &forwarded {
+                assert_eq!(
+                    forwarded.header.as_ref(),
+                    v1::Addresses::from((source, target)).to_string()
+                );
+            }
+        }
+    }
+}
+
+fn haproxy(backend: SocketAddr, option: &str) -> Option<Proxy> {
+    start("haproxy", "PPP_HAPROXY", &["-db", "-f"], |frontend, _| {
+        format!(
+            "defaults\n\
+             \x20   mode tcp\n\
+             \x20   timeout connect 5s\n\
+             \x20   timeout client 5s\n\
+             \x20   timeout server 5s\n\
+             frontend interop\n\
+             \x20   bind {} accept-proxy\n\
+             \x20   default_backend parser\n\
+             backend parser\n\
+             \x20   server parser {} {}\n",
+            frontend, backend, option
+        )
+    })
+}
+
+#[test]
+fn haproxy_v1() {
+    let backend = TcpListener::bind("127.0.0.1:0").unwrap();
+
+    if let Some(proxy) = haproxy(backend.local_addr().unwrap(), "send-proxy") {
+        round_trip(&proxy, &backend, ppp::Version::One, true);
+    }
+}
+
+#[test]
+fn haproxy_v2() {
+    let backend = TcpListener::bind("127.0.0.1:0").unwrap();
+
+    if let Some(proxy) = haproxy(backend.local_addr().unwrap(), "send-proxy-v2") {
+        round_trip(&proxy, &backend, ppp::Version::Two, true);
+    }
+}
+
+#[test]
+fn nginx_v1() {
+    let backend = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = backend.local_addr().unwrap();
+    // Distributions that build the stream module dynamically enable it in `modules-enabled`.
+    let proxy = start("nginx", "PPP_NGINX", &["-c"], |frontend, directory| {
+        format!(
+            "daemon off;\n\
+             include /etc/nginx/modules-enabled/*.conf;\n\
+             pid {directory}/nginx.pid;\n\
+             error_log {directory}/error.log;\n\
+             events {{}}\n\
+             stream {{\n\
+             \x20   server {{\n\
+             \x20       listen {frontend} proxy_protocol;\n\
+             \x20       set_real_ip_from 127.0.0.1;\n\
+             \x20       proxy_protocol on;\n\
+             \x20       proxy_pass {backend};\n\
+             \x20   }}\n\
+             }}\n",
+            directory = directory,
+            frontend = frontend,
+            backend = address
+        )
+    });
+
+    // NGINX only takes the client address from the header, and sends its own address as the destination.
+    if let Some(proxy) = proxy {
+        round_trip(&proxy, &backend, ppp::Version::One, false);
+    }
+}